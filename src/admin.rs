@@ -0,0 +1,33 @@
+use std::{env, sync::OnceLock};
+
+use teloxide::types::{ChatId, UserId};
+
+static ADMIN_CHAT: OnceLock<Option<ChatId>> = OnceLock::new();
+
+static ADMINS: OnceLock<Vec<UserId>> = OnceLock::new();
+
+/// The chat maintainers use to receive feedback and reports, configured with
+/// `JIGARPICH_ADMIN_CHAT`.
+pub fn admin_chat() -> Option<ChatId> {
+    *ADMIN_CHAT.get_or_init(|| {
+        env::var("JIGARPICH_ADMIN_CHAT")
+            .ok()
+            .and_then(|chat_id| chat_id.parse().ok())
+            .map(ChatId)
+    })
+}
+
+/// Users allowed to run admin commands, configured with a comma separated
+/// list of user IDs in `JIGARPICH_ADMINS`.
+pub fn is_admin(user_id: UserId) -> bool {
+    ADMINS
+        .get_or_init(|| {
+            env::var("JIGARPICH_ADMINS")
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|id| id.trim().parse().ok())
+                .map(UserId)
+                .collect()
+        })
+        .contains(&user_id)
+}
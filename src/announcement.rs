@@ -0,0 +1,102 @@
+use std::{sync::OnceLock, time::Duration};
+
+use teloxide::prelude::*;
+
+use crate::{admin::is_admin, storage::Store, users, HandlerResult};
+
+/// Telegram allows about 30 messages per second, stay well below it.
+const DELAY_BETWEEN_MESSAGES: Duration = Duration::from_millis(100);
+
+/// Only one announcement runs at a time, stored under this key.
+const PENDING: &str = "pending";
+
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Announcement {
+    text: String,
+    remaining: Vec<UserId>,
+}
+
+static ANNOUNCEMENTS: OnceLock<Store<String, Announcement>> = OnceLock::new();
+
+fn announcements() -> &'static Store<String, Announcement> {
+    ANNOUNCEMENTS.get_or_init(|| Store::open("announcements"))
+}
+
+pub async fn handle_announce_command(bot: Bot, msg: Message, text: String) -> HandlerResult {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    if !is_admin(user.id) {
+        bot.send_message(msg.chat.id, "Only admins can send announcements")
+            .await?;
+        return Ok(());
+    }
+
+    if text.trim().is_empty() {
+        bot.send_message(msg.chat.id, "Usage: /announce <message>")
+            .await?;
+        return Ok(());
+    }
+
+    if announcements().contains(&PENDING.to_owned()) {
+        bot.send_message(msg.chat.id, "Another announcement is still being sent")
+            .await?;
+        return Ok(());
+    }
+
+    let remaining = users::all();
+    bot.send_message(
+        msg.chat.id,
+        format!("Sending the announcement to {} users", remaining.len()),
+    )
+    .await?;
+
+    announcements().insert(
+        PENDING.to_owned(),
+        Announcement {
+            text: text.trim().to_owned(),
+            remaining,
+        },
+    );
+    tokio::spawn(resume(bot, Some(msg.chat.id)));
+    Ok(())
+}
+
+/// Sends the pending announcement, if any, to everyone who hasn't received it
+/// yet. Progress is stored after every message so a restart picks up where
+/// it stopped.
+pub async fn resume(bot: Bot, report_to: Option<ChatId>) {
+    let mut sent = 0;
+    let mut failed = 0;
+
+    while let Some(mut announcement) = announcements().get(&PENDING.to_owned()) {
+        let Some(user_id) = announcement.remaining.pop() else {
+            announcements().remove(&PENDING.to_owned());
+            break;
+        };
+
+        match bot.send_message(user_id, announcement.text.as_str()).await {
+            Ok(_) => sent += 1,
+            Err(err) => {
+                failed += 1;
+                log::warn!("Can not send announcement to {}: {}", user_id, err);
+            }
+        }
+
+        announcements().insert(PENDING.to_owned(), announcement);
+        tokio::time::sleep(DELAY_BETWEEN_MESSAGES).await;
+    }
+
+    if let Some(chat_id) = report_to {
+        if let Err(err) = bot
+            .send_message(
+                chat_id,
+                format!("Announcement sent to {} users, {} failed", sent, failed),
+            )
+            .await
+        {
+            log::warn!("Can not report announcement progress: {}", err);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+    Json, Router,
+};
+use futures::{stream, Stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    metrics,
+    room::{RoomId, RoomScore, RoomStatus},
+    scoreboard, Rooms,
+};
+
+#[derive(Clone)]
+struct ApiState {
+    rooms: Rooms,
+    token: String,
+}
+
+#[derive(serde::Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// Read-only endpoints for external scoreboards and monitoring. Every request
+/// must carry `Authorization: Bearer <token>`, or `?token=<token>` for
+/// browsers' `EventSource` which can't set headers.
+pub fn router(rooms: Rooms, token: String) -> Router {
+    Router::new()
+        .route("/rooms/:id", get(get_room))
+        .route("/rooms/:id/score", get(get_score))
+        .route("/rooms/:id/events", get(get_events))
+        .route("/metrics", get(get_metrics))
+        .with_state(ApiState { rooms, token })
+}
+
+fn authorize(state: &ApiState, headers: &HeaderMap, query: &TokenQuery) -> Result<(), StatusCode> {
+    if query.token.as_deref() == Some(state.token.as_str()) {
+        return Ok(());
+    }
+
+    let expected = format!("Bearer {}", state.token);
+    match headers.get(AUTHORIZATION) {
+        Some(value) if value.as_bytes() == expected.as_bytes() => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+async fn get_room(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Query(query): Query<TokenQuery>,
+    Path(room_id): Path<u32>,
+) -> Result<Json<RoomStatus>, StatusCode> {
+    authorize(&state, &headers, &query)?;
+    let Some(room) = state.rooms.get(&RoomId(room_id)) else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let room = room.lock().await;
+    Ok(Json(room.status()))
+}
+
+async fn get_metrics(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Query(query): Query<TokenQuery>,
+) -> Result<String, StatusCode> {
+    authorize(&state, &headers, &query)?;
+    Ok(metrics::render(&state.rooms).await)
+}
+
+async fn get_score(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Query(query): Query<TokenQuery>,
+    Path(room_id): Path<u32>,
+) -> Result<Json<RoomScore>, StatusCode> {
+    authorize(&state, &headers, &query)?;
+    let Some(room) = state.rooms.get(&RoomId(room_id)) else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let room = room.lock().await;
+    Ok(Json(room.score()))
+}
+
+/// Streams the room score as server-sent events, starting with the current
+/// state and followed by every change.
+async fn get_events(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Query(query): Query<TokenQuery>,
+    Path(room_id): Path<u32>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    authorize(&state, &headers, &query)?;
+    let room_id = RoomId(room_id);
+    let Some(room) = state.rooms.get(&room_id) else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let room = room.lock().await;
+
+    let initial = serde_json::to_string(&room.score()).map_err(|err| {
+        log::warn!("Can not serialize score of {:?}: {}", room_id, err);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let receiver = scoreboard::subscribe(room_id);
+
+    let updates = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(json) => return Some((json, receiver)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    let events = stream::once(async { initial })
+        .chain(updates)
+        .map(|json| Ok(Event::default().data(json)));
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,169 @@
+//! The awards handed out when a game finishes, worked out from the log of
+//! every word the pairs played. `/stats` shows the numbers behind them.
+
+use std::{cmp::Reverse, time::Duration};
+
+use teloxide::types::{User, UserId};
+
+/// A word a pair played, guessed or skipped.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct WordEvent {
+    pub round: usize,
+    pub describer: UserId,
+    pub guesser: UserId,
+    pub guessed: bool,
+    /// From the word coming up until it was guessed or skipped.
+    pub took: Duration,
+}
+
+pub struct PlayerStats {
+    pub player: User,
+    pub described: usize,
+    pub guessed: usize,
+    /// Words skipped while describing.
+    pub skipped: usize,
+    pub fastest_guess: Option<Duration>,
+    /// Words described or guessed in the last round.
+    pub final_round_words: usize,
+    /// Time spent on the words described or guessed.
+    pub time: Duration,
+}
+
+impl PlayerStats {
+    fn new(player: User) -> Self {
+        PlayerStats {
+            player,
+            described: 0,
+            guessed: 0,
+            skipped: 0,
+            fastest_guess: None,
+            final_round_words: 0,
+            time: Duration::from_secs(0),
+        }
+    }
+
+    fn words(&self) -> usize {
+        self.described + self.guessed
+    }
+}
+
+/// Adds up the log per player. `final_round` is the index of the last round,
+/// which has no clutch award when it's the only one.
+pub fn player_stats(
+    players: Vec<User>,
+    events: &[WordEvent],
+    final_round: usize,
+) -> Vec<PlayerStats> {
+    let mut stats = players
+        .into_iter()
+        .map(PlayerStats::new)
+        .collect::<Vec<_>>();
+    for event in events {
+        for player in stats.iter_mut() {
+            let describer = player.player.id == event.describer;
+            let guesser = player.player.id == event.guesser;
+            if !event.guessed {
+                if describer {
+                    player.skipped += 1;
+                }
+                continue;
+            }
+
+            if describer {
+                player.described += 1;
+            }
+            if guesser {
+                player.guessed += 1;
+                player.fastest_guess = Some(
+                    player
+                        .fastest_guess
+                        .map_or(event.took, |fastest| fastest.min(event.took)),
+                );
+            }
+            if describer || guesser {
+                player.time += event.took;
+                if event.round == final_round && final_round > 0 {
+                    player.final_round_words += 1;
+                }
+            }
+        }
+    }
+    stats
+}
+
+/// The message of the awards, unless nobody guessed a word.
+pub fn ceremony(stats: &[PlayerStats]) -> Option<String> {
+    let mvp = stats
+        .iter()
+        .filter(|stats| stats.words() > 0)
+        .max_by_key(|stats| (stats.words(), Reverse(stats.time)))?;
+    let mut awards = vec![format!(
+        "🏅 MVP: {}, {} words in {:.2}s",
+        mvp.player.full_name(),
+        mvp.words(),
+        mvp.time.as_secs_f32()
+    )];
+
+    if let Some((player, fastest)) = stats
+        .iter()
+        .filter_map(|stats| Some((&stats.player, stats.fastest_guess?)))
+        .min_by_key(|(_, fastest)| *fastest)
+    {
+        awards.push(format!(
+            "⚡ Fastest guess: {}, {:.2}s",
+            player.full_name(),
+            fastest.as_secs_f32()
+        ));
+    }
+
+    if let Some(skipper) = stats
+        .iter()
+        .filter(|stats| stats.skipped > 0)
+        .max_by_key(|stats| stats.skipped)
+    {
+        awards.push(format!(
+            "⏩️ Most skips: {}, {} skipped",
+            skipper.player.full_name(),
+            skipper.skipped
+        ));
+    }
+
+    if let Some(clutch) = stats
+        .iter()
+        .filter(|stats| stats.final_round_words > 0)
+        .max_by_key(|stats| stats.final_round_words)
+    {
+        awards.push(format!(
+            "🎯 Clutch: {}, {} words in the final round",
+            clutch.player.full_name(),
+            clutch.final_round_words
+        ));
+    }
+
+    Some(format!(
+        "🏆 Awards\n\n{}\n\nSend /stats for everyone's numbers",
+        awards.join("\n")
+    ))
+}
+
+/// Everyone's numbers, for `/stats`.
+pub fn stats_text(stats: &[PlayerStats]) -> String {
+    stats
+        .iter()
+        .fold("📊 Stats\n".to_owned(), |mut res, stats| {
+            res += &format!(
+                "\n{}: {} described, {} guessed, {} skipped",
+                stats.player.full_name(),
+                stats.described,
+                stats.guessed,
+                stats.skipped
+            );
+            if let Some(fastest) = stats.fastest_guess {
+                res += &format!(", fastest guess {:.2}s", fastest.as_secs_f32());
+            }
+            if stats.final_round_words > 0 {
+                res += &format!(", {} in the final round", stats.final_round_words);
+            }
+            res
+        })
+}
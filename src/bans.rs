@@ -0,0 +1,83 @@
+use std::sync::OnceLock;
+
+use teloxide::{prelude::*, types::UpdateKind};
+
+use crate::{admin::is_admin, storage::Store, HandlerResult};
+
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum Ban {
+    /// The user is told they are banned.
+    Notice,
+    /// The user is silently ignored.
+    Shadow,
+}
+
+static BANS: OnceLock<Store<UserId, Ban>> = OnceLock::new();
+
+fn bans() -> &'static Store<UserId, Ban> {
+    BANS.get_or_init(|| Store::open("bans"))
+}
+
+pub fn is_banned(update: Update) -> bool {
+    update.user().is_some_and(|user| bans().contains(&user.id))
+}
+
+/// Answers updates of banned users instead of handling them.
+pub async fn handle_banned(bot: Bot, update: Update) -> HandlerResult {
+    let Some(user) = update.user() else {
+        return Ok(());
+    };
+    let Some(Ban::Notice) = bans().get(&user.id) else {
+        return Ok(());
+    };
+
+    match update.kind {
+        UpdateKind::Message(msg) => {
+            bot.send_message(msg.chat.id, "You are banned from this bot.")
+                .await?;
+        }
+        UpdateKind::CallbackQuery(q) => {
+            bot.answer_callback_query(q.id)
+                .text("You are banned from this bot.")
+                .await?;
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+pub async fn handle_ban_command(bot: Bot, msg: Message, arguments: String) -> HandlerResult {
+    if !msg.from().is_some_and(|user| is_admin(user.id)) {
+        return Ok(());
+    }
+
+    let mut arguments = arguments.split_whitespace();
+    let user_id = arguments.next().and_then(|id| id.parse().ok()).map(UserId);
+    let ban = match arguments.next() {
+        None => Some(Ban::Notice),
+        Some("shadow") => Some(Ban::Shadow),
+        Some(_) => None,
+    };
+
+    let (Some(user_id), Some(ban)) = (user_id, ban) else {
+        bot.send_message(msg.chat.id, "Usage: /ban <user id> [shadow]")
+            .await?;
+        return Ok(());
+    };
+
+    bans().insert(user_id, ban);
+    bot.send_message(msg.chat.id, format!("{} is banned", user_id))
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_unban_command(bot: Bot, msg: Message, user_id: u64) -> HandlerResult {
+    if !msg.from().is_some_and(|user| is_admin(user.id)) {
+        return Ok(());
+    }
+
+    bans().remove(&UserId(user_id));
+    bot.send_message(msg.chat.id, format!("{} is unbanned", user_id))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! White-label bots: communities can run the game under their own bot
+//! username while sharing the rooms and the word bank of one instance. The
+//! tokens of these bots are listed in `JIGARPICH_BRAND_TOKENS`, comma
+//! separated, each getting a webhook of its own next to the main one.
+//!
+//! Users and groups only hear from the bot they talk to, so every chat is
+//! remembered with the bot it last used and the engine's messages to it go
+//! through that bot. Not supported along with `JIGARPICH_SHARDS`.
+
+use std::{env, sync::OnceLock};
+
+use teloxide::prelude::*;
+
+use crate::storage::Store;
+
+static BRANDS: OnceLock<Vec<Bot>> = OnceLock::new();
+
+/// The ID of the bot each chat last talked to, by chat.
+static CHAT_BOTS: OnceLock<Store<ChatId, u64>> = OnceLock::new();
+
+/// The bots next to the main one, empty without white-label bots.
+pub fn brands() -> &'static [Bot] {
+    BRANDS.get_or_init(|| {
+        let Ok(tokens) = env::var("JIGARPICH_BRAND_TOKENS") else {
+            return Vec::new();
+        };
+        assert!(
+            env::var("JIGARPICH_SHARDS").is_err(),
+            "JIGARPICH_BRAND_TOKENS can not be used along with JIGARPICH_SHARDS"
+        );
+        tokens
+            .split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(Bot::new)
+            .collect()
+    })
+}
+
+fn chat_bots() -> &'static Store<ChatId, u64> {
+    CHAT_BOTS.get_or_init(|| Store::open("chat_bots"))
+}
+
+/// The ID of a bot, the part of its token before the colon.
+fn bot_id(bot: &Bot) -> Option<u64> {
+    bot.token().split(':').next()?.parse().ok()
+}
+
+/// Notes that `chat_id` talks to `bot`.
+pub fn record(bot: &Bot, chat_id: ChatId) {
+    if brands().is_empty() {
+        return;
+    }
+    let Some(bot_id) = bot_id(bot) else {
+        return;
+    };
+    if chat_bots().get(&chat_id) != Some(bot_id) {
+        chat_bots().insert(chat_id, bot_id);
+    }
+}
+
+pub fn forget(user_id: UserId) {
+    let chat_id = ChatId::from(user_id);
+    if chat_bots().contains(&chat_id) {
+        chat_bots().remove(&chat_id);
+    }
+}
+
+/// The bot to reach `chat_id` with: the white-label bot it talks to, or
+/// `main` otherwise.
+pub fn bot_for(chat_id: ChatId, main: &Bot) -> Bot {
+    if brands().is_empty() {
+        return main.clone();
+    }
+    chat_bots()
+        .get(&chat_id)
+        .and_then(|id| brands().iter().find(|brand| bot_id(brand) == Some(id)))
+        .unwrap_or(main)
+        .clone()
+}
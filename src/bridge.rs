@@ -0,0 +1,274 @@
+//! Lets rooms be played on other chat platforms next to Telegram. Their users
+//! and chats get IDs in a range Telegram doesn't hand out, kept in storage so
+//! the engine, persistence and stats treat them like any other, and `Bridge`
+//! sends every message through the platform of its recipient.
+
+use std::{
+    fmt,
+    sync::{Mutex, OnceLock},
+};
+
+use teloxide::{
+    types::{ChatId, MessageId},
+    Bot, RequestError,
+};
+
+#[cfg(feature = "discord")]
+use crate::discord::{self, Discord};
+#[cfg(feature = "matrix")]
+use crate::matrix::{self, Matrix};
+use crate::{
+    frontend::{Buttons, Frontend},
+    room::RoomId,
+    storage::Store,
+};
+
+/// Bridged users and chats get IDs from here up in Telegram's ranges of users
+/// and basic groups, which Telegram doesn't hand out yet.
+const LOCAL_IDS_FROM: i64 = 1 << 39;
+
+/// Where Telegram's supergroup IDs start, below the basic group range.
+const LOCAL_IDS_TO: i64 = 1_000_000_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Platform {
+    Discord,
+    Matrix,
+}
+
+static EXTERNAL_IDS: OnceLock<Store<i64, (Platform, String)>> = OnceLock::new();
+
+static LOCAL_IDS: OnceLock<Store<String, i64>> = OnceLock::new();
+
+static ALLOCATION: Mutex<()> = Mutex::new(());
+
+fn external_ids() -> &'static Store<i64, (Platform, String)> {
+    EXTERNAL_IDS.get_or_init(|| Store::open("bridged_external_ids"))
+}
+
+fn local_ids() -> &'static Store<String, i64> {
+    LOCAL_IDS.get_or_init(|| Store::open("bridged_local_ids"))
+}
+
+/// The ID the engine knows a user or chat of another platform by, negative
+/// for group chats like Telegram's.
+pub fn local_id(platform: Platform, external_id: &str, group: bool) -> i64 {
+    let key = format!("{:?} {}", platform, external_id);
+    let _allocation = ALLOCATION.lock().unwrap();
+    if let Some(id) = local_ids().get(&key) {
+        return id;
+    }
+
+    let id = LOCAL_IDS_FROM + external_ids().keys().len() as i64;
+    let id = if group { -id } else { id };
+    local_ids().insert(key, id);
+    external_ids().insert(id, (platform, external_id.to_owned()));
+    id
+}
+
+/// The platform and the ID there of a bridged user or chat.
+pub fn external_id(chat_id: ChatId) -> Option<(Platform, String)> {
+    if !(LOCAL_IDS_FROM..LOCAL_IDS_TO).contains(&chat_id.0.abs()) {
+        return None;
+    }
+    external_ids().get(&chat_id.0)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Telegram(RequestError),
+    #[cfg(feature = "discord")]
+    Discord(discord::Error),
+    #[cfg(feature = "matrix")]
+    Matrix(matrix::Error),
+    /// A chat of a platform this instance isn't configured for.
+    NotConfigured,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Telegram(err) => write!(f, "{}", err),
+            #[cfg(feature = "discord")]
+            Error::Discord(err) => write!(f, "{}", err),
+            #[cfg(feature = "matrix")]
+            Error::Matrix(err) => write!(f, "{}", err),
+            Error::NotConfigured => write!(f, "The platform of the chat is not configured"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<RequestError> for Error {
+    fn from(err: RequestError) -> Self {
+        Error::Telegram(err)
+    }
+}
+
+#[cfg(feature = "discord")]
+impl From<discord::Error> for Error {
+    fn from(err: discord::Error) -> Self {
+        Error::Discord(err)
+    }
+}
+
+#[cfg(feature = "matrix")]
+impl From<matrix::Error> for Error {
+    fn from(err: matrix::Error) -> Self {
+        Error::Matrix(err)
+    }
+}
+
+#[derive(Clone)]
+pub struct Bridge {
+    telegram: Bot,
+    #[cfg(feature = "discord")]
+    discord: Option<Discord>,
+    #[cfg(feature = "matrix")]
+    matrix: Option<Matrix>,
+}
+
+impl Bridge {
+    pub fn new(telegram: Bot) -> Self {
+        Bridge {
+            telegram,
+            #[cfg(feature = "discord")]
+            discord: None,
+            #[cfg(feature = "matrix")]
+            matrix: None,
+        }
+    }
+
+    #[cfg(feature = "discord")]
+    pub fn with_discord(self, discord: Option<Discord>) -> Self {
+        Bridge { discord, ..self }
+    }
+
+    #[cfg(feature = "matrix")]
+    pub fn with_matrix(self, matrix: Option<Matrix>) -> Self {
+        Bridge { matrix, ..self }
+    }
+}
+
+/// Runs `$call` with `$frontend` bound to the frontend of the platform
+/// `$chat_id` belongs to.
+macro_rules! route {
+    ($bridge:expr, $chat_id:expr, |$frontend:ident| $call:expr) => {{
+        match external_id($chat_id).map(|(platform, _)| platform) {
+            #[cfg(feature = "discord")]
+            Some(Platform::Discord) => {
+                let $frontend = $bridge.discord.as_ref().ok_or(Error::NotConfigured)?;
+                Ok($call.await?)
+            }
+            #[cfg(feature = "matrix")]
+            Some(Platform::Matrix) => {
+                let $frontend = $bridge.matrix.as_ref().ok_or(Error::NotConfigured)?;
+                Ok($call.await?)
+            }
+            #[allow(unreachable_patterns)]
+            Some(_) => Err(Error::NotConfigured),
+            None => {
+                let $frontend = &$bridge.telegram;
+                Ok($call.await?)
+            }
+        }
+    }};
+}
+
+impl Frontend for Bridge {
+    type Error = Error;
+
+    async fn send_card_to_topic(
+        &self,
+        to: ChatId,
+        topic: Option<i32>,
+        text: String,
+        buttons: Buttons,
+    ) -> Result<(ChatId, MessageId), Self::Error> {
+        route!(self, to, |frontend| frontend
+            .send_card_to_topic(to, topic, text, buttons))
+    }
+
+    async fn edit_buttons(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        buttons: Buttons,
+    ) -> Result<(), Self::Error> {
+        route!(self, chat_id, |frontend| frontend
+            .edit_buttons(chat_id, message_id, buttons))
+    }
+
+    async fn edit_text(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        text: String,
+    ) -> Result<(), Self::Error> {
+        route!(self, chat_id, |frontend| frontend
+            .edit_text(chat_id, message_id, text))
+    }
+
+    async fn edit_card(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        text: String,
+        buttons: Buttons,
+    ) -> Result<(), Self::Error> {
+        route!(self, chat_id, |frontend| frontend
+            .edit_card(chat_id, message_id, text, buttons))
+    }
+
+    async fn pin_message(&self, chat_id: ChatId, message_id: MessageId) -> Result<(), Self::Error> {
+        route!(self, chat_id, |frontend| frontend
+            .pin_message(chat_id, message_id))
+    }
+
+    async fn prompt_to_topic(
+        &self,
+        to: ChatId,
+        topic: Option<i32>,
+        text: String,
+    ) -> Result<(), Self::Error> {
+        route!(self, to, |frontend| frontend
+            .prompt_to_topic(to, topic, text))
+    }
+
+    async fn prompt_with_spoiler(
+        &self,
+        to: ChatId,
+        text: String,
+        spoiler: String,
+    ) -> Result<(), Self::Error> {
+        route!(self, to, |frontend| frontend
+            .prompt_with_spoiler(to, text, spoiler))
+    }
+
+    /// Links only exist on Telegram, players of other platforms join with the
+    /// room number.
+    async fn join_link(&self, room_id: RoomId) -> Result<Option<String>, Self::Error> {
+        Ok(self.telegram.join_link(room_id).await?)
+    }
+
+    async fn send_image(
+        &self,
+        to: ChatId,
+        topic: Option<i32>,
+        png: Vec<u8>,
+        caption: String,
+    ) -> Result<(), Self::Error> {
+        route!(self, to, |frontend| frontend
+            .send_image(to, topic, png, caption))
+    }
+
+    async fn send_sound(
+        &self,
+        to: ChatId,
+        topic: Option<i32>,
+        ogg: Vec<u8>,
+    ) -> Result<(), Self::Error> {
+        route!(self, to, |frontend| frontend.send_sound(to, topic, ogg))
+    }
+}
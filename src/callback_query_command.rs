@@ -1,12 +1,98 @@
-use crate::room::RoomId;
+use std::str::FromStr;
 
+use teloxide::types::UserId;
+
+use crate::{
+    notifications::Toggle,
+    room::{RoomId, MAX_NUMBER_OF_ROUNDS, MAX_NUMBER_OF_TEAMS},
+    tutorial::NUMBER_OF_STEPS,
+    words::Complexity,
+};
+
+#[derive(Clone, Debug)]
 pub enum CbQueryCommand {
-    Join { team_index: usize },
+    Join {
+        team_index: usize,
+    },
     GetTeams,
     Play,
     Start,
     Correct,
     Skip,
+    Pause,
+    Resume,
+    CycleWordBroadcast,
+    CycleWordTimeLimit,
+    CycleVictory,
+    ToggleHandicap,
+    ToggleAlertEveryone,
+    ToggleAudioAlerts,
+    ToggleAnimatedTimer,
+    DifficultyPlan,
+    CycleRoundDifficulty {
+        round: usize,
+    },
+    ComplexityWeights,
+    AdjustComplexityWeight {
+        complexity: Complexity,
+        increase: bool,
+    },
+    ToggleDescriberDraws,
+    Draw {
+        complexity: Complexity,
+    },
+    ToggleHostApproval,
+    ToggleAutoStart,
+    CancelAutoStart,
+    ApproveJoin {
+        user_id: UserId,
+    },
+    DenyJoin {
+        user_id: UserId,
+    },
+    Rematch {
+        swap_roles: bool,
+    },
+    Scoreboard,
+    Dispute,
+    DisputeWord {
+        index: usize,
+    },
+    WriteCard,
+    Invite,
+    ToggleTabooWords,
+    TogglePublic,
+    TogglePassThePhone,
+    ToggleWarmUp,
+    WarmUpCorrect {
+        team_index: usize,
+    },
+    WarmUpSkip {
+        team_index: usize,
+    },
+    FinishWarmUp,
+    Substitute {
+        seat: usize,
+    },
+    ToggleTeamLock,
+    BalanceTeams,
+    AcceptBalancedTeams,
+    ToggleCaptains,
+    PickCaptains,
+    MakeCaptain {
+        user_id: UserId,
+    },
+    ToggleRelay,
+    CycleCoopTarget,
+    CycleBreak,
+    CycleTieMargin,
+    ToggleBalanceTurns,
+    RelayClaim {
+        index: usize,
+    },
+    RelaySkip {
+        index: usize,
+    },
 }
 
 pub fn serialize_command(room_id: RoomId, query_command: CbQueryCommand) -> String {
@@ -17,35 +103,535 @@ pub fn serialize_command(room_id: RoomId, query_command: CbQueryCommand) -> Stri
         CbQueryCommand::Start => format!("start {}", room_id.0),
         CbQueryCommand::Correct => format!("correct {}", room_id.0),
         CbQueryCommand::Skip => format!("skip {}", room_id.0),
+        CbQueryCommand::Pause => format!("pause {}", room_id.0),
+        CbQueryCommand::Resume => format!("resume {}", room_id.0),
+        CbQueryCommand::CycleWordBroadcast => format!("word_broadcast {}", room_id.0),
+        CbQueryCommand::CycleWordTimeLimit => format!("word_time_limit {}", room_id.0),
+        CbQueryCommand::CycleVictory => format!("victory {}", room_id.0),
+        CbQueryCommand::ToggleHandicap => format!("handicap {}", room_id.0),
+        CbQueryCommand::ToggleAlertEveryone => format!("alert_everyone {}", room_id.0),
+        CbQueryCommand::ToggleAudioAlerts => format!("audio_alerts {}", room_id.0),
+        CbQueryCommand::ToggleAnimatedTimer => format!("animated_timer {}", room_id.0),
+        CbQueryCommand::DifficultyPlan => format!("difficulty_plan {}", room_id.0),
+        CbQueryCommand::ToggleDescriberDraws => format!("describer_draws {}", room_id.0),
+        CbQueryCommand::Draw { complexity } => {
+            format!("draw {} {}", room_id.0, complexity as u8)
+        }
+        CbQueryCommand::CycleRoundDifficulty { round } => {
+            format!("round_difficulty {} {}", room_id.0, round)
+        }
+        CbQueryCommand::ComplexityWeights => format!("complexity_weights {}", room_id.0),
+        CbQueryCommand::AdjustComplexityWeight {
+            complexity,
+            increase,
+        } => format!(
+            "{} {} {}",
+            if increase {
+                "more_complexity"
+            } else {
+                "less_complexity"
+            },
+            room_id.0,
+            complexity as u8
+        ),
+        CbQueryCommand::ToggleHostApproval => format!("host_approval {}", room_id.0),
+        CbQueryCommand::ToggleAutoStart => format!("auto_start {}", room_id.0),
+        CbQueryCommand::CancelAutoStart => format!("cancel_auto_start {}", room_id.0),
+        CbQueryCommand::ApproveJoin { user_id } => {
+            format!("approve {} {}", room_id.0, user_id.0)
+        }
+        CbQueryCommand::DenyJoin { user_id } => format!("deny {} {}", room_id.0, user_id.0),
+        CbQueryCommand::Rematch { swap_roles } => format!(
+            "{} {}",
+            if swap_roles {
+                "rematch_swapped"
+            } else {
+                "rematch"
+            },
+            room_id.0
+        ),
+        CbQueryCommand::Scoreboard => format!("scoreboard {}", room_id.0),
+        CbQueryCommand::Dispute => format!("dispute {}", room_id.0),
+        CbQueryCommand::DisputeWord { index } => format!("dispute_word {} {}", room_id.0, index),
+        CbQueryCommand::WriteCard => format!("write_card {}", room_id.0),
+        CbQueryCommand::Invite => format!("invite {}", room_id.0),
+        CbQueryCommand::ToggleTabooWords => format!("taboo_words {}", room_id.0),
+        CbQueryCommand::TogglePublic => format!("public {}", room_id.0),
+        CbQueryCommand::TogglePassThePhone => format!("pass_the_phone {}", room_id.0),
+        CbQueryCommand::ToggleWarmUp => format!("warm_up {}", room_id.0),
+        CbQueryCommand::WarmUpCorrect { team_index } => {
+            format!("warm_up_correct {} {}", room_id.0, team_index)
+        }
+        CbQueryCommand::WarmUpSkip { team_index } => {
+            format!("warm_up_skip {} {}", room_id.0, team_index)
+        }
+        CbQueryCommand::FinishWarmUp => format!("finish_warm_up {}", room_id.0),
+        CbQueryCommand::Substitute { seat } => format!("substitute {} {}", room_id.0, seat),
+        CbQueryCommand::ToggleTeamLock => format!("team_lock {}", room_id.0),
+        CbQueryCommand::BalanceTeams => format!("balance_teams {}", room_id.0),
+        CbQueryCommand::AcceptBalancedTeams => format!("accept_teams {}", room_id.0),
+        CbQueryCommand::ToggleCaptains => format!("captains {}", room_id.0),
+        CbQueryCommand::PickCaptains => format!("pick_captains {}", room_id.0),
+        CbQueryCommand::MakeCaptain { user_id } => {
+            format!("captain {} {}", room_id.0, user_id.0)
+        }
+        CbQueryCommand::ToggleRelay => format!("relay {}", room_id.0),
+        CbQueryCommand::CycleCoopTarget => format!("coop {}", room_id.0),
+        CbQueryCommand::CycleBreak => format!("break {}", room_id.0),
+        CbQueryCommand::CycleTieMargin => format!("tie_margin {}", room_id.0),
+        CbQueryCommand::ToggleBalanceTurns => format!("balance {}", room_id.0),
+        CbQueryCommand::RelayClaim { index } => format!("relay_claim {} {}", room_id.0, index),
+        CbQueryCommand::RelaySkip { index } => format!("relay_skip {} {}", room_id.0, index),
     }
 }
 
+/// Parses a number spelled the way `format!` writes it, without signs or
+/// leading zeros, so every command has exactly one spelling.
+fn parse_field<T: FromStr>(field: &str) -> Option<T> {
+    let canonical = !field.is_empty()
+        && field.bytes().all(|byte| byte.is_ascii_digit())
+        && (field == "0" || !field.starts_with('0'));
+    if !canonical {
+        return None;
+    }
+    field.parse().ok()
+}
+
+/// Splits callback data into its fields, which are separated by exactly one
+/// space.
+fn split_fields(data: &str) -> Option<Vec<&str>> {
+    let fields = data.split(' ').collect::<Vec<_>>();
+    if fields.iter().any(|field| field.is_empty()) {
+        return None;
+    }
+    Some(fields)
+}
+
 pub fn parse_command(data: String) -> Option<(RoomId, CbQueryCommand)> {
-    let (command, room_id, tail) = if let Some((index, _)) = data.match_indices(' ').nth(1) {
-        let (header, tail) = data.split_at(index);
-        let (command, room_id) = sscanf::sscanf!(header, "{} {}", String, u32).ok()?;
-        (
-            command,
-            room_id,
-            // Drop starting " "
-            &tail[tail.char_indices().nth(1).unwrap().0..],
-        )
-    } else {
-        let (command, room_id) = sscanf::sscanf!(data, "{} {}", String, u32).ok()?;
-        (command, room_id, "")
+    let fields = split_fields(&data)?;
+    let (command, room_id, argument) = match fields[..] {
+        [command, room_id] => (command, room_id, None),
+        [command, room_id, argument] => (command, room_id, Some(argument)),
+        _ => return None,
     };
+    let room_id = RoomId(parse_field(room_id)?);
 
-    let room_id = RoomId(room_id);
-    match command.as_str() {
-        "join" => {
-            let team_index = sscanf::sscanf!(tail, "{}", usize).ok()?;
-            Some((room_id, CbQueryCommand::Join { team_index }))
-        }
-        "get_teams" => Some((room_id, CbQueryCommand::GetTeams)),
-        "play" => Some((room_id, CbQueryCommand::Play)),
-        "start" => Some((room_id, CbQueryCommand::Start)),
-        "correct" => Some((room_id, CbQueryCommand::Correct)),
-        "skip" => Some((room_id, CbQueryCommand::Skip)),
+    let command = match (command, argument) {
+        ("join", Some(team_index)) => {
+            let team_index = parse_field(team_index)?;
+            if team_index >= MAX_NUMBER_OF_TEAMS {
+                return None;
+            }
+            CbQueryCommand::Join { team_index }
+        }
+        ("get_teams", None) => CbQueryCommand::GetTeams,
+        ("play", None) => CbQueryCommand::Play,
+        ("start", None) => CbQueryCommand::Start,
+        ("correct", None) => CbQueryCommand::Correct,
+        ("skip", None) => CbQueryCommand::Skip,
+        ("pause", None) => CbQueryCommand::Pause,
+        ("resume", None) => CbQueryCommand::Resume,
+        ("word_broadcast", None) => CbQueryCommand::CycleWordBroadcast,
+        ("word_time_limit", None) => CbQueryCommand::CycleWordTimeLimit,
+        ("victory", None) => CbQueryCommand::CycleVictory,
+        ("handicap", None) => CbQueryCommand::ToggleHandicap,
+        ("alert_everyone", None) => CbQueryCommand::ToggleAlertEveryone,
+        ("audio_alerts", None) => CbQueryCommand::ToggleAudioAlerts,
+        ("animated_timer", None) => CbQueryCommand::ToggleAnimatedTimer,
+        ("difficulty_plan", None) => CbQueryCommand::DifficultyPlan,
+        ("describer_draws", None) => CbQueryCommand::ToggleDescriberDraws,
+        ("draw", Some(complexity)) => CbQueryCommand::Draw {
+            complexity: Complexity::from_repr(parse_field(complexity)?)?,
+        },
+        ("round_difficulty", Some(round)) => {
+            let round = parse_field(round)?;
+            if round >= MAX_NUMBER_OF_ROUNDS {
+                return None;
+            }
+            CbQueryCommand::CycleRoundDifficulty { round }
+        }
+        ("complexity_weights", None) => CbQueryCommand::ComplexityWeights,
+        ("more_complexity", Some(complexity)) => CbQueryCommand::AdjustComplexityWeight {
+            complexity: Complexity::from_repr(parse_field(complexity)?)?,
+            increase: true,
+        },
+        ("less_complexity", Some(complexity)) => CbQueryCommand::AdjustComplexityWeight {
+            complexity: Complexity::from_repr(parse_field(complexity)?)?,
+            increase: false,
+        },
+        ("host_approval", None) => CbQueryCommand::ToggleHostApproval,
+        ("auto_start", None) => CbQueryCommand::ToggleAutoStart,
+        ("cancel_auto_start", None) => CbQueryCommand::CancelAutoStart,
+        ("rematch", None) => CbQueryCommand::Rematch { swap_roles: false },
+        ("rematch_swapped", None) => CbQueryCommand::Rematch { swap_roles: true },
+        ("scoreboard", None) => CbQueryCommand::Scoreboard,
+        ("dispute", None) => CbQueryCommand::Dispute,
+        ("dispute_word", Some(index)) => CbQueryCommand::DisputeWord {
+            index: parse_field(index)?,
+        },
+        ("write_card", None) => CbQueryCommand::WriteCard,
+        ("invite", None) => CbQueryCommand::Invite,
+        ("taboo_words", None) => CbQueryCommand::ToggleTabooWords,
+        ("public", None) => CbQueryCommand::TogglePublic,
+        ("pass_the_phone", None) => CbQueryCommand::TogglePassThePhone,
+        ("warm_up", None) => CbQueryCommand::ToggleWarmUp,
+        ("warm_up_correct", Some(team_index)) => CbQueryCommand::WarmUpCorrect {
+            team_index: parse_field(team_index)?,
+        },
+        ("warm_up_skip", Some(team_index)) => CbQueryCommand::WarmUpSkip {
+            team_index: parse_field(team_index)?,
+        },
+        ("finish_warm_up", None) => CbQueryCommand::FinishWarmUp,
+        ("substitute", Some(seat)) => CbQueryCommand::Substitute {
+            seat: parse_field(seat)?,
+        },
+        ("team_lock", None) => CbQueryCommand::ToggleTeamLock,
+        ("balance_teams", None) => CbQueryCommand::BalanceTeams,
+        ("accept_teams", None) => CbQueryCommand::AcceptBalancedTeams,
+        ("captains", None) => CbQueryCommand::ToggleCaptains,
+        ("pick_captains", None) => CbQueryCommand::PickCaptains,
+        ("captain", Some(user_id)) => CbQueryCommand::MakeCaptain {
+            user_id: UserId(parse_field(user_id)?),
+        },
+        ("relay", None) => CbQueryCommand::ToggleRelay,
+        ("coop", None) => CbQueryCommand::CycleCoopTarget,
+        ("break", None) => CbQueryCommand::CycleBreak,
+        ("tie_margin", None) => CbQueryCommand::CycleTieMargin,
+        ("balance", None) => CbQueryCommand::ToggleBalanceTurns,
+        ("relay_claim", Some(index)) => CbQueryCommand::RelayClaim {
+            index: parse_field(index)?,
+        },
+        ("relay_skip", Some(index)) => CbQueryCommand::RelaySkip {
+            index: parse_field(index)?,
+        },
+        ("approve", Some(user_id)) => CbQueryCommand::ApproveJoin {
+            user_id: UserId(parse_field(user_id)?),
+        },
+        ("deny", Some(user_id)) => CbQueryCommand::DenyJoin {
+            user_id: UserId(parse_field(user_id)?),
+        },
+        _ => return None,
+    };
+    Some((room_id, command))
+}
+
+/// Callbacks that belong to a user rather than a room.
+#[derive(Clone, Debug)]
+pub enum UserCbQueryCommand {
+    ToggleNotification {
+        toggle: Toggle,
+    },
+    Tutorial {
+        step: usize,
+    },
+    SwitchRoom {
+        room_id: RoomId,
+    },
+    ReviewWord {
+        id: u32,
+        verdict: Option<Complexity>,
+    },
+    TabooWords {
+        use_taboo_words: bool,
+    },
+    SetupBack,
+}
+
+pub fn serialize_user_command(query_command: UserCbQueryCommand) -> String {
+    match query_command {
+        UserCbQueryCommand::ToggleNotification { toggle } => {
+            format!("notification {}", toggle.index())
+        }
+        UserCbQueryCommand::Tutorial { step } => format!("tutorial {}", step),
+        UserCbQueryCommand::SwitchRoom { room_id } => format!("switch {}", room_id.0),
+        UserCbQueryCommand::ReviewWord { id, verdict } => match verdict {
+            Some(complexity) => format!("approve_word {} {}", id, complexity as u8),
+            None => format!("reject_word {}", id),
+        },
+        UserCbQueryCommand::TabooWords { use_taboo_words } => {
+            format!("use_taboo_words {}", use_taboo_words as u8)
+        }
+        UserCbQueryCommand::SetupBack => "setup_back".to_owned(),
+    }
+}
+
+pub fn parse_user_command(data: &str) -> Option<UserCbQueryCommand> {
+    let fields = split_fields(data)?;
+    if let ["setup_back"] = fields[..] {
+        return Some(UserCbQueryCommand::SetupBack);
+    }
+    if let ["approve_word", id, complexity] = fields[..] {
+        return Some(UserCbQueryCommand::ReviewWord {
+            id: parse_field(id)?,
+            verdict: Some(Complexity::from_repr(parse_field(complexity)?)?),
+        });
+    }
+
+    let [command, argument] = fields[..] else {
+        return None;
+    };
+    match command {
+        "notification" => Some(UserCbQueryCommand::ToggleNotification {
+            toggle: Toggle::from_index(parse_field(argument)?)?,
+        }),
+        "tutorial" => {
+            let step = parse_field(argument)?;
+            if step >= NUMBER_OF_STEPS {
+                return None;
+            }
+            Some(UserCbQueryCommand::Tutorial { step })
+        }
+        "switch" => Some(UserCbQueryCommand::SwitchRoom {
+            room_id: RoomId(parse_field(argument)?),
+        }),
+        "reject_word" => Some(UserCbQueryCommand::ReviewWord {
+            id: parse_field(argument)?,
+            verdict: None,
+        }),
+        "use_taboo_words" => Some(UserCbQueryCommand::TabooWords {
+            use_taboo_words: match parse_field(argument)? {
+                0u8 => false,
+                1 => true,
+                _ => return None,
+            },
+        }),
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::{prelude::*, sample::select};
+
+    use super::*;
+
+    fn complexity() -> impl Strategy<Value = Complexity> {
+        select(Complexity::ALL.to_vec())
+    }
+
+    fn command() -> impl Strategy<Value = CbQueryCommand> {
+        let plain = select(vec![
+            CbQueryCommand::GetTeams,
+            CbQueryCommand::Play,
+            CbQueryCommand::Start,
+            CbQueryCommand::Correct,
+            CbQueryCommand::Skip,
+            CbQueryCommand::Pause,
+            CbQueryCommand::Resume,
+            CbQueryCommand::CycleWordBroadcast,
+            CbQueryCommand::CycleWordTimeLimit,
+            CbQueryCommand::CycleVictory,
+            CbQueryCommand::ToggleHandicap,
+            CbQueryCommand::ToggleAlertEveryone,
+            CbQueryCommand::ToggleAudioAlerts,
+            CbQueryCommand::ToggleAnimatedTimer,
+            CbQueryCommand::DifficultyPlan,
+            CbQueryCommand::ComplexityWeights,
+            CbQueryCommand::ToggleDescriberDraws,
+            CbQueryCommand::ToggleHostApproval,
+            CbQueryCommand::ToggleAutoStart,
+            CbQueryCommand::CancelAutoStart,
+            CbQueryCommand::Scoreboard,
+            CbQueryCommand::Dispute,
+            CbQueryCommand::WriteCard,
+            CbQueryCommand::Invite,
+            CbQueryCommand::ToggleTabooWords,
+            CbQueryCommand::TogglePublic,
+            CbQueryCommand::TogglePassThePhone,
+            CbQueryCommand::ToggleWarmUp,
+            CbQueryCommand::FinishWarmUp,
+            CbQueryCommand::ToggleTeamLock,
+            CbQueryCommand::BalanceTeams,
+            CbQueryCommand::AcceptBalancedTeams,
+            CbQueryCommand::ToggleCaptains,
+            CbQueryCommand::PickCaptains,
+            CbQueryCommand::ToggleRelay,
+            CbQueryCommand::CycleCoopTarget,
+            CbQueryCommand::CycleBreak,
+            CbQueryCommand::CycleTieMargin,
+            CbQueryCommand::ToggleBalanceTurns,
+        ]);
+        let bounded = prop_oneof![
+            (0..MAX_NUMBER_OF_TEAMS).prop_map(|team_index| CbQueryCommand::Join { team_index }),
+            (0..MAX_NUMBER_OF_ROUNDS)
+                .prop_map(|round| CbQueryCommand::CycleRoundDifficulty { round }),
+            complexity().prop_map(|complexity| CbQueryCommand::Draw { complexity }),
+            (complexity(), any::<bool>()).prop_map(|(complexity, increase)| {
+                CbQueryCommand::AdjustComplexityWeight {
+                    complexity,
+                    increase,
+                }
+            }),
+            any::<bool>().prop_map(|swap_roles| CbQueryCommand::Rematch { swap_roles }),
+        ];
+        let user = any::<u64>().prop_map(UserId);
+        let index = any::<usize>();
+        let unbounded = prop_oneof![
+            user.clone()
+                .prop_map(|user_id| CbQueryCommand::ApproveJoin { user_id }),
+            user.clone()
+                .prop_map(|user_id| CbQueryCommand::DenyJoin { user_id }),
+            user.prop_map(|user_id| CbQueryCommand::MakeCaptain { user_id }),
+            index.prop_map(|index| CbQueryCommand::DisputeWord { index }),
+            index.prop_map(|team_index| CbQueryCommand::WarmUpCorrect { team_index }),
+            index.prop_map(|team_index| CbQueryCommand::WarmUpSkip { team_index }),
+            index.prop_map(|seat| CbQueryCommand::Substitute { seat }),
+            index.prop_map(|index| CbQueryCommand::RelayClaim { index }),
+            index.prop_map(|index| CbQueryCommand::RelaySkip { index }),
+        ];
+        prop_oneof![plain, bounded, unbounded]
+    }
+
+    fn user_command() -> impl Strategy<Value = UserCbQueryCommand> {
+        prop_oneof![
+            (0..8usize)
+                .prop_filter_map("no such toggle", Toggle::from_index)
+                .prop_map(|toggle| UserCbQueryCommand::ToggleNotification { toggle }),
+            (0..NUMBER_OF_STEPS).prop_map(|step| UserCbQueryCommand::Tutorial { step }),
+            any::<u32>().prop_map(|id| UserCbQueryCommand::SwitchRoom {
+                room_id: RoomId(id)
+            }),
+            (any::<u32>(), proptest::option::of(complexity()))
+                .prop_map(|(id, verdict)| UserCbQueryCommand::ReviewWord { id, verdict }),
+            any::<bool>()
+                .prop_map(|use_taboo_words| UserCbQueryCommand::TabooWords { use_taboo_words }),
+            Just(UserCbQueryCommand::SetupBack),
+        ]
+    }
+
+    /// Callback data close to a valid command: one of its fields swapped
+    /// for signs, spaces, digits or any other characters.
+    fn near_miss() -> impl Strategy<Value = String> {
+        let data = prop_oneof![
+            (any::<u32>(), command())
+                .prop_map(|(room_id, command)| serialize_command(RoomId(room_id), command)),
+            user_command().prop_map(serialize_user_command),
+        ];
+        let token = prop_oneof!["[0-9 +-]{0,4}", "\\PC{0,3}", "0[0-9]{1,3}"];
+        (data, any::<prop::sample::Index>(), token).prop_map(|(data, field, token)| {
+            let mut fields = data.split(' ').collect::<Vec<_>>();
+            let field = field.index(fields.len());
+            fields[field] = &token;
+            fields.join(" ")
+        })
+    }
+
+    fn assert_canonical(data: &str) {
+        if let Some((room_id, command)) = parse_command(data.to_owned()) {
+            assert_eq!(serialize_command(room_id, command), data);
+        }
+        if let Some(command) = parse_user_command(data) {
+            assert_eq!(serialize_user_command(command), data);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn commands_round_trip(room_id in any::<u32>(), command in command()) {
+            let data = serialize_command(RoomId(room_id), command);
+            let parsed = parse_command(data.clone());
+            prop_assert!(parsed.is_some(), "{:?} not parsed", data);
+            let (parsed_room_id, parsed) = parsed.unwrap();
+            prop_assert_eq!(parsed_room_id, RoomId(room_id));
+            prop_assert_eq!(serialize_command(parsed_room_id, parsed), data);
+        }
+
+        #[test]
+        fn user_commands_round_trip(command in user_command()) {
+            let data = serialize_user_command(command);
+            let parsed = parse_user_command(&data);
+            prop_assert!(parsed.is_some(), "{:?} not parsed", data);
+            prop_assert_eq!(serialize_user_command(parsed.unwrap()), data);
+        }
+
+        #[test]
+        fn any_string_parses_canonically(data in "\\PC*") {
+            assert_canonical(&data);
+        }
+
+        #[test]
+        fn near_misses_parse_canonically(data in near_miss()) {
+            assert_canonical(&data);
+        }
+
+        #[test]
+        fn non_ascii_is_rejected(
+            room_id in any::<u32>(),
+            command in command(),
+            position in any::<prop::sample::Index>(),
+            letter in any::<char>().prop_filter("non-ASCII", |letter| !letter.is_ascii()),
+        ) {
+            let mut data = serialize_command(RoomId(room_id), command);
+            data.insert(position.index(data.len() + 1), letter);
+            prop_assert!(parse_command(data.clone()).is_none(), "{:?} parsed", data);
+            prop_assert!(parse_user_command(&data).is_none(), "{:?} parsed", data);
+        }
+    }
+
+    #[test]
+    fn malformed_commands_are_rejected() {
+        for data in [
+            "",
+            " ",
+            "play",
+            "play 1 ",
+            " play 1",
+            "play  1",
+            "play 1 2",
+            "play -1",
+            "play +1",
+            "play 01",
+            "play 1x",
+            "play 99999999999999999999999",
+            "join 1",
+            "join 1 -1",
+            "join 1 07",
+            &format!("join 1 {}", MAX_NUMBER_OF_TEAMS),
+            &format!("round_difficulty 1 {}", MAX_NUMBER_OF_ROUNDS),
+            "draw 1 0",
+            "draw 1 4",
+            "more_complexity 1",
+            "captain 1 abc",
+            "unknown 1",
+        ] {
+            assert!(
+                parse_command(data.to_owned()).is_none(),
+                "{:?} parsed",
+                data
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_user_commands_are_rejected() {
+        for data in [
+            "",
+            "setup_back ",
+            "setup_back 1",
+            "tutorial",
+            "tutorial 01",
+            &format!("tutorial {}", NUMBER_OF_STEPS),
+            "notification 99",
+            "use_taboo_words 2",
+            "approve_word 1 0",
+            "reject_word -1",
+            "switch 1 2",
+        ] {
+            assert!(parse_user_command(data).is_none(), "{:?} parsed", data);
+        }
+    }
+
+    #[test]
+    fn field_parsing_is_canonical() {
+        assert_eq!(parse_field::<usize>("0"), Some(0));
+        assert_eq!(parse_field::<usize>("10"), Some(10));
+        assert_eq!(parse_field::<usize>("00"), None);
+        assert_eq!(parse_field::<usize>("010"), None);
+        assert_eq!(parse_field::<usize>("+1"), None);
+        assert_eq!(parse_field::<usize>(""), None);
+        assert_eq!(parse_field::<u8>("256"), None);
+        assert_eq!(split_fields("a b"), Some(vec!["a", "b"]));
+        assert_eq!(split_fields("a  b"), None);
+        assert_eq!(split_fields("a b "), None);
+    }
+}
@@ -1,6 +1,20 @@
-use teloxide::{dispatching::dialogue::InMemStorage, prelude::*};
+use std::sync::Arc;
 
-use crate::HandlerResult;
+use teloxide::{
+    dispatching::dialogue::InMemStorage,
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId},
+};
+
+use crate::{
+    callback_query_command::{serialize_user_command, UserCbQueryCommand},
+    persistence,
+    room::{Room, RoomConfig, RoomId, MAX_NUMBER_OF_ROUNDS, MAX_NUMBER_OF_TEAMS},
+    telegram::topic,
+    users,
+    words::Word,
+    HandlerResult,
+};
 
 #[derive(Clone, Default)]
 pub enum State {
@@ -17,39 +31,210 @@ pub enum State {
     ReceiveTabooWords {
         number_of_teams: u8,
         number_of_rounds: u8,
-        round_duration: u8,
+        round_duration_in_seconds: u16,
+    },
+    ReceiveCustomCard {
+        room_id: RoomId,
+    },
+    ReceiveInvitees {
+        room_id: RoomId,
     },
 }
 
 pub type MyDialogue = Dialogue<State, InMemStorage<State>>;
 
+/// The setup step before `state`, if it has one.
+fn previous(state: &State) -> Option<State> {
+    match *state {
+        State::ReceiveNumberOfRounds { .. } => Some(State::ReceiveNumberOfTeams),
+        State::ReceiveRoundDuration {
+            number_of_teams, ..
+        } => Some(State::ReceiveNumberOfRounds { number_of_teams }),
+        State::ReceiveTabooWords {
+            number_of_teams,
+            number_of_rounds,
+            ..
+        } => Some(State::ReceiveRoundDuration {
+            number_of_teams,
+            number_of_rounds,
+        }),
+        State::Initial
+        | State::ReceiveNumberOfTeams
+        | State::ReceiveCustomCard { .. }
+        | State::ReceiveInvitees { .. } => None,
+    }
+}
+
+/// Sends the question of a setup step, with a button back to the previous
+/// one.
+pub async fn ask(bot: &Bot, chat_id: ChatId, topic: Option<i32>, state: &State) -> HandlerResult {
+    let (question, mut keyboard) = match state {
+        State::ReceiveNumberOfTeams => (
+            format!(
+                "How many teams are playing?\n(2 to {})",
+                MAX_NUMBER_OF_TEAMS
+            ),
+            Vec::new(),
+        ),
+        State::ReceiveNumberOfRounds { .. } => (
+            format!(
+                "How many rounds are you going to play?\n(1 to {})",
+                MAX_NUMBER_OF_ROUNDS
+            ),
+            Vec::new(),
+        ),
+        State::ReceiveRoundDuration { .. } => (
+            "How long is each round?\n(in minutes up to 10, or like 1:30 or 90s)".to_owned(),
+            Vec::new(),
+        ),
+        State::ReceiveTabooWords { .. } => (
+            "Include taboo words?".to_owned(),
+            vec![[true, false]
+                .map(|use_taboo_words| {
+                    InlineKeyboardButton::callback(
+                        if use_taboo_words { "Yes" } else { "No" },
+                        serialize_user_command(UserCbQueryCommand::TabooWords { use_taboo_words }),
+                    )
+                })
+                .to_vec()],
+        ),
+        State::Initial | State::ReceiveCustomCard { .. } | State::ReceiveInvitees { .. } => {
+            return Ok(())
+        }
+    };
+
+    if previous(state).is_some() {
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            "⬅️ Back",
+            serialize_user_command(UserCbQueryCommand::SetupBack),
+        )]);
+    }
+
+    let mut request = bot.send_message(chat_id, question);
+    if let Some(topic) = topic {
+        request = request.message_thread_id(topic);
+    }
+    if !keyboard.is_empty() {
+        request = request.reply_markup(InlineKeyboardMarkup::new(keyboard));
+    }
+    request.await?;
+    Ok(())
+}
+
+/// Returns to the previous setup step and asks its question again.
+pub async fn handle_setup_back(
+    bot: Bot,
+    q: CallbackQuery,
+    storage: Arc<InMemStorage<State>>,
+) -> HandlerResult {
+    let Some(message) = q.message else {
+        return Ok(());
+    };
+
+    let dialogue = MyDialogue::new(storage, message.chat.id);
+    let Some(state) = dialogue.get().await?.as_ref().and_then(previous) else {
+        return Ok(());
+    };
+
+    bot.edit_message_reply_markup(message.chat.id, message.id)
+        .await?;
+    dialogue.update(state.clone()).await?;
+    ask(&bot, message.chat.id, topic(&message), &state).await
+}
+
+/// Answers a setup message in the forum topic it was sent in.
+fn reply(bot: &Bot, msg: &Message, text: impl Into<String>) -> <Bot as Requester>::SendMessage {
+    let request = bot.send_message(msg.chat.id, text);
+    match topic(msg) {
+        Some(topic) => request.message_thread_id(topic),
+        None => request,
+    }
+}
+
+/// Numbers spelled out, in English and Persian, starting from one.
+const NUMBER_WORDS: [[&str; 2]; 10] = [
+    ["one", "یک"],
+    ["two", "دو"],
+    ["three", "سه"],
+    ["four", "چهار"],
+    ["five", "پنج"],
+    ["six", "شش"],
+    ["seven", "هفت"],
+    ["eight", "هشت"],
+    ["nine", "نه"],
+    ["ten", "ده"],
+];
+
+/// Reads Persian and Arabic-Indic digits as their ASCII counterparts.
+fn to_ascii_digit(c: char) -> char {
+    match c {
+        '۰'..='۹' => char::from(b'0' + (c as u32 - '۰' as u32) as u8),
+        '٠'..='٩' => char::from(b'0' + (c as u32 - '٠' as u32) as u8),
+        c => c,
+    }
+}
+
+fn parse_number_text(text: &str) -> Option<u16> {
+    let text = text.trim().to_lowercase();
+    if let Some(position) = NUMBER_WORDS
+        .iter()
+        .position(|words| words.contains(&text.as_str()))
+    {
+        return Some(position as u16 + 1);
+    }
+    text.chars()
+        .map(to_ascii_digit)
+        .collect::<String>()
+        .parse::<u16>()
+        .ok()
+}
+
 fn parse_number(msg: &Message) -> Option<u8> {
-    let text = msg.text()?;
-    text.parse::<u8>().ok()
+    u8::try_from(parse_number_text(msg.text()?)?).ok()
+}
+
+/// Reads a duration in seconds, written as minutes ("2"), minutes and
+/// seconds ("1:30") or with a unit ("90s", "2m").
+fn parse_duration(msg: &Message) -> Option<u16> {
+    let text = msg.text()?.trim().to_lowercase();
+    if let Some((minutes, seconds)) = text.split_once(':') {
+        let seconds = parse_number_text(seconds)?;
+        if seconds >= 60 {
+            return None;
+        }
+        return parse_number_text(minutes)?
+            .checked_mul(60)?
+            .checked_add(seconds);
+    }
+    if let Some(seconds) = text.strip_suffix('s') {
+        return parse_number_text(seconds);
+    }
+    let minutes = text.strip_suffix('m').unwrap_or(&text);
+    parse_number_text(minutes)?.checked_mul(60)
 }
 
 pub async fn get_number_of_teams(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
     let Some(number_of_teams) = parse_number(&msg) else {
-        bot.send_message(msg.chat.id, "Please send a number!")
-            .await?;
+        reply(&bot, &msg, "Please send a number!").await?;
         return Ok(());
     };
 
-    if !(2..=7).contains(&number_of_teams) {
-        bot.send_message(msg.chat.id, "Number of teams should be between 2 and 7")
-            .await?;
+    if !(2..=MAX_NUMBER_OF_TEAMS).contains(&(number_of_teams as usize)) {
+        reply(
+            &bot,
+            &msg,
+            format!(
+                "Number of teams should be between 2 and {}",
+                MAX_NUMBER_OF_TEAMS
+            ),
+        )
+        .await?;
         return Ok(());
     }
 
-    dialogue
-        .update(State::ReceiveNumberOfRounds { number_of_teams })
-        .await?;
-    bot.send_message(
-        msg.chat.id,
-        "How many rounds are you going to play?\n(1 to 7)",
-    )
-    .await?;
-    Ok(())
+    let state = State::ReceiveNumberOfRounds { number_of_teams };
+    dialogue.update(state.clone()).await?;
+    ask(&bot, msg.chat.id, topic(&msg), &state).await
 }
 
 pub async fn get_number_of_rounds(
@@ -59,31 +244,29 @@ pub async fn get_number_of_rounds(
     msg: Message,
 ) -> HandlerResult {
     let Some(number_of_rounds) = parse_number(&msg) else {
-        bot.send_message(msg.chat.id, "Please send a number!")
-            .await?;
+        reply(&bot, &msg, "Please send a number!").await?;
         return Ok(());
     };
 
-    if !(1..=7).contains(&number_of_rounds) {
-        bot.send_message(msg.chat.id, "Number of rounds should be between 1 and 7")
-            .await?;
+    if !(1..=MAX_NUMBER_OF_ROUNDS).contains(&(number_of_rounds as usize)) {
+        reply(
+            &bot,
+            &msg,
+            format!(
+                "Number of rounds should be between 1 and {}",
+                MAX_NUMBER_OF_ROUNDS
+            ),
+        )
+        .await?;
         return Ok(());
     }
 
-    dialogue
-        .update(State::ReceiveRoundDuration {
-            number_of_teams,
-            number_of_rounds,
-        })
-        .await?;
-
-    bot.send_message(
-        msg.chat.id,
-        "How long is each round?\n(in minutes up to 10)",
-    )
-    .await?;
-
-    Ok(())
+    let state = State::ReceiveRoundDuration {
+        number_of_teams,
+        number_of_rounds,
+    };
+    dialogue.update(state.clone()).await?;
+    ask(&bot, msg.chat.id, topic(&msg), &state).await
 }
 
 pub async fn get_round_duration(
@@ -92,75 +275,215 @@ pub async fn get_round_duration(
     (number_of_teams, number_of_rounds): (u8, u8),
     msg: Message,
 ) -> HandlerResult {
-    let Some(round_duration) = parse_number(&msg) else {
-        bot.send_message(msg.chat.id, "Please send a number!")
-            .await?;
+    let Some(round_duration_in_seconds) = parse_duration(&msg) else {
+        reply(&bot, &msg, "Please send a duration, e.g. 2, 1:30 or 90s").await?;
         return Ok(());
     };
 
-    if !(1..=10).contains(&round_duration) {
-        bot.send_message(
-            msg.chat.id,
-            "Round duration should be between 1 minute and 10 minutes",
+    if !(30..=600).contains(&round_duration_in_seconds) {
+        reply(
+            &bot,
+            &msg,
+            "Round duration should be between 30 seconds and 10 minutes",
         )
         .await?;
         return Ok(());
     }
 
-    dialogue
-        .update(State::ReceiveTabooWords {
-            number_of_teams,
-            number_of_rounds,
-            round_duration,
-        })
+    let state = State::ReceiveTabooWords {
+        number_of_teams,
+        number_of_rounds,
+        round_duration_in_seconds,
+    };
+    dialogue.update(state.clone()).await?;
+    ask(&bot, msg.chat.id, topic(&msg), &state).await
+}
+
+/// Reads a card written for the other teams: the word on the first line and
+/// its taboo words on the following ones.
+pub async fn get_custom_card(
+    bot: Bot,
+    dialogue: MyDialogue,
+    room_id: RoomId,
+    rooms: crate::Rooms,
+    msg: Message,
+) -> HandlerResult {
+    let mut lines = msg
+        .text()
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    let Some(text) = lines.next() else {
+        bot.send_message(
+            msg.chat.id,
+            "Please send the word followed by its taboo words, one per line",
+        )
         .await?;
+        return Ok(());
+    };
+    let word = Word::custom(text.to_owned(), lines.map(str::to_owned).collect());
+
+    dialogue.update(State::Initial).await?;
 
-    bot.send_message(msg.chat.id, "Include taboo words? (\"Yes\" or \"No\")")
+    let (Some(user), Some(room)) = (msg.from(), rooms.get(&room_id)) else {
+        return Ok(());
+    };
+    let mut room = room.lock().await;
+    crate::game::handle_custom_card(bot, &mut room, user.clone(), word).await?;
+    persistence::save(room_id, &room);
+    Ok(())
+}
+
+/// Reads who the host invites to a lobby: a shared contact, or the usernames
+/// in the text of the message.
+pub async fn get_invitees(
+    bot: Bot,
+    dialogue: MyDialogue,
+    room_id: RoomId,
+    rooms: crate::Rooms,
+    msg: Message,
+) -> HandlerResult {
+    let mut invitees = Vec::new();
+    let mut unknown = Vec::new();
+    if let Some(contact) = msg.contact() {
+        let name = format!(
+            "{} {}",
+            contact.first_name,
+            contact.last_name.clone().unwrap_or_default()
+        );
+        match contact
+            .user_id
+            .filter(|&user_id| users::known(user_id).is_some())
+        {
+            Some(user_id) => invitees.push((user_id, name.trim().to_owned())),
+            None => unknown.push(name.trim().to_owned()),
+        }
+    }
+    for username in msg
+        .text()
+        .unwrap_or_default()
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| word.starts_with('@') && word.len() > 1)
+    {
+        match users::find_by_username(username) {
+            Some(user_id) => invitees.push((user_id, username.to_owned())),
+            None => unknown.push(username.to_owned()),
+        }
+    }
+    if invitees.is_empty() && unknown.is_empty() {
+        bot.send_message(
+            msg.chat.id,
+            "Please send usernames like @alice @bob, or share a contact",
+        )
         .await?;
+        return Ok(());
+    }
+
+    dialogue.update(State::Initial).await?;
+
+    let (Some(user), Some(room)) = (msg.from(), rooms.get(&room_id)) else {
+        return Ok(());
+    };
+    let mut room = room.lock().await;
+    crate::game::handle_invitees(bot, &mut room, room_id, user.clone(), invitees, unknown).await?;
+    persistence::save(room_id, &room);
+    Ok(())
+}
+
+/// Reads a typed answer to a yes or no question, in English or Persian.
+fn parse_yes_no(text: &str) -> Option<bool> {
+    match text.trim().to_lowercase().as_str() {
+        "yes" | "y" | "بله" | "آره" => Some(true),
+        "no" | "n" | "نه" | "خیر" => Some(false),
+        _ => None,
+    }
+}
+
+async fn create_room(
+    bot: Bot,
+    dialogue: MyDialogue,
+    rooms: crate::Rooms,
+    host: UserId,
+    (number_of_teams, number_of_rounds, round_duration_in_seconds): (u8, u8, u16),
+    use_taboo_words: bool,
+    (question, topic): (Option<MessageId>, Option<i32>),
+) -> HandlerResult {
+    dialogue.update(State::Initial).await?;
+
+    let chat_id = dialogue.chat_id();
+    let config = RoomConfig::new(
+        number_of_teams as usize,
+        number_of_rounds as usize,
+        round_duration_in_seconds as usize,
+        use_taboo_words,
+    );
+    let mut room = Room::new(config, (!chat_id.is_user()).then_some(chat_id), host);
+    room.set_group_topic(topic);
+
+    crate::game::handle_new_command(bot, chat_id, rooms, room, question).await?;
 
     Ok(())
 }
 
 pub async fn get_should_use_taboo_words(
     bot: Bot,
-    (number_of_teams, number_of_rounds, round_duration): (u8, u8, u8),
+    dialogue: MyDialogue,
+    settings: (u8, u8, u16),
     rooms: crate::Rooms,
     msg: Message,
 ) -> HandlerResult {
-    let wrong_input_error = "Please send \"Yes\" or \"No\"";
-    let Some(text) = msg.text() else {
-        bot.send_message(msg.chat.id, wrong_input_error).await?;
+    let Some(use_taboo_words) = msg.text().and_then(parse_yes_no) else {
+        reply(&bot, &msg, "Please answer with the buttons").await?;
+        let state = dialogue.get_or_default().await?;
+        return ask(&bot, msg.chat.id, topic(&msg), &state).await;
+    };
+
+    let Some(host) = msg.from() else {
         return Ok(());
     };
+    create_room(
+        bot,
+        dialogue,
+        rooms,
+        host.id,
+        settings,
+        use_taboo_words,
+        (None, topic(&msg)),
+    )
+    .await
+}
 
-    let use_taboo_words = match text {
-        "yes" | "Yes" | "y" | "Y" => true,
-        "no" | "No" | "n" | "N" => false,
-        _ => {
-            bot.send_message(msg.chat.id, wrong_input_error).await?;
-            return Ok(());
-        }
+/// Answers the taboo words question with its buttons.
+pub async fn handle_taboo_words_answer(
+    bot: Bot,
+    q: CallbackQuery,
+    rooms: crate::Rooms,
+    storage: Arc<InMemStorage<State>>,
+    use_taboo_words: bool,
+) -> HandlerResult {
+    let Some(message) = q.message else {
+        return Ok(());
     };
 
-    bot.send_message(
-        msg.chat.id,
-        format!(
-            "You are going to play {} rounds with {} teams, each round will last {} minutes.\nTaboo words are {}.",
-            number_of_rounds, number_of_teams, round_duration, if use_taboo_words { "enabled" } else { "disabled" }
-        ),
-    )
-    .await?;
+    let dialogue = MyDialogue::new(storage, message.chat.id);
+    let Some(State::ReceiveTabooWords {
+        number_of_teams,
+        number_of_rounds,
+        round_duration_in_seconds,
+    }) = dialogue.get().await?
+    else {
+        return Ok(());
+    };
 
-    crate::handle_new_command(
+    create_room(
         bot,
-        msg,
+        dialogue,
         rooms,
-        number_of_teams as usize,
-        number_of_rounds as usize,
-        round_duration as usize,
+        q.from.id,
+        (number_of_teams, number_of_rounds, round_duration_in_seconds),
         use_taboo_words,
+        (Some(message.id), topic(&message)),
     )
-    .await?;
-
-    Ok(())
+    .await
 }
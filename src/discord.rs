@@ -0,0 +1,710 @@
+//! Runs games in Discord servers with the same engine, word bank and storage
+//! as the Telegram bot. Discord posts slash commands and button presses to
+//! the `/discord` endpoint of the webhook server, configured as the
+//! interactions endpoint of the application, and the bot answers through the
+//! REST API.
+//!
+//! Configured with `JIGARPICH_DISCORD_TOKEN`, `JIGARPICH_DISCORD_PUBLIC_KEY`
+//! and `JIGARPICH_DISCORD_APPLICATION_ID`, and only built with the `discord`
+//! feature.
+
+use std::{
+    env, fmt,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    },
+};
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use dashmap::DashMap;
+use openssl::{
+    pkey::{Id, PKey},
+    sign::Verifier,
+};
+use reqwest::{multipart, Method, RequestBuilder};
+use serde_json::{json, Value};
+use teloxide::types::{ChatId, MessageId, User, UserId};
+
+use crate::{
+    bridge::{self, external_id, local_id, Bridge, Platform},
+    callback_query_command::{parse_command, serialize_command, CbQueryCommand},
+    frontend::{Button, Buttons, Frontend},
+    game, persistence,
+    room::{Room, RoomConfig, RoomId, MAX_NUMBER_OF_ROUNDS, MAX_NUMBER_OF_TEAMS},
+    Rooms,
+};
+
+const API_URL: &str = "https://discord.com/api/v10";
+
+/// Discord shows at most five rows of five buttons under a message.
+const MAX_ROWS: usize = 5;
+
+const MAX_BUTTONS_PER_ROW: usize = 5;
+
+const MAX_LABEL_LENGTH: usize = 80;
+
+/// Interactions and responses, see
+/// https://discord.com/developers/docs/interactions/receiving-and-responding
+const PING: u8 = 1;
+const APPLICATION_COMMAND: u8 = 2;
+const MESSAGE_COMPONENT: u8 = 3;
+const PONG: u8 = 1;
+const CHANNEL_MESSAGE: u8 = 4;
+const DEFERRED_UPDATE_MESSAGE: u8 = 6;
+const EPHEMERAL: u32 = 1 << 6;
+
+/// The ID of a Discord user or channel known to the engine by `chat_id`.
+fn snowflake(chat_id: ChatId) -> Result<u64, Error> {
+    match external_id(chat_id) {
+        Some((Platform::Discord, snowflake)) => {
+            snowflake.parse().map_err(|_| Error::UnknownChat(chat_id))
+        }
+        _ => Err(Error::UnknownChat(chat_id)),
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Request(reqwest::Error),
+    /// A message sent before the last restart, Discord IDs of messages are
+    /// only kept in memory.
+    UnknownMessage(MessageId),
+    UnknownChat(ChatId),
+    BadResponse,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Request(err) => write!(f, "{}", err),
+            Error::UnknownMessage(message_id) => write!(f, "Unknown message {}", message_id.0),
+            Error::UnknownChat(chat_id) => write!(f, "Unknown chat {}", chat_id),
+            Error::BadResponse => write!(f, "Unexpected response from Discord"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Request(err)
+    }
+}
+
+struct Inner {
+    token: String,
+    public_key: Vec<u8>,
+    application_id: String,
+    client: reqwest::Client,
+    dm_channels: DashMap<UserId, u64>,
+    /// The engine counts message IDs in 32 bits, Discord's are mapped onto
+    /// local ones.
+    messages: DashMap<MessageId, (u64, u64)>,
+    message_ids: DashMap<u64, MessageId>,
+    next_message_id: AtomicI32,
+}
+
+#[derive(Clone)]
+pub struct Discord(Arc<Inner>);
+
+impl Discord {
+    pub fn from_env() -> Option<Self> {
+        let token = env::var("JIGARPICH_DISCORD_TOKEN").ok()?;
+        let public_key = env::var("JIGARPICH_DISCORD_PUBLIC_KEY")
+            .ok()
+            .and_then(|key| from_hex(&key))
+            .expect("JIGARPICH_DISCORD_PUBLIC_KEY must be set along with JIGARPICH_DISCORD_TOKEN");
+        let application_id = env::var("JIGARPICH_DISCORD_APPLICATION_ID").expect(
+            "JIGARPICH_DISCORD_APPLICATION_ID must be set along with JIGARPICH_DISCORD_TOKEN",
+        );
+
+        Some(Discord(Arc::new(Inner {
+            token,
+            public_key,
+            application_id,
+            client: reqwest::Client::new(),
+            dm_channels: DashMap::new(),
+            messages: DashMap::new(),
+            message_ids: DashMap::new(),
+            next_message_id: AtomicI32::new(1),
+        })))
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.0
+            .client
+            .request(method, format!("{}{}", API_URL, path))
+            .header("Authorization", format!("Bot {}", self.0.token))
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Value, Error> {
+        let response = request.send().await?.error_for_status()?;
+        if response.status() == StatusCode::NO_CONTENT {
+            return Ok(Value::Null);
+        }
+        Ok(response.json().await?)
+    }
+
+    /// The channel messages to `chat_id` go to, opening a direct message
+    /// channel for users.
+    async fn channel(&self, chat_id: ChatId) -> Result<u64, Error> {
+        if !chat_id.is_user() {
+            return snowflake(chat_id);
+        }
+
+        let user_id = UserId(chat_id.0 as u64);
+        if let Some(channel) = self.0.dm_channels.get(&user_id).map(|channel| *channel) {
+            return Ok(channel);
+        }
+        let response = self
+            .send(
+                self.request(Method::POST, "/users/@me/channels")
+                    .json(&json!({ "recipient_id": snowflake(chat_id)?.to_string() })),
+            )
+            .await?;
+        let channel = parse_snowflake(&response["id"]).ok_or(Error::BadResponse)?;
+        self.0.dm_channels.insert(user_id, channel);
+        Ok(channel)
+    }
+
+    fn remember(&self, channel: u64, message: u64) -> MessageId {
+        let message_id = MessageId(self.0.next_message_id.fetch_add(1, Ordering::Relaxed));
+        self.0.messages.insert(message_id, (channel, message));
+        self.0.message_ids.insert(message, message_id);
+        message_id
+    }
+
+    fn message(&self, message_id: MessageId) -> Result<(u64, u64), Error> {
+        self.0
+            .messages
+            .get(&message_id)
+            .map(|message| *message)
+            .ok_or(Error::UnknownMessage(message_id))
+    }
+
+    async fn post_message(&self, to: ChatId, body: Value) -> Result<(ChatId, MessageId), Error> {
+        let channel = self.channel(to).await?;
+        let response = self
+            .send(
+                self.request(Method::POST, &format!("/channels/{}/messages", channel))
+                    .json(&body),
+            )
+            .await?;
+        let message = parse_snowflake(&response["id"]).ok_or(Error::BadResponse)?;
+        Ok((to, self.remember(channel, message)))
+    }
+
+    async fn patch_message(&self, message_id: MessageId, body: Value) -> Result<(), Error> {
+        let (channel, message) = self.message(message_id)?;
+        self.send(
+            self.request(
+                Method::PATCH,
+                &format!("/channels/{}/messages/{}", channel, message),
+            )
+            .json(&body),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn upload(
+        &self,
+        to: ChatId,
+        file_name: &str,
+        file: Vec<u8>,
+        content: String,
+    ) -> Result<(), Error> {
+        let channel = self.channel(to).await?;
+        let form = multipart::Form::new()
+            .text("payload_json", json!({ "content": content }).to_string())
+            .part(
+                "files[0]",
+                multipart::Part::bytes(file).file_name(file_name.to_owned()),
+            );
+        self.send(
+            self.request(Method::POST, &format!("/channels/{}/messages", channel))
+                .multipart(form),
+        )
+        .await?;
+        Ok(())
+    }
+
+    fn verify(&self, headers: &HeaderMap, body: &[u8]) -> bool {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let (Some(signature), Some(timestamp)) = (
+            header("X-Signature-Ed25519").and_then(from_hex),
+            header("X-Signature-Timestamp"),
+        ) else {
+            return false;
+        };
+
+        let Ok(key) = PKey::public_key_from_raw_bytes(&self.0.public_key, Id::ED25519) else {
+            return false;
+        };
+        let Ok(mut verifier) = Verifier::new_without_digest(&key) else {
+            return false;
+        };
+        let message = [timestamp.as_bytes(), body].concat();
+        verifier
+            .verify_oneshot(&signature, &message)
+            .unwrap_or(false)
+    }
+
+    /// Registers `/new` and `/join` as slash commands of the application.
+    pub async fn register_commands(self) {
+        let integer = |name: &str, description: &str, range: Option<(usize, usize)>| {
+            let mut option = json!({
+                "type": 4,
+                "name": name,
+                "description": description,
+                "required": true,
+            });
+            if let Some((min, max)) = range {
+                option["min_value"] = json!(min);
+                option["max_value"] = json!(max);
+            }
+            option
+        };
+        let commands = json!([
+            {
+                "name": "new",
+                "description": "Create a new room",
+                "options": [
+                    integer("teams", "How many teams are playing", Some((2, MAX_NUMBER_OF_TEAMS))),
+                    integer("rounds", "How many rounds you play", Some((1, MAX_NUMBER_OF_ROUNDS))),
+                    integer("minutes", "How long each round is", Some((1, 10))),
+                    {
+                        "type": 5,
+                        "name": "taboo",
+                        "description": "Include taboo words",
+                    },
+                ],
+            },
+            {
+                "name": "join",
+                "description": "Join a room",
+                "options": [integer("room", "The number of the room", None)],
+            },
+        ]);
+
+        let path = format!("/applications/{}/commands", self.0.application_id);
+        if let Err(err) = self
+            .send(self.request(Method::PUT, &path).json(&commands))
+            .await
+        {
+            log::warn!("Can not register Discord commands: {}", err);
+        }
+    }
+}
+
+fn parse_snowflake(value: &Value) -> Option<u64> {
+    value.as_str()?.parse().ok()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect()
+}
+
+fn button(button: Button) -> Value {
+    json!({
+        "type": 2,
+        "style": 2,
+        "label": button.label.chars().take(MAX_LABEL_LENGTH).collect::<String>(),
+        "custom_id": serialize_command(button.room_id, button.command),
+    })
+}
+
+/// Lays the buttons out in rows Discord accepts, packing them tighter when
+/// there are too many rows.
+fn components(buttons: Buttons) -> Value {
+    let fits =
+        buttons.len() <= MAX_ROWS && buttons.iter().all(|row| row.len() <= MAX_BUTTONS_PER_ROW);
+    let rows = if fits {
+        buttons
+    } else {
+        let mut buttons = buttons.into_iter().flatten().peekable();
+        let mut rows = Vec::new();
+        while buttons.peek().is_some() {
+            rows.push(buttons.by_ref().take(MAX_BUTTONS_PER_ROW).collect());
+        }
+        rows
+    };
+    if rows.len() > MAX_ROWS {
+        log::warn!(
+            "Dropping {} rows of buttons on Discord",
+            rows.len() - MAX_ROWS
+        );
+    }
+
+    rows.into_iter()
+        .take(MAX_ROWS)
+        .map(|row| {
+            json!({
+                "type": 1,
+                "components": row.into_iter().map(button).collect::<Vec<_>>(),
+            })
+        })
+        .collect()
+}
+
+impl Frontend for Discord {
+    type Error = Error;
+
+    async fn send_card_to_topic(
+        &self,
+        to: ChatId,
+        _topic: Option<i32>,
+        text: String,
+        buttons: Buttons,
+    ) -> Result<(ChatId, MessageId), Self::Error> {
+        self.post_message(
+            to,
+            json!({ "content": text, "components": components(buttons) }),
+        )
+        .await
+    }
+
+    async fn edit_buttons(
+        &self,
+        _chat_id: ChatId,
+        message_id: MessageId,
+        buttons: Buttons,
+    ) -> Result<(), Self::Error> {
+        self.patch_message(message_id, json!({ "components": components(buttons) }))
+            .await
+    }
+
+    async fn edit_text(
+        &self,
+        _chat_id: ChatId,
+        message_id: MessageId,
+        text: String,
+    ) -> Result<(), Self::Error> {
+        self.patch_message(message_id, json!({ "content": text }))
+            .await
+    }
+
+    async fn edit_card(
+        &self,
+        _chat_id: ChatId,
+        message_id: MessageId,
+        text: String,
+        buttons: Buttons,
+    ) -> Result<(), Self::Error> {
+        self.patch_message(
+            message_id,
+            json!({ "content": text, "components": components(buttons) }),
+        )
+        .await
+    }
+
+    async fn pin_message(
+        &self,
+        _chat_id: ChatId,
+        message_id: MessageId,
+    ) -> Result<(), Self::Error> {
+        let (channel, message) = self.message(message_id)?;
+        self.send(self.request(
+            Method::PUT,
+            &format!("/channels/{}/pins/{}", channel, message),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn prompt_to_topic(
+        &self,
+        to: ChatId,
+        _topic: Option<i32>,
+        text: String,
+    ) -> Result<(), Self::Error> {
+        self.post_message(to, json!({ "content": text })).await?;
+        Ok(())
+    }
+
+    async fn prompt_with_spoiler(
+        &self,
+        to: ChatId,
+        text: String,
+        spoiler: String,
+    ) -> Result<(), Self::Error> {
+        let content = format!("{}||{}||", text, spoiler);
+        self.post_message(to, json!({ "content": content })).await?;
+        Ok(())
+    }
+
+    async fn join_link(&self, _room_id: RoomId) -> Result<Option<String>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn send_image(
+        &self,
+        to: ChatId,
+        _topic: Option<i32>,
+        png: Vec<u8>,
+        caption: String,
+    ) -> Result<(), Self::Error> {
+        self.upload(to, "scoreboard.png", png, caption).await
+    }
+
+    async fn send_sound(
+        &self,
+        to: ChatId,
+        _topic: Option<i32>,
+        ogg: Vec<u8>,
+    ) -> Result<(), Self::Error> {
+        self.upload(to, "alert.ogg", ogg, String::new()).await
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct DiscordUser {
+    id: String,
+    username: String,
+    global_name: Option<String>,
+}
+
+impl DiscordUser {
+    fn to_user(&self) -> Option<User> {
+        Some(User {
+            id: UserId(local_id(Platform::Discord, &self.id, false) as u64),
+            is_bot: false,
+            first_name: self
+                .global_name
+                .clone()
+                .unwrap_or_else(|| self.username.clone()),
+            last_name: None,
+            username: Some(self.username.clone()),
+            language_code: None,
+            is_premium: false,
+            added_to_attachment_menu: false,
+        })
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct Member {
+    user: DiscordUser,
+}
+
+#[derive(serde::Deserialize)]
+struct CommandOption {
+    name: String,
+    value: Value,
+}
+
+#[derive(serde::Deserialize)]
+struct InteractionData {
+    name: Option<String>,
+    custom_id: Option<String>,
+    #[serde(default)]
+    options: Vec<CommandOption>,
+}
+
+impl InteractionData {
+    fn option(&self, name: &str) -> Option<&Value> {
+        self.options
+            .iter()
+            .find(|option| option.name == name)
+            .map(|option| &option.value)
+    }
+
+    fn number(&self, name: &str) -> Option<usize> {
+        self.option(name)?.as_u64().map(|number| number as usize)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct InteractionMessage {
+    id: String,
+}
+
+#[derive(serde::Deserialize)]
+struct Interaction {
+    #[serde(rename = "type")]
+    kind: u8,
+    channel_id: Option<String>,
+    guild_id: Option<String>,
+    member: Option<Member>,
+    user: Option<DiscordUser>,
+    data: Option<InteractionData>,
+    message: Option<InteractionMessage>,
+}
+
+#[derive(Clone)]
+struct DiscordState {
+    discord: Discord,
+    frontend: Bridge,
+    rooms: Rooms,
+}
+
+pub fn router(discord: Discord, frontend: Bridge, rooms: Rooms) -> Router {
+    Router::new()
+        .route("/discord", post(handle_interaction))
+        .with_state(DiscordState {
+            discord,
+            frontend,
+            rooms,
+        })
+}
+
+/// Answers Discord right away, as it expects within three seconds, and plays
+/// the interaction out in the background.
+async fn handle_interaction(
+    State(state): State<DiscordState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Value>, StatusCode> {
+    if !state.discord.verify(&headers, &body) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    let interaction =
+        serde_json::from_slice::<Interaction>(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    if interaction.kind == PING {
+        return Ok(Json(json!({ "type": PONG })));
+    }
+
+    let user = interaction
+        .member
+        .as_ref()
+        .map(|member| &member.user)
+        .or(interaction.user.as_ref())
+        .and_then(DiscordUser::to_user)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let channel = interaction
+        .channel_id
+        .as_ref()
+        .and_then(|channel| channel.parse::<u64>().ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let chat_id = match interaction.guild_id {
+        Some(_) => ChatId(local_id(Platform::Discord, &channel.to_string(), true)),
+        None => {
+            state.discord.0.dm_channels.insert(user.id, channel);
+            user.id.into()
+        }
+    };
+    let data = interaction.data.ok_or(StatusCode::BAD_REQUEST)?;
+
+    match interaction.kind {
+        APPLICATION_COMMAND => {
+            tokio::spawn(async move {
+                if let Err(err) = run_command(state, chat_id, user, data).await {
+                    log::warn!("Can not run Discord command: {}", err);
+                }
+            });
+            Ok(Json(json!({
+                "type": CHANNEL_MESSAGE,
+                "data": { "content": "👌", "flags": EPHEMERAL },
+            })))
+        }
+        MESSAGE_COMPONENT => {
+            let card = interaction
+                .message
+                .and_then(|message| message.id.parse::<u64>().ok())
+                .and_then(|message| state.discord.0.message_ids.get(&message).map(|id| *id))
+                .map(|message_id| (chat_id, message_id));
+            tokio::spawn(async move {
+                if let Err(err) = press_button(state, user, data, card).await {
+                    log::warn!("Can not handle Discord button: {}", err);
+                }
+            });
+            Ok(Json(json!({ "type": DEFERRED_UPDATE_MESSAGE })))
+        }
+        _ => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+async fn run_command(
+    state: DiscordState,
+    chat_id: ChatId,
+    user: User,
+    data: InteractionData,
+) -> Result<(), bridge::Error> {
+    match data.name.as_deref() {
+        Some("new") => {
+            let (Some(teams), Some(rounds), Some(minutes)) = (
+                data.number("teams"),
+                data.number("rounds"),
+                data.number("minutes"),
+            ) else {
+                return Ok(());
+            };
+            let use_taboo_words = data
+                .option("taboo")
+                .and_then(Value::as_bool)
+                .unwrap_or(true);
+            let config = RoomConfig::new(teams, rounds, minutes * 60, use_taboo_words);
+            let room = Room::new(config, (!chat_id.is_user()).then_some(chat_id), user.id);
+            game::handle_new_command(state.frontend, chat_id, state.rooms, room, None).await
+        }
+        Some("join") => {
+            let Some(room_id) = data.number("room") else {
+                return Ok(());
+            };
+            let room_id = RoomId(room_id as u32);
+            game::handle_join_command(state.frontend, chat_id, state.rooms, user, room_id).await
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn press_button(
+    state: DiscordState,
+    user: User,
+    data: InteractionData,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<(), bridge::Error> {
+    let Some((room_id, command)) = data.custom_id.and_then(parse_command) else {
+        return Ok(());
+    };
+
+    if matches!(command, CbQueryCommand::WriteCard) {
+        return state
+            .frontend
+            .prompt(
+                user.id.into(),
+                "✍️ Custom cards can only be written on Telegram for now.".to_owned(),
+            )
+            .await;
+    }
+
+    let Some(room) = state.rooms.get(&room_id) else {
+        return Ok(());
+    };
+    let mut room = room.lock().await;
+    let user_id = user.id;
+    game::handle_room_command(
+        state.frontend.clone(),
+        state.rooms.clone(),
+        &mut room,
+        room_id,
+        command,
+        user,
+        card,
+    )
+    .await?;
+    persistence::save(room_id, &room);
+    // The press is already answered, so notices come as a message
+    if let Some(toast) = game::take_toast(user_id) {
+        state
+            .frontend
+            .prompt(user_id.into(), toast.to_owned())
+            .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,216 @@
+//! Standing duos: two users who play together register once with `/duo`,
+//! get seated in the same team of the lobbies they join and build up stats
+//! and a rating over the games they play as a pair.
+
+use std::sync::OnceLock;
+
+use teloxide::prelude::*;
+
+use crate::{
+    ratings::{self, DEFAULT_RATING},
+    storage::Store,
+    users, HandlerResult,
+};
+
+const USAGE: &str = "Send /duo @partner to play as a standing duo, reply to one of their \
+                     messages with /duo, or send /duo off to split up.";
+
+/// Kept for both members, with the other one as the partner.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Duo {
+    pub partner: UserId,
+    pub games: usize,
+    pub wins: usize,
+    pub rating: f64,
+}
+
+static DUOS: OnceLock<Store<UserId, Duo>> = OnceLock::new();
+
+/// Who each user asked to form a duo with, until the partner asks back.
+static REQUESTS: OnceLock<Store<UserId, UserId>> = OnceLock::new();
+
+fn duos() -> &'static Store<UserId, Duo> {
+    DUOS.get_or_init(|| Store::open("duos"))
+}
+
+fn requests() -> &'static Store<UserId, UserId> {
+    REQUESTS.get_or_init(|| Store::open("duo_requests"))
+}
+
+pub fn duo_of(user_id: UserId) -> Option<Duo> {
+    duos().get(&user_id)
+}
+
+pub fn partner_of(user_id: UserId) -> Option<UserId> {
+    duo_of(user_id).map(|duo| duo.partner)
+}
+
+/// The rating of the pair if they are a duo.
+fn rating_of(first: UserId, second: UserId) -> Option<f64> {
+    duo_of(first)
+        .filter(|duo| duo.partner == second)
+        .map(|duo| duo.rating)
+}
+
+fn split(user_id: UserId) -> Option<UserId> {
+    let partner = partner_of(user_id)?;
+    duos().remove(&user_id);
+    if duos().contains(&partner) {
+        duos().remove(&partner);
+    }
+    Some(partner)
+}
+
+pub fn forget(user_id: UserId) {
+    split(user_id);
+    if requests().contains(&user_id) {
+        requests().remove(&user_id);
+    }
+}
+
+/// Counts a finished game for the duos among its pairs, given in the order
+/// of the teams with the position of the winner. Teams that aren't a duo are
+/// rated as new ones.
+pub fn record_game(pairs: &[(UserId, UserId)], winner: Option<usize>) {
+    let duo_ratings = pairs
+        .iter()
+        .map(|&(first, second)| rating_of(first, second))
+        .collect::<Vec<_>>();
+    let team_ratings = duo_ratings
+        .iter()
+        .map(|rating| rating.unwrap_or(DEFAULT_RATING))
+        .collect::<Vec<_>>();
+    let changes = ratings::changes(&team_ratings, winner);
+
+    for (team, &(first, second)) in pairs.iter().enumerate() {
+        if duo_ratings[team].is_none() {
+            continue;
+        }
+        let change = changes[team];
+        for member in [first, second] {
+            if let Some(mut duo) = duo_of(member) {
+                duo.games += 1;
+                duo.wins += usize::from(winner == Some(team));
+                duo.rating += change;
+                duos().insert(member, duo);
+            }
+        }
+    }
+}
+
+/// The partner named in the command: the author of the replied message, a
+/// `@username` or a user ID of someone who talked to the bot.
+fn named_partner(msg: &Message, arguments: &str) -> Option<UserId> {
+    if let Some(author) = msg.reply_to_message().and_then(Message::from) {
+        return Some(author.id);
+    }
+    let argument = arguments.trim();
+    if argument.starts_with('@') {
+        return users::find_by_username(argument);
+    }
+    let user_id = UserId(argument.parse().ok()?);
+    users::known(user_id).map(|_| user_id)
+}
+
+fn status(duo: &Duo) -> String {
+    let partner = users::known(duo.partner)
+        .map(|known| known.name)
+        .unwrap_or_else(|| "your partner".to_owned());
+    format!(
+        "🤝 You play as a duo with {}: {} games, {} wins, rated {:.0}.\n\n\
+         Send /duo off to split up.",
+        partner, duo.games, duo.wins, duo.rating
+    )
+}
+
+pub async fn handle_duo_command(bot: Bot, msg: Message, arguments: String) -> HandlerResult {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    if arguments.trim().eq_ignore_ascii_case("off") {
+        let text = match split(user.id) {
+            Some(partner) => {
+                let text = format!("{} split up your duo.", user.full_name());
+                if let Err(err) = bot.send_message(partner, text).await {
+                    log::warn!("Can not tell the partner about the split: {}", err);
+                }
+                "👋 You're no longer a duo."
+            }
+            None => "You're not in a duo.",
+        };
+        bot.send_message(msg.chat.id, text).await?;
+        return Ok(());
+    }
+
+    if let Some(duo) = duo_of(user.id) {
+        bot.send_message(msg.chat.id, status(&duo)).await?;
+        return Ok(());
+    }
+
+    let Some(partner) = named_partner(&msg, &arguments) else {
+        let text = if arguments.trim().is_empty() {
+            USAGE
+        } else {
+            "I don't know them yet, they need to talk to the bot first."
+        };
+        bot.send_message(msg.chat.id, text).await?;
+        return Ok(());
+    };
+    if partner == user.id {
+        bot.send_message(msg.chat.id, "You can't form a duo with yourself!")
+            .await?;
+        return Ok(());
+    }
+    if duos().contains(&partner) {
+        bot.send_message(msg.chat.id, "They already play in a duo.")
+            .await?;
+        return Ok(());
+    }
+
+    if requests().get(&partner) != Some(user.id) {
+        requests().insert(user.id, partner);
+        let accept = match &user.username {
+            Some(username) => format!("/duo @{}", username),
+            None => format!("/duo {}", user.id),
+        };
+        let invitation = format!(
+            "🤝 {} wants to play with you as a standing duo. Send {} to accept.",
+            user.full_name(),
+            accept
+        );
+        if let Err(err) = bot.send_message(partner, invitation).await {
+            log::warn!("Can not send the duo request: {}", err);
+        }
+        bot.send_message(
+            msg.chat.id,
+            "📨 Asked them, you're a duo once they ask you back.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    requests().remove(&partner);
+    if requests().contains(&user.id) {
+        requests().remove(&user.id);
+    }
+    for (member, other) in [(user.id, partner), (partner, user.id)] {
+        duos().insert(
+            member,
+            Duo {
+                partner: other,
+                games: 0,
+                wins: 0,
+                rating: DEFAULT_RATING,
+            },
+        );
+    }
+
+    let text = "🤝 You're a standing duo now! You'll be seated together in the lobbies you \
+                both join.";
+    if let Err(err) = bot.send_message(partner, text).await {
+        log::warn!("Can not tell the partner about the duo: {}", err);
+    }
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Forwards problems that can leave a game stuck to the maintainers, in the
+//! admin chat and to Sentry when `JIGARPICH_SENTRY_DSN` is set, so they hear
+//! about them before the players complain.
+
+use std::{
+    env,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use reqwest::Url;
+use serde_json::json;
+use teloxide::prelude::*;
+
+use crate::{admin::admin_chat, room::RoomId};
+
+/// The same problem in the same room is forwarded at most this often.
+const REPORT_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+static BOT: OnceLock<Bot> = OnceLock::new();
+
+static SENTRY: OnceLock<Option<Sentry>> = OnceLock::new();
+
+static REPORTED: OnceLock<DashMap<(RoomId, String), Instant>> = OnceLock::new();
+
+/// Where Sentry's store endpoint is and the key to post to it with, parsed
+/// from a DSN like `https://<key>@<host>/<project>`.
+struct Sentry {
+    store_url: Url,
+    key: String,
+}
+
+impl Sentry {
+    fn from_dsn(dsn: &str) -> Option<Self> {
+        let dsn = Url::parse(dsn).ok()?;
+        let project = dsn.path().trim_matches('/');
+        if dsn.username().is_empty() || project.is_empty() {
+            return None;
+        }
+
+        let mut store_url = dsn.clone();
+        store_url.set_username("").ok()?;
+        store_url.set_path(&format!("/api/{}/store/", project));
+        Some(Sentry {
+            store_url,
+            key: dsn.username().to_owned(),
+        })
+    }
+}
+
+fn sentry() -> Option<&'static Sentry> {
+    SENTRY
+        .get_or_init(|| {
+            let dsn = env::var("JIGARPICH_SENTRY_DSN").ok()?;
+            let sentry = Sentry::from_dsn(&dsn);
+            if sentry.is_none() {
+                log::warn!("Can not parse JIGARPICH_SENTRY_DSN");
+            }
+            sentry
+        })
+        .as_ref()
+}
+
+fn reported() -> &'static DashMap<(RoomId, String), Instant> {
+    REPORTED.get_or_init(DashMap::new)
+}
+
+/// Gives the reporter the bot to message the admin chat with.
+pub fn init(bot: Bot) {
+    let _ = BOT.set(bot);
+}
+
+/// Logs the problem and forwards it in the background, unless it was
+/// forwarded for the room recently.
+pub fn report(room_id: RoomId, problem: String) {
+    log::warn!("{} in room {:?}", problem, room_id);
+
+    let now = Instant::now();
+    let key = (room_id, problem.clone());
+    if reported()
+        .get(&key)
+        .is_some_and(|at| now.duration_since(*at) < REPORT_INTERVAL)
+    {
+        return;
+    }
+    reported().insert(key, now);
+    reported().retain(|_, at| now.duration_since(*at) < REPORT_INTERVAL);
+
+    tokio::spawn(forward(room_id, problem));
+}
+
+async fn forward(room_id: RoomId, problem: String) {
+    if let (Some(bot), Some(admin_chat)) = (BOT.get(), admin_chat()) {
+        let text = format!("⚠️ Room {}: {}", room_id.0, problem);
+        if let Err(err) = bot.send_message(admin_chat, text).await {
+            log::warn!("Can not report error to the admin chat: {}", err);
+        }
+    }
+
+    if let Some(sentry) = sentry() {
+        let response = reqwest::Client::new()
+            .post(sentry.store_url.clone())
+            .header(
+                "X-Sentry-Auth",
+                format!(
+                    "Sentry sentry_version=7, sentry_client=jigarpich/{}, sentry_key={}",
+                    env!("CARGO_PKG_VERSION"),
+                    sentry.key
+                ),
+            )
+            .json(&json!({
+                "message": problem,
+                "level": "warning",
+                "platform": "other",
+                "tags": { "room": room_id.0 },
+            }))
+            .send()
+            .await;
+        if let Err(err) = response.and_then(|response| response.error_for_status()) {
+            log::warn!("Can not report error to Sentry: {}", err);
+        }
+    }
+}
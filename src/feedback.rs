@@ -0,0 +1,96 @@
+use std::sync::OnceLock;
+
+use dashmap::DashMap;
+use teloxide::{prelude::*, types::MessageId};
+
+use crate::{admin::admin_chat, memberships, HandlerResult};
+
+/// Feedback messages in the admin chat and the users who sent them, so
+/// replies can be routed back.
+static FEEDBACK_MESSAGES: OnceLock<DashMap<MessageId, UserId>> = OnceLock::new();
+
+fn feedback_messages() -> &'static DashMap<MessageId, UserId> {
+    FEEDBACK_MESSAGES.get_or_init(DashMap::new)
+}
+
+pub async fn handle_feedback_command(bot: Bot, msg: Message, text: String) -> HandlerResult {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    if text.trim().is_empty() {
+        bot.send_message(
+            msg.chat.id,
+            "Please write your feedback after the command, e.g. /feedback I love this game!",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let Some(admin_chat) = admin_chat() else {
+        bot.send_message(msg.chat.id, "Feedback is not enabled on this bot, sorry!")
+            .await?;
+        return Ok(());
+    };
+
+    let room = memberships::active_room(user.id)
+        .map(|room_id| format!("\nRoom: {}", room_id.0))
+        .unwrap_or_default();
+    let username = user
+        .username
+        .as_ref()
+        .map(|username| format!(" @{}", username))
+        .unwrap_or_default();
+
+    let sent_message = bot
+        .send_message(
+            admin_chat,
+            format!(
+                "📝 Feedback from {}{} ({}){}\n\n{}\n\nReply to this message to answer.",
+                user.full_name(),
+                username,
+                user.id,
+                room,
+                text.trim()
+            ),
+        )
+        .await?;
+    feedback_messages().insert(sent_message.id, user.id);
+
+    bot.send_message(
+        msg.chat.id,
+        "Thanks! Your feedback was sent to the maintainers.",
+    )
+    .await?;
+    Ok(())
+}
+
+/// Whether the message is an admin replying to a forwarded feedback.
+pub fn is_admin_reply(msg: Message) -> bool {
+    Some(msg.chat.id) == admin_chat()
+        && msg
+            .reply_to_message()
+            .is_some_and(|replied| feedback_messages().contains_key(&replied.id))
+}
+
+pub async fn handle_admin_reply(bot: Bot, msg: Message) -> HandlerResult {
+    let Some(user_id) = msg
+        .reply_to_message()
+        .and_then(|replied| feedback_messages().get(&replied.id).map(|user_id| *user_id))
+    else {
+        return Ok(());
+    };
+    let Some(text) = msg.text() else {
+        return Ok(());
+    };
+
+    bot.send_message(
+        user_id,
+        format!("💬 Reply from the maintainers:\n\n{}", text),
+    )
+    .await?;
+    bot.send_message(msg.chat.id, "Reply sent ✅")
+        .reply_to_message_id(msg.id)
+        .await?;
+    Ok(())
+}
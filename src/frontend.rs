@@ -0,0 +1,416 @@
+use std::future::Future;
+
+use teloxide::types::{ChatId, MessageId, UserId};
+
+use crate::{callback_query_command::CbQueryCommand, mute, room::RoomId};
+
+pub struct Button {
+    pub label: String,
+    pub room_id: RoomId,
+    pub command: CbQueryCommand,
+}
+
+impl Button {
+    pub fn new(label: &str, room_id: RoomId, command: CbQueryCommand) -> Self {
+        Button {
+            label: label.to_owned(),
+            room_id,
+            command,
+        }
+    }
+}
+
+pub type Buttons = Vec<Vec<Button>>;
+
+/// Everything the game engine needs from a chat platform. The Telegram bot is
+/// the only real implementation, but the engine never talks to teloxide
+/// directly so it can be driven by other frontends as well.
+pub trait Frontend: Clone + Send + Sync + 'static {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Sends a message with inline buttons into a topic of a forum group, or
+    /// its general topic when `topic` is `None`, and returns its location so
+    /// the buttons can be edited later.
+    fn send_card_to_topic(
+        &self,
+        to: ChatId,
+        topic: Option<i32>,
+        text: String,
+        buttons: Buttons,
+    ) -> impl Future<Output = Result<(ChatId, MessageId), Self::Error>> + Send;
+
+    fn send_card(
+        &self,
+        to: ChatId,
+        text: String,
+        buttons: Buttons,
+    ) -> impl Future<Output = Result<(ChatId, MessageId), Self::Error>> + Send {
+        self.send_card_to_topic(to, None, text, buttons)
+    }
+
+    /// Replaces the buttons of a previously sent card, an empty list removes
+    /// them.
+    fn edit_buttons(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        buttons: Buttons,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    fn edit_text(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        text: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Replaces both the text and the buttons of a previously sent card.
+    fn edit_card(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        text: String,
+        buttons: Buttons,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    fn pin_message(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Sends a plain text message into a topic of a forum group, or its
+    /// general topic when `topic` is `None`.
+    fn prompt_to_topic(
+        &self,
+        to: ChatId,
+        topic: Option<i32>,
+        text: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Sends a plain text message.
+    fn prompt(
+        &self,
+        to: ChatId,
+        text: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.prompt_to_topic(to, None, text)
+    }
+
+    /// Sends `text` followed by `spoiler`, which stays hidden until the
+    /// recipient taps on it.
+    fn prompt_with_spoiler(
+        &self,
+        to: ChatId,
+        text: String,
+        spoiler: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// A link that opens the bot and joins `room_id` right away, if the
+    /// platform supports such links.
+    fn join_link(
+        &self,
+        room_id: RoomId,
+    ) -> impl Future<Output = Result<Option<String>, Self::Error>> + Send;
+
+    /// Sends a PNG image with a caption.
+    fn send_image(
+        &self,
+        to: ChatId,
+        topic: Option<i32>,
+        png: Vec<u8>,
+        caption: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Sends an OGG file encoded with Opus as a voice message.
+    fn send_sound(
+        &self,
+        to: ChatId,
+        topic: Option<i32>,
+        ogg: Vec<u8>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    fn broadcast(
+        &self,
+        to: Vec<UserId>,
+        text: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async move {
+            for user_id in to {
+                self.prompt(user_id.into(), text.clone()).await?;
+            }
+            Ok(())
+        }
+    }
+
+    fn broadcast_with_spoiler(
+        &self,
+        to: Vec<UserId>,
+        text: String,
+        spoiler: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async move {
+            for user_id in to {
+                if mute::hold(user_id, &text, &spoiler) {
+                    continue;
+                }
+                self.prompt_with_spoiler(user_id.into(), text.clone(), spoiler.clone())
+                    .await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::{
+            atomic::{AtomicI32, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use dashmap::DashMap;
+    use teloxide::types::User;
+
+    use super::*;
+    use crate::{
+        game,
+        room::{Room, RoomConfig},
+        Rooms,
+    };
+
+    /// A message the game sent, and whether it's a word to describe.
+    struct Sent {
+        to: ChatId,
+        text: String,
+        word_card: bool,
+    }
+
+    /// Keeps every message instead of sending it.
+    #[derive(Clone, Default)]
+    struct RecordingFrontend {
+        next_message_id: Arc<AtomicI32>,
+        sent: Arc<Mutex<Vec<Sent>>>,
+    }
+
+    impl RecordingFrontend {
+        fn record(&self, to: ChatId, text: String, buttons: &Buttons) -> MessageId {
+            let word_card = buttons
+                .iter()
+                .flatten()
+                .any(|button| matches!(button.command, CbQueryCommand::Correct));
+            self.sent.lock().unwrap().push(Sent {
+                to,
+                text,
+                word_card,
+            });
+            MessageId(self.next_message_id.fetch_add(1, Ordering::Relaxed))
+        }
+
+        fn take(&self) -> Vec<Sent> {
+            std::mem::take(&mut self.sent.lock().unwrap())
+        }
+    }
+
+    impl Frontend for RecordingFrontend {
+        type Error = Infallible;
+
+        async fn send_card_to_topic(
+            &self,
+            to: ChatId,
+            _topic: Option<i32>,
+            text: String,
+            buttons: Buttons,
+        ) -> Result<(ChatId, MessageId), Self::Error> {
+            Ok((to, self.record(to, text, &buttons)))
+        }
+
+        async fn edit_buttons(
+            &self,
+            _chat_id: ChatId,
+            _message_id: MessageId,
+            _buttons: Buttons,
+        ) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn edit_text(
+            &self,
+            chat_id: ChatId,
+            _message_id: MessageId,
+            text: String,
+        ) -> Result<(), Self::Error> {
+            self.record(chat_id, text, &Vec::new());
+            Ok(())
+        }
+
+        async fn edit_card(
+            &self,
+            chat_id: ChatId,
+            _message_id: MessageId,
+            text: String,
+            buttons: Buttons,
+        ) -> Result<(), Self::Error> {
+            self.record(chat_id, text, &buttons);
+            Ok(())
+        }
+
+        async fn pin_message(
+            &self,
+            _chat_id: ChatId,
+            _message_id: MessageId,
+        ) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn prompt_to_topic(
+            &self,
+            to: ChatId,
+            _topic: Option<i32>,
+            text: String,
+        ) -> Result<(), Self::Error> {
+            self.record(to, text, &Vec::new());
+            Ok(())
+        }
+
+        async fn prompt_with_spoiler(
+            &self,
+            to: ChatId,
+            text: String,
+            spoiler: String,
+        ) -> Result<(), Self::Error> {
+            self.record(to, format!("{} {}", text, spoiler), &Vec::new());
+            Ok(())
+        }
+
+        async fn join_link(&self, _room_id: RoomId) -> Result<Option<String>, Self::Error> {
+            Ok(None)
+        }
+
+        async fn send_image(
+            &self,
+            to: ChatId,
+            _topic: Option<i32>,
+            _png: Vec<u8>,
+            caption: String,
+        ) -> Result<(), Self::Error> {
+            self.record(to, caption, &Vec::new());
+            Ok(())
+        }
+
+        async fn send_sound(
+            &self,
+            _to: ChatId,
+            _topic: Option<i32>,
+            _ogg: Vec<u8>,
+        ) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    fn player(id: u64) -> User {
+        User {
+            id: UserId(id),
+            is_bot: false,
+            first_name: format!("Player {}", id),
+            last_name: None,
+            username: None,
+            language_code: None,
+            is_premium: false,
+            added_to_attachment_menu: false,
+        }
+    }
+
+    /// The players the word cards went to.
+    fn word_cards(sent: &[Sent]) -> Vec<ChatId> {
+        sent.iter()
+            .filter(|sent| sent.word_card)
+            .inspect(|sent| assert!(sent.text.starts_with("Round 1/1"), "{}", sent.text))
+            .map(|sent| sent.to)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn plays_a_short_game() {
+        let frontend = RecordingFrontend::default();
+        let rooms: Rooms = Arc::new(DashMap::new());
+        let room_id = RoomId(1);
+        let players = (1..=4).map(player).collect::<Vec<_>>();
+        rooms.insert(
+            room_id,
+            tokio::sync::Mutex::new(Room::new(
+                RoomConfig::new(2, 1, 60, false),
+                None,
+                players[0].id,
+            )),
+        );
+
+        for user in &players {
+            game::handle_join_command(
+                frontend.clone(),
+                user.id.into(),
+                rooms.clone(),
+                user.clone(),
+                room_id,
+            )
+            .await
+            .unwrap();
+        }
+        assert!(
+            frontend
+                .take()
+                .iter()
+                .any(|sent| sent.to == players[3].id.into()),
+            "The last player didn't get a lobby card"
+        );
+
+        {
+            let entry = rooms.get(&room_id).unwrap();
+            let mut room = entry.lock().await;
+            for (i, user) in players.iter().enumerate() {
+                game::handle_team_join(
+                    frontend.clone(),
+                    rooms.clone(),
+                    &mut room,
+                    room_id,
+                    user.clone(),
+                    i / 2,
+                )
+                .await
+                .unwrap();
+            }
+            game::handle_play(&mut room, room_id, frontend.clone(), players[0].clone())
+                .await
+                .unwrap();
+            frontend.take();
+
+            game::handle_start_round(rooms.clone(), &mut room, room_id, frontend.clone())
+                .await
+                .unwrap();
+            let describer = word_cards(&frontend.take());
+            assert_eq!(describer.len(), 1, "Expected one word card to start with");
+
+            game::handle_correct(rooms.clone(), &mut room, room_id, frontend.clone())
+                .await
+                .unwrap();
+            let next = word_cards(&frontend.take());
+            assert_eq!(next.len(), 1, "Expected a new word card after a guess");
+            assert_ne!(next, describer, "The next team should describe next");
+        }
+
+        game::end_round(rooms.clone(), room_id, frontend.clone()).await;
+        let entry = rooms.get(&room_id).unwrap();
+        assert!(entry.lock().await.is_finished());
+        let sent = frontend.take();
+        for user in &players {
+            assert!(
+                sent.iter().any(|sent| sent.to == user.id.into()),
+                "{} didn't hear about the end of the game",
+                user.first_name
+            );
+        }
+    }
+}
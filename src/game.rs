@@ -0,0 +1,4473 @@
+use std::{
+    env,
+    future::Future,
+    pin::Pin,
+    sync::OnceLock,
+    time::{Duration, SystemTime},
+};
+
+use dashmap::DashMap;
+use teloxide::types::{ChatId, MessageId, User, UserId};
+use tokio::sync::Mutex;
+
+use crate::{
+    awards,
+    callback_query_command::CbQueryCommand,
+    duos, error_reporting,
+    frontend::{Button, Buttons, Frontend},
+    memberships, metrics, moderation,
+    notifications::{self, Category},
+    persistence, public_games, ratings, reachability, render, results_export, results_webhook,
+    room::{
+        self, get_new_id, get_team_emoji, get_teams, Anomaly, GameLogicError, PinnedMessage, Room,
+        RoomConfig, RoomId, Severity, HANDICAP_IN_SECONDS, MAX_CUSTOM_CARDS_PER_TEAM,
+    },
+    scoreboard,
+    sounds::{self, Sound},
+    timezone,
+    words::{self, Complexity, Word},
+    Rooms,
+};
+
+const AUTO_START_COUNTDOWN_IN_SECONDS: u64 = 30;
+
+const DEAD_ROOM_SWEEP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+const UNREACHABLE_REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+const IDLE_DESCRIBER_SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+const DEFAULT_IDLE_REMINDER_MINUTES: u64 = 2;
+
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long a round may run past its duration before the watchdog ends it.
+const OVERDUE_ROUND_GRACE: Duration = Duration::from_secs(60);
+
+/// How long nobody may start the next round before the watchdog pauses the
+/// game.
+const ABANDONED_GAME_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+static IDLE_REMINDER_INTERVAL: OnceLock<Duration> = OnceLock::new();
+
+static IDLE_AUTO_ADVANCE: OnceLock<Option<usize>> = OnceLock::new();
+
+/// How long a describer may leave the ▶️ button unpressed between reminders.
+fn idle_reminder_interval() -> Duration {
+    *IDLE_REMINDER_INTERVAL.get_or_init(|| {
+        let minutes = env::var("JIGARPICH_IDLE_REMINDER_MINUTES")
+            .ok()
+            .and_then(|minutes| minutes.parse().ok())
+            .unwrap_or(DEFAULT_IDLE_REMINDER_MINUTES);
+        Duration::from_secs(minutes * 60)
+    })
+}
+
+/// After how many unanswered reminders the partner of an idle describer
+/// starts the round instead, never unless configured.
+fn idle_auto_advance() -> Option<usize> {
+    *IDLE_AUTO_ADVANCE.get_or_init(|| {
+        env::var("JIGARPICH_IDLE_AUTO_ADVANCE_REMINDERS")
+            .ok()
+            .and_then(|reminders| reminders.parse().ok())
+    })
+}
+
+const GUESSER_CARD_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the group's countdown ticks, slow enough for Telegram's limits
+/// on editing messages in groups.
+const ANIMATED_TIMER_INTERVAL: Duration = Duration::from_secs(10);
+
+const ANIMATED_TIMER_WIDTH: u64 = 10;
+
+/// How early a round timer may fire and still end the round it was set for.
+const ROUND_END_TOLERANCE: Duration = Duration::from_secs(1);
+
+/// Alerts sent to players when this many seconds are left in the round.
+pub const TIME_ALERTS_IN_SECONDS: [u64; 3] = [60, 30, 10];
+
+/// Rounds shorter than this get their alerts moved closer to the end.
+const FULL_ALERTS_FROM_SECONDS: u64 = 120;
+
+pub fn time_alert_message(seconds: u64) -> String {
+    if seconds.is_multiple_of(60) {
+        format!("⏱️📢 {} min ❗", seconds / 60)
+    } else {
+        format!("⏱️📢 {} secs ❗", seconds)
+    }
+}
+
+/// The seconds left at which players of a round lasting `round_duration` are
+/// alerted, scaled down for short rounds.
+fn time_alerts(round_duration: Duration) -> Vec<u64> {
+    let round_duration = round_duration.as_secs().min(FULL_ALERTS_FROM_SECONDS);
+    TIME_ALERTS_IN_SECONDS
+        .iter()
+        .map(|time| time * round_duration / FULL_ALERTS_FROM_SECONDS)
+        .filter(|time| *time > 0)
+        .collect()
+}
+
+/// The settings picked while creating the room.
+fn settings_summary(config: &RoomConfig) -> String {
+    format!(
+        "You are going to play {} rounds with {} teams, each round will last {}:{:02}.\n\
+         Taboo words are {}.",
+        config.number_of_rounds,
+        config.number_of_teams,
+        config.round_duration_in_seconds / 60,
+        config.round_duration_in_seconds % 60,
+        if config.use_taboo_words {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    )
+}
+
+fn settings_summary_buttons(room: &Room, room_id: RoomId) -> Buttons {
+    let label = if room.use_taboo_words() {
+        "Turn taboo words off"
+    } else {
+        "Turn taboo words on"
+    };
+    let mut buttons = vec![vec![Button::new(
+        label,
+        room_id,
+        CbQueryCommand::ToggleTabooWords,
+    )]];
+
+    let label = if room.config().pass_the_phone {
+        "Play on everyone's phones"
+    } else {
+        "📱 Pass one phone around"
+    };
+    buttons.push(vec![Button::new(
+        label,
+        room_id,
+        CbQueryCommand::TogglePassThePhone,
+    )]);
+
+    let label = if room.config().relay {
+        "Take turns"
+    } else {
+        "🏃 Relay: all teams race for each word"
+    };
+    buttons.push(vec![Button::new(
+        label,
+        room_id,
+        CbQueryCommand::ToggleRelay,
+    )]);
+
+    let label = match room.config().coop_target {
+        Some(target) => format!("🤝 Co-op: {} words together", target),
+        None => "🤝 Co-op: off".to_owned(),
+    };
+    buttons.push(vec![Button::new(
+        &label,
+        room_id,
+        CbQueryCommand::CycleCoopTarget,
+    )]);
+
+    let label = match room.config().break_minutes {
+        Some(minutes) => format!("☕ {} minute break between rounds", minutes),
+        None => "☕ Breaks between rounds: off".to_owned(),
+    };
+    buttons.push(vec![Button::new(
+        &label,
+        room_id,
+        CbQueryCommand::CycleBreak,
+    )]);
+
+    let label = match room.config().tie_margin_in_seconds {
+        Some(seconds) => format!("⚖️ Times within {}s tie, fewer skips win", seconds),
+        None => "⚖️ Tie margin: off".to_owned(),
+    };
+    buttons.push(vec![Button::new(
+        &label,
+        room_id,
+        CbQueryCommand::CycleTieMargin,
+    )]);
+
+    let label = if room.config().balance_turns {
+        "End the last round on time"
+    } else {
+        "⚖️ Even out turns in the last round"
+    };
+    buttons.push(vec![Button::new(
+        label,
+        room_id,
+        CbQueryCommand::ToggleBalanceTurns,
+    )]);
+
+    let label = if room.config().warm_up {
+        "Skip the warm-up"
+    } else {
+        "🔥 Warm up before round 1"
+    };
+    buttons.push(vec![Button::new(
+        label,
+        room_id,
+        CbQueryCommand::ToggleWarmUp,
+    )]);
+
+    if room.config().captains {
+        buttons.push(vec![
+            Button::new("No captains", room_id, CbQueryCommand::ToggleCaptains),
+            Button::new("👑 Pick captains", room_id, CbQueryCommand::PickCaptains),
+        ]);
+    } else {
+        buttons.push(vec![Button::new(
+            "👑 Let captains confirm words",
+            room_id,
+            CbQueryCommand::ToggleCaptains,
+        )]);
+    }
+
+    if public_games::channel().is_some() {
+        let label = if room.config().public {
+            "Don't announce this game"
+        } else {
+            "📣 Announce as a public game"
+        };
+        buttons.push(vec![Button::new(
+            label,
+            room_id,
+            CbQueryCommand::TogglePublic,
+        )]);
+    }
+    buttons
+}
+
+/// The announcement of a public room in the public games channel.
+fn public_post_text(room: &Room, room_id: RoomId, link: Option<String>) -> String {
+    let (players, max_players) = room.capacity().unwrap_or_default();
+    let join = if players >= max_players {
+        "🚫 The room is full.".to_owned()
+    } else {
+        link.map(|link| format!("👉 {}", link))
+            .unwrap_or_else(|| format!("Send /join {} to the bot to play!", room_id.0))
+    };
+    format!(
+        "📣 A public game is looking for players!\n\n{}\n\n👥 {}/{} players\n{}",
+        settings_summary(room.config()),
+        players,
+        max_players,
+        join
+    )
+}
+
+async fn announce_public_game<F: Frontend>(frontend: &F, room: &mut Room, room_id: RoomId) {
+    let Some(channel) = public_games::channel() else {
+        return;
+    };
+
+    let link = frontend.join_link(room_id).await.ok().flatten();
+    let text = public_post_text(room, room_id, link);
+    match frontend.send_card(channel, text, vec![]).await {
+        Ok(post) => room.set_public_post(Some(post)),
+        Err(err) => log::warn!("Can not announce public game {:?}: {}", room_id, err),
+    }
+}
+
+/// Keeps the player count of the announcement of a public room up to date.
+async fn update_public_post<F: Frontend>(frontend: &F, room: &Room, room_id: RoomId) {
+    let Some((chat_id, message_id)) = room.public_post() else {
+        return;
+    };
+
+    let link = frontend.join_link(room_id).await.ok().flatten();
+    let text = public_post_text(room, room_id, link);
+    if let Err(err) = frontend.edit_text(chat_id, message_id, text).await {
+        log::warn!("Can not update public game {:?}: {}", room_id, err);
+    }
+}
+
+/// Replaces the announcement of a public room once it can't be joined
+/// anymore.
+async fn close_public_post<F: Frontend>(
+    frontend: &F,
+    post: Option<(ChatId, MessageId)>,
+    text: String,
+) {
+    let Some((chat_id, message_id)) = post else {
+        return;
+    };
+
+    if let Err(err) = frontend.edit_text(chat_id, message_id, text).await {
+        log::warn!("Can not close public game post: {}", err);
+    }
+}
+
+/// Creates the room, confirming its settings in `question` if given, or in a
+/// new message otherwise.
+pub async fn handle_new_command<F: Frontend>(
+    frontend: F,
+    chat_id: ChatId,
+    rooms: Rooms,
+    room: Room,
+    question: Option<MessageId>,
+) -> Result<(), F::Error> {
+    if metrics::is_at_capacity(&rooms).await {
+        frontend
+            .prompt(
+                chat_id,
+                "The bot is at capacity, please try again later!".to_owned(),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    if words::count() < room.words_needed() {
+        frontend
+            .prompt(
+                chat_id,
+                "There aren't enough words for such a long game, please choose fewer or \
+                 shorter rounds!"
+                    .to_owned(),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    let topic = room.group_topic();
+    let new_id = get_new_id();
+    let summary = settings_summary(room.config());
+    let buttons = settings_summary_buttons(&room, new_id);
+    match question {
+        Some(message_id) => {
+            frontend
+                .edit_card(chat_id, message_id, summary, buttons)
+                .await?
+        }
+        None => {
+            frontend
+                .send_card_to_topic(chat_id, topic, summary, buttons)
+                .await?;
+        }
+    }
+
+    log::info!("Created room {:?} with seed {}", new_id, room.seed());
+    persistence::save(new_id, &room);
+    rooms.insert(new_id, Mutex::new(room));
+    frontend
+        .prompt_to_topic(
+            chat_id,
+            topic,
+            "Room created! Forward following message to join:".to_owned(),
+        )
+        .await?;
+    frontend
+        .prompt_to_topic(chat_id, topic, format!("/join {}", new_id.0))
+        .await?;
+
+    let Some(link) = frontend.join_link(new_id).await? else {
+        return Ok(());
+    };
+    match render::qr_code(&link) {
+        Some(png) => {
+            frontend
+                .send_image(
+                    chat_id,
+                    topic,
+                    png,
+                    format!("Or scan this code to join room {}", new_id.0),
+                )
+                .await?;
+        }
+        None => log::warn!("Can not render QR code for {:?}", new_id),
+    }
+    Ok(())
+}
+
+pub async fn handle_join_command<F: Frontend>(
+    frontend: F,
+    chat_id: ChatId,
+    rooms: Rooms,
+    user: User,
+    room_id: RoomId,
+) -> Result<(), F::Error> {
+    let Some(room) = rooms.get(&room_id) else {
+        frontend
+            .prompt(chat_id, "Room number is wrong!".to_owned())
+            .await?;
+        return Ok(());
+    };
+
+    let mut room = room.lock().await;
+
+    match room.join(user.clone(), chat_id) {
+        Ok(others) => {
+            send_team_card(frontend, &mut room, chat_id, room_id, user, others).await?;
+        }
+        Err(err @ GameLogicError::AwaitingApproval) => {
+            frontend
+                .send_card(
+                    room.host().into(),
+                    format!("{} wants to join room {}", user.full_name(), room_id.0),
+                    vec![vec![
+                        Button::new(
+                            "✅ Approve",
+                            room_id,
+                            CbQueryCommand::ApproveJoin { user_id: user.id },
+                        ),
+                        Button::new(
+                            "❌ Deny",
+                            room_id,
+                            CbQueryCommand::DenyJoin { user_id: user.id },
+                        ),
+                    ]],
+                )
+                .await?;
+            report_error(&frontend, user.id, err).await?;
+        }
+        Err(GameLogicError::OnBench) => {
+            memberships::record_join(user.id, room_id);
+            frontend
+                .prompt(
+                    chat_id,
+                    format!(
+                        "🪑 The game in room {} has started, you're on the bench. You'll \
+                         follow the game and the host can swap you in.",
+                        room_id.0
+                    ),
+                )
+                .await?;
+            frontend
+                .broadcast(
+                    notifications::recipients(room.get_all_players(), Category::Join),
+                    format!("🪑 {} joined the bench", user.full_name()),
+                )
+                .await?;
+            send_bench_card(&frontend, &room, room_id).await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    persistence::save(room_id, &room);
+    Ok(())
+}
+
+async fn send_team_card<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    chat_id: ChatId,
+    room_id: RoomId,
+    user: User,
+    others: Vec<UserId>,
+) -> Result<(), F::Error> {
+    memberships::record_join(user.id, room_id);
+    let topic = topic_of(room, chat_id);
+    if !chat_id.is_user() {
+        check_private_chat(&frontend, (chat_id, topic), room_id, &user).await?;
+    }
+    let late = others.iter().any(|other| *other != user.id);
+    frontend
+        .broadcast(
+            notifications::recipients(others, Category::Join),
+            format!("{} joined room", user.full_name()),
+        )
+        .await?;
+
+    seat_duo(&frontend, room, user.id).await?;
+    refresh_lobby_cards(&frontend, room, room_id).await;
+    let Some(status) = room.lobby_status() else {
+        return Ok(());
+    };
+    if late {
+        frontend
+            .prompt_to_topic(chat_id, topic, lobby_digest(room, room_id, user.id))
+            .await?;
+    }
+    let (chat_id, message_id) = frontend
+        .send_card_to_topic(
+            chat_id,
+            topic,
+            lobby_card_text(room, status, chat_id),
+            lobby_buttons(room_id, room.number_of_teams(), room.has_rated_players()),
+        )
+        .await?;
+    room.set_lobby_card(chat_id, message_id);
+    Ok(())
+}
+
+/// Seats whichever of `user_id` and their duo partner is without a team next
+/// to the other one.
+async fn seat_duo<F: Frontend>(
+    frontend: &F,
+    room: &mut Room,
+    user_id: UserId,
+) -> Result<(), F::Error> {
+    let Some(partner) = duos::partner_of(user_id) else {
+        return Ok(());
+    };
+    let seated = match room.seat_next_to(user_id, partner) {
+        Some(team) => Some((user_id, team)),
+        None => room
+            .seat_next_to(partner, user_id)
+            .map(|team| (partner, team)),
+    };
+    if let Some((player, team)) = seated {
+        frontend
+            .prompt(
+                player.into(),
+                format!(
+                    "🤝 You're in {} with your duo partner",
+                    get_team_emoji(team)
+                ),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Where the cards of `player` go, the shared phone in a pass-the-phone game.
+fn player_chat(room: &Room, player: &User) -> ChatId {
+    room.device().unwrap_or(player.id).into()
+}
+
+/// The forum topic to use for messages of a room sent to `chat_id`, which is
+/// only set for its own group chat.
+fn topic_of(room: &Room, chat_id: ChatId) -> Option<i32> {
+    room.group_topic()
+        .filter(|_| room.group_chat() == Some(chat_id))
+}
+
+/// Makes sure a player joining from a group can get cards in private, and
+/// otherwise asks them in the group to start the bot.
+async fn check_private_chat<F: Frontend>(
+    frontend: &F,
+    (group_chat, topic): (ChatId, Option<i32>),
+    room_id: RoomId,
+    user: &User,
+) -> Result<(), F::Error> {
+    let greeting = format!(
+        "You joined room {}, your cards will show up here.",
+        room_id.0
+    );
+    if frontend.prompt(user.id.into(), greeting).await.is_ok() {
+        return Ok(());
+    }
+
+    let link = frontend
+        .join_link(room_id)
+        .await?
+        .map(|link| format!("\n👉 {}", link))
+        .unwrap_or_default();
+    frontend
+        .prompt_to_topic(
+            group_chat,
+            topic,
+            format!(
+                "{}, I can't send you cards yet. Please start the bot privately, the game \
+                 can't start before you do.{}",
+                user.mention().unwrap_or_else(|| user.full_name()),
+                link
+            ),
+        )
+        .await
+}
+
+/// Catches up a player joining a lobby others are already waiting in.
+fn lobby_digest(room: &Room, room_id: RoomId, user_id: UserId) -> String {
+    let players = room.lobby_players();
+    let host = players
+        .iter()
+        .find(|player| player.id == room.host())
+        .map_or("someone who left".to_owned(), User::full_name);
+    let roster = players
+        .iter()
+        .filter(|player| player.id != user_id)
+        .map(|player| format!("\t- {}\n", player.full_name()))
+        .collect::<String>();
+
+    format!(
+        "👋 Welcome to room {}, hosted by {}!\n\n{}\n\nAlready here:\n{}",
+        room_id.0,
+        host,
+        settings_summary(room.config()),
+        roster
+    )
+}
+
+/// The lobby status, telling players in their private chat which team they
+/// are in.
+fn lobby_card_text(room: &Room, status: String, chat_id: ChatId) -> String {
+    let team = chat_id
+        .is_user()
+        .then(|| room.lobby_team(UserId(chat_id.0 as u64)))
+        .flatten();
+    match team {
+        Some(team) => format!("{}\n\nYou are in {}", status, get_team_emoji(team)),
+        None => status,
+    }
+}
+
+/// Edits every lobby card of the room to show the current roster.
+async fn refresh_lobby_cards<F: Frontend>(frontend: &F, room: &Room, room_id: RoomId) {
+    let Some(status) = room.lobby_status() else {
+        return;
+    };
+
+    update_public_post(frontend, room, room_id).await;
+
+    for (chat_id, message_id) in room.lobby_cards() {
+        if let Err(err) = frontend
+            .edit_card(
+                chat_id,
+                message_id,
+                lobby_card_text(room, status.clone(), chat_id),
+                lobby_buttons(room_id, room.number_of_teams(), room.has_rated_players()),
+            )
+            .await
+        {
+            log::warn!("Can not update lobby card: {}", err);
+        }
+    }
+}
+
+fn lobby_buttons(room_id: RoomId, number_of_teams: usize, rated: bool) -> Buttons {
+    let mut buttons = get_teams(number_of_teams)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, team)| {
+            vec![Button::new(
+                &team,
+                room_id,
+                CbQueryCommand::Join { team_index: idx },
+            )]
+        })
+        .collect::<Vec<_>>();
+
+    buttons.push(vec![Button::new(
+        "🔒 Lock teams",
+        room_id,
+        CbQueryCommand::ToggleTeamLock,
+    )]);
+
+    if rated {
+        buttons.push(vec![Button::new(
+            "⚖️ Balance teams by skill",
+            room_id,
+            CbQueryCommand::BalanceTeams,
+        )]);
+    }
+
+    buttons.push(vec![Button::new(
+        "👁 Word broadcast",
+        room_id,
+        CbQueryCommand::CycleWordBroadcast,
+    )]);
+
+    buttons.push(vec![Button::new(
+        "⏳ Word time limit",
+        room_id,
+        CbQueryCommand::CycleWordTimeLimit,
+    )]);
+
+    buttons.push(vec![Button::new(
+        "🏁 Victory condition",
+        room_id,
+        CbQueryCommand::CycleVictory,
+    )]);
+
+    buttons.push(vec![Button::new(
+        "🪢 Handicap for the leader",
+        room_id,
+        CbQueryCommand::ToggleHandicap,
+    )]);
+
+    buttons.push(vec![Button::new(
+        "📢 Time alerts for everyone",
+        room_id,
+        CbQueryCommand::ToggleAlertEveryone,
+    )]);
+
+    buttons.push(vec![Button::new(
+        "⏳ Animated timer in the group",
+        room_id,
+        CbQueryCommand::ToggleAnimatedTimer,
+    )]);
+
+    if sounds::available() {
+        buttons.push(vec![Button::new(
+            "🔊 Audio alerts",
+            room_id,
+            CbQueryCommand::ToggleAudioAlerts,
+        )]);
+    }
+
+    buttons.push(vec![Button::new(
+        "🎚 Difficulty per round",
+        room_id,
+        CbQueryCommand::DifficultyPlan,
+    )]);
+
+    buttons.push(vec![Button::new(
+        "⚖️ Word complexity mix",
+        room_id,
+        CbQueryCommand::ComplexityWeights,
+    )]);
+
+    buttons.push(vec![Button::new(
+        "🎲 Describers pick difficulty",
+        room_id,
+        CbQueryCommand::ToggleDescriberDraws,
+    )]);
+
+    buttons.push(vec![Button::new(
+        "✍️ Write a card",
+        room_id,
+        CbQueryCommand::WriteCard,
+    )]);
+
+    buttons.push(vec![Button::new(
+        "➕ Invite",
+        room_id,
+        CbQueryCommand::Invite,
+    )]);
+
+    buttons.push(vec![Button::new(
+        "🛂 Host approval",
+        room_id,
+        CbQueryCommand::ToggleHostApproval,
+    )]);
+
+    buttons.push(vec![Button::new(
+        "🚀 Auto-start",
+        room_id,
+        CbQueryCommand::ToggleAutoStart,
+    )]);
+
+    buttons.push(vec![Button::new("Play", room_id, CbQueryCommand::Play)]);
+
+    buttons
+}
+
+pub async fn handle_approve_join<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    host: User,
+    user_id: UserId,
+) -> Result<(), F::Error> {
+    match room.approve_join(host.id, user_id) {
+        Ok((user, chat_id, others)) => {
+            frontend
+                .prompt(host.id.into(), format!("{} approved", user.full_name()))
+                .await?;
+            send_team_card(frontend, room, chat_id, room_id, user, others).await?;
+        }
+        Err(GameLogicError::JoinAfterPlay) => {
+            frontend
+                .prompt(
+                    host.id.into(),
+                    "Game has started. Nobody can join anymore!".to_owned(),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, host.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_deny_join<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    host: User,
+    user_id: UserId,
+) -> Result<(), F::Error> {
+    match room.deny_join(host.id, user_id) {
+        Ok((user, chat_id)) => {
+            frontend
+                .prompt(host.id.into(), format!("{} denied", user.full_name()))
+                .await?;
+            frontend
+                .prompt(
+                    chat_id,
+                    format!("The host didn't let you join room {}", room_id.0),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, host.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_host_approval<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.toggle_host_approval(user.id) {
+        Ok(requires_approval) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    if requires_approval {
+                        "The host now approves everyone who joins".to_owned()
+                    } else {
+                        "Anyone can join without the host's approval".to_owned()
+                    },
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+/// Puts a returning player back into the games they play in, re-sending
+/// whatever they have to act on. Returns whether there was any such game.
+pub async fn handle_rejoin<F: Frontend>(
+    frontend: F,
+    rooms: Rooms,
+    user: User,
+) -> Result<bool, F::Error> {
+    let room_ids = rooms.iter().map(|room| *room.key()).collect::<Vec<_>>();
+    let mut rejoined = false;
+
+    for room_id in room_ids {
+        let Some(room) = rooms.get(&room_id) else {
+            continue;
+        };
+        let mut room = room.lock().await;
+
+        let Some((describing_player, round_in_progress)) = room.describing_player() else {
+            continue;
+        };
+        if !room.get_all_players().contains(&user.id) {
+            continue;
+        }
+
+        rejoined = true;
+        memberships::record_join(user.id, room_id);
+        frontend
+            .prompt(
+                user.id.into(),
+                format!("Welcome back to room {}!", room_id.0),
+            )
+            .await?;
+
+        if room.is_paused() {
+            let (chat_id, message_id) = frontend
+                .send_card(
+                    user.id.into(),
+                    "Game is paused".to_owned(),
+                    vec![vec![Button::new("⏯️", room_id, CbQueryCommand::Resume)]],
+                )
+                .await?;
+            if room.push_to_message_stack(chat_id, message_id).is_err() {
+                error_reporting::report(room_id, "Can not push to the message stack".to_owned());
+            }
+        } else if room.device().unwrap_or(describing_player.id) == user.id {
+            // The host starts the first round after the warm-up, and the
+            // break's timer hands out the button when it ends
+            if !round_in_progress && !room.is_warming_up() && room.break_left().is_none() {
+                send_start_round_button(&frontend, &mut room, room_id, &describing_player).await?;
+            } else if let Ok(word_guess_try) = room.redeal() {
+                send_new_word(
+                    rooms.clone(),
+                    &mut room,
+                    room_id,
+                    frontend.clone(),
+                    word_guess_try,
+                )
+                .await?;
+            }
+        }
+        persistence::save(room_id, &room);
+    }
+    Ok(rejoined)
+}
+
+/// Notices for the buttons players pressed, picked up by the frontend when it
+/// answers the press.
+static TOASTS: OnceLock<DashMap<UserId, &'static str>> = OnceLock::new();
+
+fn toasts() -> &'static DashMap<UserId, &'static str> {
+    TOASTS.get_or_init(DashMap::new)
+}
+
+/// The notice to show on the button the user just pressed, if any.
+pub fn take_toast(user_id: UserId) -> Option<&'static str> {
+    toasts().remove(&user_id).map(|(_, toast)| toast)
+}
+
+/// Tells the player why their action didn't work, the way its error calls
+/// for.
+async fn report_error<F: Frontend>(
+    frontend: &F,
+    user_id: UserId,
+    err: GameLogicError,
+) -> Result<(), F::Error> {
+    match err.severity() {
+        Severity::Toast => {
+            toasts().insert(user_id, err.message());
+            Ok(())
+        }
+        Severity::Prompt => {
+            frontend
+                .prompt(user_id.into(), err.message().to_owned())
+                .await
+        }
+        Severity::Log => {
+            log::debug!("Ignoring {:?} of user {}", err, user_id);
+            Ok(())
+        }
+    }
+}
+
+/// What a user is asked to type after pressing a button.
+pub enum Awaiting {
+    CustomCard,
+    Invitees,
+}
+
+/// Runs a button pressed on one of the cards of a room, returns what the user
+/// should be asked for next, if anything.
+pub async fn handle_room_command<F: Frontend>(
+    frontend: F,
+    rooms: Rooms,
+    room: &mut Room,
+    room_id: RoomId,
+    command: CbQueryCommand,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<Option<Awaiting>, F::Error> {
+    match command {
+        // Cards of a finished game may still be pressed, only the ones
+        // looking back at it or starting over do anything
+        CbQueryCommand::GetTeams | CbQueryCommand::Rematch { .. } | CbQueryCommand::Scoreboard => {}
+        _ if room.is_finished() => {
+            report_error(&frontend, user.id, GameLogicError::IsFinished).await?;
+            return Ok(None);
+        }
+        _ => {}
+    }
+
+    match command {
+        CbQueryCommand::Join { team_index } => {
+            handle_team_join(frontend, rooms, room, room_id, user, team_index).await?
+        }
+        CbQueryCommand::GetTeams => handle_get_teams(frontend, room, user).await?,
+        CbQueryCommand::Play => handle_play(room, room_id, frontend, user).await?,
+        CbQueryCommand::Start => handle_start_round(rooms, room, room_id, frontend).await?,
+        CbQueryCommand::Correct | CbQueryCommand::Skip if room.check_control(user.id).is_err() => {
+            report_error(&frontend, user.id, GameLogicError::NotCaptain).await?
+        }
+        CbQueryCommand::Correct => handle_correct(rooms, room, room_id, frontend).await?,
+        CbQueryCommand::Skip => handle_skip(rooms, room, room_id, frontend).await?,
+        CbQueryCommand::Pause => handle_pause(room, room_id, frontend, user).await?,
+        CbQueryCommand::Resume => handle_resume(room, room_id, frontend, user).await?,
+        CbQueryCommand::CycleWordBroadcast => {
+            handle_cycle_word_broadcast(frontend, room, user).await?
+        }
+        CbQueryCommand::CycleWordTimeLimit => {
+            handle_cycle_word_time_limit(frontend, room, user).await?
+        }
+        CbQueryCommand::CycleVictory => handle_cycle_victory(frontend, room, user).await?,
+        CbQueryCommand::ToggleHandicap => handle_toggle_handicap(frontend, room, user).await?,
+        CbQueryCommand::ToggleAlertEveryone => {
+            handle_toggle_alert_everyone(frontend, room, user).await?
+        }
+        CbQueryCommand::ToggleAudioAlerts => {
+            handle_toggle_audio_alerts(frontend, room, user).await?
+        }
+        CbQueryCommand::ToggleAnimatedTimer => {
+            handle_toggle_animated_timer(frontend, room, user).await?
+        }
+        CbQueryCommand::ToggleDescriberDraws => {
+            handle_toggle_describer_draws(frontend, room, user).await?
+        }
+        CbQueryCommand::Draw { complexity } => {
+            handle_draw(rooms, room, room_id, frontend, complexity).await?
+        }
+        CbQueryCommand::DifficultyPlan => {
+            handle_difficulty_plan(frontend, room, room_id, user).await?
+        }
+        CbQueryCommand::CycleRoundDifficulty { round } => {
+            handle_cycle_round_difficulty(frontend, room, room_id, user, card, round).await?
+        }
+        CbQueryCommand::ComplexityWeights => {
+            handle_complexity_weights(frontend, room, room_id, user).await?
+        }
+        CbQueryCommand::AdjustComplexityWeight {
+            complexity,
+            increase,
+        } => {
+            handle_adjust_complexity_weight(
+                frontend, room, room_id, user, card, complexity, increase,
+            )
+            .await?
+        }
+        CbQueryCommand::ToggleHostApproval => {
+            handle_toggle_host_approval(frontend, room, user).await?
+        }
+        CbQueryCommand::ToggleAutoStart => {
+            handle_toggle_auto_start(frontend, rooms, room, room_id, user).await?
+        }
+        CbQueryCommand::CancelAutoStart => handle_cancel_auto_start(frontend, room, user).await?,
+        CbQueryCommand::ApproveJoin { user_id } => {
+            handle_approve_join(frontend, room, room_id, user, user_id).await?
+        }
+        CbQueryCommand::DenyJoin { user_id } => {
+            handle_deny_join(frontend, room, room_id, user, user_id).await?
+        }
+        CbQueryCommand::Rematch { swap_roles } => {
+            handle_rematch(room, room_id, frontend, swap_roles).await?
+        }
+        CbQueryCommand::Scoreboard => handle_scoreboard(frontend, room, user).await?,
+        CbQueryCommand::Dispute => handle_dispute(frontend, room, room_id, user).await?,
+        CbQueryCommand::DisputeWord { index } => {
+            handle_dispute_word(frontend, room, room_id, user, index).await?
+        }
+        CbQueryCommand::ToggleTeamLock => {
+            handle_toggle_team_lock(frontend, room, room_id, user).await?
+        }
+        CbQueryCommand::ToggleTabooWords => {
+            handle_toggle_taboo_words(frontend, room, room_id, user, card).await?
+        }
+        CbQueryCommand::TogglePublic => {
+            handle_toggle_public(frontend, room, room_id, user, card).await?
+        }
+        CbQueryCommand::TogglePassThePhone => {
+            handle_toggle_pass_the_phone(frontend, room, room_id, user, card).await?
+        }
+        CbQueryCommand::ToggleWarmUp => {
+            handle_toggle_warm_up(frontend, room, room_id, user, card).await?
+        }
+        CbQueryCommand::WarmUpCorrect { team_index } => {
+            handle_warm_up_correct(frontend, room, card, team_index).await?
+        }
+        CbQueryCommand::WarmUpSkip { team_index } => {
+            handle_warm_up_skip(frontend, room, room_id, card, team_index).await?
+        }
+        CbQueryCommand::FinishWarmUp => {
+            handle_finish_warm_up(frontend, room, room_id, user, card).await?
+        }
+        CbQueryCommand::Substitute { seat } => {
+            handle_substitute(frontend, room, room_id, user, card, seat).await?
+        }
+        CbQueryCommand::BalanceTeams => handle_balance_teams(frontend, room, room_id, user).await?,
+        CbQueryCommand::AcceptBalancedTeams => {
+            handle_accept_balanced_teams(frontend, rooms, room, room_id, user).await?
+        }
+        CbQueryCommand::ToggleCaptains => {
+            handle_toggle_captains(frontend, room, room_id, user, card).await?
+        }
+        CbQueryCommand::PickCaptains => handle_pick_captains(frontend, room, room_id, user).await?,
+        CbQueryCommand::ToggleRelay => {
+            handle_toggle_relay(frontend, room, room_id, user, card).await?
+        }
+        CbQueryCommand::CycleCoopTarget => {
+            handle_cycle_coop_target(frontend, room, room_id, user, card).await?
+        }
+        CbQueryCommand::CycleBreak => {
+            handle_cycle_break(frontend, room, room_id, user, card).await?
+        }
+        CbQueryCommand::CycleTieMargin => {
+            handle_cycle_tie_margin(frontend, room, room_id, user, card).await?
+        }
+        CbQueryCommand::ToggleBalanceTurns => {
+            handle_toggle_balance_turns(frontend, room, room_id, user, card).await?
+        }
+        CbQueryCommand::RelayClaim { index } => {
+            handle_relay_claim(frontend, room, room_id, user, index).await?
+        }
+        CbQueryCommand::RelaySkip { index } => {
+            handle_relay_skip(frontend, room, room_id, user, index).await?
+        }
+        CbQueryCommand::MakeCaptain { user_id } => {
+            handle_make_captain(frontend, room, room_id, user, user_id).await?
+        }
+        CbQueryCommand::WriteCard => return handle_write_card(frontend, room, user).await,
+        CbQueryCommand::Invite => return handle_invite(frontend, room, room_id, user).await,
+    };
+    Ok(None)
+}
+
+pub async fn handle_team_join<F: Frontend>(
+    frontend: F,
+    rooms: Rooms,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    team_index: usize,
+) -> Result<(), F::Error> {
+    match room.join_team(user.id, team_index) {
+        Ok(others) => {
+            frontend
+                .broadcast(
+                    notifications::recipients(others, Category::Join),
+                    format!("{} joined {}", user.full_name(), get_team_emoji(team_index)),
+                )
+                .await?;
+            frontend
+                .prompt(
+                    user.id.into(),
+                    format!("✅ You joined {}", get_team_emoji(team_index)),
+                )
+                .await?;
+            seat_duo(&frontend, room, user.id).await?;
+            refresh_lobby_cards(&frontend, room, room_id).await;
+            schedule_auto_start(frontend, rooms, room, room_id);
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_team_lock<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.toggle_team_lock(user.id) {
+        Ok(teams_locked) => {
+            refresh_lobby_cards(&frontend, room, room_id).await;
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    if teams_locked {
+                        format!("🔒 {} locked the teams", user.full_name())
+                    } else {
+                        format!("🔓 {} unlocked the teams", user.full_name())
+                    },
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_cycle_word_time_limit<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.cycle_word_time_limit(user.id) {
+        Ok(word_time_limit) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    match word_time_limit {
+                        Some(seconds) => format!(
+                            "{} set the time limit per word to {} seconds",
+                            user.full_name(),
+                            seconds
+                        ),
+                        None => format!("{} removed the time limit per word", user.full_name()),
+                    },
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+/// One button per round showing its complexity, tapping it moves on to the
+/// next one.
+fn difficulty_plan_buttons(room: &Room, room_id: RoomId) -> Buttons {
+    room.difficulty_plan()
+        .into_iter()
+        .enumerate()
+        .map(|(round, difficulty)| {
+            vec![Button::new(
+                &format!(
+                    "Round {}: {}",
+                    round + 1,
+                    difficulty
+                        .map(|complexity| complexity.to_string())
+                        .unwrap_or_else(|| "mixed".to_owned())
+                ),
+                room_id,
+                CbQueryCommand::CycleRoundDifficulty { round },
+            )]
+        })
+        .collect()
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// The full configuration of the room, each setting marked as locked once
+/// the game has started.
+fn settings_text(room: &Room, room_id: RoomId) -> String {
+    let config = room.config();
+    let difficulty_plan = config
+        .difficulty_plan
+        .iter()
+        .map(|difficulty| {
+            difficulty
+                .map(|complexity| complexity.to_string())
+                .unwrap_or_else(|| "mixed".to_owned())
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let settings = [
+        format!("Teams: {}", config.number_of_teams),
+        format!("Rounds: {}", config.number_of_rounds),
+        format!(
+            "Round duration: {}:{:02}",
+            config.round_duration_in_seconds / 60,
+            config.round_duration_in_seconds % 60
+        ),
+        format!("Taboo words: {}", on_off(config.use_taboo_words)),
+        format!("Word broadcast: {}", config.word_broadcast),
+        format!("Victory: {}", config.victory()),
+        format!(
+            "Word time limit: {}",
+            config
+                .word_time_limit
+                .map_or("off".to_owned(), |seconds| format!("{}s", seconds))
+        ),
+        format!("Handicap for the leader: {}", on_off(config.handicap)),
+        format!(
+            "Time alerts: {}",
+            if config.alert_everyone {
+                "everyone"
+            } else {
+                "the playing pair"
+            }
+        ),
+        format!("Audio alerts: {}", on_off(config.audio_alerts)),
+        format!("Animated timer: {}", on_off(config.animated_timer)),
+        format!("Public game: {}", on_off(config.public)),
+        format!("Pass the phone: {}", on_off(config.pass_the_phone)),
+        format!("Relay: {}", on_off(config.relay)),
+        format!(
+            "Co-op target: {}",
+            config
+                .coop_target
+                .map_or("off".to_owned(), |target| format!("{} words", target))
+        ),
+        format!(
+            "Break between rounds: {}",
+            config
+                .break_minutes
+                .map_or("off".to_owned(), |minutes| format!("{} min", minutes))
+        ),
+        format!(
+            "Tie margin: {}",
+            config
+                .tie_margin_in_seconds
+                .map_or("off".to_owned(), |seconds| format!("{}s", seconds))
+        ),
+        format!("Even out turns: {}", on_off(config.balance_turns)),
+        format!("Warm-up: {}", on_off(config.warm_up)),
+        format!("Team captains: {}", on_off(config.captains)),
+        format!("Difficulty per round: {}", difficulty_plan),
+        format!("Complexity mix: {}", config.complexity_weights),
+        format!(
+            "Describers pick difficulty: {}",
+            on_off(config.describer_draws)
+        ),
+    ];
+
+    let (marker, footer) = if room.is_lobby() {
+        (
+            "",
+            "The host can change them with the buttons of the lobby card.",
+        )
+    } else {
+        ("🔒 ", "The game has started, the settings are locked.")
+    };
+    let settings = settings
+        .iter()
+        .map(|setting| format!("{}{}\n", marker, setting))
+        .collect::<String>();
+    format!("⚙️ Room {} settings\n\n{}\n{}", room_id.0, settings, footer)
+}
+
+pub async fn handle_settings_command<F: Frontend>(
+    frontend: F,
+    chat_id: ChatId,
+    rooms: Rooms,
+    user_id: UserId,
+) -> Result<(), F::Error> {
+    let room_id = memberships::active_room(user_id);
+    let Some(room) = room_id.and_then(|room_id| rooms.get(&room_id)) else {
+        frontend
+            .prompt(chat_id, "You haven't joined any room yet!".to_owned())
+            .await?;
+        return Ok(());
+    };
+
+    let text = settings_text(&*room.lock().await, *room.key());
+    frontend.prompt(chat_id, text).await
+}
+
+pub async fn handle_stats_command<F: Frontend>(
+    frontend: F,
+    chat_id: ChatId,
+    rooms: Rooms,
+    user_id: UserId,
+) -> Result<(), F::Error> {
+    let room_id = memberships::active_room(user_id);
+    let Some(room) = room_id.and_then(|room_id| rooms.get(&room_id)) else {
+        frontend
+            .prompt(chat_id, "You haven't joined any room yet!".to_owned())
+            .await?;
+        return Ok(());
+    };
+
+    let stats = room.lock().await.player_stats();
+    let text = if stats.is_empty() {
+        "Your game hasn't started yet!".to_owned()
+    } else {
+        awards::stats_text(&stats)
+    };
+    frontend.prompt(chat_id, text).await
+}
+
+pub async fn handle_history_command<F: Frontend>(
+    frontend: F,
+    chat_id: ChatId,
+    rooms: Rooms,
+    user_id: UserId,
+) -> Result<(), F::Error> {
+    let room_id = memberships::active_room(user_id);
+    let Some(room) = room_id.and_then(|room_id| rooms.get(&room_id)) else {
+        frontend
+            .prompt(chat_id, "You haven't joined any room yet!".to_owned())
+            .await?;
+        return Ok(());
+    };
+
+    let text = room
+        .lock()
+        .await
+        .history()
+        .unwrap_or_else(|| "Your game isn't being played right now!".to_owned());
+    frontend.prompt(chat_id, text).await
+}
+
+/// Sends the standings of the user's game, the final ones with the rematch
+/// button once it's over.
+pub async fn handle_results_command<F: Frontend>(
+    frontend: F,
+    chat_id: ChatId,
+    rooms: Rooms,
+    user_id: UserId,
+) -> Result<(), F::Error> {
+    let room_id = memberships::active_room(user_id);
+    let Some((room_id, room)) =
+        room_id.and_then(|room_id| rooms.get(&room_id).map(|room| (room_id, room)))
+    else {
+        frontend
+            .prompt(chat_id, "You haven't joined any room yet!".to_owned())
+            .await?;
+        return Ok(());
+    };
+
+    let room = room.lock().await;
+    if room.is_lobby() {
+        frontend
+            .prompt(chat_id, GameLogicError::IsNotPlaying.message().to_owned())
+            .await?;
+    } else if room.is_finished() {
+        frontend
+            .send_card(
+                chat_id,
+                format!("🏁 Final results\n\n{}", room.get_teams()),
+                vec![
+                    vec![Button::new(
+                        "📊 Scoreboard",
+                        room_id,
+                        CbQueryCommand::Scoreboard,
+                    )],
+                    rematch_buttons(room_id),
+                ],
+            )
+            .await?;
+    } else {
+        frontend
+            .prompt(
+                chat_id,
+                format!("📊 Standings so far\n\n{}", room.get_teams()),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Sends the scoreboard image of a finished game to the user who asked.
+async fn handle_scoreboard<F: Frontend>(
+    frontend: F,
+    room: &Room,
+    user: User,
+) -> Result<(), F::Error> {
+    if !room.is_finished() {
+        return report_error(&frontend, user.id, GameLogicError::AlreadyPlaying).await;
+    }
+
+    match render::scoreboard(&room.round_times(), scoreboard_winner(room)) {
+        Ok(image) => {
+            frontend
+                .send_image(user.id.into(), None, image, room.get_teams())
+                .await
+        }
+        Err(err) => {
+            log::warn!("Can not render scoreboard: {}", err);
+            frontend.prompt(user.id.into(), room.get_teams()).await
+        }
+    }
+}
+
+fn complexity_weights_text(room: &Room) -> String {
+    format!(
+        "How often should each complexity come up in rounds with mixed words?\n\n{}",
+        room.config().complexity_weights
+    )
+}
+
+fn complexity_weights_buttons(room_id: RoomId) -> Buttons {
+    Complexity::ALL
+        .into_iter()
+        .map(|complexity| {
+            [false, true]
+                .into_iter()
+                .map(|increase| {
+                    Button::new(
+                        &format!("{} {}", if increase { "➕" } else { "➖" }, complexity),
+                        room_id,
+                        CbQueryCommand::AdjustComplexityWeight {
+                            complexity,
+                            increase,
+                        },
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub async fn handle_complexity_weights<F: Frontend>(
+    frontend: F,
+    room: &Room,
+    room_id: RoomId,
+    user: User,
+) -> Result<(), F::Error> {
+    if user.id != room.host() {
+        return report_error(&frontend, user.id, GameLogicError::NotHost).await;
+    }
+
+    frontend
+        .send_card(
+            user.id.into(),
+            complexity_weights_text(room),
+            complexity_weights_buttons(room_id),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_adjust_complexity_weight<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+    complexity: Complexity,
+    increase: bool,
+) -> Result<(), F::Error> {
+    match room.adjust_complexity_weight(user.id, complexity, increase) {
+        Ok(true) => {
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_card(
+                        chat_id,
+                        message_id,
+                        complexity_weights_text(room),
+                        complexity_weights_buttons(room_id),
+                    )
+                    .await?;
+            }
+        }
+        Ok(false) => (),
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+const DIFFICULTY_PLAN_TEXT: &str = "Which words should each round use?";
+
+pub async fn handle_difficulty_plan<F: Frontend>(
+    frontend: F,
+    room: &Room,
+    room_id: RoomId,
+    user: User,
+) -> Result<(), F::Error> {
+    if user.id != room.host() {
+        return report_error(&frontend, user.id, GameLogicError::NotHost).await;
+    }
+
+    frontend
+        .send_card(
+            user.id.into(),
+            DIFFICULTY_PLAN_TEXT.to_owned(),
+            difficulty_plan_buttons(room, room_id),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_cycle_round_difficulty<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+    round: usize,
+) -> Result<(), F::Error> {
+    match room.cycle_round_difficulty(user.id, round) {
+        Ok(()) => {
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_card(
+                        chat_id,
+                        message_id,
+                        DIFFICULTY_PLAN_TEXT.to_owned(),
+                        difficulty_plan_buttons(room, room_id),
+                    )
+                    .await?;
+            }
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_alert_everyone<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.toggle_alert_everyone(user.id) {
+        Ok(alert_everyone) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    if alert_everyone {
+                        format!("{} sent the time alerts to everyone", user.full_name())
+                    } else {
+                        format!(
+                            "{} sent the time alerts to the playing pair only",
+                            user.full_name()
+                        )
+                    },
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_animated_timer<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.toggle_animated_timer(user.id) {
+        Ok(animated_timer) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    if animated_timer {
+                        format!(
+                            "{} turned the animated timer on, the group gets a countdown \
+                             each round",
+                            user.full_name()
+                        )
+                    } else {
+                        format!("{} turned the animated timer off", user.full_name())
+                    },
+                )
+                .await?;
+        }
+        Err(GameLogicError::NoGroupChat) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "The animated timer is shown in the group chat, create the room in a \
+                     group to use it!"
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_audio_alerts<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.toggle_audio_alerts(user.id) {
+        Ok(audio_alerts) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    if audio_alerts {
+                        format!(
+                            "{} turned audio alerts on, a sound plays before and when each \
+                             round ends",
+                            user.full_name()
+                        )
+                    } else {
+                        format!("{} turned audio alerts off", user.full_name())
+                    },
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_handicap<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.toggle_handicap(user.id) {
+        Ok(handicap) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    if handicap {
+                        format!(
+                            "{} turned the handicap on, the leading team waits {} seconds \
+                             longer to skip in the next round",
+                            user.full_name(),
+                            HANDICAP_IN_SECONDS
+                        )
+                    } else {
+                        format!("{} turned the handicap off", user.full_name())
+                    },
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_cycle_word_broadcast<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.cycle_word_broadcast(user.id) {
+        Ok(word_broadcast) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    format!(
+                        "{} changed who sees the words to: {}",
+                        user.full_name(),
+                        word_broadcast
+                    ),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_cycle_victory<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.cycle_victory(user.id) {
+        Ok(victory) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    format!(
+                        "{} changed how a team wins to: {}",
+                        user.full_name(),
+                        victory
+                    ),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_get_teams<F: Frontend>(
+    frontend: F,
+    room: &Room,
+    user: User,
+) -> Result<(), F::Error> {
+    frontend.prompt(user.id.into(), room.get_teams()).await?;
+    Ok(())
+}
+
+pub async fn handle_play<F: Frontend>(
+    room: &mut Room,
+    room_id: RoomId,
+    frontend: F,
+    user: User,
+) -> Result<(), F::Error> {
+    let public_post = room.public_post();
+    match room.play() {
+        Ok(describing_player) => {
+            close_public_post(&frontend, public_post, game_started_post(room_id)).await;
+            announce_game_start(&frontend, room, room_id, &describing_player).await?;
+        }
+        Err(GameLogicError::UnreachablePlayers) => {
+            let players = room
+                .unreachable_players()
+                .iter()
+                .map(User::full_name)
+                .collect::<Vec<_>>()
+                .join(", ");
+            frontend
+                .prompt(
+                    user.id.into(),
+                    format!(
+                        "The bot can't send cards to {} yet, they need to start a private \
+                         chat with it first",
+                        players
+                    ),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_rematch<F: Frontend>(
+    room: &mut Room,
+    room_id: RoomId,
+    frontend: F,
+    swap_roles: bool,
+) -> Result<(), F::Error> {
+    // Only the first press counts, the game is running for later ones
+    if let Ok(describing_player) = room.rematch(swap_roles) {
+        announce_game_start(&frontend, room, room_id, &describing_player).await?;
+    }
+    Ok(())
+}
+
+fn game_started_post(room_id: RoomId) -> String {
+    format!(
+        "▶️ The game in room {} has started, watch this channel for the next one!",
+        room_id.0
+    )
+}
+
+async fn announce_game_start<F: Frontend>(
+    frontend: &F,
+    room: &mut Room,
+    room_id: RoomId,
+    describing_player: &User,
+) -> Result<(), F::Error> {
+    scoreboard::publish(room_id, room);
+    if room.is_warming_up() {
+        return start_warm_up(frontend, room, room_id).await;
+    }
+
+    frontend
+        .broadcast(
+            room.get_all_players(),
+            format!(
+                "Game has started. {} should start the first round!",
+                describing_player.full_name()
+            ),
+        )
+        .await?;
+
+    send_start_round_button(frontend, room, room_id, describing_player).await?;
+    pin_scoreboard(frontend, room).await?;
+    Ok(())
+}
+
+/// A word for a pair to try the buttons with, nothing it does counts.
+fn warm_up_card(room: &mut Room, team_index: usize) -> Option<(ChatId, String)> {
+    let (_, _, describing, guessing) = room.warm_up_pairs().into_iter().nth(team_index)?;
+    let word = room.warm_up_word().ok()?;
+    let text = format!(
+        "🔥 Warm-up, nothing counts and there's no clock. {} describes this word to \
+         {}:\n\n{}\n\nPress ✅ once it's guessed or ⏩️ for another one.",
+        describing.full_name(),
+        guessing.full_name(),
+        word.get_message_string(room.use_taboo_words())
+    );
+    Some((player_chat(room, &describing), text))
+}
+
+fn warm_up_buttons(room_id: RoomId, team_index: usize) -> Buttons {
+    vec![vec![
+        Button::new("✅", room_id, CbQueryCommand::WarmUpCorrect { team_index }),
+        Button::new("⏩️", room_id, CbQueryCommand::WarmUpSkip { team_index }),
+    ]]
+}
+
+async fn send_finish_warm_up_card<F: Frontend>(
+    frontend: &F,
+    room: &Room,
+    room_id: RoomId,
+) -> Result<(), F::Error> {
+    frontend
+        .send_card(
+            room.host().into(),
+            "Start round 1 once everyone has warmed up".to_owned(),
+            vec![vec![Button::new(
+                "▶️ Start the game",
+                room_id,
+                CbQueryCommand::FinishWarmUp,
+            )]],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Deals every pair a warm-up word and lets the host start the real game
+/// whenever they are ready.
+async fn start_warm_up<F: Frontend>(
+    frontend: &F,
+    room: &mut Room,
+    room_id: RoomId,
+) -> Result<(), F::Error> {
+    frontend
+        .broadcast(
+            room.get_all_players(),
+            "🔥 Let's warm up! Every pair gets a word to try the buttons with before \
+             round 1."
+                .to_owned(),
+        )
+        .await?;
+
+    for (team_index, _, _, guessing) in room.warm_up_pairs() {
+        let Some((chat_id, text)) = warm_up_card(room, team_index) else {
+            continue;
+        };
+        frontend
+            .send_card(chat_id, text, warm_up_buttons(room_id, team_index))
+            .await?;
+        if room.device().is_none() {
+            frontend
+                .prompt(
+                    guessing.id.into(),
+                    "🔥 Your partner is describing a warm-up word to you".to_owned(),
+                )
+                .await?;
+        }
+    }
+    send_finish_warm_up_card(frontend, room, room_id).await
+}
+
+pub async fn handle_warm_up_correct<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    card: Option<(ChatId, MessageId)>,
+    team_index: usize,
+) -> Result<(), F::Error> {
+    let Ok((team, pairs_left)) = room.finish_warm_up_word(team_index) else {
+        return Ok(());
+    };
+
+    if let Some((chat_id, message_id)) = card {
+        frontend
+            .edit_card(
+                chat_id,
+                message_id,
+                "✅ Warmed up! Wait for the host to start the game.".to_owned(),
+                Vec::new(),
+            )
+            .await?;
+    }
+    let text = match pairs_left {
+        0 => "🔥 Every pair has warmed up, the host can start the game".to_owned(),
+        _ => format!("🔥 {} warmed up, {} pairs to go", team, pairs_left),
+    };
+    frontend.broadcast(room.get_all_players(), text).await?;
+    Ok(())
+}
+
+pub async fn handle_warm_up_skip<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    card: Option<(ChatId, MessageId)>,
+    team_index: usize,
+) -> Result<(), F::Error> {
+    if !room.is_warming_up() {
+        return Ok(());
+    }
+    let (Some((chat_id, message_id)), Some((_, text))) = (card, warm_up_card(room, team_index))
+    else {
+        return Ok(());
+    };
+
+    frontend
+        .edit_card(
+            chat_id,
+            message_id,
+            text,
+            warm_up_buttons(room_id, team_index),
+        )
+        .await
+}
+
+pub async fn handle_finish_warm_up<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<(), F::Error> {
+    match room.finish_warm_up(user.id) {
+        Ok(describing_player) => {
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_buttons(chat_id, message_id, Vec::new())
+                    .await?;
+            }
+            announce_game_start(&frontend, room, room_id, &describing_player).await?;
+        }
+        Err(GameLogicError::NotHost) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "Only the host can start the game!".to_owned(),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+fn bench_card_text(room: &Room) -> String {
+    let bench = room.bench().iter().map(User::full_name).collect::<Vec<_>>();
+    format!(
+        "🪑 On the bench: {}\n\nPick who {} replaces between rounds, they go to the bench.",
+        bench.join(", "),
+        bench.first().cloned().unwrap_or_default()
+    )
+}
+
+/// Lets the host swap the first player of the bench in for any player.
+async fn send_bench_card<F: Frontend>(
+    frontend: &F,
+    room: &Room,
+    room_id: RoomId,
+) -> Result<(), F::Error> {
+    let buttons = room
+        .seats()
+        .into_iter()
+        .map(|(seat, label)| {
+            vec![Button::new(
+                &label,
+                room_id,
+                CbQueryCommand::Substitute { seat },
+            )]
+        })
+        .collect();
+    frontend
+        .send_card(room.host().into(), bench_card_text(room), buttons)
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_substitute<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+    seat: usize,
+) -> Result<(), F::Error> {
+    match room.substitute(user.id, seat) {
+        Ok((outgoing, incoming, team)) => {
+            scoreboard::publish(room_id, room);
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_buttons(chat_id, message_id, Vec::new())
+                    .await?;
+            }
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    format!(
+                        "🔄 {} replaces {} in {}",
+                        incoming.full_name(),
+                        outgoing.full_name(),
+                        team
+                    ),
+                )
+                .await?;
+            send_bench_card(&frontend, room, room_id).await?;
+        }
+        Err(GameLogicError::NotHost) => {
+            frontend
+                .prompt(user.id.into(), "Only the host can swap players!".to_owned())
+                .await?;
+        }
+        Err(GameLogicError::RoundInProgress) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "Players can only be swapped between rounds!".to_owned(),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_auto_start<F: Frontend>(
+    frontend: F,
+    rooms: Rooms,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.toggle_auto_start(user.id) {
+        Ok(auto_start) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    if auto_start {
+                        "The game will start automatically once all teams are ready".to_owned()
+                    } else {
+                        "Auto-start is off, the game starts when someone presses Play".to_owned()
+                    },
+                )
+                .await?;
+            schedule_auto_start(frontend, rooms, room, room_id);
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_cancel_auto_start<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.cancel_auto_start(user.id) {
+        Ok(()) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    "The host cancelled the auto-start".to_owned(),
+                )
+                .await?;
+        }
+        Err(GameLogicError::NotHost) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "Only the host can cancel the auto-start!".to_owned(),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+fn schedule_auto_start<F: Frontend>(frontend: F, rooms: Rooms, room: &mut Room, room_id: RoomId) {
+    let Some((token, wait)) = room.arm_auto_start() else {
+        return;
+    };
+
+    metrics::spawn_timer(async move {
+        tokio::time::sleep(wait).await;
+        if let Err(err) = count_down_auto_start(&frontend, &rooms, room_id, token).await {
+            log::warn!("Can not count down auto-start: {}", err);
+            return;
+        }
+
+        tokio::time::sleep(Duration::from_secs(AUTO_START_COUNTDOWN_IN_SECONDS)).await;
+        let Some(room) = rooms.get(&room_id) else {
+            return;
+        };
+        let mut room = room.lock().await;
+        if !room.is_auto_start_armed(token) {
+            return;
+        }
+
+        let public_post = room.public_post();
+        let Ok(describing_player) = room.play() else {
+            return;
+        };
+        close_public_post(&frontend, public_post, game_started_post(room_id)).await;
+        if let Err(err) =
+            announce_game_start(&frontend, &mut room, room_id, &describing_player).await
+        {
+            error_reporting::report(room_id, format!("Can not auto-start game: {}", err));
+        }
+    });
+}
+
+async fn count_down_auto_start<F: Frontend>(
+    frontend: &F,
+    rooms: &Rooms,
+    room_id: RoomId,
+    token: u64,
+) -> Result<(), F::Error> {
+    let Some(room) = rooms.get(&room_id) else {
+        return Ok(());
+    };
+    let room = room.lock().await;
+    if !room.is_auto_start_armed(token) {
+        return Ok(());
+    }
+
+    let message = format!(
+        "All teams are ready! The game starts in {} seconds",
+        AUTO_START_COUNTDOWN_IN_SECONDS
+    );
+    frontend
+        .broadcast(room.get_all_players(), message.clone())
+        .await?;
+    frontend
+        .send_card(
+            room.host().into(),
+            message,
+            vec![vec![Button::new(
+                "❌ Cancel auto-start",
+                room_id,
+                CbQueryCommand::CancelAutoStart,
+            )]],
+        )
+        .await?;
+    Ok(())
+}
+
+async fn pin_scoreboard<F: Frontend>(frontend: &F, room: &mut Room) -> Result<(), F::Error> {
+    let Some(group_chat) = room.group_chat() else {
+        return Ok(());
+    };
+
+    let text = room.get_scoreboard_text();
+    let (chat_id, message_id) = frontend
+        .send_card_to_topic(group_chat, room.group_topic(), text.clone(), vec![])
+        .await?;
+    frontend.pin_message(chat_id, message_id).await?;
+    room.set_pinned_scoreboard(PinnedMessage {
+        chat_id,
+        message_id,
+        text,
+    });
+    Ok(())
+}
+
+/// Edits the pinned group scoreboard to reflect the current standings.
+async fn update_pinned_scoreboard<F: Frontend>(frontend: &F, room: &mut Room) {
+    let Some(mut pinned) = room.pinned_scoreboard() else {
+        return;
+    };
+
+    let text = room.get_scoreboard_text();
+    if text == pinned.text {
+        return;
+    }
+
+    if let Err(err) = frontend
+        .edit_text(pinned.chat_id, pinned.message_id, text.clone())
+        .await
+    {
+        log::warn!("Can not update pinned scoreboard: {}", err);
+        return;
+    }
+
+    pinned.text = text;
+    room.set_pinned_scoreboard(pinned);
+}
+
+async fn send_start_round_button<F: Frontend>(
+    frontend: &F,
+    room: &mut Room,
+    room_id: RoomId,
+    describing_player: &User,
+) -> Result<(), F::Error> {
+    let text = match room.device() {
+        Some(_) => format!(
+            "📱 Pass the phone to {}, then start the round",
+            describing_player.full_name()
+        ),
+        None => "Start round".to_owned(),
+    };
+    let (chat_id, message_id) = frontend
+        .send_card(
+            player_chat(room, describing_player),
+            text,
+            vec![vec![
+                Button::new("▶️", room_id, CbQueryCommand::Start),
+                Button::new("⏸️", room_id, CbQueryCommand::Pause),
+            ]],
+        )
+        .await?;
+
+    if room.push_to_message_stack(chat_id, message_id).is_err() {
+        error_reporting::report(room_id, "Can not push to the message stack".to_owned());
+    }
+    room.offer_start();
+    Ok(())
+}
+
+pub async fn handle_pause<F: Frontend>(
+    room: &mut Room,
+    room_id: RoomId,
+    frontend: F,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.pause() {
+        Ok(()) => {
+            scoreboard::publish(room_id, room);
+            clear_last_buttons(&frontend, room).await?;
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    format!("{} paused the game ⏸️", user.full_name()),
+                )
+                .await?;
+
+            let (chat_id, message_id) = frontend
+                .send_card(
+                    user.id.into(),
+                    "Game is paused".to_owned(),
+                    vec![vec![Button::new("⏯️", room_id, CbQueryCommand::Resume)]],
+                )
+                .await?;
+
+            if room.push_to_message_stack(chat_id, message_id).is_err() {
+                error_reporting::report(room_id, "Can not push to the message stack".to_owned());
+            }
+        }
+        Err(GameLogicError::RoundInProgress) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "You can't pause in the middle of a round".to_owned(),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_resume<F: Frontend>(
+    room: &mut Room,
+    room_id: RoomId,
+    frontend: F,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.resume() {
+        Ok(describing_player) => {
+            scoreboard::publish(room_id, room);
+            clear_last_buttons(&frontend, room).await?;
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    format!(
+                        "{} resumed the game ⏯️ {} should start the round!",
+                        user.full_name(),
+                        describing_player.full_name()
+                    ),
+                )
+                .await?;
+
+            send_start_round_button(&frontend, room, room_id, &describing_player).await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+async fn finish_round<F: Frontend>(
+    rooms: Rooms,
+    room_id: RoomId,
+    alerts: Vec<u64>,
+    time_left: Duration,
+    frontend: F,
+) {
+    let last_alert = alerts.iter().min().copied();
+    alerts
+        .into_iter()
+        .filter(|time| Duration::from_secs(*time) < time_left)
+        .for_each(|time| {
+            metrics::spawn_timer({
+                let rooms = rooms.clone();
+                let frontend = frontend.clone();
+                async move {
+                    tokio::time::sleep(time_left - Duration::from_secs(time)).await;
+                    let last = Some(time) == last_alert;
+                    send_time_alert(&frontend, &rooms, room_id, time, last).await;
+                }
+            });
+        });
+
+    tokio::time::sleep(time_left).await;
+    // A round that ended early, like by a first-to-N win, leaves its timer
+    // behind, which mustn't stop the next one
+    let Some(room) = rooms.get(&room_id) else {
+        return;
+    };
+    let due = room
+        .lock()
+        .await
+        .round_time_left()
+        .is_some_and(|left| left <= ROUND_END_TOLERANCE);
+    drop(room);
+    if due {
+        end_round(rooms, room_id, frontend).await;
+    }
+}
+
+/// Alerts the active pair, and the group chat if the room has one, that
+/// the round ends soon. Rooms with the setting on alert every player instead.
+/// The last alert comes with a sound in rooms with audio alerts.
+async fn send_time_alert<F: Frontend>(
+    frontend: &F,
+    rooms: &Rooms,
+    room_id: RoomId,
+    time: u64,
+    last: bool,
+) {
+    let Some(room) = rooms.get(&room_id) else {
+        return;
+    };
+    let (players, group, audio_alerts) = {
+        let room = room.lock().await;
+        // The round may have ended early
+        if room.round_time_left().is_none() {
+            return;
+        }
+        (
+            room.time_alert_recipients(),
+            alerted_group(&room),
+            room.audio_alerts(),
+        )
+    };
+
+    let message = time_alert_message(time);
+    let players = notifications::recipients(players, Category::TimeAlert);
+    if let Err(err) = frontend.broadcast(players.clone(), message.clone()).await {
+        log::warn!("Can not broadcast time alert: {}", err);
+    }
+    if let Some((group_chat, topic)) = group {
+        if let Err(err) = frontend.prompt_to_topic(group_chat, topic, message).await {
+            log::warn!("Can not send time alert to the group: {}", err);
+        }
+    }
+
+    if last && audio_alerts {
+        play_sound(frontend, players, group, Sound::Warning).await;
+    }
+}
+
+/// The group chat and topic time alerts go to, unless they already reach
+/// everyone in private.
+fn alerted_group(room: &Room) -> Option<(ChatId, Option<i32>)> {
+    room.group_chat()
+        .filter(|_| !room.alerts_everyone())
+        .map(|group_chat| (group_chat, room.group_topic()))
+}
+
+async fn play_sound<F: Frontend>(
+    frontend: &F,
+    players: Vec<UserId>,
+    group: Option<(ChatId, Option<i32>)>,
+    sound: Sound,
+) {
+    let Some(ogg) = sounds::get(sound) else {
+        return;
+    };
+
+    let chats = players
+        .into_iter()
+        .map(|player| (ChatId::from(player), None))
+        .chain(group);
+    for (chat_id, topic) in chats {
+        if let Err(err) = frontend.send_sound(chat_id, topic, ogg.clone()).await {
+            log::warn!("Can not send sound: {}", err);
+        }
+    }
+}
+
+/// Stops the round in progress once its time is up.
+pub async fn end_round<F: Frontend>(rooms: Rooms, room_id: RoomId, frontend: F) {
+    let Some(room) = rooms.get(&room_id) else {
+        return;
+    };
+
+    let mut room = room.lock().await;
+    stop_round(&frontend, &mut room, room_id).await;
+    if let Some(break_left) = room.break_left() {
+        metrics::spawn_timer(end_break(rooms.clone(), room_id, frontend, break_left));
+    }
+}
+
+/// Hands the next describer the ▶️ button once the break between rounds is
+/// over, unless someone started or paused the game meanwhile.
+async fn end_break<F: Frontend>(rooms: Rooms, room_id: RoomId, frontend: F, break_left: Duration) {
+    tokio::time::sleep(break_left).await;
+    let Some(room) = rooms.get(&room_id) else {
+        return;
+    };
+    let mut room = room.lock().await;
+    let Some((describing_player, false)) = room.describing_player() else {
+        return;
+    };
+    if room.is_paused() {
+        return;
+    }
+
+    if let Err(err) = frontend
+        .broadcast(
+            room.get_all_players(),
+            format!(
+                "☕ The break is over! {} should start the round",
+                describing_player.full_name()
+            ),
+        )
+        .await
+    {
+        log::warn!("Can not broadcast the end of the break: {}", err);
+    }
+    if let Err(err) =
+        send_start_round_button(&frontend, &mut room, room_id, &describing_player).await
+    {
+        error_reporting::report(
+            room_id,
+            format!("Can not send start round message: {}", err),
+        );
+    }
+    persistence::save(room_id, &room);
+}
+
+/// Stops the round in progress of a locked room, and the game with it after
+/// the last round or once a first-to-N or co-op game reached its target. The
+/// last round runs over instead while teams are owed turns.
+async fn stop_round<F: Frontend>(frontend: &F, room: &mut Room, room_id: RoomId) {
+    if let Some(teams) = room.extend_for_balance() {
+        if let Err(err) = frontend
+            .broadcast(
+                room.get_all_players(),
+                format!(
+                    "⚖️ Time's up! {} still describe once more so every team had as many turns",
+                    teams.join(", ")
+                ),
+            )
+            .await
+        {
+            log::warn!("Can not broadcast the balancing turns: {}", err);
+        }
+        persistence::save(room_id, room);
+        return;
+    }
+
+    if let Err(err) = clear_last_buttons(frontend, room).await {
+        log::warn!("Can not clear buttons: {}", err);
+    }
+    if let Err(err) = clear_relay_cards(frontend, room.relay_cards()).await {
+        log::warn!("Can not clear relay cards: {}", err);
+    }
+
+    let buzzer = room.audio_alerts().then(|| {
+        let players = notifications::recipients(room.time_alert_recipients(), Category::TimeAlert);
+        (players, alerted_group(room))
+    });
+
+    let Ok(round_stop_state) = room.stop_round() else {
+        error_reporting::report(room_id, "Room in bad state while stopping round".to_owned());
+        return;
+    };
+    scoreboard::publish(room_id, room);
+    update_pinned_scoreboard(frontend, room).await;
+
+    if let Some((players, group)) = buzzer {
+        play_sound(frontend, players, group, Sound::Buzzer).await;
+    }
+
+    match round_stop_state {
+        room::RoundStopState::RoundFinished(
+            results,
+            describing_player,
+            round,
+            total_rounds,
+            turn_order,
+        ) => {
+            if let Err(err) = frontend
+                .broadcast(
+                    notifications::recipients(room.get_all_players(), Category::Result),
+                    results,
+                )
+                .await
+            {
+                log::warn!("Can not broadcast results: {}", err);
+            }
+            send_team_summaries(frontend, room).await;
+
+            let handicap = room
+                .handicapped_team()
+                .map(|team| {
+                    format!(
+                        "\n🪢 {} leads, they wait {} seconds longer to skip this round",
+                        team, HANDICAP_IN_SECONDS
+                    )
+                })
+                .unwrap_or_default();
+            let break_left = room.break_left();
+            let intro = match break_left {
+                Some(left) => format!(
+                    "Round has finished! ☕ Take a {} minute break, then",
+                    left.as_secs().div_ceil(60)
+                ),
+                None => "Round has finished!".to_owned(),
+            };
+            if let Err(err) = frontend
+                .broadcast(
+                    room.get_all_players(),
+                    format!(
+                        "{} {} should start round {}/{}!\nTurn order: {}{}",
+                        intro,
+                        describing_player.full_name(),
+                        round,
+                        total_rounds,
+                        turn_order.join(" → "),
+                        handicap
+                    ),
+                )
+                .await
+            {
+                log::warn!("Can not broadcast round finished alert: {}", err);
+            }
+
+            offer_dispute(frontend, room, room_id).await;
+
+            // The button waits for the end of the break
+            if break_left.is_none() {
+                if let Err(err) =
+                    send_start_round_button(frontend, room, room_id, &describing_player).await
+                {
+                    error_reporting::report(
+                        room_id,
+                        format!("Can not send start round message: {}", err),
+                    );
+                }
+            }
+        }
+        room::RoundStopState::GameFinished(results) => {
+            if let Err(err) = frontend
+                .broadcast(room.get_all_players(), "Game finished!".to_owned())
+                .await
+            {
+                log::warn!("Can not broadcast game finished alert: {}", err);
+            }
+            broadcast_final_results(frontend, room, results).await;
+            if let Some(awards) = awards::ceremony(&room.player_stats()) {
+                if let Err(err) = frontend.broadcast(room.get_all_players(), awards).await {
+                    log::warn!("Can not broadcast awards: {}", err);
+                }
+            }
+            if let Some(final_pairs) = room.final_pairs() {
+                duos::record_game(&final_pairs.pairs, final_pairs.winner);
+                ratings::record_game(&final_pairs.pairs, final_pairs.winner);
+            }
+            if let Some(summary) = room.summary(room_id) {
+                results_export::export(&summary);
+                tokio::spawn(results_webhook::notify_game_finished(summary));
+            }
+            offer_rematch(frontend, room, room_id).await;
+        }
+    }
+    persistence::save(room_id, room);
+}
+
+/// Tells each team privately how their round went, next to the public
+/// standings.
+async fn send_team_summaries<F: Frontend>(frontend: &F, room: &Room) {
+    for (players, summary) in room.team_round_summaries() {
+        if let Err(err) = frontend.broadcast(players, summary).await {
+            log::warn!("Can not send team summary: {}", err);
+        }
+    }
+}
+
+/// Lets players contest the words of the round that just ended until the
+/// next one starts.
+async fn offer_dispute<F: Frontend>(frontend: &F, room: &Room, room_id: RoomId) {
+    for player in room.get_all_players() {
+        if !room
+            .disputable_words(player)
+            .is_ok_and(|words| !words.is_empty())
+        {
+            continue;
+        }
+
+        if let Err(err) = frontend
+            .send_card(
+                player.into(),
+                "Did another team break the rules? You can dispute their words until \
+                 the next round starts."
+                    .to_owned(),
+                vec![vec![Button::new(
+                    "⚖️ Dispute",
+                    room_id,
+                    CbQueryCommand::Dispute,
+                )]],
+            )
+            .await
+        {
+            log::warn!("Can not offer a dispute: {}", err);
+        }
+    }
+}
+
+pub async fn handle_dispute<F: Frontend>(
+    frontend: F,
+    room: &Room,
+    room_id: RoomId,
+    user: User,
+) -> Result<(), F::Error> {
+    let words = match room.disputable_words(user.id) {
+        Ok(words) if !words.is_empty() => words,
+        Ok(_) => {
+            frontend
+                .prompt(user.id.into(), "There is nothing to dispute".to_owned())
+                .await?;
+            return Ok(());
+        }
+        Err(GameLogicError::RoundInProgress) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "The next round has started, it's too late to dispute".to_owned(),
+                )
+                .await?;
+            return Ok(());
+        }
+        Err(err) => return report_error(&frontend, user.id, err).await,
+    };
+
+    frontend
+        .send_card(
+            user.id.into(),
+            "Which word do you want to void?".to_owned(),
+            words
+                .into_iter()
+                .map(|(index, word)| {
+                    vec![Button::new(
+                        &word,
+                        room_id,
+                        CbQueryCommand::DisputeWord { index },
+                    )]
+                })
+                .collect(),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_dispute_word<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    index: usize,
+) -> Result<(), F::Error> {
+    match room.dispute_word(user.id, index) {
+        Ok(room::DisputeOutcome::Voted {
+            word,
+            team,
+            votes,
+            needed,
+        }) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    format!(
+                        "⚖️ {} disputes \"{}\" of {} ({}/{} votes). \
+                         Tap ⚖️ Dispute to vote for voiding it.",
+                        user.full_name(),
+                        word,
+                        team,
+                        votes,
+                        needed
+                    ),
+                )
+                .await?;
+        }
+        Ok(room::DisputeOutcome::Voided { word, team }) => {
+            scoreboard::publish(room_id, room);
+            update_pinned_scoreboard(&frontend, room).await;
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    format!(
+                        "⚖️ \"{}\" of {} was voided, {} seconds are added to their time.\n\n{}",
+                        word,
+                        team,
+                        room::VOIDED_WORD_PENALTY_IN_SECONDS,
+                        room.get_scoreboard_text()
+                    ),
+                )
+                .await?;
+        }
+        Err(GameLogicError::RoundInProgress) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "The next round has started, it's too late to dispute".to_owned(),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+async fn offer_rematch<F: Frontend>(frontend: &F, room: &Room, room_id: RoomId) {
+    for player in room.get_all_players() {
+        if let Err(err) = frontend
+            .send_card(
+                player.into(),
+                "Up for another game with the same teams?".to_owned(),
+                vec![rematch_buttons(room_id)],
+            )
+            .await
+        {
+            log::warn!("Can not offer a rematch: {}", err);
+        }
+    }
+}
+
+/// A rematch as is, or with the partners swapping who describes first and
+/// the teams playing in the reverse order.
+fn rematch_buttons(room_id: RoomId) -> Vec<Button> {
+    vec![
+        Button::new(
+            "🔁 Rematch",
+            room_id,
+            CbQueryCommand::Rematch { swap_roles: false },
+        ),
+        Button::new(
+            "🔀 Rematch, roles swapped",
+            room_id,
+            CbQueryCommand::Rematch { swap_roles: true },
+        ),
+    ]
+}
+
+/// The team to highlight on the scoreboard, none in co-op games where the
+/// teams play together.
+fn scoreboard_winner(room: &Room) -> Option<usize> {
+    if room.config().coop_target.is_some() {
+        None
+    } else {
+        room.winner()
+    }
+}
+
+/// Sends the scoreboard image with the textual results as its caption, falling
+/// back to plain text wherever the image can't be rendered or delivered.
+async fn broadcast_final_results<F: Frontend>(frontend: &F, room: &Room, results: String) {
+    let image = match render::scoreboard(&room.round_times(), scoreboard_winner(room)) {
+        Ok(image) => Some(image),
+        Err(err) => {
+            log::warn!("Can not render scoreboard: {}", err);
+            None
+        }
+    };
+
+    for player in room.get_all_players() {
+        if let Some(image) = &image {
+            match frontend
+                .send_image(player.into(), None, image.clone(), results.clone())
+                .await
+            {
+                Ok(()) => continue,
+                Err(err) => log::warn!("Can not send scoreboard image: {}", err),
+            }
+        }
+
+        if let Err(err) = frontend.prompt(player.into(), results.clone()).await {
+            log::warn!("Can not send results: {}", err);
+        }
+    }
+}
+
+/// A frame of the group's countdown: a bar emptying as the round runs.
+fn animated_timer_text(time_left: Duration, round_duration: Duration) -> String {
+    let seconds = time_left.as_secs();
+    let filled = (seconds * ANIMATED_TIMER_WIDTH).div_ceil(round_duration.as_secs().max(1));
+    format!(
+        "{} {}{} {}:{:02}",
+        if seconds.is_multiple_of(20) {
+            "⏳"
+        } else {
+            "⌛"
+        },
+        "🟩".repeat(filled as usize),
+        "⬜".repeat((ANIMATED_TIMER_WIDTH - filled.min(ANIMATED_TIMER_WIDTH)) as usize),
+        seconds / 60,
+        seconds % 60
+    )
+}
+
+/// Posts a countdown in the group chat and keeps it ticking until the round
+/// ends.
+async fn start_animated_timer<F: Frontend>(
+    frontend: &F,
+    rooms: Rooms,
+    room: &Room,
+    room_id: RoomId,
+) {
+    let (Some(group_chat), true) = (room.group_chat(), room.animated_timer()) else {
+        return;
+    };
+    let round_duration = room.round_duration();
+    let text = animated_timer_text(round_duration, round_duration);
+    let timer = match frontend
+        .send_card_to_topic(group_chat, room.group_topic(), text, vec![])
+        .await
+    {
+        Ok(timer) => timer,
+        Err(err) => {
+            log::warn!("Can not send animated timer: {:?} {}", room_id, err);
+            return;
+        }
+    };
+
+    let frontend = frontend.clone();
+    metrics::spawn_timer(async move {
+        let mut last_time_left = round_duration;
+        loop {
+            tokio::time::sleep(ANIMATED_TIMER_INTERVAL).await;
+            let time_left = {
+                let Some(room) = rooms.get(&room_id) else {
+                    return;
+                };
+                let room = room.lock().await;
+                if room.is_paused() {
+                    continue;
+                }
+                room.round_time_left()
+            };
+            // Otherwise the round ended, or a new one started since the last
+            // tick.
+            let running =
+                time_left.filter(|time_left| *time_left < last_time_left && !time_left.is_zero());
+            let text = match running {
+                Some(time_left) => animated_timer_text(time_left, round_duration),
+                None => "⌛ Time's up!".to_owned(),
+            };
+
+            if let Err(err) = frontend.edit_text(timer.0, timer.1, text).await {
+                log::warn!("Can not update animated timer: {:?} {}", room_id, err);
+                return;
+            }
+            match running {
+                Some(time_left) => last_time_left = time_left,
+                None => return,
+            }
+        }
+    });
+}
+
+/// Tells every player when the round ends, in their own timezone.
+async fn announce_round_end<F: Frontend>(frontend: &F, room: &Room) {
+    let round_end = SystemTime::now() + room.round_duration();
+
+    for player in notifications::recipients(room.get_all_players(), Category::TimeAlert) {
+        let mut text = format!(
+            "⏱️ Round ends at {}",
+            timezone::format_time(player, round_end)
+        );
+        if timezone::should_ask(player) {
+            text += &format!("\n{}", timezone::ASK_MESSAGE);
+        }
+
+        if let Err(err) = frontend.prompt(player.into(), text).await {
+            log::warn!("Can not announce round end: {}", err);
+        }
+    }
+}
+
+pub async fn handle_start_round<F: Frontend>(
+    rooms: Rooms,
+    room: &mut Room,
+    room_id: RoomId,
+    frontend: F,
+) -> Result<(), F::Error> {
+    if let Ok(word_guess_try) = room.start_round() {
+        match &word_guess_try.word {
+            Some(word) if room.config().relay => {
+                send_relay_word(&frontend, room, room_id, word).await?
+            }
+            _ => {
+                send_new_word(
+                    rooms.clone(),
+                    room,
+                    room_id,
+                    frontend.clone(),
+                    word_guess_try,
+                )
+                .await?
+            }
+        }
+
+        announce_round_end(&frontend, room).await;
+        start_animated_timer(&frontend, rooms.clone(), room, room_id).await;
+
+        metrics::spawn_timer({
+            let round_duration = room.round_duration();
+            async move {
+                let alerts = time_alerts(round_duration);
+                finish_round(rooms, room_id, alerts, round_duration, frontend).await;
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Pauses every running game ahead of a shutdown so no round runs out while
+/// the bot is down, they continue when it starts again.
+pub async fn park_games<F: Frontend>(frontend: F, rooms: Rooms) {
+    let room_ids = rooms.iter().map(|room| *room.key()).collect::<Vec<_>>();
+
+    for room_id in room_ids {
+        let Some(room) = rooms.get(&room_id) else {
+            continue;
+        };
+        let mut room = room.lock().await;
+
+        if room.park() {
+            scoreboard::publish(room_id, &room);
+            if let Err(err) = clear_last_buttons(&frontend, &room).await {
+                log::warn!("Can not clear buttons: {}", err);
+            }
+            if let Err(err) = frontend
+                .broadcast(
+                    room.get_all_players(),
+                    "🛠 The bot is restarting for maintenance, your game is paused. \
+                     It continues as soon as the bot is back."
+                        .to_owned(),
+                )
+                .await
+            {
+                log::warn!("Can not broadcast maintenance notice: {}", err);
+            }
+        }
+        persistence::save(room_id, &room);
+    }
+}
+
+/// Continues the games parked by the previous shutdown and restarts the
+/// timers of rounds that were in progress when the bot stopped. Rounds that
+/// ran out in the meantime finish right away.
+pub async fn resume_games<F: Frontend>(frontend: F, rooms: Rooms) {
+    let room_ids = rooms.iter().map(|room| *room.key()).collect::<Vec<_>>();
+
+    for room_id in room_ids {
+        let Some(room) = rooms.get(&room_id) else {
+            continue;
+        };
+        let mut room = room.lock().await;
+
+        if let Ok(describing_player) = room.unpark() {
+            if let Err(err) = continue_parked_game(
+                &frontend,
+                rooms.clone(),
+                &mut room,
+                room_id,
+                &describing_player,
+            )
+            .await
+            {
+                error_reporting::report(room_id, format!("Can not continue parked game: {}", err));
+            }
+            persistence::save(room_id, &room);
+        }
+
+        let Some(time_left) = room.round_time_left() else {
+            continue;
+        };
+
+        metrics::spawn_timer(finish_round(
+            rooms.clone(),
+            room_id,
+            time_alerts(room.round_duration()),
+            time_left,
+            frontend.clone(),
+        ));
+    }
+}
+
+async fn continue_parked_game<F: Frontend>(
+    frontend: &F,
+    rooms: Rooms,
+    room: &mut Room,
+    room_id: RoomId,
+    describing_player: &User,
+) -> Result<(), F::Error> {
+    scoreboard::publish(room_id, room);
+    frontend
+        .broadcast(
+            room.get_all_players(),
+            "🛠 The bot is back, your game continues!".to_owned(),
+        )
+        .await?;
+
+    if room.is_warming_up() {
+        return send_finish_warm_up_card(frontend, room, room_id).await;
+    }
+    if let Some(break_left) = room.break_left() {
+        metrics::spawn_timer(end_break(rooms, room_id, frontend.clone(), break_left));
+        return Ok(());
+    }
+    if room.round_time_left().is_none() {
+        return send_start_round_button(frontend, room, room_id, describing_player).await;
+    }
+
+    if let Ok(word_guess_try) = room.redeal() {
+        send_new_word(rooms, room, room_id, frontend.clone(), word_guess_try).await?;
+    }
+    Ok(())
+}
+
+/// Reminds describers who leave the ▶️ button unpressed, every
+/// `idle_reminder_interval()`, and tells the others who they wait on. Past
+/// `idle_auto_advance()` reminders the partner gets the button instead.
+pub async fn remind_idle_describers<F: Frontend>(frontend: F, rooms: Rooms) {
+    loop {
+        tokio::time::sleep(IDLE_DESCRIBER_SWEEP_INTERVAL).await;
+
+        let room_ids = rooms.iter().map(|room| *room.key()).collect::<Vec<_>>();
+        for room_id in room_ids {
+            let Some(entry) = rooms.get(&room_id) else {
+                continue;
+            };
+            let mut room = entry.lock().await;
+            let Some((describer, reminder)) = room.idle_describer(idle_reminder_interval()) else {
+                continue;
+            };
+
+            let result = if idle_auto_advance().is_some_and(|reminders| reminder > reminders) {
+                advance_idle_describer(&frontend, &mut room, room_id, &describer).await
+            } else {
+                room.note_idle_reminder();
+                remind_idle_describer(&frontend, &room, &describer).await
+            };
+            if let Err(err) = result {
+                log::warn!("Can not remind idle describer in {:?}: {}", room_id, err);
+            }
+            persistence::save(room_id, &room);
+        }
+    }
+}
+
+async fn remind_idle_describer<F: Frontend>(
+    frontend: &F,
+    room: &Room,
+    describer: &User,
+) -> Result<(), F::Error> {
+    frontend
+        .prompt(
+            player_chat(room, describer),
+            "⏰ Everyone is waiting for you, press ▶️ to start the round!".to_owned(),
+        )
+        .await?;
+    let others = room
+        .get_all_players()
+        .into_iter()
+        .filter(|player| *player != describer.id)
+        .collect();
+    frontend
+        .broadcast(
+            others,
+            format!("⏳ Waiting on {} to start the round", describer.full_name()),
+        )
+        .await
+}
+
+async fn advance_idle_describer<F: Frontend>(
+    frontend: &F,
+    room: &mut Room,
+    room_id: RoomId,
+    describer: &User,
+) -> Result<(), F::Error> {
+    let Ok(partner) = room.pass_start_to_partner() else {
+        return Ok(());
+    };
+    clear_last_buttons(frontend, room).await?;
+    frontend
+        .broadcast(
+            room.get_all_players(),
+            format!(
+                "💤 {} seems to be away, {} starts the round instead",
+                describer.full_name(),
+                partner.full_name()
+            ),
+        )
+        .await?;
+    send_start_round_button(frontend, room, room_id, &partner).await
+}
+
+/// Tells the rooms of players who became unreachable, every
+/// `UNREACHABLE_REPORT_INTERVAL`, so the others know why they don't respond.
+/// The host is told in private, and the group chat too if the room has one.
+pub async fn report_unreachable_players<F: Frontend>(frontend: F, rooms: Rooms) {
+    loop {
+        tokio::time::sleep(UNREACHABLE_REPORT_INTERVAL).await;
+
+        let users = reachability::take_unreported();
+        if users.is_empty() {
+            continue;
+        }
+
+        let room_ids = rooms.iter().map(|room| *room.key()).collect::<Vec<_>>();
+        for room_id in room_ids {
+            let Some(entry) = rooms.get(&room_id) else {
+                continue;
+            };
+            let room = entry.lock().await;
+            if room.is_finished() {
+                continue;
+            }
+
+            let players = users
+                .iter()
+                .filter_map(|user_id| room.find_player(*user_id))
+                .collect::<Vec<_>>();
+            if players.is_empty() {
+                continue;
+            }
+
+            for player in players {
+                let text = format!(
+                    "⚠️ {} can't be reached, they may have blocked the bot. They won't get \
+                     any cards until they message the bot again.",
+                    player.full_name()
+                );
+                let chats = [Some(ChatId::from(room.host())), room.group_chat()];
+                for chat_id in chats.into_iter().flatten() {
+                    if chat_id == player.id.into() {
+                        continue;
+                    }
+                    let topic = topic_of(&room, chat_id);
+                    if let Err(err) = frontend.prompt_to_topic(chat_id, topic, text.clone()).await {
+                        log::warn!("Can not report unreachable player: {}", err);
+                    }
+                }
+            }
+            refresh_lobby_cards(&frontend, &room, room_id).await;
+        }
+    }
+}
+
+/// Looks for stuck games every `WATCHDOG_INTERVAL`. Rounds whose timer never
+/// stopped them are ended, as are balancing turns nobody finishes, and games
+/// nobody continues for `ABANDONED_GAME_TIMEOUT` are paused so they stop
+/// waiting on the describer.
+pub async fn watch_stuck_rooms<F: Frontend>(frontend: F, rooms: Rooms) {
+    loop {
+        tokio::time::sleep(WATCHDOG_INTERVAL).await;
+
+        let room_ids = rooms.iter().map(|room| *room.key()).collect::<Vec<_>>();
+        for room_id in room_ids {
+            let Some(entry) = rooms.get(&room_id) else {
+                continue;
+            };
+            let mut room = entry.lock().await;
+            let result = match room.anomaly(OVERDUE_ROUND_GRACE, ABANDONED_GAME_TIMEOUT) {
+                Some(Anomaly::OverdueRound) => {
+                    error_reporting::report(
+                        room_id,
+                        "Round ran past its time without stopping".to_owned(),
+                    );
+                    let result = frontend
+                        .broadcast(
+                            room.get_all_players(),
+                            "🩹 The round didn't stop on time, so it was ended.".to_owned(),
+                        )
+                        .await;
+                    drop(room);
+                    end_round(rooms.clone(), room_id, frontend.clone()).await;
+                    result
+                }
+                Some(Anomaly::OverdueBalancingTurn) => {
+                    let result = frontend
+                        .broadcast(
+                            room.get_all_players(),
+                            "⚖️ The turn took as long as a whole round, so the round was ended."
+                                .to_owned(),
+                        )
+                        .await;
+                    drop(room);
+                    end_round(rooms.clone(), room_id, frontend.clone()).await;
+                    result
+                }
+                Some(Anomaly::Abandoned) => {
+                    let result = pause_abandoned_game(&frontend, &mut room, room_id).await;
+                    persistence::save(room_id, &room);
+                    result
+                }
+                None => continue,
+            };
+            if let Err(err) = result {
+                log::warn!("Can not recover stuck room {:?}: {}", room_id, err);
+            }
+        }
+    }
+}
+
+async fn pause_abandoned_game<F: Frontend>(
+    frontend: &F,
+    room: &mut Room,
+    room_id: RoomId,
+) -> Result<(), F::Error> {
+    let Some((describing_player, false)) = room.describing_player() else {
+        return Ok(());
+    };
+    if room.pause().is_err() {
+        return Ok(());
+    }
+    log::info!("Paused room {:?}, nobody continued the game", room_id);
+
+    scoreboard::publish(room_id, room);
+    clear_last_buttons(frontend, room).await?;
+    frontend
+        .broadcast(
+            room.get_all_players(),
+            format!(
+                "⏸️ Nobody started the next round for {} minutes, so the game is paused. \
+                 {} can continue it with ⏯️",
+                ABANDONED_GAME_TIMEOUT.as_secs() / 60,
+                describing_player.full_name()
+            ),
+        )
+        .await?;
+
+    let (chat_id, message_id) = frontend
+        .send_card(
+            room.device().unwrap_or(describing_player.id).into(),
+            "Game is paused".to_owned(),
+            vec![vec![Button::new("⏯️", room_id, CbQueryCommand::Resume)]],
+        )
+        .await?;
+    if room.push_to_message_stack(chat_id, message_id).is_err() {
+        error_reporting::report(room_id, "Can not push to the message stack".to_owned());
+    }
+    Ok(())
+}
+
+/// Tears down unfinished rooms whose players all blocked the bot, every
+/// `DEAD_ROOM_SWEEP_INTERVAL`. Their timers notice the room is gone and stop,
+/// and the ID becomes free again.
+pub async fn remove_dead_rooms<F: Frontend>(frontend: F, rooms: Rooms) {
+    loop {
+        tokio::time::sleep(DEAD_ROOM_SWEEP_INTERVAL).await;
+
+        let room_ids = rooms.iter().map(|room| *room.key()).collect::<Vec<_>>();
+        for room_id in room_ids {
+            let (group, public_post) = {
+                let Some(entry) = rooms.get(&room_id) else {
+                    continue;
+                };
+                let room = entry.lock().await;
+                let players = room.get_all_players();
+                if room.is_finished()
+                    || players.is_empty()
+                    || !players.into_iter().all(reachability::is_unreachable)
+                {
+                    continue;
+                }
+                let group = room
+                    .group_chat()
+                    .map(|group_chat| (group_chat, room.group_topic()));
+                (group, room.public_post())
+            };
+
+            rooms.remove(&room_id);
+            persistence::remove(room_id);
+            log::info!(
+                "Removed room {:?}, none of its players can be reached",
+                room_id
+            );
+
+            let closed = format!("🚪 Room {} was closed.", room_id.0);
+            close_public_post(&frontend, public_post, closed).await;
+            let Some((group_chat, topic)) = group else {
+                continue;
+            };
+            if let Err(err) = frontend
+                .prompt_to_topic(
+                    group_chat,
+                    topic,
+                    format!(
+                        "🚪 Room {} was closed, none of its players can be reached anymore.",
+                        room_id.0
+                    ),
+                )
+                .await
+            {
+                log::warn!("Can not announce closing room {:?}: {}", room_id, err);
+            }
+        }
+    }
+}
+
+async fn clear_last_buttons<F: Frontend>(frontend: &F, room: &Room) -> Result<(), F::Error> {
+    let Ok(Some((chat_id, message_id))) = room.get_message_stack_top() else {
+        return Ok(());
+    };
+
+    frontend.edit_buttons(chat_id, message_id, vec![]).await?;
+
+    Ok(())
+}
+
+async fn send_new_word<F: Frontend>(
+    rooms: Rooms,
+    room: &mut Room,
+    room_id: RoomId,
+    frontend: F,
+    word_guess_try: room::WordGuessTry,
+) -> Result<(), F::Error> {
+    scoreboard::publish(room_id, room);
+    update_pinned_scoreboard(&frontend, room).await;
+    clear_last_buttons(&frontend, room).await?;
+    let Some(word) = &word_guess_try.word else {
+        return send_draw_card(&frontend, room, room_id, &word_guess_try.describing).await;
+    };
+    let mut card = describer_card_text(room, word, &word_guess_try.context);
+    if room.device().is_some() {
+        card = format!(
+            "📱 Turn {}: {} describes to {}\n\n{}",
+            word_guess_try.context.turn,
+            word_guess_try.describing.full_name(),
+            word_guess_try.guessing.full_name(),
+            card
+        );
+    }
+    // A captain who isn't describing gets the buttons on a card of their own
+    let captain = room
+        .controller()
+        .filter(|captain| room.device().is_none() && captain.id != word_guess_try.describing.id);
+    let correct_button = vec![vec![Button::new("✅", room_id, CbQueryCommand::Correct)]];
+    let sent_message = match &captain {
+        Some(captain) => {
+            frontend
+                .send_card(word_guess_try.describing.id.into(), card, Vec::new())
+                .await?;
+            frontend
+                .send_card(
+                    captain.id.into(),
+                    format!(
+                        "👑 {} is describing to {}, press ✅ once the word is guessed",
+                        word_guess_try.describing.full_name(),
+                        word_guess_try.guessing.full_name()
+                    ),
+                    correct_button,
+                )
+                .await?
+        }
+        None => {
+            frontend
+                .send_card(
+                    player_chat(room, &word_guess_try.describing),
+                    card,
+                    correct_button,
+                )
+                .await?
+        }
+    };
+
+    if room
+        .push_to_message_stack(sent_message.0, sent_message.1)
+        .is_err()
+    {
+        error_reporting::report(room_id, "Can not push to the message stack".to_owned());
+    }
+
+    // The guesser is looking at the same phone in a pass-the-phone game, and
+    // a captain guessing already has a card of their own
+    let guesser_has_card = captain.is_some_and(|captain| captain.id == word_guess_try.guessing.id);
+    if let (None, false, Some(text)) = (room.device(), guesser_has_card, guesser_card_text(room)) {
+        let guesser_card = frontend
+            .send_card(word_guess_try.guessing.id.into(), text, Vec::new())
+            .await?;
+        metrics::spawn_timer(refresh_guesser_card(
+            rooms.clone(),
+            room_id,
+            frontend.clone(),
+            sent_message,
+            guesser_card,
+        ));
+    }
+    frontend
+        .broadcast_with_spoiler(
+            notifications::recipients(room.get_word_spectators(), Category::OtherTeamWord),
+            format!(
+                "{} -> {}\n",
+                word_guess_try.describing.full_name(),
+                word_guess_try.guessing.full_name(),
+            ),
+            word.get_message_string(room.use_taboo_words()),
+        )
+        .await?;
+    if let Some(word_time_limit) = room.word_time_limit() {
+        metrics::spawn_timer(auto_skip_word(
+            rooms.clone(),
+            room_id,
+            frontend.clone(),
+            sent_message,
+            word_time_limit,
+        ));
+    }
+    let skip_cool_down = room.skip_cool_down();
+    metrics::spawn_timer(async move {
+        add_skip_button(rooms, room_id, frontend, sent_message, skip_cool_down).await;
+    });
+    Ok(())
+}
+
+/// Deals the word of a relay round to the describer of every team at once.
+async fn send_relay_word<F: Frontend>(
+    frontend: &F,
+    room: &mut Room,
+    room_id: RoomId,
+    word: &Word,
+) -> Result<(), F::Error> {
+    scoreboard::publish(room_id, room);
+    update_pinned_scoreboard(frontend, room).await;
+    let Some((index, describers)) = room.relay_describers() else {
+        return Ok(());
+    };
+
+    let text = format!(
+        "🏃 Relay: the first team to guess it claims it\n\n{}",
+        word.get_message_string(room.use_taboo_words())
+    );
+    for describer in describers {
+        let (chat_id, message_id) = frontend
+            .send_card(
+                describer.id.into(),
+                text.clone(),
+                vec![vec![
+                    Button::new("✅", room_id, CbQueryCommand::RelayClaim { index }),
+                    Button::new("⏩️", room_id, CbQueryCommand::RelaySkip { index }),
+                ]],
+            )
+            .await?;
+        room.add_relay_card(chat_id, message_id);
+    }
+    Ok(())
+}
+
+async fn clear_relay_cards<F: Frontend>(
+    frontend: &F,
+    cards: Vec<(ChatId, MessageId)>,
+) -> Result<(), F::Error> {
+    for (chat_id, message_id) in cards {
+        frontend
+            .edit_buttons(chat_id, message_id, Vec::new())
+            .await?;
+    }
+    Ok(())
+}
+
+/// Takes the buttons off the cards of a resolved relay word, tells everyone
+/// how it went and deals the next word, unless the word won the game.
+async fn continue_relay<F: Frontend>(
+    frontend: &F,
+    room: &mut Room,
+    room_id: RoomId,
+    resolution: room::RelayResolution,
+) -> Result<(), F::Error> {
+    clear_relay_cards(frontend, resolution.cards).await?;
+    frontend
+        .broadcast(
+            room.get_all_players(),
+            match &resolution.claimed_by {
+                Some(team) => format!("✅ {} claimed {}", team, resolution.word),
+                None => format!("⏩️ Every team skipped {}", resolution.word),
+            },
+        )
+        .await?;
+
+    if let Some(winner) = &resolution.winner {
+        frontend
+            .broadcast(
+                room.get_all_players(),
+                format!("🏁 {} reached the target and wins!", winner),
+            )
+            .await?;
+        stop_round(frontend, room, room_id).await;
+        return Ok(());
+    }
+    send_relay_word(frontend, room, room_id, &resolution.next).await
+}
+
+pub async fn handle_relay_claim<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    index: usize,
+) -> Result<(), F::Error> {
+    match room.claim_relay_word(user.id, index) {
+        Ok(resolution) => continue_relay(&frontend, room, room_id, resolution).await?,
+        Err(GameLogicError::WordResolved) => {
+            frontend
+                .prompt(user.id.into(), "Another team was faster!".to_owned())
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_relay_skip<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    index: usize,
+) -> Result<(), F::Error> {
+    match room.skip_relay_word(user.id, index) {
+        Ok(Some(resolution)) => continue_relay(&frontend, room, room_id, resolution).await?,
+        Ok(None) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "The word is skipped once every team wants to skip it".to_owned(),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+fn guesser_card_text(room: &Room) -> Option<String> {
+    let status = room.pair_status()?;
+    let time_left = status.round_time_left.as_secs();
+    Some(format!(
+        "🤔 {} is describing a word to you\n\n\
+         ✅ {} guessed · ⏩️ {} skipped this round\n\
+         ⏱️ {}:{:02} left in the round",
+        status.describing,
+        status.words,
+        status.skips,
+        time_left / 60,
+        time_left % 60
+    ))
+}
+
+/// Keeps the guesser's card up to date until the word of `sent_message` is
+/// resolved.
+async fn refresh_guesser_card<F: Frontend>(
+    rooms: Rooms,
+    room_id: RoomId,
+    frontend: F,
+    sent_message: (ChatId, MessageId),
+    guesser_card: (ChatId, MessageId),
+) {
+    loop {
+        tokio::time::sleep(GUESSER_CARD_REFRESH_INTERVAL).await;
+        let Some(room) = rooms.get(&room_id) else {
+            return;
+        };
+        let room = room.lock().await;
+        if room.get_message_stack_top().ok().flatten() != Some(sent_message) {
+            return;
+        }
+        let Some(text) = guesser_card_text(&room) else {
+            return;
+        };
+
+        if let Err(err) = frontend
+            .edit_text(guesser_card.0, guesser_card.1, text)
+            .await
+        {
+            log::warn!("Can not update guesser card: {:?} {}", room_id, err);
+            return;
+        }
+    }
+}
+
+/// The describer's card: the word between a header with the pair's momentum
+/// and a footer to pace themselves.
+fn describer_card_text(room: &Room, word: &Word, context: &room::TurnContext) -> String {
+    format!(
+        "Round {}/{} · {} · Word {} · ✅{} ⏩{}\n\n{}\n\n{}",
+        context.round,
+        context.rounds,
+        context.team,
+        context.words + context.skips + 1,
+        context.words,
+        context.skips,
+        word.get_message_string(room.use_taboo_words()),
+        pace_footer(room, context)
+    )
+}
+
+/// A line under the describer's card to help them pace themselves.
+fn pace_footer(room: &Room, context: &room::TurnContext) -> String {
+    let mut pace = Vec::new();
+    if let Some(words_left) = context.words_left {
+        pace.push(format!("🃏 {} left", words_left));
+    }
+    pace.push(format!("⏩️ after {}s", room.skip_cool_down().as_secs()));
+    if let Some(elapsed) = room.round_time_elapsed() {
+        let elapsed = elapsed.as_secs();
+        pace.push(format!("⏱️ {}:{:02}", elapsed / 60, elapsed % 60));
+    }
+    pace.join(" · ")
+}
+
+/// Asks the describer which complexity their next word should have.
+async fn send_draw_card<F: Frontend>(
+    frontend: &F,
+    room: &mut Room,
+    room_id: RoomId,
+    describing_player: &User,
+) -> Result<(), F::Error> {
+    let buttons = Complexity::ALL
+        .into_iter()
+        .map(|complexity| {
+            let label = match room::draw_bonus_in_seconds(complexity) {
+                0 => complexity.to_string(),
+                bonus => format!("{} (-{}s)", complexity, bonus),
+            };
+            Button::new(&label, room_id, CbQueryCommand::Draw { complexity })
+        })
+        .collect();
+
+    let (chat_id, message_id) = frontend
+        .send_card(
+            player_chat(room, describing_player),
+            "Pick your next word, harder ones take seconds off your time".to_owned(),
+            vec![buttons],
+        )
+        .await?;
+
+    if room.push_to_message_stack(chat_id, message_id).is_err() {
+        error_reporting::report(room_id, "Can not push to the message stack".to_owned());
+    }
+    Ok(())
+}
+
+pub async fn handle_draw<F: Frontend>(
+    rooms: Rooms,
+    room: &mut Room,
+    room_id: RoomId,
+    frontend: F,
+    complexity: Complexity,
+) -> Result<(), F::Error> {
+    if let Ok(word_guess_try) = room.draw(complexity) {
+        send_new_word(rooms, room, room_id, frontend, word_guess_try).await?;
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_describer_draws<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.toggle_describer_draws(user.id) {
+        Ok(describer_draws) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    if describer_draws {
+                        format!(
+                            "{} let describers pick how hard their words are",
+                            user.full_name()
+                        )
+                    } else {
+                        format!("{} turned picking word difficulty off", user.full_name())
+                    },
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+/// Announces the room in the public games channel, or takes the
+/// announcement back.
+pub async fn handle_toggle_public<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<(), F::Error> {
+    match room.toggle_public(user.id) {
+        Ok(public) => {
+            if public {
+                announce_public_game(&frontend, room, room_id).await;
+            } else {
+                let text = format!("🔒 Room {} is no longer public.", room_id.0);
+                close_public_post(&frontend, room.public_post(), text).await;
+                room.set_public_post(None);
+            }
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_card(
+                        chat_id,
+                        message_id,
+                        settings_summary(room.config()),
+                        settings_summary_buttons(room, room_id),
+                    )
+                    .await?;
+            }
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+/// Switches between dealing the cards to every player and to the host's
+/// phone alone.
+pub async fn handle_toggle_pass_the_phone<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<(), F::Error> {
+    match room.toggle_pass_the_phone(user.id) {
+        Ok(_) => {
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_card(
+                        chat_id,
+                        message_id,
+                        settings_summary(room.config()),
+                        settings_summary_buttons(room, room_id),
+                    )
+                    .await?;
+            }
+            refresh_lobby_cards(&frontend, room, room_id).await;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_cycle_break<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<(), F::Error> {
+    match room.cycle_break(user.id) {
+        Ok(_) => {
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_card(
+                        chat_id,
+                        message_id,
+                        settings_summary(room.config()),
+                        settings_summary_buttons(room, room_id),
+                    )
+                    .await?;
+            }
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_cycle_tie_margin<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<(), F::Error> {
+    match room.cycle_tie_margin(user.id) {
+        Ok(_) => {
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_card(
+                        chat_id,
+                        message_id,
+                        settings_summary(room.config()),
+                        settings_summary_buttons(room, room_id),
+                    )
+                    .await?;
+            }
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_cycle_coop_target<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<(), F::Error> {
+    match room.cycle_coop_target(user.id) {
+        Ok(_) => {
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_card(
+                        chat_id,
+                        message_id,
+                        settings_summary(room.config()),
+                        settings_summary_buttons(room, room_id),
+                    )
+                    .await?;
+            }
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_relay<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<(), F::Error> {
+    match room.toggle_relay(user.id) {
+        Ok(_) => {
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_card(
+                        chat_id,
+                        message_id,
+                        settings_summary(room.config()),
+                        settings_summary_buttons(room, room_id),
+                    )
+                    .await?;
+            }
+            refresh_lobby_cards(&frontend, room, room_id).await;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+/// Shows the host teams balanced by skill, to take or reshuffle.
+pub async fn handle_balance_teams<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+) -> Result<(), F::Error> {
+    let teams = match room.propose_teams(user.id) {
+        Ok(teams) => teams,
+        Err(err) => return report_error(&frontend, user.id, err).await,
+    };
+
+    let rating = |player: &User| {
+        ratings::rating_of(player.id).map_or("new".to_owned(), |rating| format!("{:.0}", rating))
+    };
+    let lines = teams
+        .iter()
+        .enumerate()
+        .map(|(index, team)| {
+            let players = team
+                .iter()
+                .map(|player| format!("{} ({})", player.full_name(), rating(player)))
+                .collect::<Vec<_>>();
+            format!("{}: {}", get_team_emoji(index), players.join(", "))
+        })
+        .collect::<Vec<_>>();
+    frontend
+        .send_card(
+            user.id.into(),
+            format!("⚖️ Teams balanced by skill:\n\n{}", lines.join("\n")),
+            vec![vec![
+                Button::new(
+                    "✅ Use these teams",
+                    room_id,
+                    CbQueryCommand::AcceptBalancedTeams,
+                ),
+                Button::new("🔀 Reshuffle", room_id, CbQueryCommand::BalanceTeams),
+            ]],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_accept_balanced_teams<F: Frontend>(
+    frontend: F,
+    rooms: Rooms,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+) -> Result<(), F::Error> {
+    match room.accept_team_proposal(user.id) {
+        Ok(()) => {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    format!(
+                        "⚖️ The host balanced the teams by skill\n\n{}",
+                        room.get_teams()
+                    ),
+                )
+                .await?;
+            refresh_lobby_cards(&frontend, room, room_id).await;
+            schedule_auto_start(frontend, rooms, room, room_id);
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_captains<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<(), F::Error> {
+    match room.toggle_captains(user.id) {
+        Ok(_) => {
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_card(
+                        chat_id,
+                        message_id,
+                        settings_summary(room.config()),
+                        settings_summary_buttons(room, room_id),
+                    )
+                    .await?;
+            }
+            refresh_lobby_cards(&frontend, room, room_id).await;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_pick_captains<F: Frontend>(
+    frontend: F,
+    room: &Room,
+    room_id: RoomId,
+    user: User,
+) -> Result<(), F::Error> {
+    if room.host() != user.id {
+        frontend
+            .prompt(
+                user.id.into(),
+                "Only the host can pick captains!".to_owned(),
+            )
+            .await?;
+        return Ok(());
+    }
+    let candidates = room.captain_candidates();
+    if candidates.is_empty() {
+        frontend
+            .prompt(user.id.into(), "Nobody has joined a team yet!".to_owned())
+            .await?;
+        return Ok(());
+    }
+    let buttons = candidates
+        .into_iter()
+        .map(|(team, player)| {
+            vec![Button::new(
+                &format!("{} {}", team, player.full_name()),
+                room_id,
+                CbQueryCommand::MakeCaptain { user_id: player.id },
+            )]
+        })
+        .collect();
+    frontend
+        .send_card(
+            user.id.into(),
+            "👑 Who should captain their team?".to_owned(),
+            buttons,
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_make_captain<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    captain: UserId,
+) -> Result<(), F::Error> {
+    match room.make_captain(user.id, captain) {
+        Ok(team) => {
+            refresh_lobby_cards(&frontend, room, room_id).await;
+            frontend
+                .prompt(user.id.into(), format!("👑 {} has a new captain", team))
+                .await?;
+        }
+        Err(GameLogicError::NotHost) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "Only the host can pick captains!".to_owned(),
+                )
+                .await?;
+        }
+        Err(GameLogicError::NotInTeam) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "This player is not in a team anymore!".to_owned(),
+                )
+                .await?;
+        }
+        Err(GameLogicError::SettingsLocked) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "Game has started. You can't change the captains anymore!".to_owned(),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_balance_turns<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<(), F::Error> {
+    match room.toggle_balance_turns(user.id) {
+        Ok(_) => {
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_card(
+                        chat_id,
+                        message_id,
+                        settings_summary(room.config()),
+                        settings_summary_buttons(room, room_id),
+                    )
+                    .await?;
+            }
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_warm_up<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<(), F::Error> {
+    match room.toggle_warm_up(user.id) {
+        Ok(_) => {
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_card(
+                        chat_id,
+                        message_id,
+                        settings_summary(room.config()),
+                        settings_summary_buttons(room, room_id),
+                    )
+                    .await?;
+            }
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+pub async fn handle_toggle_taboo_words<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    user: User,
+    card: Option<(ChatId, MessageId)>,
+) -> Result<(), F::Error> {
+    match room.toggle_taboo_words(user.id) {
+        Ok(_) => {
+            if let Some((chat_id, message_id)) = card {
+                frontend
+                    .edit_card(
+                        chat_id,
+                        message_id,
+                        settings_summary(room.config()),
+                        settings_summary_buttons(room, room_id),
+                    )
+                    .await?;
+            }
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+/// Asks `user` for a card to mix into the deck, if they may write one.
+pub async fn handle_write_card<F: Frontend>(
+    frontend: F,
+    room: &Room,
+    user: User,
+) -> Result<Option<Awaiting>, F::Error> {
+    match room.can_add_custom_card(user.id) {
+        Ok(()) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    format!(
+                        "✍️ Send a word for the other teams, followed by its taboo words, \
+                         one per line. Your team can write up to {} cards.",
+                        MAX_CUSTOM_CARDS_PER_TEAM
+                    ),
+                )
+                .await?;
+            return Ok(Some(Awaiting::CustomCard));
+        }
+        Err(GameLogicError::SettingsLocked) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "Game has started. You can't write cards anymore!".to_owned(),
+                )
+                .await?;
+        }
+        Err(GameLogicError::NotInTeam) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "Join a team before writing cards!".to_owned(),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(None)
+}
+
+/// Asks the host who to invite, where the room has a link to join it with.
+pub async fn handle_invite<F: Frontend>(
+    frontend: F,
+    room: &Room,
+    room_id: RoomId,
+    user: User,
+) -> Result<Option<Awaiting>, F::Error> {
+    if !room.is_lobby() {
+        report_error(&frontend, user.id, GameLogicError::JoinAfterPlay).await?;
+        return Ok(None);
+    }
+    if room.host() != user.id {
+        report_error(&frontend, user.id, GameLogicError::NotHost).await?;
+        return Ok(None);
+    }
+    if frontend.join_link(room_id).await?.is_none() {
+        frontend
+            .prompt(
+                user.id.into(),
+                format!(
+                    "Invitations aren't available here, share the room number {} instead",
+                    room_id.0
+                ),
+            )
+            .await?;
+        return Ok(None);
+    }
+
+    frontend
+        .prompt(
+            user.id.into(),
+            "➕ Send the usernames of the people to invite, like @alice @bob, or share their \
+             contact. They get the invitation if they talked to the bot before."
+                .to_owned(),
+        )
+        .await?;
+    Ok(Some(Awaiting::Invitees))
+}
+
+/// Sends the invitees of the host a link to join the room, and tells the host
+/// who got it. `unknown` are the names the host sent that no user goes by.
+pub async fn handle_invitees<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    room_id: RoomId,
+    host: User,
+    invitees: Vec<(UserId, String)>,
+    unknown: Vec<String>,
+) -> Result<(), F::Error> {
+    let Some(link) = frontend.join_link(room_id).await? else {
+        return Ok(());
+    };
+
+    let mut invited = Vec::new();
+    for (invitee, name) in invitees {
+        match room.invite(host.id, invitee, name.clone()) {
+            Ok(true) => (),
+            Ok(false) => continue,
+            Err(err) => return report_error(&frontend, host.id, err).await,
+        }
+        let invitation = format!(
+            "🎟 {} invites you to play in room {}, join here: {}",
+            host.full_name(),
+            room_id.0,
+            link
+        );
+        if let Err(err) = frontend.prompt(invitee.into(), invitation).await {
+            log::warn!("Can not send an invitation: {}", err);
+            continue;
+        }
+        invited.push(name);
+    }
+
+    let mut report = Vec::new();
+    if !invited.is_empty() {
+        report.push(format!("📨 Invited {}", invited.join(", ")));
+    }
+    if !unknown.is_empty() {
+        report.push(format!(
+            "These haven't talked to the bot yet, send them the link instead: {}\n{}",
+            unknown.join(", "),
+            link
+        ));
+    }
+    if report.is_empty() {
+        report.push("Nobody new to invite.".to_owned());
+    }
+    frontend.prompt(host.id.into(), report.join("\n\n")).await?;
+    refresh_lobby_cards(&frontend, room, room_id).await;
+    Ok(())
+}
+
+/// Adds the card `user` wrote to the deck, without telling anyone else what
+/// it is.
+pub async fn handle_custom_card<F: Frontend>(
+    frontend: F,
+    room: &mut Room,
+    user: User,
+    word: Word,
+) -> Result<(), F::Error> {
+    if let Some(problem) = moderation::check(&word) {
+        frontend
+            .prompt(
+                user.id.into(),
+                format!("Your card can't be added: {}", problem),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    match room.add_custom_card(user.id, word) {
+        Ok(written) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    format!(
+                        "✅ Card added, your team wrote {} of {}. It will only be dealt to \
+                         the other teams.",
+                        written, MAX_CUSTOM_CARDS_PER_TEAM
+                    ),
+                )
+                .await?;
+        }
+        Err(GameLogicError::TooManyCustomCards) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    format!(
+                        "Your team already wrote {} cards!",
+                        MAX_CUSTOM_CARDS_PER_TEAM
+                    ),
+                )
+                .await?;
+        }
+        Err(GameLogicError::SettingsLocked) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "Game has started. You can't write cards anymore!".to_owned(),
+                )
+                .await?;
+        }
+        Err(GameLogicError::NotInTeam) => {
+            frontend
+                .prompt(
+                    user.id.into(),
+                    "Join a team before writing cards!".to_owned(),
+                )
+                .await?;
+        }
+        Err(err) => report_error(&frontend, user.id, err).await?,
+    }
+    Ok(())
+}
+
+/// Skips the word of `sent_message` once the room's time limit per word ran
+/// out, unless it was resolved in the meantime. Boxed since sending the next
+/// word schedules another one of these.
+fn auto_skip_word<F: Frontend>(
+    rooms: Rooms,
+    room_id: RoomId,
+    frontend: F,
+    sent_message: (ChatId, MessageId),
+    word_time_limit: Duration,
+) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        tokio::time::sleep(word_time_limit).await;
+        let Some(room) = rooms.get(&room_id) else {
+            return;
+        };
+        let mut room = room.lock().await;
+        let Ok(Some(top)) = room.get_message_stack_top() else {
+            return;
+        };
+
+        if top != sent_message {
+            return;
+        }
+
+        let Ok(word_guess_try) = room.skip() else {
+            return;
+        };
+
+        let pair = match room.device() {
+            Some(device) => vec![device],
+            None => vec![word_guess_try.describing.id, word_guess_try.guessing.id],
+        };
+        if let Err(err) = frontend
+            .broadcast(
+                pair,
+                format!(
+                    "⏳ {} seconds passed, the word was skipped",
+                    word_time_limit.as_secs()
+                ),
+            )
+            .await
+        {
+            log::warn!("Can not announce auto-skip: {}", err);
+        }
+
+        if let Err(err) =
+            send_new_word(rooms.clone(), &mut room, room_id, frontend, word_guess_try).await
+        {
+            log::warn!("Can not send the word after auto-skip: {}", err);
+        }
+        persistence::save(room_id, &room);
+    })
+}
+
+async fn add_skip_button<F: Frontend>(
+    rooms: Rooms,
+    room_id: RoomId,
+    frontend: F,
+    sent_message: (ChatId, MessageId),
+    skip_cool_down: Duration,
+) {
+    tokio::time::sleep(skip_cool_down).await;
+    let Some(room) = rooms.get(&room_id) else {
+        return;
+    };
+    let room = room.lock().await;
+    let Ok(Some(top)) = room.get_message_stack_top() else {
+        return;
+    };
+
+    if top != sent_message {
+        return;
+    }
+
+    if let Err(err) = frontend
+        .edit_buttons(
+            sent_message.0,
+            sent_message.1,
+            vec![vec![
+                Button::new("✅", room_id, CbQueryCommand::Correct),
+                Button::new("⏩️", room_id, CbQueryCommand::Skip),
+            ]],
+        )
+        .await
+    {
+        log::warn!("Can not add skip button: {:?} {}", room_id, err);
+    }
+}
+
+pub async fn handle_correct<F: Frontend>(
+    rooms: Rooms,
+    room: &mut Room,
+    room_id: RoomId,
+    frontend: F,
+) -> Result<(), F::Error> {
+    if let Ok(word_guess_try) = room.correct() {
+        if let Some((team, length)) = &word_guess_try.streak {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    format!(
+                        "🔥 {} guessed {} words in a row, {} seconds come off their time!",
+                        team,
+                        length,
+                        room::STREAK_BONUS_IN_SECONDS
+                    ),
+                )
+                .await?;
+        }
+        if let Some(winner) = &word_guess_try.winner {
+            frontend
+                .broadcast(
+                    room.get_all_players(),
+                    format!("🏁 {} reached the target and wins!", winner),
+                )
+                .await?;
+            stop_round(&frontend, room, room_id).await;
+            return Ok(());
+        }
+        if room.turns_balanced() {
+            stop_round(&frontend, room, room_id).await;
+            return Ok(());
+        }
+        send_new_word(rooms, room, room_id, frontend, word_guess_try).await?;
+    }
+    Ok(())
+}
+
+pub async fn handle_skip<F: Frontend>(
+    rooms: Rooms,
+    room: &mut Room,
+    room_id: RoomId,
+    frontend: F,
+) -> Result<(), F::Error> {
+    if let Ok(word_guess_try) = room.skip() {
+        send_new_word(rooms, room, room_id, frontend, word_guess_try).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use teloxide::{
+    prelude::*,
+    types::ParseMode,
+    utils::{
+        command::BotCommands,
+        markdown::{escape, escape_code},
+    },
+};
+
+use crate::{
+    dialogue::{MyDialogue, State},
+    memberships,
+    room::{Room, MAX_NUMBER_OF_ROUNDS, MAX_NUMBER_OF_TEAMS},
+    Command, HandlerResult, Rooms,
+};
+
+/// How to use the commands that take arguments.
+fn example(command: &str) -> Option<&'static str> {
+    match command {
+        "/join" => Some("/join 12345"),
+        "/switch" => Some("/switch 12345"),
+        "/timezone" => Some("/timezone +03:30"),
+        "/forgetme" => Some("/forgetme yes"),
+        "/feedback" => Some("/feedback I love this game!"),
+        "/addword" => Some("/addword Apple"),
+        "/announce" => Some("/announce New words are in!"),
+        "/ban" => Some("/ban 123456789 shadow"),
+        "/unban" => Some("/unban 123456789"),
+        _ => None,
+    }
+}
+
+/// The supported commands with examples, formatted as MarkdownV2.
+pub fn commands_help() -> String {
+    let commands = Command::bot_commands()
+        .into_iter()
+        .map(|command| {
+            let mut line = format!(
+                "{} \\- {}",
+                escape(&command.command),
+                escape(&command.description)
+            );
+            if let Some(example) = example(&command.command) {
+                line += &format!("\n  e\\.g\\. `{}`", escape_code(example));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "These commands are supported:\n\n{}\n\nNew to the game? Send /rules to learn how to \
+         play\\.",
+        commands
+    )
+}
+
+/// Sends the list of commands.
+pub async fn send_commands_help(bot: &Bot, chat_id: ChatId) -> HandlerResult {
+    bot.send_message(chat_id, commands_help())
+        .parse_mode(ParseMode::MarkdownV2)
+        .await?;
+    Ok(())
+}
+
+/// Explains the question of a setup step.
+fn setup_help(state: &State) -> Option<String> {
+    let help = match state {
+        State::ReceiveNumberOfTeams => format!(
+            "You are creating a room. Send how many teams will play, from 2 to {}. \
+             Each team has two players.",
+            MAX_NUMBER_OF_TEAMS
+        ),
+        State::ReceiveNumberOfRounds { .. } => format!(
+            "Send how many rounds you want to play, from 1 to {}. In each round every \
+             team gets to describe words until the time runs out.",
+            MAX_NUMBER_OF_ROUNDS
+        ),
+        State::ReceiveRoundDuration { .. } => "Send how long each round lasts, in minutes \
+             like 2, or in minutes and seconds like 1:30 or 90s. Rounds last between 30 \
+             seconds and 10 minutes."
+            .to_owned(),
+        State::ReceiveTabooWords { .. } => "Choose whether cards come with taboo words, \
+             which the describer isn't allowed to say either."
+            .to_owned(),
+        State::ReceiveCustomCard { .. } => "Send a word for the other teams to describe, \
+             followed by its taboo words, one per line."
+            .to_owned(),
+        State::ReceiveInvitees { .. } => "Send the usernames of the people to invite to your \
+             lobby, like @alice @bob, or share their contact."
+            .to_owned(),
+        State::Initial => return None,
+    };
+    Some(help + "\n\nPress ⬅️ Back on the question to change an earlier answer.")
+}
+
+/// Explains what the room the user is in expects from them.
+fn room_help(room: &Room) -> Option<String> {
+    if room.is_lobby() {
+        return Some(
+            "You are in a room that hasn't started yet. Tap a team button to join that \
+             team, each team needs two players. The other buttons change the settings, \
+             and the host presses Play once every team is full."
+                .to_owned(),
+        );
+    }
+    if room.is_finished() {
+        return None;
+    }
+
+    let round_duration = room.round_duration().as_secs();
+    Some(format!(
+        "You are playing! When it's your turn to describe, you get a card: explain the \
+         word to your partner without saying it. Press ✅ once they guess it, or ⏩️ to \
+         skip it, which appears after {} seconds. The clock keeps running either way.\n\n\
+         Each round lasts {}:{:02} and the bot warns you before the time is up. The team \
+         with the least total time wins.",
+        room.skip_cool_down().as_secs(),
+        round_duration / 60,
+        round_duration % 60,
+    ))
+}
+
+pub async fn handle_help_command(
+    bot: Bot,
+    msg: Message,
+    dialogue: MyDialogue,
+    rooms: Rooms,
+) -> HandlerResult {
+    if let Some(help) = setup_help(&dialogue.get_or_default().await?) {
+        bot.send_message(msg.chat.id, help).await?;
+        return Ok(());
+    }
+
+    let room_id = msg
+        .from()
+        .and_then(|user| memberships::active_room(user.id));
+    if let Some(room) = room_id.and_then(|room_id| rooms.get(&room_id)) {
+        if let Some(help) = room_help(&*room.lock().await) {
+            bot.send_message(msg.chat.id, help).await?;
+            return Ok(());
+        }
+    }
+
+    send_commands_help(&bot, msg.chat.id).await
+}
@@ -0,0 +1,47 @@
+use teloxide::{
+    prelude::*,
+    types::{
+        InlineKeyboardButton, InlineKeyboardMarkup, InlineQueryResult, InlineQueryResultArticle,
+        InputMessageContent, InputMessageContentText,
+    },
+};
+
+use crate::{frontend::Frontend, room::RoomId, HandlerResult, Rooms};
+
+/// Answers `@bot <room id>` with an invitation card that opens the bot and
+/// joins the room, so hosts can share it in any chat.
+pub async fn handle_inline_query(bot: Bot, rooms: Rooms, q: InlineQuery) -> HandlerResult {
+    let Ok(room_id) = q.query.trim().parse().map(RoomId) else {
+        bot.answer_inline_query(q.id, []).await?;
+        return Ok(());
+    };
+
+    if !rooms.contains_key(&room_id) {
+        bot.answer_inline_query(q.id, []).await?;
+        return Ok(());
+    }
+
+    let Some(link) = bot.join_link(room_id).await? else {
+        bot.answer_inline_query(q.id, []).await?;
+        return Ok(());
+    };
+
+    let invitation = InlineQueryResultArticle::new(
+        room_id.0.to_string(),
+        "Join my Jigarpich game",
+        InputMessageContent::Text(InputMessageContentText::new(format!(
+            "🎲 Join my Jigarpich game!\nRoom {}",
+            room_id.0
+        ))),
+    )
+    .description(format!("Invite players to room {}", room_id.0))
+    .reply_markup(InlineKeyboardMarkup::new([[InlineKeyboardButton::url(
+        "Join",
+        link.parse()?,
+    )]]));
+
+    bot.answer_inline_query(q.id, [InlineQueryResult::Article(invitation)])
+        .cache_time(0)
+        .await?;
+    Ok(())
+}
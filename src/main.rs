@@ -1,24 +1,14 @@
-use std::{
-    collections::{BTreeSet, HashMap},
-    env,
-    sync::Arc,
-    time::Duration,
-};
+use std::{env, sync::Arc};
 
-use callback_query_command::{parse_command, serialize_command, CbQueryCommand};
+use callback_query_command::{parse_command, parse_user_command, UserCbQueryCommand};
 use dashmap::DashMap;
 use dialogue::get_should_use_taboo_words;
-use room::{
-    get_new_id, get_team_emoji, get_teams, GameLogicError, Room, RoomId, SKIP_COOL_DOWN_IN_SECONDS,
-};
+use room::{Room, RoomId};
 use teloxide::{
-    dispatching::dialogue::InMemStorage,
-    prelude::*,
-    types::{InlineKeyboardButton, InlineKeyboardMarkup, User},
-    update_listeners::webhooks,
+    dispatching::dialogue::InMemStorage, prelude::*, update_listeners::UpdateListener,
     utils::command::BotCommands,
 };
-use tokio::sync::Mutex;
+use tokio::{signal, sync::Mutex};
 
 mod room;
 
@@ -28,6 +18,93 @@ mod words;
 
 mod dialogue;
 
+mod frontend;
+
+mod game;
+
+mod telegram;
+
+mod api;
+
+mod results_webhook;
+
+mod scoreboard;
+
+mod render;
+
+mod storage;
+
+mod notifications;
+
+mod timezone;
+
+mod tutorial;
+
+mod admin;
+
+mod memberships;
+
+mod feedback;
+
+mod users;
+
+mod announcement;
+
+mod bans;
+
+mod inline;
+
+mod timestamp;
+
+mod persistence;
+
+mod sharding;
+
+mod metrics;
+
+mod sim;
+
+mod reachability;
+
+mod submissions;
+
+mod moderation;
+
+mod help;
+
+mod sounds;
+
+mod public_games;
+
+mod results_export;
+
+mod awards;
+
+mod mute;
+
+mod duos;
+
+mod ratings;
+
+mod privacy;
+
+mod error_reporting;
+
+mod webhook;
+
+mod tls;
+
+mod brands;
+
+#[cfg(feature = "discord")]
+mod discord;
+
+#[cfg(feature = "matrix")]
+mod matrix;
+
+#[cfg(any(feature = "discord", feature = "matrix"))]
+mod bridge;
+
 type Rooms = Arc<DashMap<RoomId, Mutex<Room>>>;
 type HandlerResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
 
@@ -38,35 +115,134 @@ type HandlerResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
 )]
 enum Command {
     #[command(description = "Start the bot")]
-    Start,
+    Start(String),
     #[command(description = "Display this text")]
     Help,
+    #[command(description = "Learn how to play")]
+    Rules,
     #[command(description = "Create a new room")]
     New,
     #[command(description = "Join a room")]
     Join(u32),
+    #[command(description = "Choose the room your commands apply to")]
+    Switch(String),
+    #[command(description = "Show the settings of your room")]
+    Settings,
+    #[command(description = "Show the numbers behind the awards of your game")]
+    Stats,
+    #[command(description = "Show the rounds of your game so far")]
+    History,
+    #[command(description = "Show the standings of your game, the final ones once it's over")]
+    Results,
+    #[command(description = "Choose which notifications you receive")]
+    Notifications,
+    #[command(description = "Set your UTC offset, e.g. /timezone +03:30")]
+    Timezone(String),
+    #[command(description = "Mute the live words for a while, e.g. /mute 30m or /mute off")]
+    Mute(String),
+    #[command(description = "Play as a standing duo, e.g. /duo @partner or /duo off")]
+    Duo(String),
+    #[command(description = "See what the bot keeps about you")]
+    Privacy,
+    #[command(description = "Get a copy of what the bot keeps about you")]
+    MyData,
+    #[command(description = "Delete what the bot keeps about you")]
+    ForgetMe(String),
+    #[command(description = "Send feedback to the maintainers")]
+    Feedback(String),
+    #[command(description = "Propose a word, with its taboo words on the following lines")]
+    AddWord(String),
+    #[command(
+        rename = "review_words",
+        description = "Review the proposed words (admins only)"
+    )]
+    ReviewWords,
+    #[command(
+        rename = "word_report",
+        description = "List the words with broken cards (admins only)"
+    )]
+    WordReport,
+    #[command(description = "Send a message to every user (admins only)")]
+    Announce(String),
+    #[command(description = "Ban a user (admins only)")]
+    Ban(String),
+    #[command(description = "Unban a user (admins only)")]
+    Unban(u64),
 }
 
 #[tokio::main]
 async fn main() {
     pretty_env_logger::init();
 
+    if env::var("JIGARPICH_SIM").is_ok() {
+        return sim::run().await;
+    }
+    if let Ok(path) = env::var("JIGARPICH_MIGRATE_WORDS") {
+        return words::migrate(&path);
+    }
+
     let bot = Bot::from_env();
+    error_reporting::init(bot.clone());
+
+    let rooms: Rooms = persistence::restore();
+
+    let addr = webhook::listen_addr();
+    let (mut listener, stop_flag, mut app) = webhook::setup(bot.clone(), addr, "").await;
+    let mut brand_listeners = Vec::new();
+    for (index, brand) in brands::brands().iter().enumerate() {
+        let suffix = format!("/brand{}", index + 1);
+        let (listener, stop_flag, router) = webhook::setup(brand.clone(), addr, &suffix).await;
+        app = app.merge(router);
+        tokio::spawn(stop_flag);
+        brand_listeners.push((brand.clone(), listener));
+    }
 
-    let addr = ([127, 0, 0, 1], 54647).into();
-    let url: String = env::var("JIGARPICH_URL").unwrap();
-    let url = url.parse().unwrap();
-    let listener = webhooks::axum(bot.clone(), webhooks::Options::new(addr, url))
-        .await
-        .expect("Couldn't setup webhook");
+    if let Ok(token) = env::var("JIGARPICH_API_TOKEN") {
+        app = app.merge(api::router(rooms.clone(), token));
+    }
+
+    #[cfg(any(feature = "discord", feature = "matrix"))]
+    let frontend = bridge::Bridge::new(bot.clone());
+    #[cfg(feature = "discord")]
+    let discord = discord::Discord::from_env();
+    #[cfg(feature = "discord")]
+    let frontend = frontend.with_discord(discord.clone());
+    #[cfg(feature = "matrix")]
+    let matrix = matrix::Matrix::from_env();
+    #[cfg(feature = "matrix")]
+    let frontend = frontend.with_matrix(matrix.clone());
+    #[cfg(feature = "discord")]
+    if let Some(discord) = discord {
+        tokio::spawn(discord.clone().register_commands());
+        app = app.merge(discord::router(discord, frontend.clone(), rooms.clone()));
+    }
+    #[cfg(feature = "matrix")]
+    if let Some(matrix) = matrix {
+        tokio::spawn(matrix.run(frontend.clone(), rooms.clone()));
+    }
+    #[cfg(not(any(feature = "discord", feature = "matrix")))]
+    let frontend = bot.clone();
 
-    let rooms: Rooms = Rooms::new(DashMap::new());
+    if let Some(router) = sharding::router(frontend.clone(), rooms.clone()) {
+        app = app.merge(router);
+    }
+
+    let stop_token = listener.stop_token();
+    tokio::spawn(async move {
+        tls::serve(addr, app, stop_flag)
+            .await
+            .inspect_err(|_| stop_token.stop())
+            .expect("Axum server error");
+    });
 
     let command_handler = Update::filter_message()
         .filter_command::<Command>()
         .enter_dialogue::<Message, InMemStorage<dialogue::State>, dialogue::State>()
         .endpoint(answer_command);
     let cb_query_handler = Update::filter_callback_query().endpoint(handle_cb_query);
+    let inline_query_handler = Update::filter_inline_query().endpoint(inline::handle_inline_query);
+    let my_chat_member_handler =
+        Update::filter_my_chat_member().endpoint(reachability::handle_my_chat_member);
 
     let dialogue_handler = Update::filter_message()
         .enter_dialogue::<Message, InMemStorage<dialogue::State>, dialogue::State>()
@@ -90,20 +266,93 @@ async fn main() {
             dptree::case![dialogue::State::ReceiveTabooWords {
                 number_of_teams,
                 number_of_rounds,
-                round_duration
+                round_duration_in_seconds
             }]
             .endpoint(get_should_use_taboo_words),
+        )
+        .branch(
+            dptree::case![dialogue::State::ReceiveCustomCard { room_id }]
+                .endpoint(dialogue::get_custom_card),
+        )
+        .branch(
+            dptree::case![dialogue::State::ReceiveInvitees { room_id }]
+                .endpoint(dialogue::get_invitees),
         );
 
-    let handler = dptree::entry()
+    let admin_reply_handler = Update::filter_message()
+        .filter(feedback::is_admin_reply)
+        .endpoint(feedback::handle_admin_reply);
+
+    let local_handler = dptree::entry()
+        .inspect(|bot: Bot, update: Update| {
+            if let Some(chat) = update.chat() {
+                users::record_chat(chat);
+                brands::record(&bot, chat.id);
+            }
+            if let Some(user) = update.user() {
+                users::record(user);
+                // Users can press buttons in groups without ever starting the bot
+                if update.chat().is_some_and(|chat| chat.is_private()) {
+                    reachability::mark_reachable(user.id);
+                }
+            }
+        })
+        .branch(dptree::filter(bans::is_banned).endpoint(bans::handle_banned))
         .branch(cb_query_handler)
+        .branch(inline_query_handler)
+        .branch(my_chat_member_handler)
+        .branch(admin_reply_handler)
         .branch(command_handler)
         .branch(dialogue_handler);
 
-    Dispatcher::builder(bot, handler)
-        .dependencies(dptree::deps![rooms, InMemStorage::<dialogue::State>::new()])
-        .enable_ctrlc_handler()
-        .build()
+    let handler = dptree::entry()
+        .branch(dptree::filter(sharding::is_foreign).endpoint(sharding::forward))
+        .branch(local_handler.clone());
+
+    tokio::spawn(announcement::resume(bot.clone(), None));
+    tokio::spawn(game::resume_games(frontend.clone(), rooms.clone()));
+    tokio::spawn(game::remove_dead_rooms(frontend.clone(), rooms.clone()));
+    tokio::spawn(game::watch_stuck_rooms(frontend.clone(), rooms.clone()));
+    tokio::spawn(game::remind_idle_describers(
+        frontend.clone(),
+        rooms.clone(),
+    ));
+    tokio::spawn(game::report_unreachable_players(frontend, rooms.clone()));
+
+    let storage = InMemStorage::<dialogue::State>::new();
+    let mut dispatcher = Dispatcher::builder(bot.clone(), handler)
+        .dependencies(dptree::deps![rooms.clone(), storage.clone()])
+        .build();
+
+    // White-label bots share the rooms and the dialogues, sharding is off
+    // with them so they all run the local handler
+    let mut shutdown_tokens = vec![dispatcher.shutdown_token()];
+    for (brand, listener) in brand_listeners {
+        let mut dispatcher = Dispatcher::builder(brand, local_handler.clone())
+            .dependencies(dptree::deps![rooms.clone(), storage.clone()])
+            .build();
+        shutdown_tokens.push(dispatcher.shutdown_token());
+        tokio::spawn(async move {
+            dispatcher
+                .dispatch_with_listener(
+                    listener,
+                    LoggingErrorHandler::with_custom_text("An error from a brand's listener"),
+                )
+                .await
+        });
+    }
+
+    tokio::spawn(async move {
+        wait_for_shutdown_signal().await;
+        game::park_games(bot, rooms).await;
+        for shutdown_token in shutdown_tokens {
+            if let Ok(shutdown) = shutdown_token.shutdown() {
+                shutdown.await;
+            }
+        }
+    });
+
+    dispatcher
         .dispatch_with_listener(
             listener,
             LoggingErrorHandler::with_custom_text("An error from the update listener"),
@@ -111,12 +360,19 @@ async fn main() {
         .await
 }
 
+async fn wait_for_shutdown_signal() {
+    let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
+        .expect("Couldn't listen for SIGTERM");
+    tokio::select! {
+        _ = signal::ctrl_c() => (),
+        _ = terminate.recv() => (),
+    }
+    log::info!("Shutting down, parking running games");
+}
+
 async fn handle_unknown_message(bot: Bot, msg: Message) -> HandlerResult {
     bot.send_message(msg.chat.id, "Unknown message!").await?;
-    bot.send_message(msg.chat.id, Command::descriptions().to_string())
-        .parse_mode(teloxide::types::ParseMode::MarkdownV2)
-        .await?;
-    Ok(())
+    help::send_commands_help(&bot, msg.chat.id).await
 }
 
 async fn answer_command(
@@ -126,458 +382,192 @@ async fn answer_command(
     msg: Message,
     cmd: Command,
 ) -> HandlerResult {
+    // Join links open the bot with the room ID as the start payload
+    let cmd = match cmd {
+        Command::Start(payload) => match payload.parse() {
+            Ok(room_id) => Command::Join(room_id),
+            Err(_) => Command::Start(payload),
+        },
+        cmd => cmd,
+    };
+
     match cmd {
-        Command::Help | Command::Start => {
-            bot.send_message(msg.chat.id, Command::descriptions().to_string())
-                .parse_mode(teloxide::types::ParseMode::MarkdownV2)
-                .await?;
+        Command::Start(_) => {
+            let Some(user) = msg.from() else {
+                return Ok(());
+            };
+            if !game::handle_rejoin(bot.clone(), rooms, user.clone()).await?
+                && !sharding::rejoin_elsewhere(user).await
+            {
+                help::send_commands_help(&bot, msg.chat.id).await?;
+            }
+        }
+        Command::Help => {
+            help::handle_help_command(bot, msg, dialogue, rooms).await?;
+        }
+        Command::Rules => {
+            tutorial::handle_rules_command(bot, msg).await?;
         }
         Command::New => {
-            dialogue
-                .update(dialogue::State::ReceiveNumberOfTeams)
-                .await?;
-            bot.send_message(msg.chat.id, "How many teams are playing?\n(2 to 7)")
+            if metrics::is_at_capacity(&rooms).await {
+                bot.send_message(
+                    msg.chat.id,
+                    "The bot is at capacity, please try again later!",
+                )
                 .await?;
+                return Ok(());
+            }
+            let state = dialogue::State::ReceiveNumberOfTeams;
+            dialogue.update(state.clone()).await?;
+            dialogue::ask(&bot, msg.chat.id, telegram::topic(&msg), &state).await?;
         }
         Command::Join(room_id) => {
-            handle_join_command(bot, msg, rooms, room_id).await?;
+            let Some(user) = msg.from() else {
+                return Ok(());
+            };
+            game::handle_join_command(bot, msg.chat.id, rooms, user.clone(), RoomId(room_id))
+                .await?;
         }
-    };
-    Ok(())
-}
-
-async fn handle_cb_query(bot: Bot, rooms: Rooms, q: CallbackQuery) -> HandlerResult {
-    let Some(data) = q.data else {
-        return Ok(());
-    };
-
-    let Some((room_id, command)) = parse_command(data) else {
-        return Ok(());
-    };
-
-    let Some(room) = rooms.get(&room_id) else {
-        return Ok(());
-    };
-    let mut room = room.lock().await;
-
-    match command {
-        CbQueryCommand::Join { team_index } => {
-            handle_team_join(bot, &mut room, q.from, team_index).await?
+        Command::Switch(room_id) => {
+            memberships::handle_switch_command(bot, msg, rooms, room_id).await?;
+        }
+        Command::Settings => {
+            let Some(user) = msg.from() else {
+                return Ok(());
+            };
+            game::handle_settings_command(bot, msg.chat.id, rooms, user.id).await?;
+        }
+        Command::Stats => {
+            let Some(user) = msg.from() else {
+                return Ok(());
+            };
+            game::handle_stats_command(bot, msg.chat.id, rooms, user.id).await?;
+        }
+        Command::History => {
+            let Some(user) = msg.from() else {
+                return Ok(());
+            };
+            game::handle_history_command(bot, msg.chat.id, rooms, user.id).await?;
+        }
+        Command::Results => {
+            let Some(user) = msg.from() else {
+                return Ok(());
+            };
+            game::handle_results_command(bot, msg.chat.id, rooms, user.id).await?;
+        }
+        Command::Notifications => {
+            notifications::handle_notifications_command(bot, msg).await?;
+        }
+        Command::Timezone(offset) => {
+            timezone::handle_timezone_command(bot, msg, offset).await?;
+        }
+        Command::Mute(arguments) => {
+            mute::handle_mute_command(bot, msg, arguments).await?;
+        }
+        Command::Duo(arguments) => {
+            duos::handle_duo_command(bot, msg, arguments).await?;
+        }
+        Command::Privacy => {
+            privacy::handle_privacy_command(bot, msg).await?;
+        }
+        Command::MyData => {
+            privacy::handle_my_data_command(bot, msg, rooms).await?;
+        }
+        Command::ForgetMe(confirmation) => {
+            privacy::handle_forget_me_command(bot, msg, confirmation).await?;
+        }
+        Command::Feedback(text) => {
+            feedback::handle_feedback_command(bot, msg, text).await?;
+        }
+        Command::AddWord(text) => {
+            submissions::handle_add_word_command(bot, msg, text).await?;
+        }
+        Command::ReviewWords => {
+            submissions::handle_review_words_command(bot, msg).await?;
+        }
+        Command::WordReport => {
+            submissions::handle_word_report_command(bot, msg).await?;
+        }
+        Command::Announce(text) => {
+            announcement::handle_announce_command(bot, msg, text).await?;
+        }
+        Command::Ban(arguments) => {
+            bans::handle_ban_command(bot, msg, arguments).await?;
+        }
+        Command::Unban(user_id) => {
+            bans::handle_unban_command(bot, msg, user_id).await?;
         }
-        CbQueryCommand::GetTeams => handle_get_teams(bot, &room, q.from).await?,
-        CbQueryCommand::Play => handle_play(&mut room, room_id, bot, q.from).await?,
-        CbQueryCommand::Start => handle_start_round(rooms.clone(), &mut room, room_id, bot).await?,
-        CbQueryCommand::Correct => handle_correct(rooms.clone(), &mut room, room_id, bot).await?,
-        CbQueryCommand::Skip => handle_skip(rooms.clone(), &mut room, room_id, bot).await?,
     };
     Ok(())
 }
 
-async fn handle_new_command(
-    bot: Bot,
-    msg: Message,
-    rooms: Rooms,
-    number_of_teams: usize,
-    number_of_rounds: usize,
-    round_duration: usize,
-    use_taboo_words: bool,
-) -> ResponseResult<()> {
-    let new_id = get_new_id();
-    rooms.insert(
-        new_id,
-        Mutex::new(Room::new(
-            number_of_teams,
-            number_of_rounds,
-            round_duration,
-            use_taboo_words,
-        )),
-    );
-    bot.send_message(
-        msg.chat.id,
-        "Room created! Forward following message to join:",
-    )
-    .await?;
-    bot.send_message(msg.chat.id, format!("/join {}", new_id.0))
-        .await?;
-    Ok(())
-}
-
-async fn handle_join_command(
+async fn handle_cb_query(
     bot: Bot,
-    msg: Message,
     rooms: Rooms,
-    room_id: u32,
-) -> ResponseResult<()> {
-    let Some(user) = msg.from() else {
-        return Ok(());
-    };
-    let room_id = RoomId(room_id);
-    let Some(room) = rooms.get(&room_id) else {
-        bot.send_message(msg.chat.id, "Room number is wrong!")
-            .await?;
+    storage: Arc<InMemStorage<dialogue::State>>,
+    q: CallbackQuery,
+) -> HandlerResult {
+    let Some(data) = q.data.clone() else {
         return Ok(());
     };
 
-    let mut room = room.lock().await;
-
-    match room.join(user.clone()) {
-        Ok((others, number_of_teams)) => {
-            broadcast(others, &bot, format!("{} joined room", user.full_name())).await?;
-
-            let mut buttons = get_teams(number_of_teams)
-                .into_iter()
-                .enumerate()
-                .map(|(idx, team)| {
-                    vec![InlineKeyboardButton::callback(
-                        team,
-                        serialize_command(room_id, CbQueryCommand::Join { team_index: idx }),
-                    )]
-                })
-                .collect::<Vec<_>>();
-
-            buttons.push(vec![InlineKeyboardButton::callback(
-                "Show Teams",
-                serialize_command(room_id, CbQueryCommand::GetTeams),
-            )]);
-
-            buttons.push(vec![InlineKeyboardButton::callback(
-                "Play",
-                serialize_command(room_id, CbQueryCommand::Play),
-            )]);
-
-            bot.send_message(msg.chat.id, "Choose your team")
-                .reply_markup(InlineKeyboardMarkup::new(buttons))
-                .await?;
+    match parse_user_command(&data) {
+        Some(UserCbQueryCommand::ToggleNotification { toggle }) => {
+            return notifications::handle_toggle(bot, q, toggle).await;
         }
-        Err(room::GameLogicError::AlreadyJoined) => {
-            bot.send_message(msg.chat.id, "You've already joined!")
-                .await?;
+        Some(UserCbQueryCommand::Tutorial { step }) => {
+            return tutorial::handle_tutorial_step(bot, q, step).await;
         }
-        Err(room::GameLogicError::JoinAfterPlay) => {
-            bot.send_message(msg.chat.id, "Game has started. You can't join anymore!")
-                .await?;
+        Some(UserCbQueryCommand::SwitchRoom { room_id }) => {
+            return memberships::handle_switch(bot, q, rooms, room_id).await;
         }
-        Err(_) => {}
-    }
-    Ok(())
-}
-
-async fn broadcast(
-    others: Vec<UserId>,
-    bot: &Bot,
-    msg: String,
-) -> Result<(), teloxide::RequestError> {
-    for other in others {
-        bot.send_message(other, msg.as_str()).await?;
-    }
-    Ok(())
-}
-
-async fn handle_team_join(
-    bot: Bot,
-    room: &mut Room,
-    user: User,
-    team_index: usize,
-) -> ResponseResult<()> {
-    match room.join_team(user.id, team_index) {
-        Ok(others) => {
-            broadcast(
-                others,
-                &bot,
-                format!("{} joined {}", user.full_name(), get_team_emoji(team_index)),
-            )
-            .await?;
+        Some(UserCbQueryCommand::ReviewWord { id, verdict }) => {
+            return submissions::handle_review(bot, q, id, verdict).await;
         }
-        Err(room::GameLogicError::TeamChangeAfterPlay) => {
-            bot.send_message(
-                user.id,
-                "Game has started. You can't change your team anymore!",
-            )
-            .await?;
-        }
-        Err(_) => (),
-    }
-    Ok(())
-}
-
-async fn handle_get_teams(bot: Bot, room: &Room, user: User) -> ResponseResult<()> {
-    bot.send_message(user.id, room.get_teams()).await?;
-    Ok(())
-}
-
-async fn handle_play(room: &mut Room, room_id: RoomId, bot: Bot, user: User) -> ResponseResult<()> {
-    match room.play() {
-        Ok(describing_player) => {
-            broadcast(
-                room.get_all_players(),
-                &bot,
-                format!(
-                    "Game has started. {} should start the first round!",
-                    describing_player.full_name()
-                ),
-            )
-            .await?;
-
-            let sent_message = bot
-                .send_message(describing_player.id, "Start round")
-                .reply_markup(InlineKeyboardMarkup::new([vec![
-                    InlineKeyboardButton::callback(
-                        "▶️",
-                        serialize_command(room_id, CbQueryCommand::Start),
-                    ),
-                ]]))
-                .await?;
-
-            if room
-                .push_to_message_stack(sent_message.chat.id, sent_message.id)
-                .is_err()
-            {
-                log::warn!("Error while pushing to message stack {:?}", room_id);
-            }
+        Some(UserCbQueryCommand::TabooWords { use_taboo_words }) => {
+            return dialogue::handle_taboo_words_answer(bot, q, rooms, storage, use_taboo_words)
+                .await;
         }
-        Err(GameLogicError::NotBalancedTeams) => {
-            bot.send_message(user.id, "Teams are not balanced").await?;
+        Some(UserCbQueryCommand::SetupBack) => {
+            return dialogue::handle_setup_back(bot, q, storage).await;
         }
-        Err(_) => (),
-    }
-    Ok(())
-}
-
-async fn finish_round(
-    rooms: Rooms,
-    room_id: RoomId,
-    players: Vec<UserId>,
-    round_duration: usize,
-    bot: Bot,
-) {
-    let round_duration_in_seconds = round_duration * 60;
-    let mut time_alerts = HashMap::new();
-    time_alerts.insert(60, "⏱️📢 1 min ❗");
-    time_alerts.insert(30, "⏱️📢 30 secs ❗");
-    time_alerts.insert(10, "⏱️📢 10 secs ❗");
-
-    time_alerts.into_iter().for_each(|(time, message)| {
-        tokio::spawn({
-            let bot = bot.clone();
-            let players = players.clone();
-            async move {
-                tokio::time::sleep(Duration::from_secs(round_duration_in_seconds as u64 - time))
-                    .await;
-                if let Err(err) = broadcast(players, &bot, message.to_string()).await {
-                    log::warn!("Can not broadcast time alert: {}", err);
-                }
-            }
-        });
-    });
-
-    tokio::time::sleep(Duration::from_secs(round_duration_in_seconds as u64)).await;
-    let Some(room) = rooms.get(&room_id) else {
-        return;
-    };
-
-    let mut room = room.lock().await;
-
-    if let Err(err) = clear_last_buttons(&bot, &room).await {
-        log::warn!("Can not clear buttons: {}", err);
+        None => (),
     }
 
-    let Ok(round_stop_state) = room.stop_round() else {
-        log::warn!("Room in bad state while stopping round {:?}", room_id);
-        return;
+    let Some((room_id, command)) = parse_command(data) else {
+        return Ok(());
     };
 
-    match round_stop_state {
-        room::RoundStopState::RoundFinished(results, describing_player, round, total_rounds) => {
-            if let Err(err) = broadcast(room.get_all_players(), &bot, results).await {
-                log::warn!("Can not broadcast results: {}", err);
-            }
-
-            if let Err(err) = broadcast(
-                room.get_all_players(),
-                &bot,
-                format!(
-                    "Round has finished! {} should start round {}/{}!",
-                    describing_player.full_name(),
-                    round,
-                    total_rounds
-                ),
-            )
-            .await
-            {
-                log::warn!("Can not broadcast round finished alert: {}", err);
-            }
-
-            let sent_message = match bot
-                .send_message(describing_player.id, "Start round")
-                .reply_markup(InlineKeyboardMarkup::new([vec![
-                    InlineKeyboardButton::callback(
-                        "▶️",
-                        serialize_command(room_id, CbQueryCommand::Start),
-                    ),
-                ]]))
-                .await
-            {
-                Ok(sent_message) => sent_message,
-                Err(err) => {
-                    log::warn!("Can not send start round message: {}", err);
-                    return;
-                }
-            };
-
-            if room
-                .push_to_message_stack(sent_message.chat.id, sent_message.id)
-                .is_err()
-            {
-                log::warn!("Error while pushing to message stack {:?}", room_id);
-            }
-        }
-        room::RoundStopState::GameFinished(results) => {
-            if let Err(err) =
-                broadcast(room.get_all_players(), &bot, "Game finished!".to_owned()).await
-            {
-                log::warn!("Can not broadcast game finished alert: {}", err);
-            }
-            if let Err(err) = broadcast(room.get_all_players(), &bot, results).await {
-                log::warn!("Can not broadcast results: {}", err);
-            }
-        }
-    }
-}
-
-async fn handle_start_round(
-    rooms: Rooms,
-    room: &mut Room,
-    room_id: RoomId,
-    bot: Bot,
-) -> ResponseResult<()> {
-    if let Ok(word_guess_try) = room.start_round() {
-        send_new_word(rooms.clone(), room, room_id, bot.clone(), word_guess_try).await?;
-
-        tokio::task::spawn({
-            let players = room.get_all_players().clone();
-            let round_duration = room.round_duration();
-            async move {
-                finish_round(rooms, room_id, players, round_duration, bot).await;
-            }
-        });
-    }
-    Ok(())
-}
-
-async fn clear_last_buttons(bot: &Bot, room: &Room) -> ResponseResult<()> {
-    let Ok(Some((chat_id, message_id))) = room.get_message_stack_top() else {
+    let Some(room) = rooms.get(&room_id) else {
         return Ok(());
     };
+    let mut room = room.lock().await;
 
-    bot.edit_message_reply_markup(chat_id, message_id)
-        .reply_markup(InlineKeyboardMarkup::new([[]]))
-        .await?;
-
-    Ok(())
-}
-
-async fn send_new_word(
-    rooms: Rooms,
-    room: &mut Room,
-    room_id: RoomId,
-    bot: Bot,
-    word_guess_try: room::WordGuessTry,
-) -> ResponseResult<()> {
-    clear_last_buttons(&bot, room).await?;
-    let sent_message = bot
-        .send_message(
-            word_guess_try.describing.id,
-            word_guess_try
-                .word
-                .get_message_string(room.use_taboo_words()),
-        )
-        .reply_markup(InlineKeyboardMarkup::new([vec![
-            InlineKeyboardButton::callback(
-                "✅",
-                serialize_command(room_id, CbQueryCommand::Correct),
-            ),
-        ]]))
-        .await?;
-
-    if room
-        .push_to_message_stack(sent_message.chat.id, sent_message.id)
-        .is_err()
-    {
-        log::warn!("Error while pushing to message stack {:?}", room_id);
-    }
-
-    bot.send_message(word_guess_try.guessing.id, "🤔").await?;
-    let mut players = BTreeSet::from_iter(room.get_all_players().into_iter());
-    players.remove(&word_guess_try.describing.id);
-    players.remove(&word_guess_try.guessing.id);
-    broadcast(
-        players.into_iter().collect(),
-        &bot,
-        format!(
-            "{} -> {}\n\t{}",
-            word_guess_try.describing.full_name(),
-            word_guess_try.guessing.full_name(),
-            word_guess_try
-                .word
-                .get_message_string(room.use_taboo_words())
-        ),
+    let user_id = q.from.id;
+    let card = q.message.map(|message| (message.chat.id, message.id));
+    let awaiting = game::handle_room_command(
+        bot.clone(),
+        rooms.clone(),
+        &mut room,
+        room_id,
+        command,
+        q.from,
+        card,
     )
     .await?;
-    tokio::task::spawn(async move {
-        add_skip_button(rooms, room_id, bot, sent_message).await;
-    });
-    Ok(())
-}
-
-async fn add_skip_button(rooms: Rooms, room_id: RoomId, bot: Bot, sent_message: Message) {
-    tokio::time::sleep(Duration::from_secs(SKIP_COOL_DOWN_IN_SECONDS as u64)).await;
-    let Some(room) = rooms.get(&room_id) else {
-        return;
-    };
-    let room = room.lock().await;
-    let Ok(Some((chat_id, message_id))) = room.get_message_stack_top() else {
-        return;
+    let next_state = match awaiting {
+        Some(game::Awaiting::CustomCard) => Some(dialogue::State::ReceiveCustomCard { room_id }),
+        Some(game::Awaiting::Invitees) => Some(dialogue::State::ReceiveInvitees { room_id }),
+        None => None,
     };
-
-    if chat_id != sent_message.chat.id || message_id != sent_message.id {
-        return;
-    }
-
-    if let Err(err) = bot
-        .edit_message_reply_markup(sent_message.chat.id, sent_message.id)
-        .reply_markup(InlineKeyboardMarkup::new([vec![
-            InlineKeyboardButton::callback(
-                "✅",
-                serialize_command(room_id, CbQueryCommand::Correct),
-            ),
-            InlineKeyboardButton::callback("⏩️", serialize_command(room_id, CbQueryCommand::Skip)),
-        ]]))
-        .await
-    {
-        log::warn!("Can not add skip button: {:?} {}", room_id, err);
-    }
-}
-
-async fn handle_correct(
-    rooms: Rooms,
-    room: &mut Room,
-    room_id: RoomId,
-    bot: Bot,
-) -> ResponseResult<()> {
-    if let Ok(word_guess_try) = room.correct() {
-        send_new_word(rooms, room, room_id, bot, word_guess_try).await?;
+    if let Some(state) = next_state {
+        dialogue::MyDialogue::new(storage, user_id.into())
+            .update(state)
+            .await?;
     }
-    Ok(())
-}
-
-async fn handle_skip(
-    rooms: Rooms,
-    room: &mut Room,
-    room_id: RoomId,
-    bot: Bot,
-) -> ResponseResult<()> {
-    if let Ok(word_guess_try) = room.skip() {
-        send_new_word(rooms, room, room_id, bot, word_guess_try).await?;
+    persistence::save(room_id, &room);
+    if let Some(toast) = game::take_toast(user_id) {
+        bot.answer_callback_query(q.id).text(toast).await?;
     }
     Ok(())
 }
@@ -0,0 +1,781 @@
+//! Runs games in Matrix rooms with the same engine, word bank and storage as
+//! the Telegram bot, for communities on their own homeservers. The bot
+//! long-polls the homeserver for events, joins the rooms it is invited to
+//! and takes `!new` and `!join` as commands. Matrix has no buttons, so they
+//! are listed under the card with an emoji each and pressed by reacting with
+//! it. The bot adds the reactions itself so a player only has to click one.
+//!
+//! Configured with `JIGARPICH_MATRIX_HOMESERVER` and `JIGARPICH_MATRIX_TOKEN`,
+//! the access token of the bot's account, and only built with the `matrix`
+//! feature.
+
+use std::{
+    env, fmt,
+    sync::{
+        atomic::{AtomicI32, AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use dashmap::DashMap;
+use reqwest::{Method, RequestBuilder, StatusCode, Url};
+use serde_json::{json, Value};
+use teloxide::types::{ChatId, MessageId, User, UserId};
+
+use crate::{
+    bridge::{self, external_id, local_id, Bridge, Platform},
+    callback_query_command::{parse_command, serialize_command, CbQueryCommand},
+    frontend::{Buttons, Frontend},
+    game, persistence,
+    room::{Room, RoomConfig, RoomId, MAX_NUMBER_OF_ROUNDS, MAX_NUMBER_OF_TEAMS},
+    storage::Store,
+    Rooms,
+};
+
+const SYNC_TIMEOUT_IN_MILLISECONDS: u64 = 30_000;
+
+const SYNC_RETRY_DELAY_IN_SECONDS: u64 = 5;
+
+/// The ten keycaps and 26 letters a card has emoji for.
+const MAX_BUTTONS: usize = 36;
+
+const USAGE: &str = "Create a room with !new <teams> <rounds> <minutes>, adding \"notaboo\" \
+                     to leave out the taboo words, or join one with !join <room number>.";
+
+static DM_ROOMS: OnceLock<Store<String, String>> = OnceLock::new();
+
+/// The direct message room of each Matrix user, kept so players get their
+/// cards in the same room after restarts.
+fn dm_rooms() -> &'static Store<String, String> {
+    DM_ROOMS.get_or_init(|| Store::open("matrix_dm_rooms"))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Request(reqwest::Error),
+    UnknownMessage(MessageId),
+    UnknownChat(ChatId),
+    BadResponse,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Request(err) => write!(f, "{}", err),
+            Error::UnknownMessage(message_id) => write!(f, "Unknown message {}", message_id.0),
+            Error::UnknownChat(chat_id) => write!(f, "Unknown chat {}", chat_id),
+            Error::BadResponse => write!(f, "Unexpected response from the homeserver"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Request(err)
+    }
+}
+
+/// A message with buttons, kept to render it again when only its text or
+/// only its buttons change.
+struct Card {
+    chat_id: ChatId,
+    message_id: MessageId,
+    room: String,
+    text: String,
+    /// The label and the serialized command of each button.
+    buttons: Vec<(String, String)>,
+    /// How many of the buttons the bot has reacted with.
+    reacted: usize,
+}
+
+struct Inner {
+    homeserver: Url,
+    token: String,
+    client: reqwest::Client,
+    /// Transaction IDs must not repeat for the same access token, so they
+    /// start with the time the bot started.
+    transaction_prefix: u128,
+    next_transaction: AtomicU64,
+    /// The engine counts message IDs in 32 bits, Matrix event IDs are mapped
+    /// onto local ones.
+    messages: DashMap<MessageId, String>,
+    cards: DashMap<String, Card>,
+    next_message_id: AtomicI32,
+}
+
+#[derive(Clone)]
+pub struct Matrix(Arc<Inner>);
+
+impl Matrix {
+    pub fn from_env() -> Option<Self> {
+        let token = env::var("JIGARPICH_MATRIX_TOKEN").ok()?;
+        let homeserver = env::var("JIGARPICH_MATRIX_HOMESERVER")
+            .ok()
+            .and_then(|homeserver| Url::parse(&homeserver).ok())
+            .filter(|homeserver| !homeserver.cannot_be_a_base())
+            .expect("JIGARPICH_MATRIX_HOMESERVER must be set along with JIGARPICH_MATRIX_TOKEN");
+
+        Some(Matrix(Arc::new(Inner {
+            homeserver,
+            token,
+            client: reqwest::Client::new(),
+            transaction_prefix: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis(),
+            next_transaction: AtomicU64::new(0),
+            messages: DashMap::new(),
+            cards: DashMap::new(),
+            next_message_id: AtomicI32::new(1),
+        })))
+    }
+
+    /// A request to an endpoint of the client-server API, or of the media
+    /// repository when `api` is `"media"`.
+    fn request(&self, method: Method, api: &str, path: &[&str]) -> RequestBuilder {
+        let mut url = self.0.homeserver.clone();
+        url.path_segments_mut()
+            .expect("The homeserver URL has a path")
+            .pop_if_empty()
+            .extend(["_matrix", api, "v3"])
+            .extend(path);
+        self.0
+            .client
+            .request(method, url)
+            .bearer_auth(&self.0.token)
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Value, Error> {
+        Ok(request.send().await?.error_for_status()?.json().await?)
+    }
+
+    async fn send_event(&self, room: &str, kind: &str, content: Value) -> Result<String, Error> {
+        let transaction = format!(
+            "jigarpich.{}.{}",
+            self.0.transaction_prefix,
+            self.0.next_transaction.fetch_add(1, Ordering::Relaxed)
+        );
+        let response = self
+            .send(
+                self.request(
+                    Method::PUT,
+                    "client",
+                    &["rooms", room, "send", kind, &transaction],
+                )
+                .json(&content),
+            )
+            .await?;
+        response["event_id"]
+            .as_str()
+            .map(str::to_owned)
+            .ok_or(Error::BadResponse)
+    }
+
+    /// The room messages to `chat_id` go to, opening a direct message room
+    /// for users.
+    async fn room(&self, chat_id: ChatId) -> Result<String, Error> {
+        let Some((Platform::Matrix, id)) = external_id(chat_id) else {
+            return Err(Error::UnknownChat(chat_id));
+        };
+        if !chat_id.is_user() {
+            return Ok(id);
+        }
+        if let Some(room) = dm_rooms().get(&id) {
+            return Ok(room);
+        }
+
+        let response = self
+            .send(
+                self.request(Method::POST, "client", &["createRoom"])
+                    .json(&json!({
+                        "is_direct": true,
+                        "invite": [id],
+                        "preset": "trusted_private_chat",
+                    })),
+            )
+            .await?;
+        let room = response["room_id"]
+            .as_str()
+            .ok_or(Error::BadResponse)?
+            .to_owned();
+        dm_rooms().insert(id, room.clone());
+        Ok(room)
+    }
+
+    fn remember(&self, event: String) -> MessageId {
+        let message_id = MessageId(self.0.next_message_id.fetch_add(1, Ordering::Relaxed));
+        self.0.messages.insert(message_id, event);
+        message_id
+    }
+
+    fn event(&self, message_id: MessageId) -> Result<String, Error> {
+        self.0
+            .messages
+            .get(&message_id)
+            .map(|event| event.clone())
+            .ok_or(Error::UnknownMessage(message_id))
+    }
+
+    /// Reacts to a card with the keys of the buttons it hasn't reacted with
+    /// yet.
+    async fn react(&self, event: &str) -> Result<(), Error> {
+        let Some((room, keys)) = self.0.cards.get_mut(event).map(|mut card| {
+            let keys = (card.reacted..card.buttons.len())
+                .filter_map(key)
+                .collect::<Vec<_>>();
+            card.reacted = card.buttons.len();
+            (card.room.clone(), keys)
+        }) else {
+            return Ok(());
+        };
+
+        for key in keys {
+            self.send_event(
+                &room,
+                "m.reaction",
+                json!({
+                    "m.relates_to": {
+                        "rel_type": "m.annotation",
+                        "event_id": event,
+                        "key": key,
+                    },
+                }),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn edit_card_with(
+        &self,
+        message_id: MessageId,
+        update: impl FnOnce(&mut Card),
+    ) -> Result<(), Error> {
+        let event = self.event(message_id)?;
+        let (room, body) = {
+            let mut card = self
+                .0
+                .cards
+                .get_mut(&event)
+                .ok_or(Error::UnknownMessage(message_id))?;
+            update(&mut card);
+            (card.room.clone(), body(&card.text, &card.buttons))
+        };
+
+        self.send_event(
+            &room,
+            "m.room.message",
+            json!({
+                "msgtype": "m.text",
+                "body": format!("* {}", body),
+                "m.new_content": { "msgtype": "m.text", "body": body },
+                "m.relates_to": { "rel_type": "m.replace", "event_id": event },
+            }),
+        )
+        .await?;
+        self.react(&event).await
+    }
+
+    async fn upload(
+        &self,
+        to: ChatId,
+        file_name: &str,
+        mime_type: &str,
+        file: Vec<u8>,
+        mut content: Value,
+    ) -> Result<(), Error> {
+        let room = self.room(to).await?;
+        let size = file.len();
+        let response = self
+            .send(
+                self.request(Method::POST, "media", &["upload"])
+                    .query(&[("filename", file_name)])
+                    .header("Content-Type", mime_type)
+                    .body(file),
+            )
+            .await?;
+        content["url"] = response["content_uri"].clone();
+        content["filename"] = json!(file_name);
+        content["info"] = json!({ "mimetype": mime_type, "size": size });
+        self.send_event(&room, "m.room.message", content).await?;
+        Ok(())
+    }
+
+    async fn join(&self, room: &str) -> Result<(), Error> {
+        self.send(
+            self.request(Method::POST, "client", &["join", room])
+                .json(&json!({})),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Syncs with the homeserver forever, playing out the commands and
+    /// reactions of every room the bot is in.
+    pub async fn run(self, frontend: Bridge, rooms: Rooms) {
+        let me = loop {
+            match self
+                .send(self.request(Method::GET, "client", &["account", "whoami"]))
+                .await
+            {
+                Ok(response) => match response["user_id"].as_str() {
+                    Some(me) => break me.to_owned(),
+                    None => log::warn!("Can not find out the Matrix user of the bot"),
+                },
+                Err(err) => log::warn!("Can not find out the Matrix user of the bot: {}", err),
+            }
+            tokio::time::sleep(Duration::from_secs(SYNC_RETRY_DELAY_IN_SECONDS)).await;
+        };
+        let state = MatrixState {
+            matrix: self,
+            frontend,
+            rooms,
+        };
+
+        let mut since = None::<String>;
+        loop {
+            let mut request = state
+                .matrix
+                .request(Method::GET, "client", &["sync"])
+                .query(&[("timeout", SYNC_TIMEOUT_IN_MILLISECONDS)]);
+            if let Some(since) = &since {
+                request = request.query(&[("since", since)]);
+            }
+            let response = match state.matrix.send(request).await {
+                Ok(response) => response,
+                Err(err) => {
+                    log::warn!("Can not sync with the Matrix homeserver: {}", err);
+                    tokio::time::sleep(Duration::from_secs(SYNC_RETRY_DELAY_IN_SECONDS)).await;
+                    continue;
+                }
+            };
+
+            for (room, invite) in response["rooms"]["invite"]
+                .as_object()
+                .into_iter()
+                .flatten()
+            {
+                state.matrix.accept_invite(&me, room, invite).await;
+            }
+            // Commands sent while the bot was down are stale by now.
+            let first_sync = since.is_none();
+            since = response["next_batch"].as_str().map(str::to_owned);
+            if first_sync {
+                continue;
+            }
+
+            for (room, joined) in response["rooms"]["join"].as_object().into_iter().flatten() {
+                for event in joined["timeline"]["events"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                {
+                    if event["sender"] == me.as_str() {
+                        continue;
+                    }
+                    let (state, room, event) = (state.clone(), room.clone(), event.clone());
+                    tokio::spawn(async move {
+                        if let Err(err) = handle_event(state, room, event).await {
+                            log::warn!("Can not handle Matrix event: {}", err);
+                        }
+                    });
+                }
+            }
+        }
+    }
+
+    /// Joins a room the bot is invited to, remembering it as the direct
+    /// message room of the inviter when it is one.
+    async fn accept_invite(&self, me: &str, room: &str, invite: &Value) {
+        if let Err(err) = self.join(room).await {
+            log::warn!("Can not join the Matrix room {}: {}", room, err);
+            return;
+        }
+
+        let direct_invite = invite["invite_state"]["events"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|event| {
+                event["type"] == "m.room.member"
+                    && event["state_key"] == me
+                    && event["content"]["is_direct"] == true
+            });
+        if let Some(inviter) = direct_invite.and_then(|event| event["sender"].as_str()) {
+            dm_rooms().insert(inviter.to_owned(), room.to_owned());
+        }
+    }
+}
+
+/// The emoji pressing the button at `index` is reacted with.
+fn key(index: usize) -> Option<String> {
+    match index {
+        0..=8 => Some(format!("{}\u{fe0f}\u{20e3}", index + 1)),
+        9 => Some("🔟".to_owned()),
+        _ if index < MAX_BUTTONS => {
+            char::from_u32(0x1f1e6 + (index - 10) as u32).map(|letter| letter.to_string())
+        }
+        _ => None,
+    }
+}
+
+fn body(text: &str, buttons: &[(String, String)]) -> String {
+    let mut body = text.to_owned();
+    if !buttons.is_empty() {
+        body.push('\n');
+    }
+    for (index, (label, _)) in buttons.iter().enumerate() {
+        if let Some(key) = key(index) {
+            body.push_str(&format!("\n{} {}", key, label));
+        }
+    }
+    body
+}
+
+fn flatten(buttons: Buttons) -> Vec<(String, String)> {
+    let buttons = buttons
+        .into_iter()
+        .flatten()
+        .map(|button| {
+            (
+                button.label,
+                serialize_command(button.room_id, button.command),
+            )
+        })
+        .collect::<Vec<_>>();
+    if buttons.len() > MAX_BUTTONS {
+        log::warn!("Dropping {} buttons on Matrix", buttons.len() - MAX_BUTTONS);
+    }
+    buttons.into_iter().take(MAX_BUTTONS).collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl Frontend for Matrix {
+    type Error = Error;
+
+    async fn send_card_to_topic(
+        &self,
+        to: ChatId,
+        _topic: Option<i32>,
+        text: String,
+        buttons: Buttons,
+    ) -> Result<(ChatId, MessageId), Self::Error> {
+        let room = self.room(to).await?;
+        let buttons = flatten(buttons);
+        let event = self
+            .send_event(
+                &room,
+                "m.room.message",
+                json!({ "msgtype": "m.text", "body": body(&text, &buttons) }),
+            )
+            .await?;
+        let message_id = self.remember(event.clone());
+        self.0.cards.insert(
+            event.clone(),
+            Card {
+                chat_id: to,
+                message_id,
+                room,
+                text,
+                buttons,
+                reacted: 0,
+            },
+        );
+        self.react(&event).await?;
+        Ok((to, message_id))
+    }
+
+    async fn edit_buttons(
+        &self,
+        _chat_id: ChatId,
+        message_id: MessageId,
+        buttons: Buttons,
+    ) -> Result<(), Self::Error> {
+        let buttons = flatten(buttons);
+        self.edit_card_with(message_id, |card| card.buttons = buttons)
+            .await
+    }
+
+    async fn edit_text(
+        &self,
+        _chat_id: ChatId,
+        message_id: MessageId,
+        text: String,
+    ) -> Result<(), Self::Error> {
+        self.edit_card_with(message_id, |card| card.text = text)
+            .await
+    }
+
+    async fn edit_card(
+        &self,
+        _chat_id: ChatId,
+        message_id: MessageId,
+        text: String,
+        buttons: Buttons,
+    ) -> Result<(), Self::Error> {
+        let buttons = flatten(buttons);
+        self.edit_card_with(message_id, |card| {
+            card.text = text;
+            card.buttons = buttons;
+        })
+        .await
+    }
+
+    async fn pin_message(&self, chat_id: ChatId, message_id: MessageId) -> Result<(), Self::Error> {
+        let room = self.room(chat_id).await?;
+        let event = self.event(message_id)?;
+        let path = ["rooms", &room, "state", "m.room.pinned_events"];
+        let pinned = match self.send(self.request(Method::GET, "client", &path)).await {
+            Ok(response) => response["pinned"].as_array().cloned().unwrap_or_default(),
+            Err(Error::Request(err)) if err.status() == Some(StatusCode::NOT_FOUND) => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        let pinned = pinned.into_iter().chain([json!(event)]).collect::<Vec<_>>();
+        self.send(
+            self.request(Method::PUT, "client", &path)
+                .json(&json!({ "pinned": pinned })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn prompt_to_topic(
+        &self,
+        to: ChatId,
+        _topic: Option<i32>,
+        text: String,
+    ) -> Result<(), Self::Error> {
+        let room = self.room(to).await?;
+        self.send_event(
+            &room,
+            "m.room.message",
+            json!({ "msgtype": "m.text", "body": text }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn prompt_with_spoiler(
+        &self,
+        to: ChatId,
+        text: String,
+        spoiler: String,
+    ) -> Result<(), Self::Error> {
+        let room = self.room(to).await?;
+        self.send_event(
+            &room,
+            "m.room.message",
+            json!({
+                "msgtype": "m.text",
+                "body": format!("{}{}", text, spoiler),
+                "format": "org.matrix.custom.html",
+                "formatted_body": format!(
+                    "{}<span data-mx-spoiler>{}</span>",
+                    escape_html(&text).replace('\n', "<br>"),
+                    escape_html(&spoiler)
+                ),
+            }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn join_link(&self, _room_id: RoomId) -> Result<Option<String>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn send_image(
+        &self,
+        to: ChatId,
+        _topic: Option<i32>,
+        png: Vec<u8>,
+        caption: String,
+    ) -> Result<(), Self::Error> {
+        let body = if caption.is_empty() {
+            "scoreboard.png".to_owned()
+        } else {
+            caption
+        };
+        self.upload(
+            to,
+            "scoreboard.png",
+            "image/png",
+            png,
+            json!({ "msgtype": "m.image", "body": body }),
+        )
+        .await
+    }
+
+    async fn send_sound(
+        &self,
+        to: ChatId,
+        _topic: Option<i32>,
+        ogg: Vec<u8>,
+    ) -> Result<(), Self::Error> {
+        self.upload(
+            to,
+            "alert.ogg",
+            "audio/ogg",
+            ogg,
+            json!({ "msgtype": "m.audio", "body": "alert.ogg" }),
+        )
+        .await
+    }
+}
+
+#[derive(Clone)]
+struct MatrixState {
+    matrix: Matrix,
+    frontend: Bridge,
+    rooms: Rooms,
+}
+
+fn to_user(sender: &str) -> User {
+    let localpart = sender
+        .trim_start_matches('@')
+        .split(':')
+        .next()
+        .unwrap_or(sender);
+    User {
+        id: UserId(local_id(Platform::Matrix, sender, false) as u64),
+        is_bot: false,
+        first_name: localpart.to_owned(),
+        last_name: None,
+        username: Some(sender.to_owned()),
+        language_code: None,
+        is_premium: false,
+        added_to_attachment_menu: false,
+    }
+}
+
+async fn handle_event(state: MatrixState, room: String, event: Value) -> Result<(), bridge::Error> {
+    let Some(sender) = event["sender"].as_str() else {
+        return Ok(());
+    };
+    let user = to_user(sender);
+    let chat_id = match dm_rooms().get(&sender.to_owned()) {
+        Some(dm_room) if dm_room == room => user.id.into(),
+        _ => ChatId(local_id(Platform::Matrix, &room, true)),
+    };
+
+    let content = &event["content"];
+    match event["type"].as_str() {
+        Some("m.room.message") => {
+            let Some(command) = content["body"]
+                .as_str()
+                .and_then(|body| body.strip_prefix('!'))
+            else {
+                return Ok(());
+            };
+            run_command(state, chat_id, user, command).await
+        }
+        Some("m.reaction") => {
+            let relation = &content["m.relates_to"];
+            let (Some(event), Some(key)) =
+                (relation["event_id"].as_str(), relation["key"].as_str())
+            else {
+                return Ok(());
+            };
+            press_button(state, user, event, key).await
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn run_command(
+    state: MatrixState,
+    chat_id: ChatId,
+    user: User,
+    command: &str,
+) -> Result<(), bridge::Error> {
+    let mut words = command.split_whitespace();
+    let name = words.next();
+    let use_taboo_words = !command.split_whitespace().any(|word| word == "notaboo");
+    let numbers = words
+        .filter_map(|word| word.parse::<usize>().ok())
+        .collect::<Vec<_>>();
+
+    match (name, &numbers[..]) {
+        (Some("new"), &[teams, rounds, minutes])
+            if (2..=MAX_NUMBER_OF_TEAMS).contains(&teams)
+                && (1..=MAX_NUMBER_OF_ROUNDS).contains(&rounds)
+                && (1..=10).contains(&minutes) =>
+        {
+            let config = RoomConfig::new(teams, rounds, minutes * 60, use_taboo_words);
+            let room = Room::new(config, (!chat_id.is_user()).then_some(chat_id), user.id);
+            game::handle_new_command(state.frontend, chat_id, state.rooms, room, None).await
+        }
+        (Some("join"), &[room_id]) => {
+            let room_id = RoomId(room_id as u32);
+            game::handle_join_command(state.frontend, chat_id, state.rooms, user, room_id).await
+        }
+        (Some("new" | "join" | "help"), _) => {
+            state.frontend.prompt(chat_id, USAGE.to_owned()).await
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn press_button(
+    state: MatrixState,
+    user: User,
+    event: &str,
+    key: &str,
+) -> Result<(), bridge::Error> {
+    let Some((card, command)) = state.matrix.0.cards.get(event).and_then(|card| {
+        let index =
+            (0..card.buttons.len()).find(|&index| self::key(index).as_deref() == Some(key))?;
+        Some((
+            (card.chat_id, card.message_id),
+            card.buttons[index].1.clone(),
+        ))
+    }) else {
+        return Ok(());
+    };
+    let Some((room_id, command)) = parse_command(command) else {
+        return Ok(());
+    };
+
+    if matches!(command, CbQueryCommand::WriteCard) {
+        return state
+            .frontend
+            .prompt(
+                user.id.into(),
+                "✍️ Custom cards can only be written on Telegram for now.".to_owned(),
+            )
+            .await;
+    }
+
+    let Some(room) = state.rooms.get(&room_id) else {
+        return Ok(());
+    };
+    let mut room = room.lock().await;
+    let user_id = user.id;
+    game::handle_room_command(
+        state.frontend.clone(),
+        state.rooms.clone(),
+        &mut room,
+        room_id,
+        command,
+        user,
+        Some(card),
+    )
+    .await?;
+    persistence::save(room_id, &room);
+    // The press is already answered, so notices come as a message
+    if let Some(toast) = game::take_toast(user_id) {
+        state
+            .frontend
+            .prompt(user_id.into(), toast.to_owned())
+            .await?;
+    }
+    Ok(())
+}
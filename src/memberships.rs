@@ -0,0 +1,146 @@
+use std::sync::OnceLock;
+
+use dashmap::DashMap;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+
+use crate::{
+    callback_query_command::{serialize_user_command, UserCbQueryCommand},
+    room::RoomId,
+    HandlerResult, Rooms,
+};
+
+/// The rooms a user has joined, in joining order, and the one their commands
+/// target.
+#[derive(Default)]
+struct Memberships {
+    rooms: Vec<RoomId>,
+    active: Option<RoomId>,
+}
+
+static MEMBERSHIPS: OnceLock<DashMap<UserId, Memberships>> = OnceLock::new();
+
+fn memberships() -> &'static DashMap<UserId, Memberships> {
+    MEMBERSHIPS.get_or_init(DashMap::new)
+}
+
+/// Adds the room to the user's memberships and makes it the active one.
+pub fn record_join(user_id: UserId, room_id: RoomId) {
+    let mut memberships = memberships().entry(user_id).or_default();
+    if !memberships.rooms.contains(&room_id) {
+        memberships.rooms.push(room_id);
+    }
+    memberships.active = Some(room_id);
+}
+
+/// The room the user's commands target, the latest joined one unless they
+/// switched since.
+pub fn active_room(user_id: UserId) -> Option<RoomId> {
+    memberships().get(&user_id)?.active
+}
+
+/// The rooms the user joined that still exist.
+pub fn joined_rooms(user_id: UserId, rooms: &Rooms) -> Vec<RoomId> {
+    memberships()
+        .get(&user_id)
+        .map(|memberships| {
+            memberships
+                .rooms
+                .iter()
+                .filter(|room_id| rooms.contains_key(room_id))
+                .copied()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn switch(user_id: UserId, room_id: RoomId) -> bool {
+    let Some(mut memberships) = memberships().get_mut(&user_id) else {
+        return false;
+    };
+
+    if !memberships.rooms.contains(&room_id) {
+        return false;
+    }
+
+    memberships.active = Some(room_id);
+    true
+}
+
+/// A keyboard with one button per room of the user, the active one marked.
+fn room_picker(user_id: UserId, rooms: &Rooms) -> InlineKeyboardMarkup {
+    let active = active_room(user_id);
+    InlineKeyboardMarkup::new(joined_rooms(user_id, rooms).into_iter().map(|room_id| {
+        [InlineKeyboardButton::callback(
+            format!(
+                "{}Room {}",
+                if Some(room_id) == active { "✅ " } else { "" },
+                room_id.0
+            ),
+            serialize_user_command(UserCbQueryCommand::SwitchRoom { room_id }),
+        )]
+    }))
+}
+
+pub async fn handle_switch_command(
+    bot: Bot,
+    msg: Message,
+    rooms: Rooms,
+    room_id: String,
+) -> HandlerResult {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    if room_id.trim().is_empty() {
+        if joined_rooms(user.id, &rooms).is_empty() {
+            bot.send_message(msg.chat.id, "You haven't joined any room yet!")
+                .await?;
+            return Ok(());
+        }
+
+        bot.send_message(msg.chat.id, "Which room do you want to switch to?")
+            .reply_markup(room_picker(user.id, &rooms))
+            .await?;
+        return Ok(());
+    }
+
+    let Ok(room_id) = room_id.trim().parse().map(RoomId) else {
+        bot.send_message(msg.chat.id, "Please send a room number, e.g. /switch 12345")
+            .await?;
+        return Ok(());
+    };
+
+    if switch(user.id, room_id) {
+        bot.send_message(msg.chat.id, format!("Switched to room {}", room_id.0))
+            .await?;
+    } else {
+        bot.send_message(msg.chat.id, "You haven't joined this room!")
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn handle_switch(
+    bot: Bot,
+    q: CallbackQuery,
+    rooms: Rooms,
+    room_id: RoomId,
+) -> HandlerResult {
+    if !switch(q.from.id, room_id) {
+        return Ok(());
+    }
+
+    if let Some(message) = q.message {
+        bot.edit_message_text(
+            message.chat.id,
+            message.id,
+            format!("Switched to room {}", room_id.0),
+        )
+        .reply_markup(room_picker(q.from.id, &rooms))
+        .await?;
+    }
+    Ok(())
+}
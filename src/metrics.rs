@@ -0,0 +1,116 @@
+use std::{
+    env,
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+};
+
+use crate::{users, Rooms};
+
+static TIMERS_SPAWNED: AtomicUsize = AtomicUsize::new(0);
+
+static TIMERS_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+static MAX_ROOMS: OnceLock<Option<usize>> = OnceLock::new();
+
+struct RunningTimer;
+
+impl Drop for RunningTimer {
+    fn drop(&mut self) {
+        TIMERS_RUNNING.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Spawns a task that sleeps for a part of the game, counting it so leaking
+/// timers show up in the metrics.
+pub fn spawn_timer(timer: impl Future<Output = ()> + Send + 'static) {
+    TIMERS_SPAWNED.fetch_add(1, Ordering::Relaxed);
+    TIMERS_RUNNING.fetch_add(1, Ordering::Relaxed);
+    tokio::spawn(async move {
+        let _running = RunningTimer;
+        timer.await;
+    });
+}
+
+/// How many unfinished rooms may exist at once, configured with
+/// `JIGARPICH_MAX_ROOMS`. Unlimited when not set.
+fn max_rooms() -> Option<usize> {
+    *MAX_ROOMS.get_or_init(|| {
+        env::var("JIGARPICH_MAX_ROOMS")
+            .ok()
+            .and_then(|max| max.parse().ok())
+    })
+}
+
+async fn count_active_rooms(rooms: &Rooms) -> usize {
+    let room_ids = rooms.iter().map(|room| *room.key()).collect::<Vec<_>>();
+
+    let mut active = 0;
+    for room_id in room_ids {
+        let Some(room) = rooms.get(&room_id) else {
+            continue;
+        };
+        if !room.lock().await.is_finished() {
+            active += 1;
+        }
+    }
+    active
+}
+
+pub async fn is_at_capacity(rooms: &Rooms) -> bool {
+    match max_rooms() {
+        Some(max_rooms) => count_active_rooms(rooms).await >= max_rooms,
+        None => false,
+    }
+}
+
+/// The gauges in the Prometheus text format.
+pub async fn render(rooms: &Rooms) -> String {
+    let room_ids = rooms.iter().map(|room| *room.key()).collect::<Vec<_>>();
+
+    let (mut active_rooms, mut players, mut message_stack, mut largest_message_stack) =
+        (0, 0, 0, 0);
+    for room_id in &room_ids {
+        let Some(room) = rooms.get(room_id) else {
+            continue;
+        };
+        let room = room.lock().await;
+        if !room.is_finished() {
+            active_rooms += 1;
+        }
+        players += room.get_all_players().len();
+        message_stack += room.message_stack_len();
+        largest_message_stack = largest_message_stack.max(room.message_stack_len());
+    }
+
+    let mut metrics = vec![
+        ("jigarpich_rooms", room_ids.len()),
+        ("jigarpich_active_rooms", active_rooms),
+        ("jigarpich_players", players),
+        ("jigarpich_known_users", users::all().len()),
+        ("jigarpich_known_groups", users::groups().len()),
+        ("jigarpich_message_stack_size", message_stack),
+        (
+            "jigarpich_largest_message_stack_size",
+            largest_message_stack,
+        ),
+        (
+            "jigarpich_timers_running",
+            TIMERS_RUNNING.load(Ordering::Relaxed),
+        ),
+        (
+            "jigarpich_timers_spawned_total",
+            TIMERS_SPAWNED.load(Ordering::Relaxed),
+        ),
+    ];
+    if let Some(max_rooms) = max_rooms() {
+        metrics.push(("jigarpich_max_rooms", max_rooms));
+    }
+
+    metrics
+        .into_iter()
+        .map(|(name, value)| format!("{} {}\n", name, value))
+        .collect()
+}
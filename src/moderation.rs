@@ -0,0 +1,107 @@
+//! Checks run on words written by players before they reach the game. The
+//! blocklist is read from the file in `JIGARPICH_BLOCKLIST`, one word or
+//! phrase per line.
+
+use std::{collections::HashSet, env, fmt::Display, fs, sync::OnceLock};
+
+use crate::words::{self, Word};
+
+static BLOCKLIST: OnceLock<HashSet<String>> = OnceLock::new();
+
+fn blocklist() -> &'static HashSet<String> {
+    BLOCKLIST.get_or_init(|| {
+        let Ok(path) = env::var("JIGARPICH_BLOCKLIST") else {
+            return HashSet::new();
+        };
+        match fs::read_to_string(&path) {
+            Ok(blocklist) => blocklist
+                .lines()
+                .map(normalize)
+                .filter(|line| !line.is_empty())
+                .collect(),
+            Err(err) => {
+                log::warn!("Can not read the blocklist {}: {}", path, err);
+                HashSet::new()
+            }
+        }
+    })
+}
+
+pub enum Problem {
+    Blocked(String),
+    SimilarTo(String),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::Blocked(text) => write!(f, "\"{}\" is not allowed in the game", text),
+            Problem::SimilarTo(text) => {
+                write!(
+                    f,
+                    "It is too close to \"{}\", which is already in the game",
+                    text
+                )
+            }
+        }
+    }
+}
+
+/// Lowercases and drops punctuation, so spelling variants compare equal.
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(|token| {
+            token
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|token| !token.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Words this close are near-duplicates: one typo for short words, two for
+/// longer ones.
+fn is_similar(a: &str, b: &str) -> bool {
+    let max_distance = if a.chars().count() <= 5 { 1 } else { 2 };
+    a.chars().count().abs_diff(b.chars().count()) <= max_distance
+        && edit_distance(a, b) <= max_distance
+}
+
+/// The first blocked phrase in `text`, matched on whole words.
+fn blocked_phrase(text: &str) -> Option<String> {
+    let text = format!(" {} ", normalize(text));
+    blocklist()
+        .iter()
+        .find(|phrase| text.contains(&format!(" {} ", phrase)))
+        .cloned()
+}
+
+/// The first problem of a word written by a player, if any.
+pub fn check(word: &Word) -> Option<Problem> {
+    let blocked = std::iter::once(word.text.as_str())
+        .chain(word.taboo_words())
+        .find_map(blocked_phrase);
+    if let Some(phrase) = blocked {
+        return Some(Problem::Blocked(phrase));
+    }
+
+    let text = normalize(&word.text);
+    words::find(|other| is_similar(&text, &normalize(other))).map(Problem::SimilarTo)
+}
@@ -0,0 +1,126 @@
+//! Lets spectators and idle players mute the live word broadcasts for a while
+//! without leaving their game. The words they miss are kept and sent as a
+//! digest once the mute ends.
+
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use teloxide::prelude::*;
+
+use crate::HandlerResult;
+
+/// Words kept for the digest of a single mute, the oldest are dropped first.
+const MAX_DIGEST_WORDS: usize = 50;
+
+const USAGE: &str = "Send /mute 30m or /mute 2h to mute the live words, or /mute off";
+
+struct Mute {
+    until: Instant,
+    missed: Vec<String>,
+}
+
+static MUTES: OnceLock<DashMap<UserId, Mute>> = OnceLock::new();
+
+fn mutes() -> &'static DashMap<UserId, Mute> {
+    MUTES.get_or_init(DashMap::new)
+}
+
+/// Keeps a broadcast word for the digest of `user_id` instead of sending it,
+/// returning false when the user isn't muted.
+pub fn hold(user_id: UserId, text: &str, spoiler: &str) -> bool {
+    let Some(mut mute) = mutes().get_mut(&user_id) else {
+        return false;
+    };
+    if mute.until <= Instant::now() {
+        return false;
+    }
+
+    if mute.missed.len() == MAX_DIGEST_WORDS {
+        mute.missed.remove(0);
+    }
+    mute.missed.push(format!(
+        "{}: {}",
+        text.trim_end(),
+        spoiler.lines().next().unwrap_or_default()
+    ));
+    true
+}
+
+pub fn forget(user_id: UserId) {
+    mutes().remove(&user_id);
+}
+
+/// Parses durations like `30m`, `2h` or `45`, in minutes by default.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim().to_lowercase();
+    let (number, unit) = match text.strip_suffix('h') {
+        Some(number) => (number, 60 * 60),
+        None => (text.strip_suffix('m').unwrap_or(&text), 60),
+    };
+    let number = number.trim().parse::<u64>().ok()?;
+    if number == 0 || number * unit > 24 * 60 * 60 {
+        return None;
+    }
+    Some(Duration::from_secs(number * unit))
+}
+
+fn digest(missed: Vec<String>) -> String {
+    if missed.is_empty() {
+        return "🔔 You're unmuted, you didn't miss any words.".to_owned();
+    }
+    format!(
+        "🔔 You're unmuted, these are the words you missed:\n\n{}",
+        missed.join("\n")
+    )
+}
+
+async fn end_mute(bot: Bot, chat_id: ChatId, user_id: UserId, until: Instant) {
+    tokio::time::sleep_until(until.into()).await;
+    let Some((_, mute)) = mutes().remove_if(&user_id, |_, mute| mute.until == until) else {
+        return;
+    };
+    if let Err(err) = bot.send_message(chat_id, digest(mute.missed)).await {
+        log::warn!("Could not send the mute digest: {}", err);
+    }
+}
+
+pub async fn handle_mute_command(bot: Bot, msg: Message, arguments: String) -> HandlerResult {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    if arguments.trim().eq_ignore_ascii_case("off") {
+        let text = match mutes().remove(&user.id) {
+            Some((_, mute)) => digest(mute.missed),
+            None => "You're not muted.".to_owned(),
+        };
+        bot.send_message(msg.chat.id, text).await?;
+        return Ok(());
+    }
+
+    let Some(duration) = parse_duration(&arguments) else {
+        bot.send_message(msg.chat.id, USAGE).await?;
+        return Ok(());
+    };
+
+    let until = Instant::now() + duration;
+    let missed = mutes()
+        .remove(&user.id)
+        .map(|(_, mute)| mute.missed)
+        .unwrap_or_default();
+    mutes().insert(user.id, Mute { until, missed });
+    tokio::spawn(end_mute(bot.clone(), msg.chat.id, user.id, until));
+
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "🔕 Muted the live words for {} minutes, you'll get what you missed afterwards.",
+            duration.as_secs() / 60
+        ),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use std::sync::OnceLock;
+
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+
+use crate::{
+    callback_query_command::{serialize_user_command, UserCbQueryCommand},
+    storage::Store,
+    HandlerResult,
+};
+
+/// What a broadcast is about, so players can opt out of the noisy kinds.
+#[derive(Clone, Copy)]
+pub enum Category {
+    Join,
+    TimeAlert,
+    OtherTeamWord,
+    Result,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Toggle {
+    Joins,
+    TimeAlerts,
+    OtherTeamWords,
+    ResultsOnly,
+}
+
+const TOGGLES: [Toggle; 4] = [
+    Toggle::Joins,
+    Toggle::TimeAlerts,
+    Toggle::OtherTeamWords,
+    Toggle::ResultsOnly,
+];
+
+impl Toggle {
+    pub fn from_index(index: usize) -> Option<Toggle> {
+        TOGGLES.get(index).copied()
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Toggle::Joins => "Joins",
+            Toggle::TimeAlerts => "Time alerts",
+            Toggle::OtherTeamWords => "Other teams' words",
+            Toggle::ResultsOnly => "Results only",
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Preferences {
+    joins: bool,
+    time_alerts: bool,
+    other_team_words: bool,
+    results_only: bool,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            joins: true,
+            time_alerts: true,
+            other_team_words: true,
+            results_only: false,
+        }
+    }
+}
+
+impl Preferences {
+    fn allows(&self, category: Category) -> bool {
+        match category {
+            Category::Result => true,
+            _ if self.results_only => false,
+            Category::Join => self.joins,
+            Category::TimeAlert => self.time_alerts,
+            Category::OtherTeamWord => self.other_team_words,
+        }
+    }
+
+    fn get(&self, toggle: Toggle) -> bool {
+        match toggle {
+            Toggle::Joins => self.joins,
+            Toggle::TimeAlerts => self.time_alerts,
+            Toggle::OtherTeamWords => self.other_team_words,
+            Toggle::ResultsOnly => self.results_only,
+        }
+    }
+
+    fn flip(&mut self, toggle: Toggle) {
+        let value = match toggle {
+            Toggle::Joins => &mut self.joins,
+            Toggle::TimeAlerts => &mut self.time_alerts,
+            Toggle::OtherTeamWords => &mut self.other_team_words,
+            Toggle::ResultsOnly => &mut self.results_only,
+        };
+        *value = !*value;
+    }
+}
+
+static PREFERENCES: OnceLock<Store<UserId, Preferences>> = OnceLock::new();
+
+fn preferences() -> &'static Store<UserId, Preferences> {
+    PREFERENCES.get_or_init(|| Store::open("notifications"))
+}
+
+/// Drops the players who opted out of this kind of broadcast.
+pub fn recipients(players: Vec<UserId>, category: Category) -> Vec<UserId> {
+    players
+        .into_iter()
+        .filter(|player| {
+            preferences()
+                .get(player)
+                .unwrap_or_default()
+                .allows(category)
+        })
+        .collect()
+}
+
+/// The user's preferences, if they ever changed them.
+pub fn preferences_of(user_id: UserId) -> Option<Preferences> {
+    preferences().get(&user_id)
+}
+
+pub fn forget(user_id: UserId) {
+    if preferences().contains(&user_id) {
+        preferences().remove(&user_id);
+    }
+}
+
+fn keyboard(preferences: &Preferences) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(TOGGLES.map(|toggle| {
+        vec![InlineKeyboardButton::callback(
+            format!(
+                "{} {}",
+                if preferences.get(toggle) {
+                    "✅"
+                } else {
+                    "❌"
+                },
+                toggle.label()
+            ),
+            serialize_user_command(UserCbQueryCommand::ToggleNotification { toggle }),
+        )]
+    }))
+}
+
+pub async fn handle_notifications_command(bot: Bot, msg: Message) -> HandlerResult {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    let preferences = preferences().get(&user.id).unwrap_or_default();
+    bot.send_message(msg.chat.id, "Which notifications do you want to receive?")
+        .reply_markup(keyboard(&preferences))
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_toggle(bot: Bot, q: CallbackQuery, toggle: Toggle) -> HandlerResult {
+    let preferences = preferences().update(q.from.id, |preferences| preferences.flip(toggle));
+
+    if let Some(message) = q.message {
+        bot.edit_message_reply_markup(message.chat.id, message.id)
+            .reply_markup(keyboard(&preferences))
+            .await?;
+    }
+    Ok(())
+}
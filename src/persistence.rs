@@ -0,0 +1,39 @@
+use std::sync::{Arc, OnceLock};
+
+use dashmap::DashMap;
+use tokio::sync::Mutex;
+
+use crate::{
+    room::{Room, RoomId},
+    storage::Store,
+    Rooms,
+};
+
+static ROOMS: OnceLock<Store<RoomId, Room>> = OnceLock::new();
+
+fn rooms() -> &'static Store<RoomId, Room> {
+    ROOMS.get_or_init(|| Store::open("rooms"))
+}
+
+/// Writes the room's current state so it survives a restart.
+pub fn save(room_id: RoomId, room: &Room) {
+    rooms().insert(room_id, room.clone());
+}
+
+pub fn remove(room_id: RoomId) {
+    rooms().remove(&room_id);
+}
+
+/// The rooms saved by the previous run.
+pub fn restore() -> Rooms {
+    let restored = rooms()
+        .keys()
+        .into_iter()
+        .filter_map(|room_id| Some((room_id, Mutex::new(rooms().get(&room_id)?))))
+        .collect::<DashMap<_, _>>();
+
+    if !restored.is_empty() {
+        log::info!("Restored {} rooms", restored.len());
+    }
+    Arc::new(restored)
+}
@@ -0,0 +1,115 @@
+//! `/privacy` tells users what the bot keeps about them, `/mydata` sends it
+//! to them as a JSON file and `/forgetme` deletes it.
+
+use serde_json::json;
+use teloxide::{prelude::*, types::InputFile};
+
+use crate::{
+    brands, duos, memberships, mute, notifications, ratings, reachability, submissions, timezone,
+    users, HandlerResult, Rooms,
+};
+
+const PRIVACY_MESSAGE: &str = "🔒 What the bot keeps about you:
+
+- Your name, to reach you with announcements
+- Whether your messages get through, to warn your room when they don't
+- Which of the bots you talk to, when the game runs under several names
+- Your timezone and notification settings
+- The words you proposed that weren't reviewed yet
+- Your rating, and your standing duo with its stats
+- The names of the groups the bot is used in
+
+Games in progress keep their players until they end. Bans are kept as well.
+
+Send /mydata to get a copy, or /forgetme yes to delete the rest. Talking to the bot
+again starts over.";
+
+pub async fn handle_privacy_command(bot: Bot, msg: Message) -> HandlerResult {
+    bot.send_message(msg.chat.id, PRIVACY_MESSAGE).await?;
+    Ok(())
+}
+
+pub async fn handle_forget_me_command(
+    bot: Bot,
+    msg: Message,
+    confirmation: String,
+) -> HandlerResult {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    if !confirmation.trim().eq_ignore_ascii_case("yes") {
+        bot.send_message(
+            msg.chat.id,
+            "This deletes everything the bot keeps about you, see /privacy. \
+             Send /forgetme yes to go ahead.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    users::forget(user.id);
+    reachability::forget(user.id);
+    timezone::forget(user.id);
+    notifications::forget(user.id);
+    submissions::forget(user.id);
+    mute::forget(user.id);
+    duos::forget(user.id);
+    ratings::forget(user.id);
+    brands::forget(user.id);
+    log::info!("Deleted the data of user {}", user.id);
+
+    bot.send_message(msg.chat.id, "🗑 Done, the bot forgot about you.")
+        .await?;
+    Ok(())
+}
+
+/// The user's numbers in the rooms they joined that still exist.
+async fn games(user_id: UserId, rooms: &Rooms) -> Vec<serde_json::Value> {
+    let mut games = Vec::new();
+    for room_id in memberships::joined_rooms(user_id, rooms) {
+        let Some(room) = rooms.get(&room_id) else {
+            continue;
+        };
+        let stats = room.lock().await.player_stats();
+        let stats = stats.iter().find(|stats| stats.player.id == user_id);
+        games.push(json!({
+            "room": room_id.0,
+            "stats": stats.map(|stats| json!({
+                "described": stats.described,
+                "guessed": stats.guessed,
+                "skipped": stats.skipped,
+                "fastest_guess_in_seconds": stats
+                    .fastest_guess
+                    .map(|fastest| fastest.as_secs_f32()),
+            })),
+        }));
+    }
+    games
+}
+
+pub async fn handle_my_data_command(bot: Bot, msg: Message, rooms: Rooms) -> HandlerResult {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    let data = json!({
+        "user_id": user.id.0,
+        "name": users::known(user.id).map(|known| known.name),
+        "unreachable": reachability::is_unreachable(user.id),
+        "timezone": timezone::setting(user.id),
+        "notifications": notifications::preferences_of(user.id),
+        "proposed_words": submissions::pending_of(user.id),
+        "rating": ratings::rating_of(user.id),
+        "duo": duos::duo_of(user.id),
+        "games": games(user.id, &rooms).await,
+    });
+
+    bot.send_document(
+        msg.chat.id,
+        InputFile::memory(serde_json::to_vec_pretty(&data)?).file_name("mydata.json"),
+    )
+    .caption("📦 Everything the bot keeps about you")
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,18 @@
+//! Rooms whose host opts in are announced in a channel anyone can join them
+//! from. The channel is configured with `JIGARPICH_PUBLIC_GAMES_CHANNEL`, the
+//! bot has to be one of its admins to post there.
+
+use std::{env, sync::OnceLock};
+
+use teloxide::types::ChatId;
+
+static CHANNEL: OnceLock<Option<ChatId>> = OnceLock::new();
+
+pub fn channel() -> Option<ChatId> {
+    *CHANNEL.get_or_init(|| {
+        env::var("JIGARPICH_PUBLIC_GAMES_CHANNEL")
+            .ok()
+            .and_then(|chat_id| chat_id.parse().ok())
+            .map(ChatId)
+    })
+}
@@ -0,0 +1,72 @@
+//! Ratings of single players, built up over the games they finish and used
+//! to balance the teams of a lobby by skill.
+
+use std::sync::OnceLock;
+
+use teloxide::types::UserId;
+
+use crate::storage::Store;
+
+/// The rating of a player or duo who didn't finish a game yet.
+pub const DEFAULT_RATING: f64 = 1000.0;
+
+/// How far a single game can move a rating.
+const RATING_STEP: f64 = 32.0;
+
+static RATINGS: OnceLock<Store<UserId, f64>> = OnceLock::new();
+
+fn ratings() -> &'static Store<UserId, f64> {
+    RATINGS.get_or_init(|| Store::open("ratings"))
+}
+
+pub fn rating_of(user_id: UserId) -> Option<f64> {
+    ratings().get(&user_id)
+}
+
+pub fn forget(user_id: UserId) {
+    if ratings().contains(&user_id) {
+        ratings().remove(&user_id);
+    }
+}
+
+/// How much the rating of each team moves after a game, given the position
+/// of the winner. Each team is rated against every other one, the winner
+/// beating all of them and the others drawing.
+pub fn changes(team_ratings: &[f64], winner: Option<usize>) -> Vec<f64> {
+    team_ratings
+        .iter()
+        .enumerate()
+        .map(|(team, rating)| {
+            team_ratings
+                .iter()
+                .enumerate()
+                .filter(|(opponent, _)| *opponent != team)
+                .map(|(opponent, opponent_rating)| {
+                    let expected = 1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / 400.0));
+                    let score = match winner {
+                        Some(winner) if winner == team => 1.0,
+                        Some(winner) if winner == opponent => 0.0,
+                        _ => 0.5,
+                    };
+                    RATING_STEP * (score - expected)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Moves the ratings of the players of a finished game, given by team with
+/// the position of the winner. A team is rated as the average of its players.
+pub fn record_game(pairs: &[(UserId, UserId)], winner: Option<usize>) {
+    let rating = |user_id| rating_of(user_id).unwrap_or(DEFAULT_RATING);
+    let team_ratings = pairs
+        .iter()
+        .map(|&(first, second)| (rating(first) + rating(second)) / 2.0)
+        .collect::<Vec<_>>();
+
+    for (&(first, second), change) in pairs.iter().zip(changes(&team_ratings, winner)) {
+        for player in [first, second] {
+            ratings().insert(player, rating(player) + change);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::{Mutex, OnceLock};
+
+use dashmap::DashMap;
+use teloxide::{prelude::*, types::ChatMemberUpdated};
+
+use crate::{storage::Store, users, HandlerResult};
+
+/// Messages to a user that fail in a row before they count as unreachable.
+const MAX_DELIVERY_FAILURES: u32 = 3;
+
+/// Users who blocked the bot or deleted their account.
+static UNREACHABLE: OnceLock<Store<UserId, ()>> = OnceLock::new();
+
+static FAILURES: OnceLock<DashMap<UserId, u32>> = OnceLock::new();
+
+/// Users marked unreachable whose rooms weren't told yet.
+static UNREPORTED: OnceLock<Mutex<Vec<UserId>>> = OnceLock::new();
+
+fn unreachable() -> &'static Store<UserId, ()> {
+    UNREACHABLE.get_or_init(|| Store::open("unreachable"))
+}
+
+fn failures() -> &'static DashMap<UserId, u32> {
+    FAILURES.get_or_init(DashMap::new)
+}
+
+fn unreported() -> &'static Mutex<Vec<UserId>> {
+    UNREPORTED.get_or_init(Mutex::default)
+}
+
+pub fn mark_unreachable(user_id: UserId) {
+    if !unreachable().contains(&user_id) {
+        log::info!("User {} can not be reached anymore", user_id);
+        unreachable().insert(user_id, ());
+        unreported().lock().unwrap().push(user_id);
+    }
+}
+
+/// Counts a message that couldn't be delivered to the user, who is marked
+/// unreachable once too many fail in a row.
+pub fn record_failure(user_id: UserId) {
+    let mut failures = failures().entry(user_id).or_default();
+    *failures += 1;
+    if *failures >= MAX_DELIVERY_FAILURES {
+        mark_unreachable(user_id);
+    }
+}
+
+/// The users who became unreachable since the last call.
+pub fn take_unreported() -> Vec<UserId> {
+    std::mem::take(&mut unreported().lock().unwrap())
+}
+
+/// Called whenever the user talks to the bot or gets a message from it, which
+/// proves they unblocked it.
+pub fn mark_reachable(user_id: UserId) {
+    failures().remove(&user_id);
+    if unreachable().contains(&user_id) {
+        unreachable().remove(&user_id);
+    }
+}
+
+pub fn is_unreachable(user_id: UserId) -> bool {
+    unreachable().contains(&user_id)
+}
+
+/// Drops everything kept about whether the user can be reached.
+pub fn forget(user_id: UserId) {
+    failures().remove(&user_id);
+    if unreachable().contains(&user_id) {
+        unreachable().remove(&user_id);
+    }
+}
+
+/// Telegram reports when a user blocks or unblocks the bot in their private
+/// chat, and when the bot is removed from a group.
+pub async fn handle_my_chat_member(update: ChatMemberUpdated) -> HandlerResult {
+    if !update.chat.is_private() {
+        if !update.new_chat_member.is_present() {
+            users::forget_chat(update.chat.id);
+        }
+        return Ok(());
+    }
+
+    if update.new_chat_member.is_banned() {
+        mark_unreachable(update.from.id);
+    } else {
+        mark_reachable(update.from.id);
+    }
+    Ok(())
+}
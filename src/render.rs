@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use qrcode::{Color, QrCode};
+
+const TEAM_COLORS: [[u8; 3]; 7] = [
+    [0x1f, 0x77, 0xd0],
+    [0xf5, 0xc5, 0x18],
+    [0xdc, 0x26, 0x26],
+    [0xf9, 0x73, 0x16],
+    [0x16, 0xa3, 0x4a],
+    [0x93, 0x33, 0xea],
+    [0x92, 0x40, 0x0e],
+];
+
+const WHITE: [u8; 3] = [0xff, 0xff, 0xff];
+const BLACK: [u8; 3] = [0x20, 0x20, 0x20];
+const GOLD: [u8; 3] = [0xff, 0xf3, 0xc4];
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+/// 3x5 bitmap glyphs, one row per byte using the lowest three bits.
+fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT]> {
+    let rows = match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        's' => [0b000, 0b011, 0b110, 0b011, 0b110],
+        _ => return None,
+    };
+    Some(rows)
+}
+
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize, background: [u8; 3]) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: background.repeat(width * height),
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: [u8; 3]) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                let offset = (row * self.width + column) * 3;
+                self.pixels[offset..offset + 3].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Draws text with the built-in bitmap font, unknown characters are left
+    /// blank. Returns the x coordinate right after the text.
+    fn draw_text(&mut self, x: usize, y: usize, text: &str, scale: usize, color: [u8; 3]) -> usize {
+        let mut cursor = x;
+        for c in text.chars() {
+            if let Some(rows) = glyph(c) {
+                for (row, bits) in rows.iter().enumerate() {
+                    for column in 0..GLYPH_WIDTH {
+                        if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                            self.fill_rect(
+                                cursor + column * scale,
+                                y + row * scale,
+                                scale,
+                                scale,
+                                color,
+                            );
+                        }
+                    }
+                }
+            }
+            cursor += (GLYPH_WIDTH + 1) * scale;
+        }
+        cursor
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut buffer = Vec::new();
+        let mut encoder = png::Encoder::new(&mut buffer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(buffer)
+    }
+}
+
+fn shade(color: [u8; 3], round: usize) -> [u8; 3] {
+    // Alternate between the team color and a lighter tint so rounds are
+    // distinguishable inside one bar
+    if round.is_multiple_of(2) {
+        color
+    } else {
+        color.map(|channel| channel + (0xff - channel) / 3)
+    }
+}
+
+/// Renders a bar per team, split into one segment per round, with the total
+/// time next to it. The `winner`, by its position in `teams`, is highlighted.
+pub fn scoreboard(
+    teams: &[(usize, Vec<Duration>)],
+    winner: Option<usize>,
+) -> Result<Vec<u8>, png::EncodingError> {
+    const WIDTH: usize = 800;
+    const MARGIN: usize = 20;
+    const ROW_HEIGHT: usize = 60;
+    const BAR_HEIGHT: usize = 40;
+    const BAR_START: usize = 80;
+    const BAR_END: usize = 600;
+    const TEXT_SCALE: usize = 4;
+
+    let totals = teams
+        .iter()
+        .map(|(_, rounds)| rounds.iter().sum::<Duration>())
+        .collect::<Vec<_>>();
+    let longest = totals.iter().max().copied().unwrap_or_default();
+
+    let mut canvas = Canvas::new(WIDTH, MARGIN * 2 + ROW_HEIGHT * teams.len(), WHITE);
+
+    for (i, ((team_index, rounds), total)) in teams.iter().zip(&totals).enumerate() {
+        let y = MARGIN + i * ROW_HEIGHT;
+        let bar_y = y + (ROW_HEIGHT - BAR_HEIGHT) / 2;
+        let color = TEAM_COLORS[*team_index % TEAM_COLORS.len()];
+
+        if Some(i) == winner {
+            canvas.fill_rect(0, y, WIDTH, ROW_HEIGHT, GOLD);
+        }
+
+        canvas.fill_rect(MARGIN, bar_y, BAR_HEIGHT, BAR_HEIGHT, color);
+
+        let mut x = BAR_START;
+        for (round, time) in rounds.iter().enumerate() {
+            let width = if longest.is_zero() {
+                0
+            } else {
+                (time.as_secs_f64() / longest.as_secs_f64() * (BAR_END - BAR_START) as f64) as usize
+            };
+            canvas.fill_rect(x, bar_y, width, BAR_HEIGHT, shade(color, round));
+            x += width;
+        }
+
+        let text_y = y + (ROW_HEIGHT - GLYPH_HEIGHT * TEXT_SCALE) / 2;
+        canvas.draw_text(
+            x + MARGIN,
+            text_y,
+            &format!("{:.2}s", total.as_secs_f32()),
+            TEXT_SCALE,
+            BLACK,
+        );
+    }
+
+    canvas.to_png()
+}
+
+/// Renders `data` as a black on white QR code, or `None` when it doesn't fit
+/// in one.
+pub fn qr_code(data: &str) -> Option<Vec<u8>> {
+    const MODULE_SIZE: usize = 8;
+    const QUIET_ZONE: usize = 4;
+
+    let code = QrCode::new(data).ok()?;
+    let size = (code.width() + QUIET_ZONE * 2) * MODULE_SIZE;
+    let mut canvas = Canvas::new(size, size, WHITE);
+
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color == Color::Dark {
+            canvas.fill_rect(
+                (i % code.width() + QUIET_ZONE) * MODULE_SIZE,
+                (i / code.width() + QUIET_ZONE) * MODULE_SIZE,
+                MODULE_SIZE,
+                MODULE_SIZE,
+                [0, 0, 0],
+            );
+        }
+    }
+
+    canvas.to_png().ok()
+}
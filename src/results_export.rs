@@ -0,0 +1,208 @@
+//! Keeps a record of finished games for communities tracking long-running
+//! standings, one row per game with its date, teams, players, scores and
+//! words.
+//!
+//! Rows are appended to a CSV file per month in `JIGARPICH_RESULTS_CSV_DIR`,
+//! and to the Google Sheet with the ID in `JIGARPICH_RESULTS_SHEET_ID`. The
+//! sheet is written by the service account whose JSON key file is at
+//! `JIGARPICH_GOOGLE_CREDENTIALS`, so it has to be shared with it.
+
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Utc};
+use openssl::{base64, hash::MessageDigest, pkey::PKey, sign::Signer};
+use tokio::sync::Mutex;
+
+use crate::room::GameSummary;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+const HEADER: [&str; 6] = ["date", "room", "teams", "players", "scores", "words"];
+
+const SHEETS_SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets";
+
+/// Access tokens are renewed this long before Google says they expire.
+const TOKEN_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(serde::Deserialize)]
+struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+#[derive(serde::Deserialize)]
+struct AccessToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+struct Sheet {
+    id: String,
+    account: ServiceAccount,
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+static CSV_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+static SHEET: OnceLock<Option<Sheet>> = OnceLock::new();
+
+fn csv_dir() -> Option<&'static Path> {
+    CSV_DIR
+        .get_or_init(|| {
+            env::var("JIGARPICH_RESULTS_CSV_DIR")
+                .ok()
+                .map(PathBuf::from)
+        })
+        .as_deref()
+}
+
+fn sheet() -> Option<&'static Sheet> {
+    SHEET
+        .get_or_init(|| {
+            let id = env::var("JIGARPICH_RESULTS_SHEET_ID").ok()?;
+            let path = env::var("JIGARPICH_GOOGLE_CREDENTIALS").expect(
+                "JIGARPICH_GOOGLE_CREDENTIALS must be set along with JIGARPICH_RESULTS_SHEET_ID",
+            );
+            let account = fs::read(&path)
+                .ok()
+                .and_then(|key| serde_json::from_slice(&key).ok())
+                .expect("JIGARPICH_GOOGLE_CREDENTIALS must be a service account key file");
+
+            Some(Sheet {
+                id,
+                account,
+                token: Mutex::new(None),
+            })
+        })
+        .as_ref()
+}
+
+/// Records a finished game in the configured CSV files and Google Sheet.
+pub fn export(summary: &GameSummary) {
+    if csv_dir().is_none() && sheet().is_none() {
+        return;
+    }
+
+    let date = DateTime::<Utc>::from(SystemTime::now());
+    let row = [date.format("%Y-%m-%d %H:%M").to_string()]
+        .into_iter()
+        .chain(summary.cells())
+        .collect::<Vec<_>>();
+
+    if let Some(dir) = csv_dir() {
+        let path = dir.join(format!("results-{}.csv", date.format("%Y-%m")));
+        if let Err(err) = append_to_csv(&path, &row) {
+            log::warn!("Can not export game results to {}: {}", path.display(), err);
+        }
+    }
+
+    if let Some(sheet) = sheet() {
+        tokio::spawn(async move {
+            if let Err(err) = append_to_sheet(sheet, row).await {
+                log::warn!("Can not export game results to the sheet: {}", err);
+            }
+        });
+    }
+}
+
+fn append_to_csv(path: &Path, row: &[String]) -> Result<(), Error> {
+    fs::create_dir_all(path.parent().unwrap_or(path))?;
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let is_new = file.metadata()?.len() == 0;
+
+    let mut writer = csv::Writer::from_writer(file);
+    if is_new {
+        writer.write_record(HEADER)?;
+    }
+    writer.write_record(row)?;
+    writer.flush()?;
+    Ok(())
+}
+
+async fn append_to_sheet(sheet: &Sheet, row: Vec<String>) -> Result<(), Error> {
+    let token = access_token(sheet).await?;
+    reqwest::Client::new()
+        .post(format!(
+            "https://sheets.googleapis.com/v4/spreadsheets/{}/values/A1:append",
+            sheet.id
+        ))
+        .query(&[
+            ("valueInputOption", "RAW"),
+            ("insertDataOption", "INSERT_ROWS"),
+        ])
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "values": [row] }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Returns a cached access token of the service account, or trades a newly
+/// signed assertion for one.
+async fn access_token(sheet: &Sheet) -> Result<String, Error> {
+    let mut token = sheet.token.lock().await;
+    if let Some((access_token, expires_at)) = token.as_ref() {
+        if Instant::now() < *expires_at {
+            return Ok(access_token.clone());
+        }
+    }
+
+    let assertion = sign_assertion(&sheet.account)?;
+    let response = reqwest::Client::new()
+        .post(&sheet.account.token_uri)
+        .form(&[
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            ("assertion", &assertion),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<AccessToken>()
+        .await?;
+
+    let lifetime = Duration::from_secs(response.expires_in).saturating_sub(TOKEN_MARGIN);
+    *token = Some((response.access_token.clone(), Instant::now() + lifetime));
+    Ok(response.access_token)
+}
+
+fn base64_url(bytes: &[u8]) -> String {
+    base64::encode_block(bytes)
+        .trim_end_matches('=')
+        .replace('+', "-")
+        .replace('/', "_")
+}
+
+/// The JSON Web Token the service account proves its identity with.
+fn sign_assertion(account: &ServiceAccount) -> Result<String, Error> {
+    let issued_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let header = serde_json::json!({ "alg": "RS256", "typ": "JWT" });
+    let claims = serde_json::json!({
+        "iss": account.client_email,
+        "scope": SHEETS_SCOPE,
+        "aud": account.token_uri,
+        "iat": issued_at,
+        "exp": issued_at + 3600,
+    });
+    let message = format!(
+        "{}.{}",
+        base64_url(header.to_string().as_bytes()),
+        base64_url(claims.to_string().as_bytes())
+    );
+
+    let key = PKey::private_key_from_pem(account.private_key.as_bytes())?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(message.as_bytes())?;
+    Ok(format!(
+        "{}.{}",
+        message,
+        base64_url(&signer.sign_to_vec()?)
+    ))
+}
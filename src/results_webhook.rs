@@ -0,0 +1,21 @@
+use std::{env, sync::OnceLock};
+
+use crate::room::GameSummary;
+
+static RESULTS_WEBHOOK_URL: OnceLock<Option<String>> = OnceLock::new();
+
+/// Posts the summary of a finished game to `JIGARPICH_RESULTS_WEBHOOK_URL`,
+/// if configured.
+pub async fn notify_game_finished(summary: GameSummary) {
+    let Some(url) =
+        RESULTS_WEBHOOK_URL.get_or_init(|| env::var("JIGARPICH_RESULTS_WEBHOOK_URL").ok())
+    else {
+        return;
+    };
+
+    let response = reqwest::Client::new().post(url).json(&summary).send().await;
+
+    if let Err(err) = response.and_then(|response| response.error_for_status()) {
+        log::warn!("Can not post game summary: {}", err);
+    }
+}
@@ -1,17 +1,87 @@
 use std::{
+    cmp::Reverse,
     collections::{BTreeSet, HashMap, HashSet},
+    env,
+    sync::OnceLock,
     time::{Duration, Instant},
 };
 
-use rand::{seq::SliceRandom, Rng};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 use teloxide::types::{ChatId, MessageId, User, UserId};
 
-use crate::words::{get_random_word, Word};
+use crate::{
+    awards::{self, PlayerStats, WordEvent},
+    ratings::{self, DEFAULT_RATING},
+    reachability, sharding,
+    words::{self, get_random_word, Complexity, ComplexityWeights, Word},
+};
 
 pub const SKIP_COOL_DOWN_IN_SECONDS: usize = 10;
 
+/// Added to the skip cool down of the leading team when the handicap is on.
+pub const HANDICAP_IN_SECONDS: usize = 10;
+
+/// Every this many words a team guesses in a row without skipping, the
+/// bonus comes off its time.
+pub const STREAK_LENGTH: usize = 3;
+
+pub const STREAK_BONUS_IN_SECONDS: u64 = 5;
+
+/// Seconds taken off a team's time for a guessed word of the complexity its
+/// describer picked.
+pub fn draw_bonus_in_seconds(complexity: Complexity) -> u64 {
+    match complexity {
+        Complexity::Easy => 0,
+        Complexity::Medium => 2,
+        Complexity::Hard => 5,
+    }
+}
+
+/// A generous estimate of how many words a team goes through in a minute.
+const WORDS_PER_MINUTE: usize = 12;
+
+/// How many cards each team may write for the others.
+pub const MAX_CUSTOM_CARDS_PER_TEAM: usize = 5;
+
+/// One in this many words is a custom card, while there are any left.
+const CUSTOM_CARD_ODDS: u32 = 3;
+
+/// Added to the time of a team whose word gets voided.
+pub const VOIDED_WORD_PENALTY_IN_SECONDS: u64 = 30;
+
+pub const MAX_NUMBER_OF_TEAMS: usize = 7;
+
+pub const MAX_NUMBER_OF_ROUNDS: usize = 7;
+
+const DEFAULT_MAX_PLAYERS_PER_ROOM: usize = 20;
+
+/// The per-word time limits a lobby cycles through, in seconds.
+const WORD_TIME_LIMITS: [Option<u64>; 4] = [None, Some(30), Some(60), Some(90)];
+
+/// How long a lobby stays open before auto-start may kick in.
+const AUTO_START_LOBBY_TIME: Duration = Duration::from_secs(2 * 60);
+
+static MAX_PLAYERS_PER_ROOM: OnceLock<usize> = OnceLock::new();
+
+/// How many players may join a single room, configured with
+/// `JIGARPICH_MAX_PLAYERS_PER_ROOM`.
+fn max_players_per_room() -> usize {
+    *MAX_PLAYERS_PER_ROOM.get_or_init(|| {
+        env::var("JIGARPICH_MAX_PLAYERS_PER_ROOM")
+            .ok()
+            .and_then(|max| max.parse().ok())
+            .unwrap_or(DEFAULT_MAX_PLAYERS_PER_ROOM)
+    })
+}
+
+/// A random room ID owned by this instance.
 pub fn get_new_id() -> RoomId {
-    RoomId(rand::thread_rng().gen_range(10_000..=99_999))
+    loop {
+        let room_id = RoomId(rand::thread_rng().gen_range(10_000..=99_999));
+        if sharding::owns_room(room_id) {
+            return room_id;
+        }
+    }
 }
 
 pub fn get_team_emoji(team_id: usize) -> String {
@@ -19,14 +89,147 @@ pub fn get_team_emoji(team_id: usize) -> String {
     format!("Team {}", EMOJI_LIST[team_id])
 }
 
+/// A player's name in the rosters, flagged when the bot can't reach them.
+fn roster_name(player: &User) -> String {
+    if reachability::is_unreachable(player.id) {
+        format!("{} ⚠️ unreachable", player.full_name())
+    } else {
+        player.full_name()
+    }
+}
+
+/// A seat of a pass-the-phone game, taken by someone who may not have
+/// Telegram. Its ID only tells the seats of the room apart, nothing is ever
+/// sent to it.
+fn local_player(team_index: usize, seat: usize) -> User {
+    let number = team_index * 2 + seat + 1;
+    User {
+        id: UserId(number as u64),
+        is_bot: false,
+        first_name: format!("Player {}", number),
+        last_name: None,
+        username: None,
+        language_code: None,
+        is_premium: false,
+        added_to_attachment_menu: false,
+    }
+}
+
 pub fn get_teams(number_of_teams: usize) -> Vec<String> {
     (0..number_of_teams).map(get_team_emoji).collect()
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, serde::Serialize, serde::Deserialize)]
 pub struct RoomId(pub u32);
 
-#[derive(Debug)]
+/// Every random choice of a room comes from here, so a game can be replayed
+/// from its seed. Each draw gets a generator of its own, which keeps the state
+/// small enough to be stored along with the room.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct RoomRng {
+    seed: u64,
+    draws: u64,
+}
+
+impl RoomRng {
+    /// Seeds from `JIGARPICH_SEED` when set, to replay a reported game.
+    fn new() -> Self {
+        let seed = env::var("JIGARPICH_SEED")
+            .ok()
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or_else(|| rand::thread_rng().gen());
+        RoomRng { seed, draws: 0 }
+    }
+
+    fn next(&mut self) -> StdRng {
+        let mut seed = [0; 32];
+        seed[..8].copy_from_slice(&self.seed.to_le_bytes());
+        seed[8..16].copy_from_slice(&self.draws.to_le_bytes());
+        self.draws += 1;
+        StdRng::from_seed(seed)
+    }
+}
+
+/// Who, besides the describing and guessing players, sees each word.
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub enum WordBroadcast {
+    #[default]
+    Everyone,
+    DescriberOnly,
+    OpposingTeams,
+}
+
+impl WordBroadcast {
+    fn next(self) -> Self {
+        match self {
+            WordBroadcast::Everyone => WordBroadcast::DescriberOnly,
+            WordBroadcast::DescriberOnly => WordBroadcast::OpposingTeams,
+            WordBroadcast::OpposingTeams => WordBroadcast::Everyone,
+        }
+    }
+}
+
+impl std::fmt::Display for WordBroadcast {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WordBroadcast::Everyone => write!(f, "everyone"),
+            WordBroadcast::DescriberOnly => write!(f, "only the describer"),
+            WordBroadcast::OpposingTeams => write!(f, "the describer and opposing teams"),
+        }
+    }
+}
+
+/// The word counts a first-to-N game can be played to.
+const VICTORY_TARGETS: [usize; 3] = [10, 20, 30];
+
+/// The word counts all teams of a co-op game can try to reach together.
+const COOP_TARGETS: [usize; 3] = [20, 40, 60];
+
+/// The breaks between rounds the host can choose, in minutes.
+const BREAK_MINUTES: [u64; 3] = [1, 2, 5];
+
+/// The margins within which close times count as a tie, in seconds.
+const TIE_MARGINS: [u64; 3] = [1, 2, 5];
+
+/// How the winning team is decided.
+#[derive(Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum VictoryCondition {
+    /// The lowest total time once all rounds are played.
+    #[default]
+    BestTime,
+    /// The most words guessed once all rounds are played.
+    MostWords,
+    /// The first team to guess this many words, right away. Otherwise the
+    /// most words once all rounds are played.
+    FirstTo(usize),
+}
+
+impl VictoryCondition {
+    fn next(self) -> Self {
+        match self {
+            VictoryCondition::BestTime => VictoryCondition::MostWords,
+            VictoryCondition::MostWords => VictoryCondition::FirstTo(VICTORY_TARGETS[0]),
+            VictoryCondition::FirstTo(target) => VICTORY_TARGETS
+                .iter()
+                .find(|next| **next > target)
+                .map_or(VictoryCondition::BestTime, |next| {
+                    VictoryCondition::FirstTo(*next)
+                }),
+        }
+    }
+}
+
+impl std::fmt::Display for VictoryCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VictoryCondition::BestTime => write!(f, "best time"),
+            VictoryCondition::MostWords => write!(f, "most words"),
+            VictoryCondition::FirstTo(target) => write!(f, "first to {} words", target),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
 pub enum GameLogicError {
     AlreadyJoined,
     NotJoinedToRoom,
@@ -35,44 +238,447 @@ pub enum GameLogicError {
     AlreadyPlaying,
     NotBalancedTeams,
     IsNotPlaying,
+    IsPaused,
+    IsNotPaused,
+    IsFinished,
+    RoundInProgress,
+    NoRoundInProgress,
+    SettingsLocked,
+    RoomFull,
+    AwaitingApproval,
+    NotHost,
+    NoPendingJoin,
+    NotDisputable,
+    AwaitingDraw,
+    NotAwaitingDraw,
+    NotInTeam,
+    TooManyCustomCards,
+    TeamsLocked,
+    NoGroupChat,
+    UnreachablePlayers,
+    WarmingUp,
+    NotWarmingUp,
+    OnBench,
+    BenchEmpty,
+    NotCaptain,
+    /// A relay round has no single team to confirm or skip a word.
+    RelayRound,
+    /// The word of a relay round was already claimed or skipped.
+    WordResolved,
+    /// Balancing needs exactly two players for every team.
+    WrongNumberOfPlayers,
+    /// The balanced teams shown to the host no longer match the lobby.
+    StaleTeamProposal,
+    /// The button belongs to a team the room doesn't have.
+    NoSuchTeam,
 }
 
-#[derive(Default)]
-pub struct NewRoom {
-    players: HashMap<UserId, User>,
-    number_of_teams: usize,
-    number_of_rounds: usize,
-    round_duration: usize,
-    use_taboo_words: bool,
-    teams: Vec<HashSet<UserId>>,
+/// How an error reaches the player whose action caused it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// A short notice on the button they pressed, for stale buttons.
+    Toast,
+    /// A private message, for things they need to act on.
+    Prompt,
+    /// Races between timers and players nobody needs to hear about.
+    Log,
 }
 
-impl NewRoom {
-    fn new(
+impl GameLogicError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            GameLogicError::AlreadyJoined => "You've already joined!",
+            GameLogicError::NotJoinedToRoom => "You're not in this room!",
+            GameLogicError::JoinAfterPlay => "Game has started. You can't join anymore!",
+            GameLogicError::TeamChangeAfterPlay => {
+                "Game has started. You can't change your team anymore!"
+            }
+            GameLogicError::AlreadyPlaying => "The game has already started!",
+            GameLogicError::NotBalancedTeams => "Teams are not balanced",
+            GameLogicError::IsNotPlaying => "The game hasn't started yet!",
+            GameLogicError::IsPaused => "The game is paused!",
+            GameLogicError::IsNotPaused => "The game isn't paused!",
+            GameLogicError::IsFinished => "The game is over! Send /results for the final standings",
+            GameLogicError::RoundInProgress => "Wait for the round to end!",
+            GameLogicError::NoRoundInProgress => "The round is over!",
+            GameLogicError::SettingsLocked => {
+                "Game has started. You can't change the settings anymore!"
+            }
+            GameLogicError::RoomFull => "This room is full!",
+            GameLogicError::AwaitingApproval => {
+                "The host needs to approve your request. Please wait!"
+            }
+            GameLogicError::NotHost => "Only the host can change this setting!",
+            GameLogicError::NoPendingJoin => "This request was already answered!",
+            GameLogicError::NotDisputable => "You can't dispute this word anymore",
+            GameLogicError::AwaitingDraw => "Pick the difficulty of the next word first!",
+            GameLogicError::NotAwaitingDraw => "The word was already drawn!",
+            GameLogicError::NotInTeam => "Join a team first!",
+            GameLogicError::TooManyCustomCards => "Your team can't write more cards!",
+            GameLogicError::TeamsLocked => {
+                "The host locked the teams. You can't change your team now!"
+            }
+            GameLogicError::NoGroupChat => "This only works in rooms created in a group!",
+            GameLogicError::UnreachablePlayers => {
+                "Some players need to start a private chat with the bot first"
+            }
+            GameLogicError::WarmingUp => "Finish the warm-up first!",
+            GameLogicError::NotWarmingUp => "The warm-up is over!",
+            GameLogicError::OnBench => "You're on the bench!",
+            GameLogicError::BenchEmpty => "Nobody is on the bench!",
+            GameLogicError::NotCaptain => "Only your team's captain can press ✅ and ⏩️!",
+            GameLogicError::RelayRound => "Claim the word with your team's button!",
+            GameLogicError::WordResolved => "This word is already gone!",
+            GameLogicError::WrongNumberOfPlayers => {
+                "Every team needs exactly two players to balance them!"
+            }
+            GameLogicError::StaleTeamProposal => "The players changed, balance the teams again!",
+            GameLogicError::NoSuchTeam => "This team doesn't exist!",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            GameLogicError::NotJoinedToRoom
+            | GameLogicError::IsNotPlaying
+            | GameLogicError::IsPaused
+            | GameLogicError::IsFinished
+            | GameLogicError::RoundInProgress
+            | GameLogicError::NoRoundInProgress
+            | GameLogicError::NoPendingJoin
+            | GameLogicError::NotDisputable
+            | GameLogicError::AwaitingDraw
+            | GameLogicError::NotAwaitingDraw
+            | GameLogicError::WarmingUp
+            | GameLogicError::NotWarmingUp
+            | GameLogicError::NotCaptain
+            | GameLogicError::RelayRound
+            | GameLogicError::WordResolved
+            | GameLogicError::StaleTeamProposal
+            | GameLogicError::NoSuchTeam => Severity::Toast,
+            GameLogicError::AlreadyJoined
+            | GameLogicError::JoinAfterPlay
+            | GameLogicError::TeamChangeAfterPlay
+            | GameLogicError::NotBalancedTeams
+            | GameLogicError::SettingsLocked
+            | GameLogicError::RoomFull
+            | GameLogicError::AwaitingApproval
+            | GameLogicError::NotHost
+            | GameLogicError::NotInTeam
+            | GameLogicError::TooManyCustomCards
+            | GameLogicError::TeamsLocked
+            | GameLogicError::NoGroupChat
+            | GameLogicError::UnreachablePlayers
+            | GameLogicError::OnBench
+            | GameLogicError::BenchEmpty
+            | GameLogicError::WrongNumberOfPlayers => Severity::Prompt,
+            GameLogicError::AlreadyPlaying | GameLogicError::IsNotPaused => Severity::Log,
+        }
+    }
+}
+
+/// A word a player wrote for the other teams.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct CustomCard {
+    author: UserId,
+    word: Word,
+}
+
+/// The settings of a room, picked while creating it and tweaked in the
+/// lobby. They are locked once the game starts.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct RoomConfig {
+    pub number_of_teams: usize,
+    pub number_of_rounds: usize,
+    pub round_duration_in_seconds: usize,
+    pub use_taboo_words: bool,
+    pub word_broadcast: WordBroadcast,
+    pub victory: VictoryCondition,
+    /// Seconds after which an unresolved word is skipped automatically.
+    pub word_time_limit: Option<u64>,
+    /// Whether the leading team waits longer to skip, to keep games close.
+    pub handicap: bool,
+    /// Whether time alerts go to every player instead of the active pair.
+    pub alert_everyone: bool,
+    /// Whether the last time alert and the round end come with a sound.
+    pub audio_alerts: bool,
+    /// Whether the group chat gets a countdown that ticks during each round.
+    pub animated_timer: bool,
+    /// The complexity of the words of each round, mixed where missing.
+    pub difficulty_plan: Vec<Option<Complexity>>,
+    /// Whether describers pick the complexity of each of their words.
+    pub describer_draws: bool,
+    /// The mix of complexities for words not fixed by the difficulty plan.
+    pub complexity_weights: ComplexityWeights,
+    /// Whether the room is announced in the public games channel.
+    pub public: bool,
+    /// Whether the table shares the host's phone, which gets every card
+    /// while the players pass it around.
+    pub pass_the_phone: bool,
+    /// Whether each pair gets an untimed word that doesn't count before the
+    /// first round.
+    pub warm_up: bool,
+    /// Whether only the captain of the playing team may press ✅ and ⏩️.
+    pub captains: bool,
+    /// Whether all teams play each round at once, racing for the same words.
+    pub relay: bool,
+    /// The words all teams try to guess together over the game, instead of
+    /// playing against each other.
+    pub coop_target: Option<usize>,
+    /// Minutes the table rests between rounds before the next describer
+    /// gets the ▶️ button.
+    pub break_minutes: Option<u64>,
+    /// Seconds within which teams count as tied on time, the one that
+    /// skipped fewer words winning the tie.
+    pub tie_margin_in_seconds: Option<u64>,
+    /// Whether the last round runs over until every team described as many
+    /// words, so the teams that went first have no edge.
+    pub balance_turns: bool,
+}
+
+impl RoomConfig {
+    /// The settings asked while creating a room, with the defaults for the
+    /// ones tweaked in the lobby.
+    pub fn new(
         number_of_teams: usize,
         number_of_rounds: usize,
-        round_duration: usize,
+        round_duration_in_seconds: usize,
         use_taboo_words: bool,
     ) -> Self {
-        NewRoom {
-            players: HashMap::new(),
-            teams: vec![HashSet::new(); number_of_teams],
+        RoomConfig {
             number_of_teams,
             number_of_rounds,
-            round_duration,
+            round_duration_in_seconds,
             use_taboo_words,
+            word_broadcast: WordBroadcast::default(),
+            victory: VictoryCondition::default(),
+            word_time_limit: None,
+            handicap: false,
+            alert_everyone: false,
+            audio_alerts: false,
+            animated_timer: false,
+            difficulty_plan: vec![None; number_of_rounds],
+            describer_draws: false,
+            complexity_weights: ComplexityWeights::default(),
+            public: false,
+            pass_the_phone: false,
+            warm_up: false,
+            captains: false,
+            relay: false,
+            coop_target: None,
+            break_minutes: None,
+            tie_margin_in_seconds: None,
+            balance_turns: false,
+        }
+    }
+
+    pub fn round_duration(&self) -> Duration {
+        Duration::from_secs(self.round_duration_in_seconds as u64)
+    }
+
+    /// The victory condition in effect. Teams of a relay don't take turns,
+    /// so their times don't compare and they play for words instead.
+    pub fn victory(&self) -> VictoryCondition {
+        match self.victory {
+            VictoryCondition::BestTime if self.relay => VictoryCondition::MostWords,
+            victory => victory,
+        }
+    }
+
+    /// How close times have to be to count as a tie.
+    fn tie_margin(&self) -> Duration {
+        Duration::from_secs(self.tie_margin_in_seconds.unwrap_or(0))
+    }
+
+    /// How many words a game with these settings may go through.
+    fn words_needed(&self) -> usize {
+        self.number_of_rounds
+            * words_per_round(self.number_of_teams, self.round_duration_in_seconds)
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct NewRoom {
+    players: HashMap<UserId, User>,
+    config: RoomConfig,
+    group_chat: Option<ChatId>,
+    /// The forum topic of the group chat the room was created in.
+    group_topic: Option<i32>,
+    custom_cards: Vec<CustomCard>,
+    /// Whether the host stopped players from changing teams.
+    teams_locked: bool,
+    teams: Vec<HashSet<UserId>>,
+    /// The captain of each team, its first member unless the host picked
+    /// another one.
+    captains: Vec<Option<UserId>>,
+    host: UserId,
+    max_players: usize,
+    requires_approval: bool,
+    pending_joins: HashMap<UserId, (User, ChatId)>,
+    lobby_cards: HashMap<ChatId, MessageId>,
+    /// The announcement of the room in the public games channel.
+    public_post: Option<(ChatId, MessageId)>,
+    rng: RoomRng,
+    #[serde(with = "crate::timestamp")]
+    created_at: Instant,
+    auto_start: bool,
+    // Countdowns don't survive a restart, the next team change arms a new one
+    #[serde(skip)]
+    auto_start_countdown: Option<u64>,
+    next_countdown: u64,
+    /// The teams balanced by skill last shown to the host.
+    team_proposal: Option<Vec<Vec<UserId>>>,
+    /// The names of the users the host invited.
+    invitations: HashMap<UserId, String>,
+}
+
+impl NewRoom {
+    fn new(config: RoomConfig, group_chat: Option<ChatId>, host: UserId) -> Self {
+        NewRoom {
+            players: HashMap::new(),
+            teams: vec![HashSet::new(); config.number_of_teams],
+            captains: vec![None; config.number_of_teams],
+            config,
+            group_chat,
+            group_topic: None,
+            custom_cards: Vec::new(),
+            teams_locked: false,
+            host,
+            max_players: max_players_per_room(),
+            requires_approval: false,
+            pending_joins: HashMap::new(),
+            lobby_cards: HashMap::new(),
+            public_post: None,
+            rng: RoomRng::new(),
+            created_at: Instant::now(),
+            auto_start: false,
+            auto_start_countdown: None,
+            next_countdown: 0,
+            team_proposal: None,
+            invitations: HashMap::new(),
         }
     }
 
-    fn join(&mut self, user: User) -> Result<(Vec<UserId>, usize), GameLogicError> {
+    fn join(&mut self, user: User) -> Result<Vec<UserId>, GameLogicError> {
+        if self.players.len() >= self.max_players {
+            return Err(GameLogicError::RoomFull);
+        }
+
         if let std::collections::hash_map::Entry::Vacant(e) = self.players.entry(user.id) {
             e.insert(user);
-            Ok((self.players.keys().cloned().collect(), self.number_of_teams))
+            Ok(self.players.keys().cloned().collect())
         } else {
             Err(GameLogicError::AlreadyJoined)
         }
     }
 
+    /// Joins directly when the host doesn't need to approve the user,
+    /// otherwise queues the request until the host answers it.
+    fn request_join(&mut self, user: User, chat_id: ChatId) -> Result<Vec<UserId>, GameLogicError> {
+        if self.players.contains_key(&user.id) {
+            return Err(GameLogicError::AlreadyJoined);
+        }
+
+        if self.requires_approval && user.id != self.host {
+            if self.players.len() >= self.max_players {
+                return Err(GameLogicError::RoomFull);
+            }
+            self.pending_joins.insert(user.id, (user, chat_id));
+            return Err(GameLogicError::AwaitingApproval);
+        }
+
+        self.join(user)
+    }
+
+    fn take_pending_join(
+        &mut self,
+        host: UserId,
+        user_id: UserId,
+    ) -> Result<(User, ChatId), GameLogicError> {
+        if host != self.host {
+            return Err(GameLogicError::NotHost);
+        }
+
+        self.pending_joins
+            .remove(&user_id)
+            .ok_or(GameLogicError::NoPendingJoin)
+    }
+
+    /// Splits the joined players into teams whose ratings add up as evenly
+    /// as possible, for the host to look at. Starting from a random split,
+    /// players are swapped between teams while that narrows the spread, so
+    /// asking again may give other teams that are as even.
+    fn propose_teams(&mut self, user_id: UserId) -> Result<Vec<Vec<User>>, GameLogicError> {
+        if self.host != user_id {
+            return Err(GameLogicError::NotHost);
+        }
+        if self.players.len() != 2 * self.teams.len() {
+            return Err(GameLogicError::WrongNumberOfPlayers);
+        }
+
+        let mut players = self.players.keys().copied().collect::<Vec<_>>();
+        players.sort_by_key(|player| player.0);
+        players.shuffle(&mut self.rng.next());
+        let mut teams = players
+            .chunks(2)
+            .map(<[UserId]>::to_vec)
+            .collect::<Vec<_>>();
+
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for first in 0..teams.len() {
+                for second in first + 1..teams.len() {
+                    for (i, j) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                        let spread = rating_spread(&teams);
+                        swap_players(&mut teams, (first, i), (second, j));
+                        if rating_spread(&teams) < spread - f64::EPSILON {
+                            improved = true;
+                        } else {
+                            swap_players(&mut teams, (first, i), (second, j));
+                        }
+                    }
+                }
+            }
+        }
+
+        self.team_proposal = Some(teams.clone());
+        Ok(teams
+            .iter()
+            .map(|team| {
+                team.iter()
+                    .map(|player| self.players[player].clone())
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Seats the players in the teams last proposed to the host.
+    fn accept_team_proposal(&mut self, user_id: UserId) -> Result<(), GameLogicError> {
+        if self.host != user_id {
+            return Err(GameLogicError::NotHost);
+        }
+        let proposal = self
+            .team_proposal
+            .take()
+            .ok_or(GameLogicError::StaleTeamProposal)?;
+        let proposed = proposal.iter().flatten().collect::<HashSet<_>>();
+        if proposal.len() != self.teams.len()
+            || proposed.len() != self.players.len()
+            || !self.players.keys().all(|player| proposed.contains(player))
+        {
+            return Err(GameLogicError::StaleTeamProposal);
+        }
+
+        self.teams = proposal
+            .iter()
+            .map(|team| team.iter().copied().collect())
+            .collect();
+        self.captains = proposal.iter().map(|team| team.first().copied()).collect();
+        self.auto_start_countdown = None;
+        Ok(())
+    }
+
     fn join_team(
         &mut self,
         user_id: UserId,
@@ -80,17 +686,53 @@ impl NewRoom {
     ) -> Result<Vec<UserId>, GameLogicError> {
         if !self.players.contains_key(&user_id) {
             Err(GameLogicError::NotJoinedToRoom)
+        } else if team_index >= self.teams.len() {
+            Err(GameLogicError::NoSuchTeam)
+        } else if self.teams_locked {
+            Err(GameLogicError::TeamsLocked)
         } else {
             self.teams.iter_mut().for_each(|team| {
                 team.remove(&user_id);
             });
+            self.auto_start_countdown = None;
+            // A leaving captain hands over to whoever stays in the team
+            for (team, captain) in self.teams.iter().zip(&mut self.captains) {
+                if *captain == Some(user_id) {
+                    *captain = team.iter().next().copied();
+                }
+            }
 
             self.teams[team_index].insert(user_id);
+            self.captains[team_index].get_or_insert(user_id);
 
-            Ok(self.players.keys().cloned().collect())
+            Ok(self
+                .players
+                .keys()
+                .filter(|player| **player != user_id)
+                .cloned()
+                .collect())
         }
     }
 
+    fn add_custom_card(&mut self, author: UserId, word: Word) -> Result<usize, GameLogicError> {
+        let team = self
+            .teams
+            .iter()
+            .find(|team| team.contains(&author))
+            .ok_or(GameLogicError::NotInTeam)?;
+        let written = self
+            .custom_cards
+            .iter()
+            .filter(|card| team.contains(&card.author))
+            .count();
+        if written >= MAX_CUSTOM_CARDS_PER_TEAM {
+            return Err(GameLogicError::TooManyCustomCards);
+        }
+
+        self.custom_cards.push(CustomCard { author, word });
+        Ok(written + 1)
+    }
+
     fn get_teams(&self) -> String {
         self.teams
             .iter()
@@ -100,7 +742,7 @@ impl NewRoom {
 
                 res += &members.iter().fold("".to_owned(), |mut res, member| {
                     if let Some(player) = self.players.get(member) {
-                        res += &format!("\t- {}\n", player.full_name());
+                        res += &format!("\t- {}\n", roster_name(player));
                     }
 
                     res
@@ -110,7 +752,74 @@ impl NewRoom {
             })
     }
 
+    /// The roster shown on the lobby cards, a team is marked ready once it
+    /// has both of its players.
+    fn get_status(&self) -> String {
+        let mut status = self.teams.iter().enumerate().fold(
+            "Choose your team\n\n".to_owned(),
+            |mut res, (i, members)| {
+                res += &format!(
+                    "{} {}\n",
+                    get_team_emoji(i),
+                    if members.len() == 2 { "✅" } else { "⏳" }
+                );
+
+                res += &members.iter().fold("".to_owned(), |mut res, member| {
+                    if let Some(player) = self.players.get(member) {
+                        let captain = self.config.captains && self.captains[i] == Some(*member);
+                        res += &format!(
+                            "\t- {}{}\n",
+                            roster_name(player),
+                            if captain { " 👑" } else { "" }
+                        );
+                    }
+
+                    res
+                });
+
+                res
+            },
+        );
+
+        let waiting = self
+            .players
+            .iter()
+            .filter(|(id, _)| !self.teams.iter().any(|members| members.contains(id)))
+            .map(|(_, player)| format!("\t- {}\n", roster_name(player)))
+            .collect::<String>();
+        if !waiting.is_empty() {
+            status += &format!("\nWithout a team:\n{}", waiting);
+        }
+        let mut invited = self
+            .invitations
+            .iter()
+            .filter(|(id, _)| !self.players.contains_key(id))
+            .map(|(_, name)| name.as_str())
+            .collect::<Vec<_>>();
+        if !invited.is_empty() {
+            invited.sort();
+            status += &format!("\n📨 Invited: {}\n", invited.join(", "));
+        }
+        if self.teams_locked {
+            status += "\n🔒 The host locked the teams";
+        }
+        if self.config.pass_the_phone {
+            status += "\n📱 The host's phone deals every card, the teams get numbered players";
+        }
+
+        status
+    }
+
+    fn play(self) -> PlayingRoom {
+        PlayingRoom::from(self)
+    }
+
     fn check_teams_ready(&self) -> Result<(), GameLogicError> {
+        // The seats are filled with local players when the game starts
+        if self.config.pass_the_phone {
+            return Ok(());
+        }
+
         if self
             .teams
             .iter()
@@ -125,16 +834,46 @@ impl NewRoom {
             return Err(GameLogicError::NotBalancedTeams);
         }
 
+        // Group games deal the cards in private chats
+        if self.group_chat.is_some()
+            && self
+                .teams
+                .iter()
+                .flatten()
+                .any(|member| reachability::is_unreachable(*member))
+        {
+            return Err(GameLogicError::UnreachablePlayers);
+        }
+
         Ok(())
     }
 }
 
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 struct PlayingTeam {
     first: User,
     second: User,
     time: Duration,
+    words: usize,
+    round_times: Vec<Duration>,
     turn: u8,
+    /// The player who described the first word of the game.
+    opener: u8,
+    /// Words each player described so far, over all rounds. The partner
+    /// guessed every one of them.
+    described: [usize; 2],
+    /// Words guessed in a row without skipping.
+    streak: usize,
+    /// Taken off `time` for the streaks, which stays the time spent
+    /// describing.
+    bonus: Duration,
+    /// Words skipped in the current or the last round.
+    round_skips: usize,
+    /// Words skipped in each round played so far.
+    skips: Vec<usize>,
+    index: usize,
     name: String,
+    captain: UserId,
 }
 
 impl PlayingTeam {
@@ -153,6 +892,85 @@ impl PlayingTeam {
         }
     }
 
+    /// The time the team is ranked by.
+    fn score(&self) -> Duration {
+        self.time.saturating_sub(self.bonus)
+    }
+
+    /// Orders the teams from the winner down, ties on words broken by time.
+    fn rank(&self, victory: VictoryCondition) -> (Reverse<usize>, Duration) {
+        match victory {
+            VictoryCondition::BestTime => (Reverse(0), self.score()),
+            VictoryCondition::MostWords | VictoryCondition::FirstTo(_) => {
+                (Reverse(self.words), self.score())
+            }
+        }
+    }
+
+    /// Counts a guessed word towards the streak and returns the streak's
+    /// length if it earned a bonus.
+    fn extend_streak(&mut self) -> Option<usize> {
+        self.streak += 1;
+        if !self.streak.is_multiple_of(STREAK_LENGTH) {
+            return None;
+        }
+        self.bonus += Duration::from_secs(STREAK_BONUS_IN_SECONDS);
+        Some(self.streak)
+    }
+
+    /// Words the team skipped in the rounds played so far.
+    fn total_skips(&self) -> usize {
+        self.skips.iter().sum()
+    }
+
+    /// Turns the team had so far.
+    fn turns(&self) -> usize {
+        self.described.iter().sum()
+    }
+
+    /// Describes a line for the results if one player described more than
+    /// one word more than the other, which happens when a game ends early.
+    fn role_note(&self) -> Option<String> {
+        let [first, second] = self.described;
+        if first.abs_diff(second) <= 1 {
+            return None;
+        }
+        Some(format!(
+            "{} described {} and guessed {} words, {} described {} and guessed {}",
+            self.first.full_name(),
+            first,
+            second,
+            self.second.full_name(),
+            second,
+            first
+        ))
+    }
+
+    /// The team for a rematch, with whoever described less this game
+    /// describing first, or whoever guessed first if the roles are swapped.
+    fn rematch(&self, swap_roles: bool) -> PlayingTeam {
+        let turn = if swap_roles {
+            1 - self.opener
+        } else if self.described[1] < self.described[0] {
+            1
+        } else {
+            0
+        };
+        PlayingTeam {
+            time: Duration::from_secs(0),
+            words: 0,
+            round_times: Vec::new(),
+            turn,
+            opener: turn,
+            described: [0, 0],
+            streak: 0,
+            bonus: Duration::from_secs(0),
+            round_skips: 0,
+            skips: Vec::new(),
+            ..self.clone()
+        }
+    }
+
     fn advance_turn(&mut self) {
         if self.turn == 0 {
             self.turn = 1;
@@ -164,47 +982,208 @@ impl PlayingTeam {
     fn update_time(&mut self, instant: Instant) {
         self.time += Instant::now() - instant;
     }
+
+    fn close_round(&mut self) {
+        let previous_rounds: Duration = self.round_times.iter().sum();
+        self.round_times.push(self.time - previous_rounds);
+        self.skips.push(self.round_skips);
+    }
 }
 
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct PlayingRoom {
     teams: Vec<PlayingTeam>,
     turn: u8,
     round: u8,
+    #[serde(with = "crate::timestamp")]
     instant: Instant,
+    #[serde(with = "crate::timestamp")]
+    started_at: Instant,
+    #[serde(with = "crate::timestamp")]
+    round_started_at: Instant,
+    #[serde(with = "crate::timestamp")]
+    round_ended_at: Instant,
+    /// When the describer got the ▶️ button, and how often they were
+    /// reminded of it since.
+    #[serde(with = "crate::timestamp")]
+    start_offered_at: Instant,
+    idle_reminders: usize,
+    round_in_progress: bool,
     message_stack: Vec<(ChatId, MessageId)>,
-    number_of_rounds: usize,
-    round_duration: usize,
-    use_taboo_words: bool,
+    config: RoomConfig,
+    group_chat: Option<ChatId>,
+    group_topic: Option<i32>,
+    /// The team that led when the last round ended, if the handicap is on.
+    handicapped_team: Option<usize>,
+    /// Cards written by players that weren't dealt yet.
+    custom_cards: Vec<CustomCard>,
+    /// The describer is yet to pick the complexity of the next word.
+    awaiting_draw: bool,
+    current_complexity: Option<Complexity>,
+    pinned_scoreboard: Option<PinnedMessage>,
+    rng: RoomRng,
+    host: UserId,
+    current_word: Option<String>,
+    /// The card in play, to show again to a describer who lost it.
+    dealt_word: Option<Word>,
+    /// The words dealt for each round that weren't drawn yet.
+    deck: Vec<Vec<Word>>,
+    /// The words guessed in the current or the last round.
+    round_words: Vec<PlayedWord>,
+    /// Votes to void a word, keyed by its index in `round_words`.
+    disputes: HashMap<usize, HashSet<UserId>>,
+    /// The pairs, by their index in `teams`, yet to finish their warm-up
+    /// word. `None` once the host started the real game.
+    warm_up: Option<HashSet<usize>>,
+    /// Players who joined after the game started, in the order they came.
+    /// They spectate until the host swaps them in.
+    bench: Vec<User>,
+    /// Every word played so far, for the awards.
+    events: Vec<WordEvent>,
+    /// The word of the relay round in progress.
+    relay: Option<RelayWord>,
+    /// Relay words dealt so far, to tell presses on old words apart.
+    relay_words: usize,
+    #[serde(with = "crate::timestamp")]
+    word_dealt_at: Instant,
+    /// The last round ran out of time and goes on until the teams had as
+    /// many turns.
+    balancing: bool,
+    /// When the team describing got its turn, for the deadline of each
+    /// balancing turn now that no timer stops the round.
+    #[serde(with = "crate::timestamp")]
+    turn_started_at: Instant,
+}
+
+/// The word every describer of a relay round has at once.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct RelayWord {
+    index: usize,
+    /// The cards showing the word, which lose their buttons once it's
+    /// claimed or skipped.
+    cards: Vec<(ChatId, MessageId)>,
+    /// The teams whose describer wants to skip the word.
+    skips: HashSet<usize>,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct PlayedWord {
+    team: usize,
+    text: String,
+    voided: bool,
+}
+
+fn words_per_round(number_of_teams: usize, round_duration_in_seconds: usize) -> usize {
+    (number_of_teams * round_duration_in_seconds * WORDS_PER_MINUTE).div_ceil(60)
+}
+
+/// Deals the words of the whole game up-front, one pile per round following
+/// the difficulty plan, so no word comes up twice.
+fn deal_deck(rng: &mut RoomRng, number_of_teams: usize, config: &RoomConfig) -> Vec<Vec<Word>> {
+    let round_size = words_per_round(number_of_teams, config.round_duration_in_seconds);
+    let complexities = (0..config.number_of_rounds)
+        .flat_map(|round| {
+            let complexity = config.difficulty_plan.get(round).copied().flatten();
+            std::iter::repeat_n(complexity, round_size)
+        })
+        .collect::<Vec<_>>();
+
+    let mut words =
+        words::deal(&mut rng.next(), &complexities, config.complexity_weights).into_iter();
+    (0..config.number_of_rounds)
+        .map(|_| words.by_ref().take(round_size).collect())
+        .collect()
+}
+
+/// A message kept up to date in the group chat instead of sending new ones.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct PinnedMessage {
+    pub chat_id: ChatId,
+    pub message_id: MessageId,
+    pub text: String,
 }
 
 impl PlayingRoom {
-    fn from(lobby: NewRoom) -> PlayingRoom {
-        let mut rng = rand::thread_rng();
+    fn from(mut lobby: NewRoom) -> PlayingRoom {
+        let mut rng = lobby.rng.next();
         let mut teams = lobby
             .teams
             .into_iter()
             .enumerate()
             .map(|(team_id, team)| {
-                let team: Vec<_> = team.into_iter().collect();
+                // Sorted first, the hash set's order isn't replayable
+                let mut team: Vec<_> = team.into_iter().collect();
+                team.sort_by_key(|player| player.0);
+                team.shuffle(&mut rng);
+                let (first, second) = if lobby.config.pass_the_phone {
+                    (local_player(team_id, 0), local_player(team_id, 1))
+                } else {
+                    (
+                        lobby.players.get(team.first().unwrap()).unwrap().to_owned(),
+                        lobby.players.get(team.get(1).unwrap()).unwrap().to_owned(),
+                    )
+                };
+                let captain = match lobby.captains[team_id] {
+                    Some(captain) if !lobby.config.pass_the_phone => captain,
+                    _ => first.id,
+                };
                 PlayingTeam {
-                    first: lobby.players.get(team.first().unwrap()).unwrap().to_owned(),
-                    second: lobby.players.get(team.get(1).unwrap()).unwrap().to_owned(),
+                    first,
+                    second,
+                    captain,
                     time: Duration::from_secs(0),
+                    words: 0,
+                    round_times: Vec::new(),
                     turn: 0,
+                    opener: 0,
+                    described: [0, 0],
+                    streak: 0,
+                    bonus: Duration::from_secs(0),
+                    round_skips: 0,
+                    skips: Vec::new(),
+                    index: team_id,
                     name: get_team_emoji(team_id),
                 }
             })
             .collect::<Vec<_>>();
         teams.shuffle(&mut rng);
+        let deck = deal_deck(&mut lobby.rng, teams.len(), &lobby.config);
+        let warm_up = lobby.config.warm_up.then(|| (0..teams.len()).collect());
         PlayingRoom {
             teams,
             turn: 0,
             round: 0,
             instant: Instant::now(),
+            started_at: Instant::now(),
+            round_started_at: Instant::now(),
+            round_ended_at: Instant::now(),
+            start_offered_at: Instant::now(),
+            idle_reminders: 0,
+            round_in_progress: false,
             message_stack: Vec::new(),
-            number_of_rounds: lobby.number_of_rounds,
-            round_duration: lobby.round_duration,
-            use_taboo_words: lobby.use_taboo_words,
+            config: lobby.config,
+            group_chat: lobby.group_chat,
+            group_topic: lobby.group_topic,
+            handicapped_team: None,
+            custom_cards: lobby.custom_cards,
+            awaiting_draw: false,
+            current_complexity: None,
+            pinned_scoreboard: None,
+            rng: lobby.rng,
+            host: lobby.host,
+            current_word: None,
+            dealt_word: None,
+            deck,
+            round_words: Vec::new(),
+            disputes: HashMap::new(),
+            warm_up,
+            bench: Vec::new(),
+            events: Vec::new(),
+            relay: None,
+            relay_words: 0,
+            word_dealt_at: Instant::now(),
+            balancing: false,
+            turn_started_at: Instant::now(),
         }
     }
 
@@ -212,226 +1191,3078 @@ impl PlayingRoom {
         self.teams[self.turn as usize].get_describing_player()
     }
 
-    fn get_guessing_player(&self) -> User {
-        self.teams[self.turn as usize].get_guessing_player()
+    fn leader(&self) -> Option<usize> {
+        winner(&self.teams, &self.config)
     }
 
-    fn next(&mut self) {
-        self.update_time();
-        self.teams[self.turn as usize].advance_turn();
-        self.turn += 1;
-        self.turn %= self.teams.len() as u8;
+    /// Whether a team got to the words of a first-to-N game.
+    fn target_reached(&self) -> bool {
+        if let Some(target) = self.config.coop_target {
+            return self.group_words() >= target;
+        }
+        match self.config.victory {
+            VictoryCondition::FirstTo(target) => self.teams.iter().any(|team| team.words >= target),
+            VictoryCondition::BestTime | VictoryCondition::MostWords => false,
+        }
     }
 
-    fn update_time(&mut self) {
-        self.teams[self.turn as usize].update_time(self.instant);
+    /// Who won with the last word of `team`, everyone together in co-op.
+    fn target_winner(&self, team: String) -> Option<String> {
+        if !self.target_reached() {
+            return None;
+        }
+        Some(match self.config.coop_target {
+            Some(_) => "🤝 Everyone".to_owned(),
+            None => team,
+        })
     }
 
-    fn get_teams(&self) -> String {
-        let Some((min_index, _)) = self
-            .teams
-            .iter()
-            .enumerate()
-            .min_by_key(|(_, team)| team.time)
-        else {
-            return "".to_owned();
-        };
+    /// The words all teams guessed, the score of a co-op game.
+    fn group_words(&self) -> usize {
+        self.teams.iter().map(|team| team.words).sum()
+    }
 
-        self.teams
+    /// The line of the standings with the progress of a co-op game.
+    fn coop_text(&self) -> String {
+        match self.config.coop_target {
+            Some(target) => format!("🤝 Together: {}/{} words\n", self.group_words(), target),
+            None => String::new(),
+        }
+    }
+
+    /// Draws the next word, unless the describer picks its complexity first.
+    fn deal_word(&mut self) -> Option<Word> {
+        if self.config.describer_draws {
+            self.awaiting_draw = true;
+            return None;
+        }
+
+        let complexity = self
+            .config
+            .difficulty_plan
+            .get(self.round as usize)
+            .copied()
+            .flatten();
+        Some(self.draw_word(complexity))
+    }
+
+    /// Takes one of the custom cards the describing team didn't write, every
+    /// `CUSTOM_CARD_ODDS` words on average.
+    fn take_custom_card(&mut self, rng: &mut impl Rng) -> Option<Word> {
+        // Relay words go to every team, the authors' included
+        if self.config.relay {
+            return None;
+        }
+        let team = &self.teams[self.turn as usize];
+        let dealable = self
+            .custom_cards
             .iter()
             .enumerate()
-            .fold("".to_owned(), |mut res, (i, team)| {
+            .filter(|(_, card)| card.author != team.first.id && card.author != team.second.id)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        if dealable.is_empty() || !rng.gen_ratio(1, CUSTOM_CARD_ODDS) {
+            return None;
+        }
+
+        let index = *dealable.choose(rng)?;
+        let card = self.custom_cards.swap_remove(index);
+        Some(Word::select_taboo_words(&card.word, rng))
+    }
+
+    /// Takes the next word of the round's pile, the last one of `complexity`
+    /// if given.
+    fn take_from_deck(&mut self, complexity: Option<Complexity>) -> Option<Word> {
+        let pile = self.deck.get_mut(self.round as usize)?;
+        let index = match complexity {
+            Some(complexity) => pile
+                .iter()
+                .rposition(|word| word.complexity() == complexity)?,
+            None => pile.len().checked_sub(1)?,
+        };
+        Some(pile.remove(index))
+    }
+
+    /// Draws a word and remembers it, so it can be disputed later. Custom
+    /// cards only mix in when the complexity is left to chance. Draws from the
+    /// whole bank once the round's pile runs out.
+    fn draw_word(&mut self, complexity: Option<Complexity>) -> Word {
+        let mut rng = self.rng.next();
+        let planned = self
+            .config
+            .difficulty_plan
+            .get(self.round as usize)
+            .copied()
+            .flatten();
+        let custom_card = match complexity {
+            None => self.take_custom_card(&mut rng),
+            Some(_) => None,
+        };
+        let word = custom_card
+            .or_else(|| {
+                // The pile already follows the plan, any word of it will do
+                let wanted = complexity.filter(|_| complexity != planned);
+                self.take_from_deck(wanted)
+            })
+            .unwrap_or_else(|| {
+                get_random_word(&mut rng, complexity, self.config.complexity_weights)
+            });
+        self.current_word = Some(word.text.clone());
+        self.dealt_word = Some(word.clone());
+        self.word_dealt_at = Instant::now();
+        self.current_complexity = complexity;
+        self.awaiting_draw = false;
+        word
+    }
+
+    fn team_of(&self, user_id: UserId) -> Option<usize> {
+        self.teams
+            .iter()
+            .position(|team| team.first.id == user_id || team.second.id == user_id)
+    }
+
+    /// Takes the word back from its team, which gets a penalty instead.
+    fn void_word(&mut self, index: usize) {
+        let word = &mut self.round_words[index];
+        word.voided = true;
+
+        let penalty = Duration::from_secs(VOIDED_WORD_PENALTY_IN_SECONDS);
+        let team = &mut self.teams[word.team];
+        team.words = team.words.saturating_sub(1);
+        team.time += penalty;
+        if let Some(round_time) = team.round_times.last_mut() {
+            *round_time += penalty;
+        }
+        self.disputes.remove(&index);
+    }
+
+    fn get_guessing_player(&self) -> User {
+        self.teams[self.turn as usize].get_guessing_player()
+    }
+
+    fn next(&mut self) {
+        self.update_time();
+        let team = &mut self.teams[self.turn as usize];
+        team.described[team.turn as usize] += 1;
+        team.advance_turn();
+        self.turn += 1;
+        self.turn %= self.teams.len() as u8;
+        self.turn_started_at = Instant::now();
+    }
+
+    /// Hands the first turn of the next round to the team that had the fewest
+    /// turns so far, so rounds ending mid-rotation don't favour anyone. Ties
+    /// go to whoever was up next anyway.
+    fn rotate_for_next_round(&mut self) {
+        let number_of_teams = self.teams.len();
+        self.turn = (0..number_of_teams)
+            .map(|offset| (self.turn as usize + offset) % number_of_teams)
+            .min_by_key(|&team| self.teams[team].turns())
+            .unwrap_or(0) as u8;
+    }
+
+    /// Where the game stands for the word being dealt to the playing team.
+    fn turn_context(&self) -> TurnContext {
+        let team = &self.teams[self.turn as usize];
+        let round_skips: usize = self.teams.iter().map(|team| team.round_skips).sum();
+        TurnContext {
+            round: self.round as usize + 1,
+            rounds: self.config.number_of_rounds,
+            turn: self.round_words.len() + round_skips + 1,
+            team: team.name.clone(),
+            words: self
+                .round_words
+                .iter()
+                .filter(|word| word.team == self.turn as usize && !word.voided)
+                .count(),
+            skips: team.round_skips,
+            words_left: self.deck.get(self.round as usize).map(Vec::len),
+        }
+    }
+
+    /// Whether every team had as many turns.
+    fn turns_even(&self) -> bool {
+        self.teams
+            .iter()
+            .all(|team| team.turns() == self.teams[0].turns())
+    }
+
+    /// The team names in the order they describe, starting with the current
+    /// one.
+    fn turn_order(&self) -> Vec<String> {
+        let number_of_teams = self.teams.len();
+        (0..number_of_teams)
+            .map(|offset| {
+                self.teams[(self.turn as usize + offset) % number_of_teams]
+                    .name
+                    .clone()
+            })
+            .collect()
+    }
+
+    /// Charges the time since `instant` to the playing team. Relay teams are
+    /// charged per word they claim instead.
+    fn update_time(&mut self) {
+        if self.config.relay {
+            return;
+        }
+        self.teams[self.turn as usize].update_time(self.instant);
+    }
+
+    fn get_teams(&self) -> String {
+        let Some(min_index) = self.leader() else {
+            return "".to_owned();
+        };
+
+        self.teams
+            .iter()
+            .enumerate()
+            .fold("".to_owned(), |mut res, (i, team)| {
                 res += &format!(
-                    "{}{}:\n\t- {}\n\t- {}\n\t⏱️ {:.2}s\n\n",
-                    if i == min_index { "🏆 " } else { "" },
+                    "{}{}:\n\t- {}\n\t- {}\n\t⏱️ {:.2}s\n{}{}\n",
+                    if i == min_index && self.config.coop_target.is_none() {
+                        "🏆 "
+                    } else {
+                        ""
+                    },
                     team.name,
-                    team.first.full_name(),
-                    team.second.full_name(),
-                    team.time.as_secs_f32()
+                    roster_name(&team.first),
+                    roster_name(&team.second),
+                    team.score().as_secs_f32(),
+                    if self.config.victory() == VictoryCondition::BestTime {
+                        String::new()
+                    } else {
+                        format!("\t✅ {} words\n", team.words)
+                    },
+                    skips_line(team)
                 );
                 res
             })
+            + &self.coop_text()
+            + &self.bench_text()
+    }
+
+    fn bench_text(&self) -> String {
+        if self.bench.is_empty() {
+            return String::new();
+        }
+        let names = self.bench.iter().map(roster_name).collect::<Vec<_>>();
+        format!("🪑 Bench: {}\n", names.join(", "))
+    }
+
+    /// Players who get the spectator broadcast of the current word.
+    fn get_word_spectators(&self) -> Vec<UserId> {
+        let current_team = &self.teams[self.turn as usize];
+        let active = [current_team.first.id, current_team.second.id];
+        match self.config.word_broadcast {
+            WordBroadcast::Everyone => self
+                .get_all_players()
+                .into_iter()
+                .filter(|player| !active.contains(player))
+                .collect(),
+            WordBroadcast::DescriberOnly => Vec::new(),
+            WordBroadcast::OpposingTeams => self
+                .teams
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != self.turn as usize)
+                .flat_map(|(_, team)| [team.first.id, team.second.id])
+                .chain(self.bench.iter().map(|player| player.id))
+                .collect(),
+        }
+    }
+
+    fn get_all_players(&self) -> Vec<UserId> {
+        self.teams
+            .iter()
+            .map(|team| vec![team.first.id, team.second.id])
+            .collect::<Vec<Vec<_>>>()
+            .concat()
+            .into_iter()
+            .chain(self.bench.iter().map(|player| player.id))
+            .collect()
+    }
+
+    /// Puts a user who joined too late on the bench.
+    fn join_bench(&mut self, user: User) -> Result<(), GameLogicError> {
+        if self.config.pass_the_phone {
+            return Err(GameLogicError::JoinAfterPlay);
+        }
+        if self.get_all_players().contains(&user.id) {
+            return Err(GameLogicError::AlreadyJoined);
+        }
+        self.bench.push(user);
+        Ok(())
+    }
+
+    fn pause(self) -> PausedRoom {
+        PausedRoom {
+            playing: self,
+            parked: false,
+            round_time_left: None,
+        }
+    }
+
+    /// Pauses for a restart, even in the middle of a round. The clock of the
+    /// describing team stops and the rest of the round is kept for later.
+    fn park(mut self) -> PausedRoom {
+        let round_time_left = self.round_in_progress.then(|| {
+            self.update_time();
+            self.round_duration()
+                .saturating_sub(self.round_started_at.elapsed())
+        });
+
+        PausedRoom {
+            playing: self,
+            parked: true,
+            round_time_left,
+        }
+    }
+
+    fn round_duration(&self) -> Duration {
+        self.config.round_duration()
+    }
+
+    fn finish(self, mut results: String) -> FinishedRoom {
+        let role_notes = self
+            .teams
+            .iter()
+            .filter_map(PlayingTeam::role_note)
+            .collect::<Vec<_>>();
+        if !role_notes.is_empty() {
+            results += &format!("⚖️ Roles were uneven:\n{}\n", role_notes.join("\n"));
+        }
+        if let Some(target) = self.config.coop_target {
+            let words = self.group_words();
+            results += &if words >= target {
+                "🎉 You beat the target together!\n".to_owned()
+            } else {
+                format!("😅 You fell {} words short of the target\n", target - words)
+            };
+        }
+
+        FinishedRoom {
+            duration: self.started_at.elapsed(),
+            teams: self.teams,
+            results,
+            config: self.config,
+            group_chat: self.group_chat,
+            group_topic: self.group_topic,
+            custom_cards: self.custom_cards,
+            pinned_scoreboard: self.pinned_scoreboard,
+            rng: self.rng,
+            host: self.host,
+            events: self.events,
+        }
+    }
+
+    /// Logs the word in play as guessed or skipped by the playing pair.
+    fn log_word(&mut self, guessed: bool) {
+        self.log_team_word(self.turn as usize, guessed);
+    }
+
+    /// Logs the word in play as guessed or skipped by the pair of `team`.
+    fn log_team_word(&mut self, team: usize, guessed: bool) {
+        let team = &self.teams[team];
+        self.events.push(WordEvent {
+            round: self.round as usize,
+            describer: team.get_describing_player().id,
+            guesser: team.get_guessing_player().id,
+            guessed,
+            took: self.word_dealt_at.elapsed(),
+        });
+    }
+
+    /// Resolves the word of a relay round, handing every team its next
+    /// describer and the next word. Returns the cards of the resolved word.
+    fn next_relay_word(&mut self) -> (Vec<(ChatId, MessageId)>, Word) {
+        self.teams.iter_mut().for_each(PlayingTeam::advance_turn);
+        let cards = self
+            .relay
+            .take()
+            .map(|relay| relay.cards)
+            .unwrap_or_default();
+        let word = self.deal_relay_word();
+        (cards, word)
+    }
+
+    /// Deals the next word of a relay round to every team at once.
+    fn deal_relay_word(&mut self) -> Word {
+        self.relay_words += 1;
+        self.relay = Some(RelayWord {
+            index: self.relay_words,
+            cards: Vec::new(),
+            skips: HashSet::new(),
+        });
+        let complexity = self
+            .config
+            .difficulty_plan
+            .get(self.round as usize)
+            .copied()
+            .flatten();
+        self.draw_word(complexity)
+    }
+
+    fn get_team_statuses(&self) -> Vec<TeamStatus> {
+        get_team_statuses(&self.teams)
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct PausedRoom {
+    playing: PlayingRoom,
+    parked: bool,
+    round_time_left: Option<Duration>,
+}
+
+impl PausedRoom {
+    fn resume(self) -> PlayingRoom {
+        self.playing
+    }
+
+    fn unpark(self) -> PlayingRoom {
+        let mut playing = self.playing;
+        playing.instant = Instant::now();
+        playing.word_dealt_at = Instant::now();
+        if let Some(round_time_left) = self.round_time_left {
+            playing.round_started_at = Instant::now()
+                .checked_sub(playing.round_duration().saturating_sub(round_time_left))
+                .unwrap_or_else(Instant::now);
+        }
+        playing
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct FinishedRoom {
+    teams: Vec<PlayingTeam>,
+    duration: Duration,
+    results: String,
+    config: RoomConfig,
+    group_chat: Option<ChatId>,
+    group_topic: Option<i32>,
+    custom_cards: Vec<CustomCard>,
+    pinned_scoreboard: Option<PinnedMessage>,
+    rng: RoomRng,
+    host: UserId,
+    events: Vec<WordEvent>,
+}
+
+impl FinishedRoom {
+    /// A new game between the same teams with the same settings. Swapping
+    /// the roles also has the teams describe in the reverse order.
+    fn rematch(mut self, swap_roles: bool) -> PlayingRoom {
+        let deck = deal_deck(&mut self.rng, self.teams.len(), &self.config);
+        let mut teams = self
+            .teams
+            .iter()
+            .map(|team| team.rematch(swap_roles))
+            .collect::<Vec<_>>();
+        if swap_roles {
+            teams.reverse();
+        }
+        PlayingRoom {
+            teams,
+            turn: 0,
+            round: 0,
+            instant: Instant::now(),
+            started_at: Instant::now(),
+            round_started_at: Instant::now(),
+            round_ended_at: Instant::now(),
+            start_offered_at: Instant::now(),
+            idle_reminders: 0,
+            round_in_progress: false,
+            message_stack: Vec::new(),
+            config: self.config,
+            group_chat: self.group_chat,
+            group_topic: self.group_topic,
+            handicapped_team: None,
+            custom_cards: self.custom_cards,
+            awaiting_draw: false,
+            current_complexity: None,
+            pinned_scoreboard: None,
+            rng: self.rng,
+            host: self.host,
+            current_word: None,
+            dealt_word: None,
+            deck,
+            round_words: Vec::new(),
+            disputes: HashMap::new(),
+            warm_up: None,
+            bench: Vec::new(),
+            events: Vec::new(),
+            relay: None,
+            relay_words: 0,
+            word_dealt_at: Instant::now(),
+            balancing: false,
+            turn_started_at: Instant::now(),
+        }
+    }
+}
+
+/// How far apart the summed ratings of the teams are, as their variance.
+/// Players without a rating count as new ones.
+fn rating_spread(teams: &[Vec<UserId>]) -> f64 {
+    let totals = teams
+        .iter()
+        .map(|team| {
+            team.iter()
+                .map(|player| ratings::rating_of(*player).unwrap_or(DEFAULT_RATING))
+                .sum::<f64>()
+        })
+        .collect::<Vec<_>>();
+    let mean = totals.iter().sum::<f64>() / totals.len() as f64;
+    totals
+        .iter()
+        .map(|total| (total - mean).powi(2))
+        .sum::<f64>()
+        / totals.len() as f64
+}
+
+fn swap_players(teams: &mut [Vec<UserId>], first: (usize, usize), second: (usize, usize)) {
+    let player = teams[first.0][first.1];
+    teams[first.0][first.1] = teams[second.0][second.1];
+    teams[second.0][second.1] = player;
+}
+
+/// The best ranked team. Teams level on words whose times are within the
+/// tie margin of the best one are told apart by the words they skipped.
+fn winner(teams: &[PlayingTeam], config: &RoomConfig) -> Option<usize> {
+    let victory = config.victory();
+    let (words, best) = teams.iter().map(|team| team.rank(victory)).min()?;
+    teams
+        .iter()
+        .enumerate()
+        .filter(|(_, team)| {
+            let (team_words, score) = team.rank(victory);
+            team_words == words && score <= best + config.tie_margin()
+        })
+        .min_by_key(|(_, team)| (team.total_skips(), team.rank(victory)))
+        .map(|(index, _)| index)
+}
+
+/// The words a team skipped, with the count of each round.
+fn skips_line(team: &PlayingTeam) -> String {
+    if team.skips.is_empty() {
+        return String::new();
+    }
+    let rounds = team.skips.iter().map(usize::to_string).collect::<Vec<_>>();
+    format!(
+        "\t⏩️ {} skipped ({})\n",
+        team.total_skips(),
+        rounds.join(", ")
+    )
+}
+
+fn get_team_statuses(teams: &[PlayingTeam]) -> Vec<TeamStatus> {
+    teams
+        .iter()
+        .map(|team| TeamStatus {
+            name: team.name.clone(),
+            players: vec![team.first.full_name(), team.second.full_name()],
+            time_in_seconds: team.score().as_secs_f32(),
+            bonus_in_seconds: team.bonus.as_secs_f32(),
+            words: team.words,
+        })
+        .collect()
+}
+
+#[derive(serde::Serialize)]
+pub struct TeamStatus {
+    name: String,
+    players: Vec<String>,
+    time_in_seconds: f32,
+    bonus_in_seconds: f32,
+    words: usize,
+}
+
+#[derive(serde::Serialize)]
+pub struct TeamScore {
+    name: String,
+    time_in_seconds: f32,
+}
+
+#[derive(serde::Serialize)]
+pub struct RoomStatus {
+    state: &'static str,
+    round: usize,
+    number_of_rounds: usize,
+    config: RoomConfig,
+    teams: Vec<TeamStatus>,
+}
+
+#[derive(serde::Serialize)]
+pub struct GameSummary {
+    room_id: u32,
+    teams: Vec<TeamStatus>,
+    words: usize,
+    duration_in_seconds: u64,
+    /// The players were numbered seats around one phone, not Telegram users.
+    pass_the_phone: bool,
+}
+
+impl GameSummary {
+    /// The game as spreadsheet cells: room, teams, players, scores and words,
+    /// with the values of each team separated by " | ".
+    pub fn cells(&self) -> [String; 5] {
+        let join = |cell: &dyn Fn(&TeamStatus) -> String| {
+            self.teams.iter().map(cell).collect::<Vec<_>>().join(" | ")
+        };
+        [
+            self.room_id.to_string(),
+            join(&|team| team.name.clone()),
+            join(&|team| team.players.join(", ")),
+            join(&|team| format!("{:.1}", team.time_in_seconds)),
+            self.words.to_string(),
+        ]
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct TurnStatus {
+    team: String,
+    describing: String,
+    guessing: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct RoomScore {
+    state: &'static str,
+    round: usize,
+    number_of_rounds: usize,
+    turn: Option<TurnStatus>,
+    scores: Vec<TeamScore>,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub enum Room {
+    Lobby(NewRoom),
+    Playing(PlayingRoom),
+    Paused(PausedRoom),
+    Finished(FinishedRoom),
+}
+
+impl Default for Room {
+    fn default() -> Self {
+        Room::new(RoomConfig::new(0, 0, 0, false), None, UserId(0))
+    }
+}
+
+pub enum DisputeOutcome {
+    /// The word stays until more players vote to void it.
+    Voted {
+        word: String,
+        team: String,
+        votes: usize,
+        needed: usize,
+    },
+    Voided {
+        word: String,
+        team: String,
+    },
+}
+
+/// Signs that a game got stuck.
+pub enum Anomaly {
+    /// The round ran past its time without its timer stopping it.
+    OverdueRound,
+    /// A turn owed after the last round's time was up went on for as long
+    /// as a whole round.
+    OverdueBalancingTurn,
+    /// Nobody started the next round for a long time.
+    Abandoned,
+}
+
+/// How the describing pair is doing this round.
+pub struct PairStatus {
+    pub describing: String,
+    /// Words the team guessed this round.
+    pub words: usize,
+    /// Words the team skipped this round.
+    pub skips: usize,
+    pub round_time_left: Duration,
+}
+
+/// Where the game stands when a word is dealt, for the cards to show.
+pub struct TurnContext {
+    /// Counted from 1.
+    pub round: usize,
+    pub rounds: usize,
+    /// The word's position among those dealt this round, counted from 1.
+    pub turn: usize,
+    /// The playing team.
+    pub team: String,
+    /// Words the team guessed this round.
+    pub words: usize,
+    /// Words the team skipped this round.
+    pub skips: usize,
+    /// Words of the round's pile that weren't drawn yet.
+    pub words_left: Option<usize>,
+}
+
+pub struct WordGuessTry {
+    /// Missing while the describer is yet to pick its complexity.
+    pub word: Option<Word>,
+    pub describing: User,
+    pub guessing: User,
+    pub context: TurnContext,
+    /// The team and the length of its streak when the last word earned a
+    /// streak bonus.
+    pub streak: Option<(String, usize)>,
+    /// The team that just won a first-to-N game with the last word.
+    pub winner: Option<String>,
+}
+
+pub struct FinalPairs {
+    /// The two players of each team, in the order of the teams.
+    pub pairs: Vec<(UserId, UserId)>,
+    /// The position of the winning team, `None` in co-op games.
+    pub winner: Option<usize>,
+}
+
+/// A relay word that was claimed or skipped, and the one dealt after it.
+pub struct RelayResolution {
+    pub word: String,
+    /// The team that claimed the word, `None` if every team skipped it.
+    pub claimed_by: Option<String>,
+    pub cards: Vec<(ChatId, MessageId)>,
+    pub next: Word,
+    /// The team that just won a first-to-N game with the word.
+    pub winner: Option<String>,
+}
+
+pub enum RoundStopState {
+    /// Results, the describer starting the next round, its number, the total
+    /// number of rounds and the turn order in it.
+    RoundFinished(String, User, u8, usize, Vec<String>),
+    GameFinished(String),
+}
+
+/// The state a room ends up in after a transition, together with its outcome.
+type Transition<T> = (Room, Result<T, GameLogicError>);
+
+fn illegal<T>(room: Room) -> Transition<T> {
+    let err = room.state_error();
+    (room, Err(err))
+}
+
+impl Room {
+    pub fn new(config: RoomConfig, group_chat: Option<ChatId>, host: UserId) -> Self {
+        Room::Lobby(NewRoom::new(config, group_chat, host))
+    }
+
+    /// How many words a new room may go through, to check up-front that the
+    /// bank can cover it.
+    pub fn words_needed(&self) -> usize {
+        match self {
+            Room::Lobby(lobby) => lobby.config.words_needed(),
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => 0,
+        }
+    }
+
+    fn state_error(&self) -> GameLogicError {
+        match self {
+            Room::Lobby(_) => GameLogicError::IsNotPlaying,
+            Room::Playing(_) => GameLogicError::AlreadyPlaying,
+            Room::Paused(_) => GameLogicError::IsPaused,
+            Room::Finished(_) => GameLogicError::IsFinished,
+        }
+    }
+
+    fn transition<T>(
+        &mut self,
+        step: impl FnOnce(Room) -> Transition<T>,
+    ) -> Result<T, GameLogicError> {
+        let (room, result) = step(std::mem::take(self));
+        *self = room;
+        result
+    }
+
+    pub fn join(&mut self, user: User, chat_id: ChatId) -> Result<Vec<UserId>, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => lobby.request_join(user, chat_id),
+            // Like a join awaiting approval, joining the bench is reported
+            // as an error since the user doesn't get a lobby card
+            Room::Playing(playing) => playing.join_bench(user).and(Err(GameLogicError::OnBench)),
+            Room::Paused(paused) => paused
+                .playing
+                .join_bench(user)
+                .and(Err(GameLogicError::OnBench)),
+            Room::Finished(_) => Err(GameLogicError::JoinAfterPlay),
+        }
+    }
+
+    /// The players waiting on the bench, the first one is swapped in next.
+    pub fn bench(&self) -> Vec<User> {
+        self.get_started()
+            .map(|playing| playing.bench.clone())
+            .unwrap_or_default()
+    }
+
+    /// Every seat of the teams with a label naming its team and player, to
+    /// pick who the next player of the bench replaces.
+    pub fn seats(&self) -> Vec<(usize, String)> {
+        let Ok(playing) = self.get_started() else {
+            return Vec::new();
+        };
+        playing
+            .teams
+            .iter()
+            .flat_map(|team| [(&team.name, &team.first), (&team.name, &team.second)])
+            .enumerate()
+            .map(|(seat, (team, player))| (seat, format!("{} {}", team, player.full_name())))
+            .collect()
+    }
+
+    /// Swaps the first player of the bench in for the one in `seat`, who
+    /// goes to the bench. Returns who left, who came in and their team.
+    pub fn substitute(
+        &mut self,
+        user_id: UserId,
+        seat: usize,
+    ) -> Result<(User, User, String), GameLogicError> {
+        let playing = self.get_started_mut()?;
+        if playing.host != user_id {
+            return Err(GameLogicError::NotHost);
+        }
+        if playing.round_in_progress {
+            return Err(GameLogicError::RoundInProgress);
+        }
+        if playing.bench.is_empty() {
+            return Err(GameLogicError::BenchEmpty);
+        }
+        let team = playing
+            .teams
+            .get_mut(seat / 2)
+            .ok_or(GameLogicError::NotInTeam)?;
+
+        let incoming = playing.bench.remove(0);
+        let player = match seat % 2 {
+            0 => &mut team.first,
+            _ => &mut team.second,
+        };
+        let outgoing = std::mem::replace(player, incoming.clone());
+        if team.captain == outgoing.id {
+            team.captain = incoming.id;
+        }
+        let team = team.name.clone();
+        playing.bench.push(outgoing.clone());
+        Ok((outgoing, incoming, team))
+    }
+
+    /// Lets a user the host approved into the room, returning the chat their
+    /// `/join` came from along with the usual join result.
+    pub fn approve_join(
+        &mut self,
+        host: UserId,
+        user_id: UserId,
+    ) -> Result<(User, ChatId, Vec<UserId>), GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                let (user, chat_id) = lobby.take_pending_join(host, user_id)?;
+                let others = lobby.join(user.clone())?;
+                Ok((user, chat_id, others))
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::JoinAfterPlay)
+            }
+        }
+    }
+
+    pub fn deny_join(
+        &mut self,
+        host: UserId,
+        user_id: UserId,
+    ) -> Result<(User, ChatId), GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => lobby.take_pending_join(host, user_id),
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::JoinAfterPlay)
+            }
+        }
+    }
+
+    /// Starts an auto-start countdown when the rule is on and teams are
+    /// ready, returning its token and how long to wait before counting down.
+    pub fn arm_auto_start(&mut self) -> Option<(u64, Duration)> {
+        let Room::Lobby(lobby) = self else {
+            return None;
+        };
+
+        if !lobby.auto_start
+            || lobby.auto_start_countdown.is_some()
+            || lobby.check_teams_ready().is_err()
+        {
+            return None;
+        }
+
+        let token = lobby.next_countdown;
+        lobby.next_countdown += 1;
+        lobby.auto_start_countdown = Some(token);
+        Some((
+            token,
+            AUTO_START_LOBBY_TIME.saturating_sub(lobby.created_at.elapsed()),
+        ))
+    }
+
+    pub fn is_auto_start_armed(&self, token: u64) -> bool {
+        match self {
+            Room::Lobby(lobby) => lobby.auto_start_countdown == Some(token),
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => false,
+        }
+    }
+
+    pub fn toggle_auto_start(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) if lobby.host != user_id => Err(GameLogicError::NotHost),
+            Room::Lobby(lobby) => {
+                lobby.auto_start = !lobby.auto_start;
+                lobby.auto_start_countdown = None;
+                Ok(lobby.auto_start)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    pub fn cancel_auto_start(&mut self, user_id: UserId) -> Result<(), GameLogicError> {
+        match self {
+            Room::Lobby(lobby) if lobby.host != user_id => Err(GameLogicError::NotHost),
+            Room::Lobby(lobby) => {
+                lobby.auto_start_countdown = None;
+                Ok(())
+            }
+            room => Err(room.state_error()),
+        }
+    }
+
+    pub fn lobby_status(&self) -> Option<String> {
+        match self {
+            Room::Lobby(lobby) => Some(lobby.get_status()),
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => None,
+        }
+    }
+
+    pub fn find_player(&self, user_id: UserId) -> Option<User> {
+        let teams = match self {
+            Room::Lobby(lobby) => return lobby.players.get(&user_id).cloned(),
+            Room::Playing(playing) => &playing.teams,
+            Room::Paused(paused) => &paused.playing.teams,
+            Room::Finished(finished) => &finished.teams,
+        };
+        teams
+            .iter()
+            .flat_map(|team| [&team.first, &team.second])
+            .find(|player| player.id == user_id)
+            .cloned()
+    }
+
+    /// The lobby players the bot can't message privately.
+    pub fn unreachable_players(&self) -> Vec<User> {
+        self.lobby_players()
+            .into_iter()
+            .filter(|player| reachability::is_unreachable(player.id))
+            .collect()
+    }
+
+    /// How many players the lobby has and how many it can take.
+    pub fn capacity(&self) -> Option<(usize, usize)> {
+        match self {
+            Room::Lobby(lobby) => Some((lobby.players.len(), lobby.max_players)),
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => None,
+        }
+    }
+
+    pub fn public_post(&self) -> Option<(ChatId, MessageId)> {
+        match self {
+            Room::Lobby(lobby) => lobby.public_post,
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => None,
+        }
+    }
+
+    pub fn set_public_post(&mut self, post: Option<(ChatId, MessageId)>) {
+        if let Room::Lobby(lobby) = self {
+            lobby.public_post = post;
+        }
+    }
+
+    /// The players who joined the lobby, ordered by name.
+    pub fn lobby_players(&self) -> Vec<User> {
+        let Room::Lobby(lobby) = self else {
+            return Vec::new();
+        };
+        let mut players = lobby.players.values().cloned().collect::<Vec<_>>();
+        players.sort_by_key(User::full_name);
+        players
+    }
+
+    /// Remembers the lobby card of a chat so it can be kept up to date, a
+    /// newer card replaces the older one of the same chat.
+    pub fn set_lobby_card(&mut self, chat_id: ChatId, message_id: MessageId) {
+        if let Room::Lobby(lobby) = self {
+            lobby.lobby_cards.insert(chat_id, message_id);
+        }
+    }
+
+    pub fn lobby_cards(&self) -> Vec<(ChatId, MessageId)> {
+        match self {
+            Room::Lobby(lobby) => lobby
+                .lobby_cards
+                .iter()
+                .map(|(chat_id, message_id)| (*chat_id, *message_id))
+                .collect(),
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => vec![],
+        }
+    }
+
+    pub fn config(&self) -> &RoomConfig {
+        match self {
+            Room::Lobby(lobby) => &lobby.config,
+            Room::Playing(playing) => &playing.config,
+            Room::Paused(paused) => &paused.playing.config,
+            Room::Finished(finished) => &finished.config,
+        }
+    }
+
+    pub fn number_of_teams(&self) -> usize {
+        match self {
+            Room::Lobby(lobby) => lobby.config.number_of_teams,
+            Room::Playing(playing) => playing.teams.len(),
+            Room::Paused(paused) => paused.playing.teams.len(),
+            Room::Finished(finished) => finished.teams.len(),
+        }
+    }
+
+    /// The player who should act next in a started game, and whether they
+    /// are in the middle of describing a word.
+    pub fn describing_player(&self) -> Option<(User, bool)> {
+        let playing = self.get_started().ok()?;
+        Some((playing.get_describing_player(), playing.round_in_progress))
+    }
+
+    /// The seed of the room's random choices, to replay it with
+    /// `JIGARPICH_SEED`.
+    pub fn seed(&self) -> u64 {
+        match self {
+            Room::Lobby(lobby) => lobby.rng.seed,
+            Room::Playing(playing) => playing.rng.seed,
+            Room::Paused(paused) => paused.playing.rng.seed,
+            Room::Finished(finished) => finished.rng.seed,
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        if let Room::Lobby(lobby) = self {
+            lobby.rng = RoomRng { seed, draws: 0 };
+        }
+    }
+
+    pub fn is_lobby(&self) -> bool {
+        matches!(self, Room::Lobby(_))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Room::Finished(_))
+    }
+
+    pub fn message_stack_len(&self) -> usize {
+        self.get_started()
+            .map(|playing| playing.message_stack.len())
+            .unwrap_or_default()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        matches!(self, Room::Paused(_))
+    }
+
+    /// The phone every card goes to in a started pass-the-phone game.
+    pub fn device(&self) -> Option<UserId> {
+        (self.config().pass_the_phone && !self.is_lobby()).then(|| self.host())
+    }
+
+    pub fn host(&self) -> UserId {
+        match self {
+            Room::Lobby(lobby) => lobby.host,
+            Room::Playing(playing) => playing.host,
+            Room::Paused(paused) => paused.playing.host,
+            Room::Finished(finished) => finished.host,
+        }
+    }
+
+    pub fn toggle_host_approval(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) if lobby.host != user_id => Err(GameLogicError::NotHost),
+            Room::Lobby(lobby) => {
+                lobby.requires_approval = !lobby.requires_approval;
+                Ok(lobby.requires_approval)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    pub fn join_team(
+        &mut self,
+        user_id: UserId,
+        team_index: usize,
+    ) -> Result<Vec<UserId>, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => lobby.join_team(user_id, team_index),
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::TeamChangeAfterPlay)
+            }
+        }
+    }
+
+    /// Seats `user_id` in the lobby team of their duo partner, if they
+    /// aren't in a team yet and the partner's team has a free seat.
+    pub fn seat_next_to(&mut self, user_id: UserId, partner: UserId) -> Option<usize> {
+        let Room::Lobby(lobby) = self else {
+            return None;
+        };
+        if !lobby.players.contains_key(&partner)
+            || lobby.teams.iter().any(|team| team.contains(&user_id))
+        {
+            return None;
+        }
+        let team = lobby
+            .teams
+            .iter()
+            .position(|team| team.contains(&partner))?;
+        if lobby.teams[team].len() >= 2 {
+            return None;
+        }
+        lobby.join_team(user_id, team).ok()?;
+        Some(team)
+    }
+
+    pub fn propose_teams(&mut self, user_id: UserId) -> Result<Vec<Vec<User>>, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => lobby.propose_teams(user_id),
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::TeamChangeAfterPlay)
+            }
+        }
+    }
+
+    pub fn accept_team_proposal(&mut self, user_id: UserId) -> Result<(), GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => lobby.accept_team_proposal(user_id),
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::TeamChangeAfterPlay)
+            }
+        }
+    }
+
+    /// Notes that the host invited `invitee`, returning false if they are
+    /// in the room or were invited already.
+    pub fn invite(
+        &mut self,
+        user_id: UserId,
+        invitee: UserId,
+        name: String,
+    ) -> Result<bool, GameLogicError> {
+        let Room::Lobby(lobby) = self else {
+            return Err(GameLogicError::JoinAfterPlay);
+        };
+        if lobby.host != user_id {
+            return Err(GameLogicError::NotHost);
+        }
+        if lobby.players.contains_key(&invitee) || lobby.invitations.contains_key(&invitee) {
+            return Ok(false);
+        }
+        lobby.invitations.insert(invitee, name);
+        Ok(true)
+    }
+
+    /// Whether a lobby player has a rating to balance the teams by.
+    pub fn has_rated_players(&self) -> bool {
+        let Room::Lobby(lobby) = self else {
+            return false;
+        };
+        lobby
+            .players
+            .keys()
+            .any(|player| ratings::rating_of(*player).is_some())
+    }
+
+    /// The lobby team `user_id` picked, if any.
+    pub fn lobby_team(&self, user_id: UserId) -> Option<usize> {
+        let Room::Lobby(lobby) = self else {
+            return None;
+        };
+        lobby.teams.iter().position(|team| team.contains(&user_id))
+    }
+
+    pub fn get_teams(&self) -> String {
+        match self {
+            Room::Lobby(lobby) => lobby.get_teams(),
+            Room::Playing(playing) => playing.get_teams(),
+            Room::Paused(paused) => paused.playing.get_teams(),
+            Room::Finished(finished) => finished.results.clone(),
+        }
+    }
+
+    fn get_playing(&self) -> Result<&PlayingRoom, GameLogicError> {
+        match self {
+            Room::Playing(playing) => Ok(playing),
+            _ => Err(self.state_error()),
+        }
+    }
+
+    fn get_playing_mut(&mut self) -> Result<&mut PlayingRoom, GameLogicError> {
+        match self {
+            Room::Playing(playing) => Ok(playing),
+            _ => Err(self.state_error()),
+        }
+    }
+
+    /// Like `get_playing_mut`, but also accepts a paused game, whose buttons
+    /// still need to be tracked.
+    fn get_started_mut(&mut self) -> Result<&mut PlayingRoom, GameLogicError> {
+        match self {
+            Room::Playing(playing) => Ok(playing),
+            Room::Paused(paused) => Ok(&mut paused.playing),
+            _ => Err(self.state_error()),
+        }
+    }
+
+    fn get_started(&self) -> Result<&PlayingRoom, GameLogicError> {
+        match self {
+            Room::Playing(playing) => Ok(playing),
+            Room::Paused(paused) => Ok(&paused.playing),
+            _ => Err(self.state_error()),
+        }
+    }
+
+    /// Who hears from the room, only the host in a started pass-the-phone
+    /// game since the players don't have their own phones.
+    pub fn get_all_players(&self) -> Vec<UserId> {
+        if let Some(device) = self.device() {
+            return vec![device];
+        }
+
+        match self {
+            Room::Lobby(lobby) => lobby.players.clone().into_keys().collect::<Vec<_>>(),
+            Room::Playing(playing) => playing.get_all_players(),
+            Room::Paused(paused) => paused.playing.get_all_players(),
+            Room::Finished(finished) => finished
+                .teams
+                .iter()
+                .flat_map(|team| [team.first.id, team.second.id])
+                .collect(),
+        }
+    }
+
+    pub fn status(&self) -> RoomStatus {
+        let (state, round, number_of_rounds, teams) = match self {
+            Room::Lobby(lobby) => (
+                "lobby",
+                0,
+                lobby.config.number_of_rounds,
+                lobby
+                    .teams
+                    .iter()
+                    .enumerate()
+                    .map(|(i, members)| TeamStatus {
+                        name: get_team_emoji(i),
+                        players: members
+                            .iter()
+                            .filter_map(|member| lobby.players.get(member))
+                            .map(User::full_name)
+                            .collect(),
+                        time_in_seconds: 0.0,
+                        bonus_in_seconds: 0.0,
+                        words: 0,
+                    })
+                    .collect(),
+            ),
+            Room::Playing(playing) => (
+                "playing",
+                playing.round as usize,
+                playing.config.number_of_rounds,
+                playing.get_team_statuses(),
+            ),
+            Room::Paused(paused) => (
+                "paused",
+                paused.playing.round as usize,
+                paused.playing.config.number_of_rounds,
+                paused.playing.get_team_statuses(),
+            ),
+            Room::Finished(finished) => (
+                "finished",
+                finished.config.number_of_rounds,
+                finished.config.number_of_rounds,
+                get_team_statuses(&finished.teams),
+            ),
+        };
+
+        RoomStatus {
+            state,
+            round,
+            number_of_rounds,
+            config: self.config().clone(),
+            teams,
+        }
+    }
+
+    pub fn summary(&self, room_id: RoomId) -> Option<GameSummary> {
+        let Room::Finished(finished) = self else {
+            return None;
+        };
+
+        Some(GameSummary {
+            room_id: room_id.0,
+            teams: get_team_statuses(&finished.teams),
+            words: finished.teams.iter().map(|team| team.words).sum(),
+            duration_in_seconds: finished.duration.as_secs(),
+            pass_the_phone: finished.config.pass_the_phone,
+        })
+    }
+
+    /// The numbers behind the awards for each player of the game so far.
+    pub fn player_stats(&self) -> Vec<PlayerStats> {
+        let (teams, events, config) = match self {
+            Room::Lobby(_) => return Vec::new(),
+            Room::Playing(PlayingRoom {
+                teams,
+                events,
+                config,
+                ..
+            })
+            | Room::Paused(PausedRoom {
+                playing:
+                    PlayingRoom {
+                        teams,
+                        events,
+                        config,
+                        ..
+                    },
+                ..
+            })
+            | Room::Finished(FinishedRoom {
+                teams,
+                events,
+                config,
+                ..
+            }) => (teams, events, config),
+        };
+        let players = teams
+            .iter()
+            .flat_map(|team| [team.first.clone(), team.second.clone()])
+            .collect();
+        awards::player_stats(players, events, config.number_of_rounds - 1)
+    }
+
+    /// The pairs of a finished game, `None` for a pass-the-phone game whose
+    /// seats aren't users.
+    pub fn final_pairs(&self) -> Option<FinalPairs> {
+        let Room::Finished(finished) = self else {
+            return None;
+        };
+        if finished.config.pass_the_phone {
+            return None;
+        }
+        let pairs = finished
+            .teams
+            .iter()
+            .map(|team| (team.first.id, team.second.id))
+            .collect();
+        let winner = match finished.config.coop_target {
+            Some(_) => None,
+            None => winner(&finished.teams, &finished.config),
+        };
+        Some(FinalPairs { pairs, winner })
+    }
+
+    /// The position of the winning team in `round_times`.
+    pub fn winner(&self) -> Option<usize> {
+        let Room::Finished(finished) = self else {
+            return None;
+        };
+        winner(&finished.teams, &finished.config)
+    }
+
+    /// Per-round times of every team of a finished game, keyed by team
+    /// index so the chart can use the team color.
+    pub fn round_times(&self) -> Vec<(usize, Vec<Duration>)> {
+        let Room::Finished(finished) = self else {
+            return Vec::new();
+        };
+
+        finished
+            .teams
+            .iter()
+            .map(|team| (team.index, team.round_times.clone()))
+            .collect()
+    }
+
+    pub fn score(&self) -> RoomScore {
+        let status = self.status();
+        let turn = self.get_playing().ok().map(|playing| TurnStatus {
+            team: playing.teams[playing.turn as usize].name.clone(),
+            describing: playing.get_describing_player().full_name(),
+            guessing: playing.get_guessing_player().full_name(),
+        });
+
+        RoomScore {
+            state: status.state,
+            round: status.round,
+            number_of_rounds: status.number_of_rounds,
+            turn,
+            scores: status
+                .teams
+                .into_iter()
+                .map(|team| TeamScore {
+                    name: team.name,
+                    time_in_seconds: team.time_in_seconds,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn play(&mut self) -> Result<User, GameLogicError> {
+        self.transition(|room| match room {
+            Room::Lobby(lobby) => {
+                if let Err(err) = lobby.check_teams_ready() {
+                    return (Room::Lobby(lobby), Err(err));
+                }
+
+                let playing = lobby.play();
+                let describing_player = playing.get_describing_player();
+                (Room::Playing(playing), Ok(describing_player))
+            }
+            room => illegal(room),
+        })
+    }
+
+    /// Starts another game between the same teams and returns who describes
+    /// first.
+    pub fn rematch(&mut self, swap_roles: bool) -> Result<User, GameLogicError> {
+        self.transition(|room| match room {
+            Room::Finished(finished) => {
+                let playing = finished.rematch(swap_roles);
+                let describing_player = playing.get_describing_player();
+                (Room::Playing(playing), Ok(describing_player))
+            }
+            room => illegal(room),
+        })
+    }
+
+    pub fn pause(&mut self) -> Result<(), GameLogicError> {
+        self.transition(|room| match room {
+            Room::Playing(playing) if playing.round_in_progress => {
+                (Room::Playing(playing), Err(GameLogicError::RoundInProgress))
+            }
+            Room::Playing(playing) => (Room::Paused(playing.pause()), Ok(())),
+            room => illegal(room),
+        })
+    }
+
+    pub fn resume(&mut self) -> Result<User, GameLogicError> {
+        self.transition(|room| match room {
+            Room::Paused(paused) if paused.parked => {
+                (Room::Paused(paused), Err(GameLogicError::IsPaused))
+            }
+            Room::Paused(paused) => {
+                let playing = paused.resume();
+                let describing_player = playing.get_describing_player();
+                (Room::Playing(playing), Ok(describing_player))
+            }
+            Room::Playing(playing) => (Room::Playing(playing), Err(GameLogicError::IsNotPaused)),
+            room => illegal(room),
+        })
+    }
+
+    /// Parks a running game ahead of a restart, returns whether there was one.
+    pub fn park(&mut self) -> bool {
+        self.transition(|room| match room {
+            Room::Playing(playing) => (Room::Paused(playing.park()), Ok(())),
+            room => illegal(room),
+        })
+        .is_ok()
+    }
+
+    /// Continues a game parked by a restart, returning the describing player.
+    pub fn unpark(&mut self) -> Result<User, GameLogicError> {
+        self.transition(|room| match room {
+            Room::Paused(paused) if paused.parked => {
+                let playing = paused.unpark();
+                let describing_player = playing.get_describing_player();
+                (Room::Playing(playing), Ok(describing_player))
+            }
+            room => illegal(room),
+        })
+    }
+
+    pub fn start_round(&mut self) -> Result<WordGuessTry, GameLogicError> {
+        let playing = self.get_playing_mut()?;
+
+        if playing.round_in_progress {
+            return Err(GameLogicError::RoundInProgress);
+        }
+        if playing.warm_up.is_some() {
+            return Err(GameLogicError::WarmingUp);
+        }
+
+        playing.round_in_progress = true;
+        playing.instant = Instant::now();
+        playing.round_started_at = playing.instant;
+        playing.round_words.clear();
+        playing.disputes.clear();
+        playing
+            .teams
+            .iter_mut()
+            .for_each(|team| team.round_skips = 0);
+
+        let word = if playing.config.relay {
+            Some(playing.deal_relay_word())
+        } else {
+            playing.deal_word()
+        };
+        Ok(WordGuessTry {
+            word,
+            describing: playing.get_describing_player(),
+            guessing: playing.get_guessing_player(),
+            context: playing.turn_context(),
+            streak: None,
+            winner: None,
+        })
+    }
+
+    pub fn correct(&mut self) -> Result<WordGuessTry, GameLogicError> {
+        let playing = self.get_playing_mut()?;
+
+        if !playing.round_in_progress {
+            return Err(GameLogicError::NoRoundInProgress);
+        }
+
+        if playing.config.relay {
+            return Err(GameLogicError::RelayRound);
+        }
+
+        if playing.awaiting_draw {
+            return Err(GameLogicError::AwaitingDraw);
+        }
+
+        let draw_bonus = match playing.current_complexity {
+            Some(complexity) if playing.config.describer_draws => draw_bonus_in_seconds(complexity),
+            _ => 0,
+        };
+        playing.log_word(true);
+        let team = &mut playing.teams[playing.turn as usize];
+        team.words += 1;
+        team.bonus += Duration::from_secs(draw_bonus);
+        let streak = team
+            .extend_streak()
+            .map(|length| (team.name.clone(), length));
+        let team_name = team.name.clone();
+        if let Some(text) = playing.current_word.take() {
+            playing.round_words.push(PlayedWord {
+                team: playing.turn as usize,
+                text,
+                voided: false,
+            });
+        }
+        playing.next();
+        playing.instant = Instant::now();
+        let winner = playing.target_winner(team_name);
+
+        Ok(WordGuessTry {
+            word: playing.deal_word(),
+            describing: playing.get_describing_player(),
+            guessing: playing.get_guessing_player(),
+            context: playing.turn_context(),
+            streak,
+            winner,
+        })
+    }
+
+    /// Draws a word of the complexity the describer picked.
+    pub fn draw(&mut self, complexity: Complexity) -> Result<WordGuessTry, GameLogicError> {
+        let playing = self.get_playing_mut()?;
+
+        if !playing.round_in_progress {
+            return Err(GameLogicError::NoRoundInProgress);
+        }
+
+        if !playing.awaiting_draw {
+            return Err(GameLogicError::NotAwaitingDraw);
+        }
+
+        Ok(WordGuessTry {
+            word: Some(playing.draw_word(Some(complexity))),
+            describing: playing.get_describing_player(),
+            guessing: playing.get_guessing_player(),
+            context: playing.turn_context(),
+            streak: None,
+            winner: None,
+        })
+    }
+
+    pub fn skip(&mut self) -> Result<WordGuessTry, GameLogicError> {
+        let playing = self.get_playing_mut()?;
+
+        if !playing.round_in_progress {
+            return Err(GameLogicError::NoRoundInProgress);
+        }
+
+        if playing.config.relay {
+            return Err(GameLogicError::RelayRound);
+        }
+
+        if playing.awaiting_draw {
+            return Err(GameLogicError::AwaitingDraw);
+        }
+
+        playing.log_word(false);
+        let team = &mut playing.teams[playing.turn as usize];
+        team.streak = 0;
+        team.round_skips += 1;
+
+        Ok(WordGuessTry {
+            word: playing.deal_word(),
+            describing: playing.get_describing_player(),
+            guessing: playing.get_guessing_player(),
+            context: playing.turn_context(),
+            streak: None,
+            winner: None,
+        })
+    }
+
+    /// The word in play once more, for a describer who lost its card, without
+    /// counting it as skipped.
+    pub fn redeal(&mut self) -> Result<WordGuessTry, GameLogicError> {
+        let playing = self.get_playing_mut()?;
+
+        if !playing.round_in_progress {
+            return Err(GameLogicError::NoRoundInProgress);
+        }
+
+        if playing.config.relay {
+            return Err(GameLogicError::RelayRound);
+        }
+
+        let word = match playing.dealt_word.clone() {
+            _ if playing.awaiting_draw => None,
+            // The time limit of the word starts over with its new card
+            Some(word) => {
+                playing.word_dealt_at = Instant::now();
+                Some(word)
+            }
+            None => playing.deal_word(),
+        };
+        Ok(WordGuessTry {
+            word,
+            describing: playing.get_describing_player(),
+            guessing: playing.get_guessing_player(),
+            context: playing.turn_context(),
+            streak: None,
+            winner: None,
+        })
+    }
+
+    /// The describer of each team with the index of the word in play, to
+    /// deal a relay word to.
+    pub fn relay_describers(&self) -> Option<(usize, Vec<User>)> {
+        let playing = self.get_playing().ok()?;
+        let relay = playing.relay.as_ref()?;
+        let describers = playing
+            .teams
+            .iter()
+            .map(PlayingTeam::get_describing_player)
+            .collect();
+        Some((relay.index, describers))
+    }
+
+    pub fn add_relay_card(&mut self, chat_id: ChatId, message_id: MessageId) {
+        if let Ok(PlayingRoom {
+            relay: Some(relay), ..
+        }) = self.get_playing_mut()
+        {
+            relay.cards.push((chat_id, message_id));
+        }
+    }
+
+    /// The cards of the relay word in play.
+    pub fn relay_cards(&self) -> Vec<(ChatId, MessageId)> {
+        self.get_started()
+            .ok()
+            .and_then(|playing| playing.relay.as_ref())
+            .map(|relay| relay.cards.clone())
+            .unwrap_or_default()
+    }
+
+    /// Gives the relay word `index` to the team of `user_id`, the first to
+    /// claim it. Later claims find it resolved.
+    pub fn claim_relay_word(
+        &mut self,
+        user_id: UserId,
+        index: usize,
+    ) -> Result<RelayResolution, GameLogicError> {
+        let playing = self.get_playing_mut()?;
+        if !playing.round_in_progress {
+            return Err(GameLogicError::NoRoundInProgress);
+        }
+        if playing
+            .relay
+            .as_ref()
+            .is_none_or(|relay| relay.index != index)
+        {
+            return Err(GameLogicError::WordResolved);
+        }
+        let team_index = playing
+            .team_of(user_id)
+            .ok_or(GameLogicError::NotJoinedToRoom)?;
+
+        playing.log_team_word(team_index, true);
+        let took = playing.word_dealt_at.elapsed();
+        let team = &mut playing.teams[team_index];
+        team.words += 1;
+        team.time += took;
+        team.described[team.turn as usize] += 1;
+        let team = team.name.clone();
+        let word = playing.current_word.take().unwrap_or_default();
+        playing.round_words.push(PlayedWord {
+            team: team_index,
+            text: word.clone(),
+            voided: false,
+        });
+        let winner = playing.target_winner(team.clone());
+        let (cards, next) = playing.next_relay_word();
+
+        Ok(RelayResolution {
+            word,
+            claimed_by: Some(team),
+            cards,
+            next,
+            winner,
+        })
+    }
+
+    /// Votes to skip the relay word `index` for the team of `user_id`. The
+    /// word is skipped once every team voted, `None` until then.
+    pub fn skip_relay_word(
+        &mut self,
+        user_id: UserId,
+        index: usize,
+    ) -> Result<Option<RelayResolution>, GameLogicError> {
+        let playing = self.get_playing_mut()?;
+        if !playing.round_in_progress {
+            return Err(GameLogicError::NoRoundInProgress);
+        }
+        let team_index = playing
+            .team_of(user_id)
+            .ok_or(GameLogicError::NotJoinedToRoom)?;
+        let Some(relay) = playing.relay.as_mut().filter(|relay| relay.index == index) else {
+            return Err(GameLogicError::WordResolved);
+        };
+        relay.skips.insert(team_index);
+        if relay.skips.len() < playing.teams.len() {
+            return Ok(None);
+        }
+
+        playing
+            .teams
+            .iter_mut()
+            .for_each(|team| team.round_skips += 1);
+        let word = playing.current_word.take().unwrap_or_default();
+        let (cards, next) = playing.next_relay_word();
+        Ok(Some(RelayResolution {
+            word,
+            claimed_by: None,
+            cards,
+            next,
+            winner: None,
+        }))
+    }
+
+    pub fn push_to_message_stack(
+        &mut self,
+        chat_id: ChatId,
+        message_id: MessageId,
+    ) -> Result<(), GameLogicError> {
+        let playing = self.get_started_mut()?;
+        playing.message_stack.push((chat_id, message_id));
+        Ok(())
+    }
+
+    pub fn get_message_stack_top(&self) -> Result<Option<(ChatId, MessageId)>, GameLogicError> {
+        let playing = self.get_started()?;
+        Ok(playing.message_stack.last().copied())
+    }
+
+    pub fn stop_round(&mut self) -> Result<RoundStopState, GameLogicError> {
+        self.transition(|room| match room {
+            Room::Playing(playing) if !playing.round_in_progress => (
+                Room::Playing(playing),
+                Err(GameLogicError::NoRoundInProgress),
+            ),
+            Room::Playing(mut playing) => {
+                playing.update_time();
+                playing.round_in_progress = false;
+                playing.round_ended_at = Instant::now();
+                playing.relay = None;
+                playing.balancing = false;
+                playing.teams.iter_mut().for_each(PlayingTeam::close_round);
+
+                let results = playing.get_teams();
+
+                playing.round += 1;
+                if playing.round as usize == playing.config.number_of_rounds
+                    || playing.target_reached()
+                {
+                    let finished = playing.finish(results.clone());
+                    (
+                        Room::Finished(finished),
+                        Ok(RoundStopState::GameFinished(results)),
+                    )
+                } else {
+                    playing.rotate_for_next_round();
+                    playing.handicapped_team = if playing.config.handicap {
+                        playing.leader()
+                    } else {
+                        None
+                    };
+                    let state = RoundStopState::RoundFinished(
+                        results,
+                        playing.get_describing_player(),
+                        playing.round + 1,
+                        playing.config.number_of_rounds,
+                        playing.turn_order(),
+                    );
+                    (Room::Playing(playing), Ok(state))
+                }
+            }
+            room => illegal(room),
+        })
+    }
+
+    /// Keeps the last round going once its time is up if some teams had
+    /// fewer turns, returning them. A round already running over isn't kept
+    /// going again, so it can still be stopped.
+    pub fn extend_for_balance(&mut self) -> Option<Vec<String>> {
+        let playing = self.get_playing_mut().ok()?;
+        if !playing.config.balance_turns
+            || playing.config.relay
+            || !playing.round_in_progress
+            || playing.balancing
+            || playing.round as usize + 1 != playing.config.number_of_rounds
+            || playing.target_reached()
+            || playing.turns_even()
+        {
+            return None;
+        }
+
+        playing.balancing = true;
+        playing.turn_started_at = Instant::now();
+        let most = playing.teams.iter().map(PlayingTeam::turns).max()?;
+        Some(
+            playing
+                .teams
+                .iter()
+                .filter(|team| team.turns() < most)
+                .map(|team| team.name.clone())
+                .collect(),
+        )
+    }
+
+    /// Whether the last round ran over and every team caught up on turns.
+    pub fn turns_balanced(&self) -> bool {
+        self.get_playing()
+            .is_ok_and(|playing| playing.balancing && playing.turns_even())
+    }
+
+    pub fn round_duration(&self) -> Duration {
+        self.config().round_duration()
+    }
+
+    /// How long the round in progress still runs, zero once it's overdue.
+    pub fn round_time_left(&self) -> Option<Duration> {
+        let playing = self.get_playing().ok()?;
+        if !playing.round_in_progress {
+            return None;
+        }
+
+        Some(
+            playing
+                .round_duration()
+                .saturating_sub(playing.round_started_at.elapsed()),
+        )
+    }
+
+    pub fn pair_status(&self) -> Option<PairStatus> {
+        let playing = self.get_playing().ok()?;
+        if !playing.round_in_progress {
+            return None;
+        }
+
+        let team = &playing.teams[playing.turn as usize];
+        Some(PairStatus {
+            describing: team.get_describing_player().full_name(),
+            words: playing
+                .round_words
+                .iter()
+                .filter(|word| word.team == playing.turn as usize && !word.voided)
+                .count(),
+            skips: team.round_skips,
+            round_time_left: playing
+                .round_duration()
+                .saturating_sub(playing.round_started_at.elapsed()),
+        })
+    }
+
+    /// The rounds played so far, each with the pairs in the order they took
+    /// their turns and how every team did, for players catching up.
+    pub fn history(&self) -> Option<String> {
+        let playing = self.get_started().ok()?;
+        let team_of = |player: UserId| {
+            playing
+                .teams
+                .iter()
+                .position(|team| team.first.id == player || team.second.id == player)
+        };
+        let name_of = |player: UserId| {
+            playing
+                .teams
+                .iter()
+                .flat_map(|team| [&team.first, &team.second])
+                .find(|user| user.id == player)
+                .map_or_else(|| "Someone".to_owned(), User::full_name)
+        };
+
+        let rounds = playing.round as usize + usize::from(playing.round_in_progress);
+        let mut history = "📜 History".to_owned();
+        for round in 0..rounds {
+            history += &format!("\n\nRound {}", round + 1);
+            if round == playing.round as usize {
+                history += " (in progress)";
+            }
+
+            let events = playing
+                .events
+                .iter()
+                .filter(|event| event.round == round)
+                .collect::<Vec<_>>();
+            let same_pair = |a: &&WordEvent, b: &&WordEvent| {
+                (a.describer, a.guesser) == (b.describer, b.guesser)
+            };
+            for turn in events.chunk_by(same_pair) {
+                let guessed = turn.iter().filter(|event| event.guessed).count();
+                let team = team_of(turn[0].describer)
+                    .map(|team| format!(" ({})", playing.teams[team].name))
+                    .unwrap_or_default();
+                history += &format!(
+                    "\n{} → {}{}: ✅ {} ⏩️ {}",
+                    name_of(turn[0].describer),
+                    name_of(turn[0].guesser),
+                    team,
+                    guessed,
+                    turn.len() - guessed
+                );
+            }
+
+            for (index, team) in playing.teams.iter().enumerate() {
+                let Some(time) = team.round_times.get(round) else {
+                    continue;
+                };
+                let words = events
+                    .iter()
+                    .filter(|event| event.guessed && team_of(event.describer) == Some(index))
+                    .count();
+                history += &format!(
+                    "\n{}: {} words in {:.2}s",
+                    team.name,
+                    words,
+                    time.as_secs_f32()
+                );
+            }
+        }
+        Some(history)
+    }
+
+    /// A private summary of the round that just ended for each team, with
+    /// the players it goes to. A shared phone would show it to every team, so
+    /// pass-the-phone games get none.
+    pub fn team_round_summaries(&self) -> Vec<(Vec<UserId>, String)> {
+        let Ok(playing) = self.get_playing() else {
+            return Vec::new();
+        };
+        if playing.round_in_progress || playing.config.pass_the_phone {
+            return Vec::new();
+        }
+
+        playing
+            .teams
+            .iter()
+            .enumerate()
+            .map(|(index, team)| {
+                let words = playing
+                    .round_words
+                    .iter()
+                    .filter(|word| word.team == index && !word.voided)
+                    .map(|word| word.text.as_str())
+                    .collect::<Vec<_>>();
+                let text = format!(
+                    "📋 {} in round {}\n\n⏱️ {:.2}s this round, {:.2}s in total\n\
+                     ✅ {} words: {}\n⏩️ {} skipped\n\nOnly your team sees this.",
+                    team.name,
+                    playing.round,
+                    team.round_times
+                        .last()
+                        .copied()
+                        .unwrap_or_default()
+                        .as_secs_f32(),
+                    team.score().as_secs_f32(),
+                    words.len(),
+                    if words.is_empty() {
+                        "none".to_owned()
+                    } else {
+                        words.join(", ")
+                    },
+                    team.round_skips
+                );
+                (vec![team.first.id, team.second.id], text)
+            })
+            .collect()
+    }
+
+    /// Notes that the describer was just handed the ▶️ button.
+    pub fn offer_start(&mut self) {
+        if let Ok(playing) = self.get_started_mut() {
+            playing.start_offered_at = Instant::now();
+            playing.idle_reminders = 0;
+        }
+    }
+
+    /// The describer if they left the ▶️ button unpressed for another
+    /// `interval`, with the number of the reminder that's due.
+    pub fn idle_describer(&self, interval: Duration) -> Option<(User, usize)> {
+        let playing = self.get_playing().ok()?;
+        if playing.round_in_progress || playing.warm_up.is_some() || self.break_left().is_some() {
+            return None;
+        }
+
+        let reminder = playing.idle_reminders + 1;
+        (playing.start_offered_at.elapsed() >= interval * reminder as u32)
+            .then(|| (playing.get_describing_player(), reminder))
+    }
+
+    /// Whether the round in progress outlived its duration by `grace`, a
+    /// balancing turn took as long, or the ▶️ button went unpressed for `idle`
+    /// outside of a break.
+    pub fn anomaly(&self, grace: Duration, idle: Duration) -> Option<Anomaly> {
+        let playing = self.get_playing().ok()?;
+        if playing.round_in_progress {
+            if playing.balancing {
+                return (playing.turn_started_at.elapsed() > playing.round_duration() + grace)
+                    .then_some(Anomaly::OverdueBalancingTurn);
+            }
+            return (playing.round_started_at.elapsed() > playing.round_duration() + grace)
+                .then_some(Anomaly::OverdueRound);
+        }
+        if playing.warm_up.is_some() || self.break_left().is_some() {
+            return None;
+        }
+
+        (playing.start_offered_at.elapsed() > idle).then_some(Anomaly::Abandoned)
+    }
+
+    pub fn note_idle_reminder(&mut self) {
+        if let Ok(playing) = self.get_playing_mut() {
+            playing.idle_reminders += 1;
+        }
+    }
+
+    /// Hands the start of the round to the partner of an idle describer.
+    pub fn pass_start_to_partner(&mut self) -> Result<User, GameLogicError> {
+        let playing = self.get_playing_mut()?;
+        if playing.round_in_progress {
+            return Err(GameLogicError::RoundInProgress);
+        }
+
+        playing.teams[playing.turn as usize].advance_turn();
+        Ok(playing.get_describing_player())
+    }
+
+    /// How long the break after the last round still runs.
+    pub fn break_left(&self) -> Option<Duration> {
+        let playing = self.get_playing().ok()?;
+        let minutes = playing.config.break_minutes?;
+        if playing.round_in_progress || playing.round == 0 {
+            return None;
+        }
+
+        Some(Duration::from_secs(minutes * 60).saturating_sub(playing.round_ended_at.elapsed()))
+            .filter(|left| !left.is_zero())
+    }
+
+    /// How long the round in progress has been running.
+    pub fn round_time_elapsed(&self) -> Option<Duration> {
+        let playing = self.get_playing().ok()?;
+        playing
+            .round_in_progress
+            .then(|| playing.round_started_at.elapsed())
+    }
+
+    pub fn use_taboo_words(&self) -> bool {
+        self.config().use_taboo_words
+    }
+
+    pub fn group_chat(&self) -> Option<ChatId> {
+        match self {
+            Room::Lobby(lobby) => lobby.group_chat,
+            Room::Playing(playing) => playing.group_chat,
+            Room::Paused(paused) => paused.playing.group_chat,
+            Room::Finished(finished) => finished.group_chat,
+        }
+    }
+
+    /// The forum topic group messages go to, `None` for the general one.
+    pub fn group_topic(&self) -> Option<i32> {
+        match self {
+            Room::Lobby(lobby) => lobby.group_topic,
+            Room::Playing(playing) => playing.group_topic,
+            Room::Paused(paused) => paused.playing.group_topic,
+            Room::Finished(finished) => finished.group_topic,
+        }
+    }
+
+    pub fn set_group_topic(&mut self, topic: Option<i32>) {
+        if let Room::Lobby(lobby) = self {
+            lobby.group_topic = topic;
+        }
+    }
+
+    pub fn pinned_scoreboard(&self) -> Option<PinnedMessage> {
+        match self {
+            Room::Lobby(_) => None,
+            Room::Playing(playing) => playing.pinned_scoreboard.clone(),
+            Room::Paused(paused) => paused.playing.pinned_scoreboard.clone(),
+            Room::Finished(finished) => finished.pinned_scoreboard.clone(),
+        }
+    }
+
+    pub fn set_pinned_scoreboard(&mut self, pinned: PinnedMessage) {
+        match self {
+            Room::Lobby(_) => (),
+            Room::Playing(playing) => playing.pinned_scoreboard = Some(pinned),
+            Room::Paused(paused) => paused.playing.pinned_scoreboard = Some(pinned),
+            Room::Finished(finished) => finished.pinned_scoreboard = Some(pinned),
+        }
+    }
+
+    pub fn get_scoreboard_text(&self) -> String {
+        match self {
+            Room::Lobby(lobby) => lobby.get_teams(),
+            Room::Playing(playing) => format!(
+                "📌 Round {}/{}\n\n{}",
+                playing.round + 1,
+                playing.config.number_of_rounds,
+                playing.get_teams()
+            ),
+            Room::Paused(paused) => format!(
+                "⏸️ Round {}/{}\n\n{}",
+                paused.playing.round + 1,
+                paused.playing.config.number_of_rounds,
+                paused.playing.get_teams()
+            ),
+            Room::Finished(finished) => format!("🏁 Final standings\n\n{}", finished.results),
+        }
+    }
+
+    pub fn cycle_word_broadcast(
+        &mut self,
+        user_id: UserId,
+    ) -> Result<WordBroadcast, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.word_broadcast = lobby.config.word_broadcast.next();
+                Ok(lobby.config.word_broadcast)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    pub fn cycle_victory(&mut self, user_id: UserId) -> Result<VictoryCondition, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.victory = lobby.config.victory.next();
+                Ok(lobby.config.victory)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    /// Moves on to the next per-word time limit and returns it in seconds.
+    pub fn cycle_word_time_limit(
+        &mut self,
+        user_id: UserId,
+    ) -> Result<Option<u64>, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                let current = WORD_TIME_LIMITS
+                    .iter()
+                    .position(|limit| *limit == lobby.config.word_time_limit)
+                    .unwrap_or(0);
+                lobby.config.word_time_limit =
+                    WORD_TIME_LIMITS[(current + 1) % WORD_TIME_LIMITS.len()];
+                Ok(lobby.config.word_time_limit)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    /// Moves the weight of `complexity` one step up or down, returning
+    /// whether it changed.
+    pub fn adjust_complexity_weight(
+        &mut self,
+        user_id: UserId,
+        complexity: Complexity,
+        increase: bool,
+    ) -> Result<bool, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if user_id != lobby.host {
+                    return Err(GameLogicError::NotHost);
+                }
+                Ok(lobby.config.complexity_weights.adjust(complexity, increase))
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    /// Moves the given round on to the next complexity, mixed after hard.
+    pub fn cycle_round_difficulty(
+        &mut self,
+        user_id: UserId,
+        round: usize,
+    ) -> Result<(), GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if user_id != lobby.host {
+                    return Err(GameLogicError::NotHost);
+                }
+                let Some(difficulty) = lobby.config.difficulty_plan.get_mut(round) else {
+                    return Ok(());
+                };
+                *difficulty = match difficulty {
+                    None => Some(Complexity::Easy),
+                    Some(Complexity::Easy) => Some(Complexity::Medium),
+                    Some(Complexity::Medium) => Some(Complexity::Hard),
+                    Some(Complexity::Hard) => None,
+                };
+                Ok(())
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    pub fn difficulty_plan(&self) -> Vec<Option<Complexity>> {
+        self.config().difficulty_plan.clone()
+    }
+
+    /// Checks whether `user_id` may write a card for the other teams.
+    pub fn can_add_custom_card(&self, user_id: UserId) -> Result<(), GameLogicError> {
+        let Room::Lobby(lobby) = self else {
+            return Err(GameLogicError::SettingsLocked);
+        };
+        if !lobby.teams.iter().any(|team| team.contains(&user_id)) {
+            return Err(GameLogicError::NotInTeam);
+        }
+        Ok(())
+    }
+
+    /// Adds a card written by `user_id`, returning how many their team wrote.
+    pub fn add_custom_card(
+        &mut self,
+        user_id: UserId,
+        word: Word,
+    ) -> Result<usize, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => lobby.add_custom_card(user_id, word),
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    pub fn toggle_describer_draws(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.describer_draws = !lobby.config.describer_draws;
+                Ok(lobby.config.describer_draws)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    pub fn toggle_taboo_words(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.use_taboo_words = !lobby.config.use_taboo_words;
+                Ok(lobby.config.use_taboo_words)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    pub fn toggle_public(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.public = !lobby.config.public;
+                Ok(lobby.config.public)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    pub fn toggle_captains(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.captains = !lobby.config.captains;
+                Ok(lobby.config.captains)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    /// The players of the lobby who are in a team, with their team's emoji,
+    /// for the host to pick captains from.
+    pub fn captain_candidates(&self) -> Vec<(String, User)> {
+        let Room::Lobby(lobby) = self else {
+            return Vec::new();
+        };
+        lobby
+            .teams
+            .iter()
+            .enumerate()
+            .flat_map(|(index, members)| {
+                members
+                    .iter()
+                    .filter_map(|member| lobby.players.get(member))
+                    .map(move |player| (get_team_emoji(index), player.clone()))
+            })
+            .collect()
     }
-}
 
-pub enum Room {
-    Lobby(NewRoom),
-    Playing(PlayingRoom),
-}
+    /// Makes `user_id` the captain of their team, returning the team.
+    pub fn make_captain(
+        &mut self,
+        host: UserId,
+        user_id: UserId,
+    ) -> Result<String, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if lobby.host != host {
+                    return Err(GameLogicError::NotHost);
+                }
+                let team_index = lobby
+                    .teams
+                    .iter()
+                    .position(|members| members.contains(&user_id))
+                    .ok_or(GameLogicError::NotInTeam)?;
+                lobby.captains[team_index] = Some(user_id);
+                Ok(get_team_emoji(team_index))
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
 
-pub struct WordGuessTry {
-    pub word: Word,
-    pub describing: User,
-    pub guessing: User,
-}
+    /// The captain of the playing team, who alone presses its ✅ and ⏩️
+    /// when the room has captains.
+    pub fn controller(&self) -> Option<User> {
+        let playing = self.get_started().ok()?;
+        if !playing.config.captains {
+            return None;
+        }
+        let team = &playing.teams[playing.turn as usize];
+        [&team.first, &team.second]
+            .into_iter()
+            .find(|player| player.id == team.captain)
+            .cloned()
+    }
 
-pub enum RoundStopState {
-    RoundFinished(String, User, u8, usize),
-    GameFinished(String),
-}
+    /// Checks that `user_id` may press ✅ and ⏩️ for the playing team.
+    pub fn check_control(&self, user_id: UserId) -> Result<(), GameLogicError> {
+        match self.controller() {
+            Some(captain) if self.device().unwrap_or(captain.id) != user_id => {
+                Err(GameLogicError::NotCaptain)
+            }
+            _ => Ok(()),
+        }
+    }
 
-impl Room {
-    pub fn new(
-        number_of_teams: usize,
-        number_of_rounds: usize,
-        round_duration: usize,
-        use_taboo_words: bool,
-    ) -> Self {
-        Room::Lobby(NewRoom::new(
-            number_of_teams,
-            number_of_rounds,
-            round_duration,
-            use_taboo_words,
-        ))
+    pub fn toggle_warm_up(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.warm_up = !lobby.config.warm_up;
+                Ok(lobby.config.warm_up)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
     }
 
-    pub fn join(&mut self, user: User) -> Result<(Vec<UserId>, usize), GameLogicError> {
+    pub fn toggle_balance_turns(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
         match self {
-            Room::Lobby(lobby) => lobby.join(user),
-            Room::Playing(_) => Err(GameLogicError::JoinAfterPlay),
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.balance_turns = !lobby.config.balance_turns;
+                Ok(lobby.config.balance_turns)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
         }
     }
 
-    pub fn join_team(
+    pub fn is_warming_up(&self) -> bool {
+        self.get_started()
+            .is_ok_and(|playing| playing.warm_up.is_some())
+    }
+
+    /// A word to warm up with, drawn from the room's seed like every other
+    /// word so replayed games deal it again.
+    pub fn warm_up_word(&mut self) -> Result<Word, GameLogicError> {
+        let playing = self.get_started_mut()?;
+        let mut rng = playing.rng.next();
+        Ok(get_random_word(
+            &mut rng,
+            None,
+            playing.config.complexity_weights,
+        ))
+    }
+
+    /// Every pair with its index, team name, describer and guesser for the
+    /// warm-up.
+    pub fn warm_up_pairs(&self) -> Vec<(usize, String, User, User)> {
+        let Ok(playing) = self.get_started() else {
+            return Vec::new();
+        };
+        playing
+            .teams
+            .iter()
+            .enumerate()
+            .map(|(index, team)| {
+                (
+                    index,
+                    team.name.clone(),
+                    team.get_describing_player(),
+                    team.get_guessing_player(),
+                )
+            })
+            .collect()
+    }
+
+    /// Marks the warm-up word of a pair as done and returns the team's name
+    /// and how many pairs are still warming up.
+    pub fn finish_warm_up_word(
         &mut self,
-        user_id: UserId,
         team_index: usize,
-    ) -> Result<Vec<UserId>, GameLogicError> {
+    ) -> Result<(String, usize), GameLogicError> {
+        let playing = self.get_playing_mut()?;
+        let warm_up = playing
+            .warm_up
+            .as_mut()
+            .ok_or(GameLogicError::NotWarmingUp)?;
+        if !warm_up.remove(&team_index) {
+            return Err(GameLogicError::NotWarmingUp);
+        }
+        Ok((playing.teams[team_index].name.clone(), warm_up.len()))
+    }
+
+    /// Ends the warm-up so the first round can start, returning who
+    /// describes first.
+    pub fn finish_warm_up(&mut self, user_id: UserId) -> Result<User, GameLogicError> {
+        let playing = self.get_playing_mut()?;
+        if playing.host != user_id {
+            return Err(GameLogicError::NotHost);
+        }
+        if playing.warm_up.take().is_none() {
+            return Err(GameLogicError::NotWarmingUp);
+        }
+        Ok(playing.get_describing_player())
+    }
+
+    pub fn toggle_pass_the_phone(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
         match self {
-            Room::Lobby(lobby) => lobby.join_team(user_id, team_index),
-            Room::Playing(_) => Err(GameLogicError::TeamChangeAfterPlay),
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.pass_the_phone = !lobby.config.pass_the_phone;
+                // A relay needs a phone for every describer
+                lobby.config.relay &= !lobby.config.pass_the_phone;
+                Ok(lobby.config.pass_the_phone)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
         }
     }
 
-    pub fn get_teams(&self) -> String {
+    /// Moves on to the next break between rounds, off after the longest one.
+    pub fn cycle_break(&mut self, user_id: UserId) -> Result<Option<u64>, GameLogicError> {
         match self {
-            Room::Lobby(lobby) => lobby.get_teams(),
-            Room::Playing(playing) => playing.get_teams(),
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.break_minutes = match lobby.config.break_minutes {
+                    None => Some(BREAK_MINUTES[0]),
+                    Some(minutes) => BREAK_MINUTES.iter().copied().find(|next| *next > minutes),
+                };
+                Ok(lobby.config.break_minutes)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
         }
     }
 
-    fn get_playing(&self) -> Result<&PlayingRoom, GameLogicError> {
+    /// Moves on to the next tie margin, off after the widest one.
+    pub fn cycle_tie_margin(&mut self, user_id: UserId) -> Result<Option<u64>, GameLogicError> {
         match self {
-            Room::Lobby(_) => Err(GameLogicError::IsNotPlaying),
-            Room::Playing(playing) => Ok(playing),
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.tie_margin_in_seconds = match lobby.config.tie_margin_in_seconds {
+                    None => Some(TIE_MARGINS[0]),
+                    Some(margin) => TIE_MARGINS.iter().copied().find(|next| *next > margin),
+                };
+                Ok(lobby.config.tie_margin_in_seconds)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
         }
     }
 
-    fn get_playing_mut(&mut self) -> Result<&mut PlayingRoom, GameLogicError> {
+    /// Moves on to the next co-op target, off after the largest one.
+    pub fn cycle_coop_target(&mut self, user_id: UserId) -> Result<Option<usize>, GameLogicError> {
         match self {
-            Room::Lobby(_) => Err(GameLogicError::IsNotPlaying),
-            Room::Playing(playing) => Ok(playing),
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.coop_target = match lobby.config.coop_target {
+                    None => Some(COOP_TARGETS[0]),
+                    Some(target) => COOP_TARGETS.iter().copied().find(|next| *next > target),
+                };
+                Ok(lobby.config.coop_target)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
         }
     }
 
-    pub fn get_all_players(&self) -> Vec<UserId> {
+    pub fn toggle_relay(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
         match self {
-            Room::Lobby(lobby) => lobby.players.clone().into_keys().collect::<Vec<_>>(),
-            Room::Playing(playing) => playing
-                .teams
-                .iter()
-                .map(|team| vec![team.first.id, team.second.id])
-                .collect::<Vec<Vec<_>>>()
-                .concat(),
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.relay = !lobby.config.relay;
+                lobby.config.pass_the_phone &= !lobby.config.relay;
+                Ok(lobby.config.relay)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
         }
     }
 
-    pub fn play(&mut self) -> Result<User, GameLogicError> {
+    pub fn toggle_team_lock(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.teams_locked = !lobby.teams_locked;
+                Ok(lobby.teams_locked)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
+
+    pub fn toggle_handicap(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
         match self {
-            Room::Lobby(new_game) => {
-                new_game.check_teams_ready()?;
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.handicap = !lobby.config.handicap;
+                Ok(lobby.config.handicap)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
 
-                let playing = PlayingRoom::from(std::mem::take(new_game));
+    pub fn toggle_alert_everyone(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.alert_everyone = !lobby.config.alert_everyone;
+                Ok(lobby.config.alert_everyone)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
 
-                *self = Room::Playing(playing);
+    pub fn alerts_everyone(&self) -> bool {
+        self.config().alert_everyone
+    }
 
-                Ok(self.get_playing().unwrap().get_describing_player())
+    pub fn toggle_audio_alerts(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) => {
+                if lobby.host != user_id {
+                    return Err(GameLogicError::NotHost);
+                }
+                lobby.config.audio_alerts = !lobby.config.audio_alerts;
+                Ok(lobby.config.audio_alerts)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
             }
-            Room::Playing(_) => Err(GameLogicError::AlreadyPlaying),
         }
     }
 
-    pub fn start_round(&mut self) -> Result<WordGuessTry, GameLogicError> {
-        let playing = self.get_playing_mut()?;
+    pub fn audio_alerts(&self) -> bool {
+        self.config().audio_alerts
+    }
 
-        playing.instant = Instant::now();
+    pub fn toggle_animated_timer(&mut self, user_id: UserId) -> Result<bool, GameLogicError> {
+        match self {
+            Room::Lobby(lobby) if lobby.host != user_id => Err(GameLogicError::NotHost),
+            Room::Lobby(lobby) if lobby.group_chat.is_none() => Err(GameLogicError::NoGroupChat),
+            Room::Lobby(lobby) => {
+                lobby.config.animated_timer = !lobby.config.animated_timer;
+                Ok(lobby.config.animated_timer)
+            }
+            Room::Playing(_) | Room::Paused(_) | Room::Finished(_) => {
+                Err(GameLogicError::SettingsLocked)
+            }
+        }
+    }
 
-        Ok(WordGuessTry {
-            word: get_random_word(),
-            describing: playing.get_describing_player(),
-            guessing: playing.get_guessing_player(),
-        })
+    pub fn animated_timer(&self) -> bool {
+        self.config().animated_timer
     }
 
-    pub fn correct(&mut self) -> Result<WordGuessTry, GameLogicError> {
-        let playing = self.get_playing_mut()?;
+    /// The players a time alert goes to, the pair describing and guessing
+    /// right now unless the room alerts everyone.
+    pub fn time_alert_recipients(&self) -> Vec<UserId> {
+        if self.alerts_everyone() || self.device().is_some() {
+            return self.get_all_players();
+        }
 
-        playing.next();
-        playing.instant = Instant::now();
+        match self.get_playing() {
+            Ok(playing) if playing.round_in_progress => vec![
+                playing.get_describing_player().id,
+                playing.get_guessing_player().id,
+            ],
+            _ => Vec::new(),
+        }
+    }
 
-        Ok(WordGuessTry {
-            word: get_random_word(),
-            describing: playing.get_describing_player(),
-            guessing: playing.get_guessing_player(),
-        })
+    /// The team that waits longer to skip this round.
+    pub fn handicapped_team(&self) -> Option<String> {
+        let playing = self.get_playing().ok()?;
+        Some(playing.teams[playing.handicapped_team?].name.clone())
+    }
+
+    /// How long the describing team waits before it can skip a word.
+    pub fn skip_cool_down(&self) -> Duration {
+        let handicapped = self
+            .get_playing()
+            .is_ok_and(|playing| playing.handicapped_team == Some(playing.turn as usize));
+        let seconds = if handicapped {
+            SKIP_COOL_DOWN_IN_SECONDS + HANDICAP_IN_SECONDS
+        } else {
+            SKIP_COOL_DOWN_IN_SECONDS
+        };
+        Duration::from_secs(seconds as u64)
+    }
+
+    pub fn word_time_limit(&self) -> Option<Duration> {
+        self.config().word_time_limit.map(Duration::from_secs)
     }
 
-    pub fn skip(&self) -> Result<WordGuessTry, GameLogicError> {
+    /// The words of the last round `user_id` may dispute, those the other
+    /// teams guessed, with their index.
+    pub fn disputable_words(
+        &self,
+        user_id: UserId,
+    ) -> Result<Vec<(usize, String)>, GameLogicError> {
         let playing = self.get_playing()?;
+        if playing.round_in_progress {
+            return Err(GameLogicError::RoundInProgress);
+        }
+        let team = playing
+            .team_of(user_id)
+            .ok_or(GameLogicError::NotJoinedToRoom)?;
 
-        Ok(WordGuessTry {
-            word: get_random_word(),
-            describing: playing.get_describing_player(),
-            guessing: playing.get_guessing_player(),
-        })
+        Ok(playing
+            .round_words
+            .iter()
+            .enumerate()
+            .filter(|(_, word)| word.team != team && !word.voided)
+            .map(|(index, word)| {
+                (
+                    index,
+                    format!("{} {}", playing.teams[word.team].name, word.text),
+                )
+            })
+            .collect())
     }
 
-    pub fn push_to_message_stack(
+    /// Votes to void a word of the last round. The host's vote settles it,
+    /// otherwise a majority of the players outside the word's team does.
+    pub fn dispute_word(
         &mut self,
-        chat_id: ChatId,
-        message_id: MessageId,
-    ) -> Result<(), GameLogicError> {
+        user_id: UserId,
+        index: usize,
+    ) -> Result<DisputeOutcome, GameLogicError> {
         let playing = self.get_playing_mut()?;
-        playing.message_stack.push((chat_id, message_id));
-        Ok(())
+        if playing.round_in_progress {
+            return Err(GameLogicError::RoundInProgress);
+        }
+        let team = playing
+            .team_of(user_id)
+            .ok_or(GameLogicError::NotJoinedToRoom)?;
+        let word = playing
+            .round_words
+            .get(index)
+            .filter(|word| word.team != team && !word.voided)
+            .cloned()
+            .ok_or(GameLogicError::NotDisputable)?;
+        let team_name = playing.teams[word.team].name.clone();
+
+        let votes = playing.disputes.entry(index).or_default();
+        votes.insert(user_id);
+        let votes = votes.len();
+        let voters = (playing.teams.len() - 1) * 2;
+        let needed = voters / 2 + 1;
+
+        if user_id == playing.host || votes >= needed {
+            playing.void_word(index);
+            return Ok(DisputeOutcome::Voided {
+                word: word.text,
+                team: team_name,
+            });
+        }
+
+        Ok(DisputeOutcome::Voted {
+            word: word.text,
+            team: team_name,
+            votes,
+            needed,
+        })
     }
 
-    pub fn get_message_stack_top(&self) -> Result<Option<(ChatId, MessageId)>, GameLogicError> {
-        let playing = self.get_playing()?;
-        Ok(playing.message_stack.last().copied())
+    pub fn get_word_spectators(&self) -> Vec<UserId> {
+        if self.device().is_some() {
+            return Vec::new();
+        }
+
+        self.get_playing()
+            .map(PlayingRoom::get_word_spectators)
+            .unwrap_or_default()
     }
+}
 
-    pub fn stop_round(&mut self) -> Result<RoundStopState, GameLogicError> {
-        let playing = self.get_playing_mut()?;
-        playing.update_time();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOST: UserId = UserId(1);
+
+    fn player(id: u64) -> User {
+        User {
+            id: UserId(id),
+            is_bot: false,
+            first_name: format!("Player {}", id),
+            last_name: None,
+            username: None,
+            language_code: None,
+            is_premium: false,
+            added_to_attachment_menu: false,
+        }
+    }
+
+    fn lobby() -> Room {
+        let mut room = Room::new(RoomConfig::new(2, 1, 60, false), None, HOST);
+        for id in 1..=4 {
+            room.join(player(id), ChatId(id as i64)).unwrap();
+            room.join_team(UserId(id), (id as usize - 1) / 2).unwrap();
+        }
+        room
+    }
+
+    fn playing() -> Room {
+        let mut room = lobby();
+        room.play().unwrap();
+        room
+    }
 
-        let results = playing.get_teams();
+    fn paused() -> Room {
+        let mut room = playing();
+        room.pause().unwrap();
+        room
+    }
+
+    fn parked() -> Room {
+        let mut room = playing();
+        assert!(room.park());
+        room
+    }
+
+    fn finished() -> Room {
+        let mut room = playing();
+        room.start_round().unwrap();
+        assert!(matches!(
+            room.stop_round(),
+            Ok(RoundStopState::GameFinished(_))
+        ));
+        room
+    }
+
+    fn state(room: &Room) -> &'static str {
+        match room {
+            Room::Lobby(_) => "lobby",
+            Room::Playing(_) => "playing",
+            Room::Paused(paused) if paused.parked => "parked",
+            Room::Paused(_) => "paused",
+            Room::Finished(_) => "finished",
+        }
+    }
 
-        playing.round += 1;
-        if playing.round as usize == playing.number_of_rounds {
-            playing.message_stack.clear();
-            Ok(RoundStopState::GameFinished(results))
+    type Step = fn(&mut Room) -> Result<(), GameLogicError>;
+
+    const PLAY: Step = |room| room.play().map(drop);
+    const REMATCH: Step = |room| room.rematch(false).map(drop);
+    const PAUSE: Step = |room| room.pause();
+    const RESUME: Step = |room| room.resume().map(drop);
+    const PARK: Step = |room| {
+        if room.park() {
+            Ok(())
         } else {
-            Ok(RoundStopState::RoundFinished(
-                results,
-                playing.get_describing_player(),
-                playing.round + 1,
-                playing.number_of_rounds,
-            ))
+            Err(room.state_error())
+        }
+    };
+    const UNPARK: Step = |room| room.unpark().map(drop);
+    const STOP_ROUND: Step = |room| room.stop_round().map(drop);
+
+    fn assert_illegal(make: fn() -> Room, steps: &[(&str, Step, GameLogicError)]) {
+        for (name, step, expected) in steps {
+            let mut room = make();
+            let before = state(&room);
+            assert_eq!(
+                step(&mut room).err().as_ref(),
+                Some(expected),
+                "{} from {}",
+                name,
+                before
+            );
+            assert_eq!(
+                state(&room),
+                before,
+                "{} from {} changed the state",
+                name,
+                before
+            );
         }
     }
 
-    pub fn round_duration(&self) -> usize {
-        match self {
-            Room::Lobby(lobby) => lobby.round_duration,
-            Room::Playing(playing) => playing.round_duration,
+    #[test]
+    fn lobby_only_plays() {
+        use GameLogicError::IsNotPlaying;
+        assert_illegal(
+            lobby,
+            &[
+                ("rematch", REMATCH, IsNotPlaying),
+                ("pause", PAUSE, IsNotPlaying),
+                ("resume", RESUME, IsNotPlaying),
+                ("park", PARK, IsNotPlaying),
+                ("unpark", UNPARK, IsNotPlaying),
+                ("stop round", STOP_ROUND, IsNotPlaying),
+            ],
+        );
+    }
+
+    #[test]
+    fn playing_room_cant_start_over() {
+        use GameLogicError::{AlreadyPlaying, IsNotPaused, NoRoundInProgress};
+        assert_illegal(
+            playing,
+            &[
+                ("play", PLAY, AlreadyPlaying),
+                ("rematch", REMATCH, AlreadyPlaying),
+                ("resume", RESUME, IsNotPaused),
+                ("unpark", UNPARK, AlreadyPlaying),
+                ("stop round", STOP_ROUND, NoRoundInProgress),
+            ],
+        );
+    }
+
+    #[test]
+    fn paused_room_only_resumes() {
+        use GameLogicError::IsPaused;
+        let steps = [
+            ("play", PLAY, IsPaused),
+            ("rematch", REMATCH, IsPaused),
+            ("pause", PAUSE, IsPaused),
+            ("park", PARK, IsPaused),
+            ("stop round", STOP_ROUND, IsPaused),
+        ];
+        assert_illegal(paused, &steps);
+        assert_illegal(paused, &[("unpark", UNPARK, IsPaused)]);
+        assert_illegal(parked, &steps);
+        assert_illegal(parked, &[("resume", RESUME, IsPaused)]);
+    }
+
+    #[test]
+    fn finished_room_only_rematches() {
+        use GameLogicError::IsFinished;
+        assert_illegal(
+            finished,
+            &[
+                ("play", PLAY, IsFinished),
+                ("pause", PAUSE, IsFinished),
+                ("resume", RESUME, IsFinished),
+                ("park", PARK, IsFinished),
+                ("unpark", UNPARK, IsFinished),
+                ("stop round", STOP_ROUND, IsFinished),
+            ],
+        );
+    }
+
+    #[test]
+    fn legal_transitions_move_on() {
+        let mut room = lobby();
+        assert_eq!(state(&room), "lobby");
+        room.play().unwrap();
+        assert_eq!(state(&room), "playing");
+        room.pause().unwrap();
+        assert_eq!(state(&room), "paused");
+        room.resume().unwrap();
+        assert_eq!(state(&room), "playing");
+        assert!(room.park());
+        assert_eq!(state(&room), "parked");
+        room.unpark().unwrap();
+        assert_eq!(state(&room), "playing");
+        room.start_round().unwrap();
+        room.stop_round().unwrap();
+        assert_eq!(state(&room), "finished");
+        room.rematch(true).unwrap();
+        assert_eq!(state(&room), "playing");
+    }
+
+    fn seeded_teams(seed: u64) -> Vec<(usize, UserId, UserId)> {
+        let mut room = Room::new(RoomConfig::new(4, 1, 60, false), None, HOST);
+        room.set_seed(seed);
+        for id in 1..=8 {
+            room.join(player(id), ChatId(id as i64)).unwrap();
+            room.join_team(UserId(id), (id as usize - 1) / 2).unwrap();
+        }
+        room.play().unwrap();
+        let Room::Playing(playing) = room else {
+            panic!("the game didn't start");
+        };
+        playing
+            .teams
+            .iter()
+            .map(|team| (team.index, team.first.id, team.second.id))
+            .collect()
+    }
+
+    #[test]
+    fn balancing_turns_have_a_deadline() {
+        let mut room = lobby();
+        if let Room::Lobby(lobby) = &mut room {
+            lobby.config.balance_turns = true;
+            lobby.config.round_duration_in_seconds = 0;
         }
+        room.play().unwrap();
+        room.start_round().unwrap();
+        room.correct().unwrap();
+        assert!(room.extend_for_balance().is_some());
+
+        let idle = Duration::from_secs(3600);
+        assert!(room.anomaly(Duration::from_secs(60), idle).is_none());
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(matches!(
+            room.anomaly(Duration::ZERO, idle),
+            Some(Anomaly::OverdueBalancingTurn)
+        ));
+        assert!(room.stop_round().is_ok());
     }
 
-    pub fn use_taboo_words(&self) -> bool {
-        match self {
-            Room::Lobby(lobby) => lobby.use_taboo_words,
-            Room::Playing(playing) => playing.use_taboo_words,
+    #[test]
+    fn same_seed_deals_same_teams() {
+        for seed in 0..10 {
+            assert_eq!(seeded_teams(seed), seeded_teams(seed), "seed {}", seed);
         }
     }
 }
@@ -0,0 +1,42 @@
+use std::sync::OnceLock;
+
+use dashmap::DashMap;
+use tokio::sync::broadcast;
+
+use crate::room::{Room, RoomId};
+
+const CHANNEL_CAPACITY: usize = 16;
+
+static SUBSCRIPTIONS: OnceLock<DashMap<RoomId, broadcast::Sender<String>>> = OnceLock::new();
+
+fn subscriptions() -> &'static DashMap<RoomId, broadcast::Sender<String>> {
+    SUBSCRIPTIONS.get_or_init(DashMap::new)
+}
+
+/// Returns a receiver of JSON encoded `RoomScore` updates for the room.
+pub fn subscribe(room_id: RoomId) -> broadcast::Receiver<String> {
+    subscriptions()
+        .entry(room_id)
+        .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+        .subscribe()
+}
+
+/// Pushes the current score of the room to its live scoreboards, if anyone is
+/// watching. Finished rooms close their streams.
+pub fn publish(room_id: RoomId, room: &Room) {
+    let Some(sender) = subscriptions().get(&room_id).map(|sender| sender.clone()) else {
+        return;
+    };
+
+    match serde_json::to_string(&room.score()) {
+        Ok(json) => {
+            // No receivers left is not an error worth reporting
+            let _ = sender.send(json);
+        }
+        Err(err) => log::warn!("Can not serialize score of {:?}: {}", room_id, err),
+    }
+
+    if matches!(room, Room::Finished(_)) || sender.receiver_count() == 0 {
+        subscriptions().remove(&room_id);
+    }
+}
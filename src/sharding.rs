@@ -0,0 +1,208 @@
+//! Lets several bot instances share the traffic of one bot. Every room lives
+//! in exactly one instance, picked by its ID, and everything else a user does
+//! is handled by the instance picked by their user ID. Telegram delivers each
+//! update to whichever instance is behind the webhook URL, which forwards it
+//! to its owner unless it owns it itself.
+//!
+//! Configured with `JIGARPICH_SHARDS`, a comma separated list of the webhook
+//! URLs of all instances, `JIGARPICH_SHARD_INDEX`, the position of this
+//! instance in that list, and `JIGARPICH_WEBHOOK_SECRET`, shared by all of
+//! them. Without `JIGARPICH_SHARDS` a single instance owns everything.
+//!
+//! A bare `/start` isn't about any room, but the player may have games in
+//! every instance, so the instance handling it asks all the others to put
+//! the player back into theirs.
+
+use std::{env, sync::OnceLock};
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use teloxide::{
+    prelude::*,
+    types::{UpdateKind, User},
+};
+
+use crate::{
+    callback_query_command::parse_command, frontend::Frontend, game, room::RoomId, webhook,
+    HandlerResult, Rooms,
+};
+
+const SECRET_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+
+/// Appended to the webhook paths of the instances for the rejoin requests.
+const REJOIN_PATH: &str = "/rejoin";
+
+struct Shards {
+    index: usize,
+    urls: Vec<String>,
+}
+
+static SHARDS: OnceLock<Option<Shards>> = OnceLock::new();
+
+static WEBHOOK_SECRET: OnceLock<Option<String>> = OnceLock::new();
+
+fn shards() -> Option<&'static Shards> {
+    SHARDS
+        .get_or_init(|| {
+            let urls = env::var("JIGARPICH_SHARDS")
+                .ok()?
+                .split(',')
+                .map(|url| url.trim().to_owned())
+                .filter(|url| !url.is_empty())
+                .collect::<Vec<_>>();
+            let index = env::var("JIGARPICH_SHARD_INDEX")
+                .ok()
+                .and_then(|index| index.parse().ok())
+                .expect("JIGARPICH_SHARD_INDEX must be set along with JIGARPICH_SHARDS");
+            assert!(index < urls.len(), "JIGARPICH_SHARD_INDEX is out of range");
+            // The instances tell each other apart from strangers by the secret
+            env::var("JIGARPICH_WEBHOOK_SECRET")
+                .expect("JIGARPICH_WEBHOOK_SECRET must be set along with JIGARPICH_SHARDS");
+
+            Some(Shards { index, urls })
+        })
+        .as_ref()
+}
+
+/// The secret Telegram and the other instances send along with each update.
+pub fn webhook_secret() -> Option<String> {
+    shards();
+    WEBHOOK_SECRET
+        .get_or_init(|| env::var("JIGARPICH_WEBHOOK_SECRET").ok())
+        .clone()
+}
+
+pub fn owns_room(room_id: RoomId) -> bool {
+    shards().is_none_or(|shards| room_id.0 as usize % shards.urls.len() == shards.index)
+}
+
+/// The room an update is about, if any.
+fn room_of(update: &Update) -> Option<RoomId> {
+    match &update.kind {
+        UpdateKind::CallbackQuery(q) => parse_command(q.data.clone()?).map(|(room_id, _)| room_id),
+        UpdateKind::InlineQuery(q) => q.query.trim().parse().ok().map(RoomId),
+        UpdateKind::Message(msg) => {
+            let mut words = msg.text()?.split_whitespace();
+            let command = words.next()?.split('@').next()?;
+            if command != "/join" && command != "/start" {
+                return None;
+            }
+            words.next()?.parse().ok().map(RoomId)
+        }
+        _ => None,
+    }
+}
+
+fn owner(shards: &Shards, update: &Update) -> usize {
+    match (room_of(update), update.user()) {
+        (Some(room_id), _) => room_id.0 as usize % shards.urls.len(),
+        (None, Some(user)) => (user.id.0 % shards.urls.len() as u64) as usize,
+        (None, None) => shards.index,
+    }
+}
+
+pub fn is_foreign(update: Update) -> bool {
+    shards().is_some_and(|shards| owner(shards, &update) != shards.index)
+}
+
+/// Hands an update over to the instance owning it.
+pub async fn forward(update: Update) -> HandlerResult {
+    let Some(shards) = shards() else {
+        return Ok(());
+    };
+
+    let mut request = reqwest::Client::new()
+        .post(&shards.urls[owner(shards, &update)])
+        .json(&update);
+    if let Some(secret) = webhook_secret() {
+        request = request.header(SECRET_HEADER, secret);
+    }
+
+    if let Err(err) = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+    {
+        log::warn!("Can not forward update {}: {}", update.id, err);
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
+struct RejoinState<F> {
+    frontend: F,
+    rooms: Rooms,
+}
+
+/// The endpoint the other instances send rejoin requests to, if there are
+/// other instances.
+pub fn router<F: Frontend>(frontend: F, rooms: Rooms) -> Option<Router> {
+    shards()?;
+    let path = webhook::join_path(&webhook::local_path(), REJOIN_PATH);
+    Some(
+        Router::new()
+            .route(&path, post(rejoin::<F>))
+            .with_state(RejoinState { frontend, rooms }),
+    )
+}
+
+async fn rejoin<F: Frontend>(
+    State(state): State<RejoinState<F>>,
+    headers: HeaderMap,
+    Json(user): Json<User>,
+) -> Result<Json<bool>, StatusCode> {
+    let secret = headers.get(SECRET_HEADER).map(|value| value.as_bytes());
+    if secret != webhook_secret().as_deref().map(str::as_bytes) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    match game::handle_rejoin(state.frontend, state.rooms, user).await {
+        Ok(rejoined) => Ok(Json(rejoined)),
+        Err(err) => {
+            log::warn!("Can not rejoin a player: {}", err);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Asks the other instances to put a returning player back into the games
+/// they own, returns whether any of them did.
+pub async fn rejoin_elsewhere(user: &User) -> bool {
+    let Some(shards) = shards() else {
+        return false;
+    };
+
+    let client = reqwest::Client::new();
+    let requests = shards
+        .urls
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != shards.index)
+        .map(|(_, url)| {
+            let mut request = client.post(webhook::join_path(url, REJOIN_PATH)).json(user);
+            if let Some(secret) = webhook_secret() {
+                request = request.header(SECRET_HEADER, secret);
+            }
+            async move {
+                let response = match request.send().await {
+                    Ok(response) => response.error_for_status(),
+                    Err(err) => Err(err),
+                };
+                match response {
+                    Ok(response) => response.json::<bool>().await.unwrap_or_default(),
+                    Err(err) => {
+                        log::warn!("Can not ask another instance to rejoin: {}", err);
+                        false
+                    }
+                }
+            }
+        });
+    futures::future::join_all(requests)
+        .await
+        .into_iter()
+        .any(|rejoined| rejoined)
+}
@@ -0,0 +1,377 @@
+//! Drives the game engine through synthetic games to check its invariants and
+//! measure its throughput without talking to Telegram. Run the bot with
+//! `JIGARPICH_SIM=<number of games>` to start it instead of the bot.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    env,
+    future::Future,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc, Mutex as SyncMutex,
+    },
+    time::Instant,
+};
+
+use dashmap::DashMap;
+use rand::{seq::SliceRandom, Rng};
+use teloxide::types::{ChatId, MessageId, User, UserId};
+use tokio::sync::Mutex;
+
+use crate::{
+    callback_query_command::CbQueryCommand,
+    frontend::{Buttons, Frontend},
+    game,
+    room::{Room, RoomConfig, RoomId},
+    Rooms,
+};
+
+/// A frontend that drops every message, only remembering who got the cards
+/// of the words to describe.
+#[derive(Clone, Default)]
+struct SimFrontend {
+    next_message_id: Arc<AtomicI32>,
+    word_cards: Arc<SyncMutex<Vec<ChatId>>>,
+}
+
+impl SimFrontend {
+    fn sent(&self) -> impl Future<Output = Result<(), Infallible>> + Send {
+        async { Ok(()) }
+    }
+
+    fn take_word_cards(&self) -> Vec<ChatId> {
+        std::mem::take(&mut self.word_cards.lock().unwrap())
+    }
+}
+
+impl Frontend for SimFrontend {
+    type Error = Infallible;
+
+    fn send_card_to_topic(
+        &self,
+        to: ChatId,
+        _topic: Option<i32>,
+        _text: String,
+        buttons: Buttons,
+    ) -> impl Future<Output = Result<(ChatId, MessageId), Self::Error>> + Send {
+        if buttons
+            .iter()
+            .flatten()
+            .any(|button| matches!(button.command, CbQueryCommand::Correct))
+        {
+            self.word_cards.lock().unwrap().push(to);
+        }
+        let message_id = MessageId(self.next_message_id.fetch_add(1, Ordering::Relaxed));
+        async move { Ok((to, message_id)) }
+    }
+
+    fn edit_buttons(
+        &self,
+        _chat_id: ChatId,
+        _message_id: MessageId,
+        _buttons: Buttons,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.sent()
+    }
+
+    fn edit_text(
+        &self,
+        _chat_id: ChatId,
+        _message_id: MessageId,
+        _text: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.sent()
+    }
+
+    fn edit_card(
+        &self,
+        _chat_id: ChatId,
+        _message_id: MessageId,
+        _text: String,
+        _buttons: Buttons,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.sent()
+    }
+
+    fn pin_message(
+        &self,
+        _chat_id: ChatId,
+        _message_id: MessageId,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.sent()
+    }
+
+    fn prompt_to_topic(
+        &self,
+        _to: ChatId,
+        _topic: Option<i32>,
+        _text: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.sent()
+    }
+
+    fn prompt_with_spoiler(
+        &self,
+        _to: ChatId,
+        _text: String,
+        _spoiler: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.sent()
+    }
+
+    async fn join_link(&self, _room_id: RoomId) -> Result<Option<String>, Self::Error> {
+        Ok(None)
+    }
+
+    fn send_image(
+        &self,
+        _to: ChatId,
+        _topic: Option<i32>,
+        _png: Vec<u8>,
+        _caption: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.sent()
+    }
+
+    fn send_sound(
+        &self,
+        _to: ChatId,
+        _topic: Option<i32>,
+        _ogg: Vec<u8>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.sent()
+    }
+}
+
+fn player(id: u64) -> User {
+    User {
+        id: UserId(id),
+        is_bot: false,
+        first_name: format!("Player {}", id),
+        last_name: None,
+        username: None,
+        language_code: None,
+        is_premium: false,
+        added_to_attachment_menu: false,
+    }
+}
+
+/// The time each team spent describing, streak bonuses added back.
+fn team_times(room: &Room) -> Vec<f64> {
+    let status = serde_json::to_value(room.status()).unwrap();
+    status["teams"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|team| {
+            team["time_in_seconds"].as_f64().unwrap() + team["bonus_in_seconds"].as_f64().unwrap()
+        })
+        .collect()
+}
+
+fn check_monotonic(room_id: RoomId, before: &[f64], after: &[f64]) {
+    for (before, after) in before.iter().zip(after) {
+        assert!(
+            after >= before,
+            "Team time went back in room {:?}: {} -> {}",
+            room_id,
+            before,
+            after
+        );
+    }
+}
+
+/// Plays one game with random settings and moves, returns how many moves it
+/// took.
+async fn play_game(frontend: &SimFrontend, rooms: &Rooms, room_id: RoomId, seed: u64) -> usize {
+    let mut rng = rand::thread_rng();
+    let number_of_teams = rng.gen_range(2..=7);
+    let number_of_rounds = rng.gen_range(1..=3);
+    let host = UserId(seed * 100);
+    let config = RoomConfig::new(
+        number_of_teams,
+        number_of_rounds,
+        rng.gen_range(30..=300),
+        rng.gen(),
+    );
+    let mut room = Room::new(config, None, host);
+    room.set_seed(seed);
+    rooms.insert(room_id, Mutex::new(room));
+
+    let mut players = (0..number_of_teams as u64 * 2)
+        .map(|i| player(seed * 100 + i))
+        .collect::<Vec<_>>();
+    players.shuffle(&mut rng);
+    for user in &players {
+        game::handle_join_command(
+            frontend.clone(),
+            user.id.into(),
+            rooms.clone(),
+            user.clone(),
+            room_id,
+        )
+        .await
+        .unwrap();
+    }
+
+    {
+        let entry = rooms.get(&room_id).unwrap();
+        let mut room = entry.lock().await;
+        for (i, user) in players.iter().enumerate() {
+            game::handle_team_join(
+                frontend.clone(),
+                rooms.clone(),
+                &mut room,
+                room_id,
+                user.clone(),
+                i / 2,
+            )
+            .await
+            .unwrap();
+        }
+        game::handle_play(&mut room, room_id, frontend.clone(), players[0].clone())
+            .await
+            .unwrap();
+    }
+
+    let team_of = players
+        .iter()
+        .enumerate()
+        .map(|(i, user)| (ChatId::from(user.id), i / 2))
+        .collect::<HashMap<_, _>>();
+    let mut turns = HashMap::<ChatId, usize>::new();
+    let mut moves = 0;
+
+    for round in 0..number_of_rounds {
+        {
+            let entry = rooms.get(&room_id).unwrap();
+            let mut room = entry.lock().await;
+            let mut times = team_times(&room);
+
+            frontend.take_word_cards();
+            game::handle_start_round(rooms.clone(), &mut room, room_id, frontend.clone())
+                .await
+                .unwrap();
+            // Later rounds continue the turn the previous one ran out in
+            let word_cards = frontend.take_word_cards();
+            if round == 0 {
+                for describing in word_cards {
+                    *turns.entry(describing).or_default() += 1;
+                }
+            }
+
+            for _ in 0..rng.gen_range(0..30) {
+                moves += 1;
+                let correct = rng.gen_bool(0.7);
+                if correct {
+                    game::handle_correct(rooms.clone(), &mut room, room_id, frontend.clone())
+                        .await
+                        .unwrap();
+                } else {
+                    game::handle_skip(rooms.clone(), &mut room, room_id, frontend.clone())
+                        .await
+                        .unwrap();
+                }
+
+                let word_cards = frontend.take_word_cards();
+                assert_eq!(
+                    word_cards.len(),
+                    1,
+                    "Expected one new word in {:?}",
+                    room_id
+                );
+                if correct {
+                    *turns.entry(word_cards[0]).or_default() += 1;
+                }
+
+                let new_times = team_times(&room);
+                check_monotonic(room_id, &times, &new_times);
+                times = new_times;
+            }
+        }
+
+        game::end_round(rooms.clone(), room_id, frontend.clone()).await;
+    }
+
+    {
+        let entry = rooms.get(&room_id).unwrap();
+        assert!(
+            entry.lock().await.is_finished(),
+            "Game in {:?} didn't finish after its last round",
+            room_id
+        );
+    }
+    rooms.remove(&room_id);
+
+    // Teams take turns one after another and both players of a team take
+    // turns describing
+    let mut team_turns = vec![0; number_of_teams];
+    for (chat_id, turns) in &turns {
+        team_turns[team_of[chat_id]] += turns;
+    }
+    let (fewest, most) = (
+        team_turns.iter().min().unwrap(),
+        team_turns.iter().max().unwrap(),
+    );
+    assert!(
+        most - fewest <= 1,
+        "Unfair turns between teams in {:?}: {:?}",
+        room_id,
+        team_turns
+    );
+    for team in players.chunks(2) {
+        let first = turns.get(&team[0].id.into()).copied().unwrap_or_default();
+        let second = turns.get(&team[1].id.into()).copied().unwrap_or_default();
+        assert!(
+            first.abs_diff(second) <= 1,
+            "Unfair turns within a team in {:?}: {} and {}",
+            room_id,
+            first,
+            second
+        );
+    }
+
+    moves
+}
+
+/// Plays `games` games one after another, returns how many moves they took.
+async fn play_games(games: u64) -> usize {
+    let frontend = SimFrontend::default();
+    let rooms: Rooms = Arc::new(DashMap::new());
+    let mut moves = 0;
+    for game in 0..games {
+        moves += play_game(&frontend, &rooms, RoomId(game as u32), game + 1).await;
+    }
+    moves
+}
+
+/// Plays `JIGARPICH_SIM` games one after another and reports the throughput.
+pub async fn run() {
+    let games = env::var("JIGARPICH_SIM")
+        .ok()
+        .and_then(|games| games.parse().ok())
+        .unwrap_or(1000);
+
+    let started_at = Instant::now();
+    let moves = play_games(games).await;
+
+    let elapsed = started_at.elapsed().as_secs_f64();
+    println!(
+        "Played {} games with {} moves in {:.2}s ({:.0} games/s, {:.0} moves/s)",
+        games,
+        moves,
+        elapsed,
+        games as f64 / elapsed,
+        moves as f64 / elapsed
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn simulated_games_keep_the_invariants() {
+        // Every game checks the invariants as it goes
+        super::play_games(20).await;
+    }
+}
@@ -0,0 +1,43 @@
+//! Short voice messages rooms can get along with the last time alert and at
+//! the end of each round, for tables playing with their phones face down.
+//! The files are read from `JIGARPICH_WARNING_SOUND` and
+//! `JIGARPICH_BUZZER_SOUND`, both OGG files encoded with Opus as Telegram
+//! expects for voice messages.
+
+use std::{env, fs, sync::OnceLock};
+
+#[derive(Clone, Copy)]
+pub enum Sound {
+    /// Played a few seconds before the round ends.
+    Warning,
+    /// Played when the round ends.
+    Buzzer,
+}
+
+static WARNING: OnceLock<Option<Vec<u8>>> = OnceLock::new();
+
+static BUZZER: OnceLock<Option<Vec<u8>>> = OnceLock::new();
+
+fn read(variable: &str) -> Option<Vec<u8>> {
+    let path = env::var(variable).ok()?;
+    match fs::read(&path) {
+        Ok(sound) => Some(sound),
+        Err(err) => {
+            log::warn!("Can not read the sound {}: {}", path, err);
+            None
+        }
+    }
+}
+
+pub fn get(sound: Sound) -> Option<Vec<u8>> {
+    match sound {
+        Sound::Warning => WARNING.get_or_init(|| read("JIGARPICH_WARNING_SOUND")),
+        Sound::Buzzer => BUZZER.get_or_init(|| read("JIGARPICH_BUZZER_SOUND")),
+    }
+    .clone()
+}
+
+/// Whether the bot has any sound to send, so rooms can turn audio alerts on.
+pub fn available() -> bool {
+    get(Sound::Warning).is_some() || get(Sound::Buzzer).is_some()
+}
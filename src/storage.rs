@@ -0,0 +1,110 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    hash::Hash,
+    path::{Path, PathBuf},
+};
+
+use dashmap::DashMap;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// A concurrent map mirrored to `$JIGARPICH_DATA_DIR/<name>.json` on every
+/// write. Without a data directory it only lives in memory.
+pub struct Store<K, V> {
+    path: Option<PathBuf>,
+    entries: DashMap<K, V>,
+}
+
+impl<K, V> Store<K, V>
+where
+    K: Eq + Hash + Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    pub fn open(name: &str) -> Self {
+        let path = env::var("JIGARPICH_DATA_DIR")
+            .ok()
+            .map(|dir| Path::new(&dir).join(format!("{}.json", name)));
+
+        let entries = path
+            .as_ref()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(
+                |data| match serde_json::from_slice::<HashMap<K, V>>(&data) {
+                    Ok(entries) => Some(entries.into_iter().collect()),
+                    Err(err) => {
+                        log::warn!("Can not parse stored {}: {}", name, err);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default();
+
+        Store { path, entries }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.entries.get(key).map(|entry| entry.value().clone())
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn keys(&self) -> Vec<K> {
+        self.entries
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        self.entries.insert(key, value);
+        self.save();
+    }
+
+    pub fn remove(&self, key: &K) {
+        self.entries.remove(key);
+        self.save();
+    }
+
+    /// Applies `update` to the stored value, starting from the default if the
+    /// key is missing, and returns the new value.
+    pub fn update(&self, key: K, update: impl FnOnce(&mut V)) -> V
+    where
+        V: Default,
+    {
+        let value = {
+            let mut entry = self.entries.entry(key).or_default();
+            update(entry.value_mut());
+            entry.value().clone()
+        };
+        self.save();
+        value
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let snapshot = self
+            .entries
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect::<HashMap<_, _>>();
+
+        let result = serde_json::to_vec(&snapshot)
+            .map_err(std::io::Error::from)
+            .and_then(|data| {
+                // Write to a temporary file first so a crash never leaves a
+                // truncated store behind
+                let temporary = path.with_extension("json.tmp");
+                fs::write(&temporary, data)?;
+                fs::rename(&temporary, path)
+            });
+
+        if let Err(err) = result {
+            log::warn!("Can not save {}: {}", path.display(), err);
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//! Words proposed by users with `/addword`. Submissions wait for an admin to
+//! review them with `/review_words`, and approved ones join the word bank.
+//! When `JIGARPICH_APPROVED_WORDS_CSV` is set, approved words are also
+//! exported there in the format of the words CSV file.
+
+use std::{env, sync::OnceLock};
+
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+
+use crate::{
+    admin::{admin_chat, is_admin},
+    callback_query_command::{serialize_user_command, UserCbQueryCommand},
+    moderation,
+    storage::Store,
+    words::{self, Complexity, Word},
+    HandlerResult,
+};
+
+/// The most taboo words a submission may have.
+const MAX_TABOO_WORDS: usize = 5;
+
+/// How many submissions `/review_words` shows at once.
+const REVIEW_BATCH_SIZE: usize = 10;
+
+/// How many broken words `/word_report` lists, to stay in one message.
+const REPORT_SIZE: usize = 50;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct Submission {
+    author: UserId,
+    author_name: String,
+    word: Word,
+}
+
+static PENDING: OnceLock<Store<u32, Submission>> = OnceLock::new();
+
+static APPROVED: OnceLock<Store<u32, Word>> = OnceLock::new();
+
+fn pending() -> &'static Store<u32, Submission> {
+    PENDING.get_or_init(|| Store::open("word_submissions"))
+}
+
+fn approved() -> &'static Store<u32, Word> {
+    APPROVED.get_or_init(|| Store::open("approved_words"))
+}
+
+/// Words approved so far, in approval order.
+pub fn approved_words() -> Vec<Word> {
+    let mut ids = approved().keys();
+    ids.sort();
+    ids.iter().filter_map(|id| approved().get(id)).collect()
+}
+
+/// The words the user proposed that weren't reviewed yet.
+pub fn pending_of(user_id: UserId) -> Vec<Word> {
+    pending()
+        .keys()
+        .iter()
+        .filter_map(|id| pending().get(id))
+        .filter(|submission| submission.author == user_id)
+        .map(|submission| submission.word)
+        .collect()
+}
+
+/// Withdraws the words the user proposed that weren't reviewed yet. Approved
+/// ones are part of the word bank and don't name their author.
+pub fn forget(user_id: UserId) {
+    for id in pending().keys() {
+        if pending()
+            .get(&id)
+            .is_some_and(|submission| submission.author == user_id)
+        {
+            pending().remove(&id);
+        }
+    }
+}
+
+/// IDs are shared by pending and approved submissions, so an approved word
+/// keeps its ID.
+fn next_id() -> u32 {
+    pending()
+        .keys()
+        .into_iter()
+        .chain(approved().keys())
+        .max()
+        .map_or(0, |id| id + 1)
+}
+
+fn export_approved_words() {
+    let Ok(path) = env::var("JIGARPICH_APPROVED_WORDS_CSV") else {
+        return;
+    };
+
+    let result = csv::Writer::from_path(&path).and_then(|mut writer| {
+        let header = ["text".to_owned(), "complexity".to_owned()]
+            .into_iter()
+            .chain((1..=MAX_TABOO_WORDS).map(|i| format!("taboo{}", i)));
+        writer.write_record(header)?;
+
+        for word in approved_words() {
+            let mut taboo_words = word.taboo_words();
+            taboo_words.resize(MAX_TABOO_WORDS, "");
+            let record = [word.text.clone(), (word.complexity() as u8).to_string()]
+                .into_iter()
+                .chain(taboo_words.into_iter().map(str::to_owned));
+            writer.write_record(record)?;
+        }
+        writer.flush()?;
+        Ok(())
+    });
+    if let Err(err) = result {
+        log::warn!("Can not export approved words to {}: {}", path, err);
+    }
+}
+
+pub async fn handle_add_word_command(bot: Bot, msg: Message, text: String) -> HandlerResult {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    let Some(word) = lines.next() else {
+        bot.send_message(
+            msg.chat.id,
+            "Please write the word after the command and its taboo words on the following \
+             lines, e.g.\n/addword Apple\nFruit\nRed\nTree",
+        )
+        .await?;
+        return Ok(());
+    };
+    let taboo_words = lines.map(str::to_owned).collect::<Vec<_>>();
+
+    if taboo_words.len() > MAX_TABOO_WORDS {
+        bot.send_message(
+            msg.chat.id,
+            format!("Please send at most {} taboo words", MAX_TABOO_WORDS),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let word = Word::custom(word.to_owned(), taboo_words);
+    if let Some(problem) = moderation::check(&word) {
+        bot.send_message(
+            msg.chat.id,
+            format!("Your word can't be added: {}", problem),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let text = word.text.clone();
+    pending().insert(
+        next_id(),
+        Submission {
+            author: user.id,
+            author_name: user.full_name(),
+            word,
+        },
+    );
+
+    if let Some(admin_chat) = admin_chat() {
+        bot.send_message(
+            admin_chat,
+            format!(
+                "📥 {} proposed \"{}\", send /review_words to review it",
+                user.full_name(),
+                text
+            ),
+        )
+        .await?;
+    }
+
+    bot.send_message(
+        msg.chat.id,
+        "Thanks! Your word will be added once the maintainers approve it.",
+    )
+    .await?;
+    Ok(())
+}
+
+fn review_card(id: u32, submission: &Submission) -> (String, InlineKeyboardMarkup) {
+    let taboo_words = submission
+        .word
+        .taboo_words()
+        .iter()
+        .map(|taboo_word| format!("❌ {}", taboo_word))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = format!(
+        "Proposed by {} ({})\n\n{}\n\n{}",
+        submission.author_name, submission.author, submission.word.text, taboo_words
+    );
+
+    let approve_buttons = Complexity::ALL.map(|complexity| {
+        InlineKeyboardButton::callback(
+            format!("✅ {}", complexity),
+            serialize_user_command(UserCbQueryCommand::ReviewWord {
+                id,
+                verdict: Some(complexity),
+            }),
+        )
+    });
+    let reject_button = InlineKeyboardButton::callback(
+        "❌ Reject",
+        serialize_user_command(UserCbQueryCommand::ReviewWord { id, verdict: None }),
+    );
+
+    (
+        text,
+        InlineKeyboardMarkup::new([approve_buttons.to_vec(), vec![reject_button]]),
+    )
+}
+
+/// Lists the words of the bank whose cards were found broken, so their rows
+/// can be fixed.
+pub async fn handle_word_report_command(bot: Bot, msg: Message) -> HandlerResult {
+    if !msg.from().is_some_and(|user| is_admin(user.id)) {
+        return Ok(());
+    }
+
+    let broken = words::broken();
+    let text = if broken.is_empty() {
+        "No broken words were found".to_owned()
+    } else {
+        broken.iter().take(REPORT_SIZE).fold(
+            format!("{} broken words were skipped:\n", broken.len()),
+            |mut res, (text, problem)| {
+                res += &format!("\n{}: {}", text, problem);
+                res
+            },
+        )
+    };
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
+
+pub async fn handle_review_words_command(bot: Bot, msg: Message) -> HandlerResult {
+    if !msg.from().is_some_and(|user| is_admin(user.id)) {
+        return Ok(());
+    }
+
+    let mut ids = pending().keys();
+    if ids.is_empty() {
+        bot.send_message(msg.chat.id, "There are no words to review")
+            .await?;
+        return Ok(());
+    }
+    ids.sort();
+
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "{} words to review, approve each with its complexity",
+            ids.len()
+        ),
+    )
+    .await?;
+    for id in ids.into_iter().take(REVIEW_BATCH_SIZE) {
+        let Some(submission) = pending().get(&id) else {
+            continue;
+        };
+        let (text, keyboard) = review_card(id, &submission);
+        bot.send_message(msg.chat.id, text)
+            .reply_markup(keyboard)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Approves a submission with the given complexity, or rejects it.
+pub async fn handle_review(
+    bot: Bot,
+    q: CallbackQuery,
+    id: u32,
+    verdict: Option<Complexity>,
+) -> HandlerResult {
+    if !is_admin(q.from.id) {
+        return Ok(());
+    }
+    let Some(submission) = pending().get(&id) else {
+        bot.answer_callback_query(q.id)
+            .text("This word was already reviewed")
+            .await?;
+        return Ok(());
+    };
+    pending().remove(&id);
+
+    let (result, notice) = match verdict {
+        Some(complexity) => {
+            let word = submission.word.clone().with_complexity(complexity);
+            words::add_word(word.clone());
+            approved().insert(id, word);
+            export_approved_words();
+            (
+                format!("✅ Approved as {} by {}", complexity, q.from.full_name()),
+                format!(
+                    "🎉 Your word \"{}\" was added to the game!",
+                    submission.word.text
+                ),
+            )
+        }
+        None => (
+            format!("❌ Rejected by {}", q.from.full_name()),
+            format!(
+                "Your word \"{}\" was not added to the game, sorry!",
+                submission.word.text
+            ),
+        ),
+    };
+
+    if let Some(message) = q.message {
+        let (text, _) = review_card(id, &submission);
+        bot.edit_message_text(
+            message.chat.id,
+            message.id,
+            format!("{}\n\n{}", text, result),
+        )
+        .await?;
+    }
+    bot.send_message(submission.author, notice).await?;
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use std::future::Future;
+
+use teloxide::{
+    prelude::*,
+    types::{
+        InlineKeyboardButton, InlineKeyboardMarkup, InputFile, MessageId, MessageKind, ParseMode,
+    },
+    utils::markdown::escape,
+    ApiError, RequestError,
+};
+
+use crate::{
+    brands,
+    callback_query_command::serialize_command,
+    frontend::{Buttons, Frontend},
+    reachability,
+    room::RoomId,
+};
+
+/// Notes recipients who blocked the bot or keep failing to get messages, so
+/// their rooms can show it and rooms none of the players can be reached in
+/// get torn down.
+fn track<T>(to: ChatId, result: Result<T, RequestError>) -> Result<T, RequestError> {
+    if !to.is_user() {
+        return result;
+    }
+
+    let user_id = UserId(to.0 as u64);
+    match &result {
+        Ok(_) => reachability::mark_reachable(user_id),
+        Err(RequestError::Api(
+            ApiError::BotBlocked | ApiError::UserDeactivated | ApiError::CantInitiateConversation,
+        )) => reachability::mark_unreachable(user_id),
+        Err(_) => reachability::record_failure(user_id),
+    }
+    result
+}
+
+/// The forum topic a message was sent in, `None` for the general topic and
+/// chats without topics.
+pub fn topic(msg: &Message) -> Option<i32> {
+    match &msg.kind {
+        MessageKind::Common(common) if common.is_topic_message => msg.thread_id,
+        _ => None,
+    }
+}
+
+fn keyboard(buttons: Buttons) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(buttons.into_iter().map(|row| {
+        row.into_iter()
+            .map(|button| {
+                InlineKeyboardButton::callback(
+                    button.label,
+                    serialize_command(button.room_id, button.command),
+                )
+            })
+            .collect::<Vec<_>>()
+    }))
+}
+
+impl Frontend for Bot {
+    type Error = RequestError;
+
+    fn send_card_to_topic(
+        &self,
+        to: ChatId,
+        topic: Option<i32>,
+        text: String,
+        buttons: Buttons,
+    ) -> impl Future<Output = Result<(ChatId, MessageId), Self::Error>> + Send {
+        let mut request = brands::bot_for(to, self)
+            .send_message(to, text)
+            .reply_markup(keyboard(buttons));
+        if let Some(topic) = topic {
+            request = request.message_thread_id(topic);
+        }
+        async move {
+            let sent_message = track(to, request.await)?;
+            Ok((sent_message.chat.id, sent_message.id))
+        }
+    }
+
+    fn edit_buttons(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        buttons: Buttons,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let request = brands::bot_for(chat_id, self)
+            .edit_message_reply_markup(chat_id, message_id)
+            .reply_markup(keyboard(buttons));
+        async move {
+            track(chat_id, request.await)?;
+            Ok(())
+        }
+    }
+
+    fn edit_text(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        text: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let request = brands::bot_for(chat_id, self).edit_message_text(chat_id, message_id, text);
+        async move {
+            track(chat_id, request.await)?;
+            Ok(())
+        }
+    }
+
+    fn edit_card(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        text: String,
+        buttons: Buttons,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let request = brands::bot_for(chat_id, self)
+            .edit_message_text(chat_id, message_id, text)
+            .reply_markup(keyboard(buttons));
+        async move {
+            track(chat_id, request.await)?;
+            Ok(())
+        }
+    }
+
+    fn pin_message(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let request = brands::bot_for(chat_id, self)
+            .pin_chat_message(chat_id, message_id)
+            .disable_notification(true);
+        async move {
+            track(chat_id, request.await)?;
+            Ok(())
+        }
+    }
+
+    fn prompt_to_topic(
+        &self,
+        to: ChatId,
+        topic: Option<i32>,
+        text: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let mut request = brands::bot_for(to, self).send_message(to, text);
+        if let Some(topic) = topic {
+            request = request.message_thread_id(topic);
+        }
+        async move {
+            track(to, request.await)?;
+            Ok(())
+        }
+    }
+
+    fn prompt_with_spoiler(
+        &self,
+        to: ChatId,
+        text: String,
+        spoiler: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let request = brands::bot_for(to, self)
+            .send_message(to, format!("{}||{}||", escape(&text), escape(&spoiler)))
+            .parse_mode(ParseMode::MarkdownV2);
+        async move {
+            track(to, request.await)?;
+            Ok(())
+        }
+    }
+
+    fn send_image(
+        &self,
+        to: ChatId,
+        topic: Option<i32>,
+        png: Vec<u8>,
+        caption: String,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let mut request = brands::bot_for(to, self)
+            .send_photo(to, InputFile::memory(png).file_name("scoreboard.png"))
+            .caption(caption);
+        if let Some(topic) = topic {
+            request = request.message_thread_id(topic);
+        }
+        async move {
+            track(to, request.await)?;
+            Ok(())
+        }
+    }
+
+    fn send_sound(
+        &self,
+        to: ChatId,
+        topic: Option<i32>,
+        ogg: Vec<u8>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let mut request =
+            brands::bot_for(to, self).send_voice(to, InputFile::memory(ogg).file_name("alert.ogg"));
+        if let Some(topic) = topic {
+            request = request.message_thread_id(topic);
+        }
+        async move {
+            track(to, request.await)?;
+            Ok(())
+        }
+    }
+
+    fn join_link(
+        &self,
+        room_id: RoomId,
+    ) -> impl Future<Output = Result<Option<String>, Self::Error>> + Send {
+        let request = self.get_me();
+        async move {
+            let me = request.await?;
+            Ok(me
+                .username
+                .as_ref()
+                .map(|username| format!("https://t.me/{}?start={}", username, room_id.0)))
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Serde helpers for `Instant`s, which only make sense within one process.
+//! They are stored as wall clock times and mapped back onto the monotonic
+//! clock when loaded, so elapsed time survives a restart.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn serialize<S: Serializer>(instant: &Instant, serializer: S) -> Result<S::Ok, S::Error> {
+    let wall_clock = SystemTime::now() - instant.elapsed();
+    wall_clock
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+        .serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Instant, D::Error> {
+    let millis = u64::deserialize(deserializer)?;
+    let wall_clock = UNIX_EPOCH + Duration::from_millis(millis);
+    let elapsed = SystemTime::now()
+        .duration_since(wall_clock)
+        .unwrap_or_default();
+    Ok(Instant::now()
+        .checked_sub(elapsed)
+        .unwrap_or_else(Instant::now))
+}
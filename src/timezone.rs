@@ -0,0 +1,149 @@
+use std::{
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use teloxide::prelude::*;
+
+use crate::{storage::Store, HandlerResult};
+
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct TimezoneSetting {
+    offset_in_minutes: Option<i32>,
+    asked: bool,
+}
+
+static TIMEZONES: OnceLock<Store<UserId, TimezoneSetting>> = OnceLock::new();
+
+fn timezones() -> &'static Store<UserId, TimezoneSetting> {
+    TIMEZONES.get_or_init(|| Store::open("timezones"))
+}
+
+/// Parses UTC offsets like `+03:30`, `-5`, or `UTC+4`.
+fn parse_offset(text: &str) -> Option<i32> {
+    let text = text.trim().to_uppercase();
+    let text = text
+        .strip_prefix("UTC")
+        .or_else(|| text.strip_prefix("GMT"))
+        .unwrap_or(&text)
+        .trim();
+
+    if text.is_empty() {
+        return Some(0);
+    }
+
+    let (sign, text) = match text.chars().next()? {
+        '+' => (1, &text[1..]),
+        '-' => (-1, &text[1..]),
+        _ => (1, text),
+    };
+
+    let (hours, minutes) = match text.split_once(':') {
+        Some((hours, minutes)) => (hours.parse::<i32>().ok()?, minutes.parse::<i32>().ok()?),
+        None => (text.parse::<i32>().ok()?, 0),
+    };
+
+    if !(0..=14).contains(&hours) || !(0..60).contains(&minutes) {
+        return None;
+    }
+
+    Some(sign * (hours * 60 + minutes))
+}
+
+fn format_offset(offset_in_minutes: i32) -> String {
+    format!(
+        "UTC{}{:02}:{:02}",
+        if offset_in_minutes < 0 { '-' } else { '+' },
+        offset_in_minutes.abs() / 60,
+        offset_in_minutes.abs() % 60
+    )
+}
+
+/// Formats `at` as a wall clock time in the user's timezone, falling back to
+/// UTC for users who haven't set one.
+pub fn format_time(user_id: UserId, at: SystemTime) -> String {
+    let offset_in_minutes = timezones()
+        .get(&user_id)
+        .and_then(|setting| setting.offset_in_minutes);
+
+    let seconds = at
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs() as i64)
+        .unwrap_or_default()
+        + offset_in_minutes.unwrap_or_default() as i64 * 60;
+    let seconds_of_day = seconds.rem_euclid(24 * 60 * 60);
+    let time = format!(
+        "{:02}:{:02}",
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60
+    );
+
+    match offset_in_minutes {
+        Some(_) => time,
+        None => format!("{} UTC", time),
+    }
+}
+
+/// Returns true only the first time it's called for a user without a
+/// timezone, so we ask for it once and never nag again.
+pub fn should_ask(user_id: UserId) -> bool {
+    let setting = timezones().get(&user_id).unwrap_or_default();
+    if setting.offset_in_minutes.is_some() || setting.asked {
+        return false;
+    }
+
+    timezones().update(user_id, |setting| setting.asked = true);
+    true
+}
+
+pub fn setting(user_id: UserId) -> Option<TimezoneSetting> {
+    timezones().get(&user_id)
+}
+
+pub fn forget(user_id: UserId) {
+    if timezones().contains(&user_id) {
+        timezones().remove(&user_id);
+    }
+}
+
+pub const ASK_MESSAGE: &str =
+    "Send your UTC offset (e.g. /timezone +03:30) to see times in your local time.";
+
+pub async fn handle_timezone_command(bot: Bot, msg: Message, offset: String) -> HandlerResult {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    if offset.trim().is_empty() {
+        let current = timezones()
+            .get(&user.id)
+            .and_then(|setting| setting.offset_in_minutes)
+            .map(format_offset)
+            .unwrap_or_else(|| "not set (UTC)".to_owned());
+        bot.send_message(
+            msg.chat.id,
+            format!("Your timezone is {}.\n{}", current, ASK_MESSAGE),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let Some(offset_in_minutes) = parse_offset(&offset) else {
+        bot.send_message(msg.chat.id, "Please send an offset like +03:30 or -5")
+            .await?;
+        return Ok(());
+    };
+
+    timezones().update(user.id, |setting| {
+        setting.offset_in_minutes = Some(offset_in_minutes);
+    });
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "Times will be shown in {}",
+            format_offset(offset_in_minutes)
+        ),
+    )
+    .await?;
+    Ok(())
+}
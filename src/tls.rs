@@ -0,0 +1,187 @@
+//! Lets the built-in server terminate TLS itself, for deployments without a
+//! reverse proxy in front of it. Enabled by `JIGARPICH_TLS_CERT` and
+//! `JIGARPICH_TLS_KEY`, the paths of a PEM certificate chain and its private
+//! key. The files are checked for changes every minute, so renewed
+//! certificates are served without a restart.
+
+use std::{
+    env,
+    fs::{self, File},
+    future::Future,
+    io::{self, BufReader},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use axum::Router;
+use tokio::{net::TcpListener, sync::mpsc};
+use tokio_rustls::{
+    rustls::{
+        server::{ClientHello, ResolvesServerCert},
+        sign::{self, CertifiedKey},
+        Certificate, PrivateKey, ServerConfig,
+    },
+    server::TlsStream,
+    TlsAcceptor,
+};
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+struct Files {
+    cert: PathBuf,
+    key: PathBuf,
+}
+
+impl Files {
+    fn from_env() -> Option<Self> {
+        match (
+            env::var("JIGARPICH_TLS_CERT"),
+            env::var("JIGARPICH_TLS_KEY"),
+        ) {
+            (Ok(cert), Ok(key)) => Some(Files {
+                cert: cert.into(),
+                key: key.into(),
+            }),
+            (Err(_), Err(_)) => None,
+            _ => panic!("JIGARPICH_TLS_CERT and JIGARPICH_TLS_KEY must be set together"),
+        }
+    }
+
+    fn modified(&self) -> Option<(SystemTime, SystemTime)> {
+        let modified = |path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        };
+        Some((modified(&self.cert)?, modified(&self.key)?))
+    }
+
+    fn load(&self) -> Result<CertifiedKey, String> {
+        let open = |path: &PathBuf| {
+            File::open(path)
+                .map(BufReader::new)
+                .map_err(|err| format!("Can not open {}: {}", path.display(), err))
+        };
+
+        let certs = rustls_pemfile::certs(&mut open(&self.cert)?)
+            .map_err(|err| format!("Can not read the certificates: {}", err))?;
+        if certs.is_empty() {
+            return Err(format!("No certificate in {}", self.cert.display()));
+        }
+
+        let key = rustls_pemfile::read_all(&mut open(&self.key)?)
+            .map_err(|err| format!("Can not read the private key: {}", err))?
+            .into_iter()
+            .find_map(|item| match item {
+                rustls_pemfile::Item::PKCS8Key(key)
+                | rustls_pemfile::Item::RSAKey(key)
+                | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+                _ => None,
+            })
+            .ok_or_else(|| format!("No private key in {}", self.key.display()))?;
+        let key = sign::any_supported_type(&key)
+            .map_err(|err| format!("Can not use the private key: {}", err))?;
+
+        Ok(CertifiedKey::new(
+            certs.into_iter().map(Certificate).collect(),
+            key,
+        ))
+    }
+}
+
+/// Hands out the latest certificate that could be loaded.
+struct Resolver(RwLock<Arc<CertifiedKey>>);
+
+impl ResolvesServerCert for Resolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.0.read().unwrap().clone())
+    }
+}
+
+/// Swaps in the certificate whenever its files change, keeping the old one
+/// if the new files can't be used, like when only one of them was replaced.
+async fn watch(files: Files, resolver: Arc<Resolver>) {
+    let mut loaded = files.modified();
+    let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+    loop {
+        interval.tick().await;
+        let modified = files.modified();
+        if modified.is_none() || modified == loaded {
+            continue;
+        }
+        match files.load() {
+            Ok(key) => {
+                *resolver.0.write().unwrap() = Arc::new(key);
+                loaded = modified;
+                log::info!("Reloaded the TLS certificate");
+            }
+            Err(err) => log::warn!("Can not reload the TLS certificate: {}", err),
+        }
+    }
+}
+
+/// Hands the connections that made it through the handshake to `tx`.
+async fn accept(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    tx: mpsc::UnboundedSender<io::Result<TlsStream<tokio::net::TcpStream>>>,
+) {
+    while !tx.is_closed() {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                log::warn!("Can not accept a connection: {}", err);
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            match acceptor.accept(stream).await {
+                Ok(stream) => {
+                    let _ = tx.send(Ok(stream));
+                }
+                Err(err) => log::debug!("TLS handshake failed: {}", err),
+            }
+        });
+    }
+}
+
+/// Serves `app` on `addr` until `stop_flag` resolves, over TLS when it's
+/// configured.
+pub async fn serve(
+    addr: SocketAddr,
+    app: Router,
+    stop_flag: impl Future<Output = ()>,
+) -> Result<(), hyper::Error> {
+    let Some(files) = Files::from_env() else {
+        return axum::Server::bind(&addr)
+            .serve(app.into_make_service())
+            .with_graceful_shutdown(stop_flag)
+            .await;
+    };
+
+    let key = files.load().expect("Couldn't load the TLS certificate");
+    let resolver = Arc::new(Resolver(RwLock::new(Arc::new(key))));
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(resolver.clone());
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    tokio::spawn(watch(files, resolver));
+
+    let listener = TcpListener::bind(addr)
+        .await
+        .expect("Couldn't bind the TLS listener");
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(accept(listener, TlsAcceptor::from(Arc::new(config)), tx));
+    log::info!("Serving TLS on {}", addr);
+
+    let incoming = futures::stream::poll_fn(move |cx| rx.poll_recv(cx));
+    axum::Server::builder(hyper::server::accept::from_stream(incoming))
+        .serve(app.into_make_service())
+        .with_graceful_shutdown(stop_flag)
+        .await
+}
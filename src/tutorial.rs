@@ -0,0 +1,94 @@
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+
+use crate::{
+    callback_query_command::{serialize_user_command, UserCbQueryCommand},
+    game::{time_alert_message, TIME_ALERTS_IN_SECONDS},
+    room::SKIP_COOL_DOWN_IN_SECONDS,
+    words::{get_random_word, ComplexityWeights},
+    HandlerResult,
+};
+
+pub const NUMBER_OF_STEPS: usize = 5;
+
+/// Renders a tutorial step as the message text and the label of the button
+/// leading to the next step.
+fn render_step(step: usize) -> (String, &'static str) {
+    match step {
+        0 => (
+            "👋 Jigarpich is played in teams of two. In each turn one of you \
+             describes a word and the other one guesses it, then you swap roles.\n\n\
+             Let's see how a turn looks!"
+                .to_owned(),
+            "Next ▶️",
+        ),
+        1 => (
+            format!(
+                "This is the card the describer gets:\n\n{}\n\n\
+                 Explain the word without saying it, its synonyms, opposites, rhymes \
+                 or translations. When taboo words are enabled, the ❌ words are off \
+                 limits too.\n\nPress ✅ as if your partner guessed it.",
+                get_random_word(&mut rand::thread_rng(), None, ComplexityWeights::default())
+                    .get_message_string(true)
+            ),
+            "✅",
+        ),
+        2 => (
+            format!(
+                "✅ tells the bot your partner got it, and the next pair gets a new \
+                 word right away.\n\nStuck on a word? After {} seconds a ⏩️ button \
+                 appears next to ✅ so you can skip it, but the clock keeps running.",
+                SKIP_COOL_DOWN_IN_SECONDS
+            ),
+            "⏩️",
+        ),
+        3 => (
+            format!(
+                "Each round lasts a few minutes. The bot warns everyone when time is \
+                 running out:\n\n{}",
+                TIME_ALERTS_IN_SECONDS.map(time_alert_message).join("\n")
+            ),
+            "Next ▶️",
+        ),
+        _ => (
+            "⏱️ The time each team spends describing is added up over all rounds. \
+             The team with the least total time wins 🏆\n\n\
+             That's it! Send /new to create a room or /join to join one."
+                .to_owned(),
+            "",
+        ),
+    }
+}
+
+fn keyboard(step: usize, label: &str) -> InlineKeyboardMarkup {
+    if step + 1 >= NUMBER_OF_STEPS {
+        return InlineKeyboardMarkup::new(Vec::<Vec<InlineKeyboardButton>>::new());
+    }
+
+    InlineKeyboardMarkup::new([[InlineKeyboardButton::callback(
+        label,
+        serialize_user_command(UserCbQueryCommand::Tutorial { step: step + 1 }),
+    )]])
+}
+
+pub async fn handle_rules_command(bot: Bot, msg: Message) -> HandlerResult {
+    let (text, label) = render_step(0);
+    bot.send_message(msg.chat.id, text)
+        .reply_markup(keyboard(0, label))
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_tutorial_step(bot: Bot, q: CallbackQuery, step: usize) -> HandlerResult {
+    let Some(message) = q.message else {
+        return Ok(());
+    };
+
+    let (text, label) = render_step(step);
+    bot.edit_message_text(message.chat.id, message.id, text)
+        .reply_markup(keyboard(step, label))
+        .await?;
+    Ok(())
+}
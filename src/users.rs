@@ -0,0 +1,102 @@
+use std::sync::OnceLock;
+
+use teloxide::types::{Chat, ChatId, User, UserId};
+
+use crate::storage::Store;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct KnownUser {
+    pub name: String,
+    /// Without the leading `@`.
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+static USERS: OnceLock<Store<UserId, KnownUser>> = OnceLock::new();
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct KnownChat {
+    pub title: String,
+}
+
+static CHATS: OnceLock<Store<ChatId, KnownChat>> = OnceLock::new();
+
+fn users() -> &'static Store<UserId, KnownUser> {
+    USERS.get_or_init(|| Store::open("users"))
+}
+
+fn chats() -> &'static Store<ChatId, KnownChat> {
+    CHATS.get_or_init(|| Store::open("chats"))
+}
+
+/// Remembers everyone who talks to the bot, so they can be reached later,
+/// and the username they go by.
+pub fn record(user: &User) {
+    if user.is_bot {
+        return;
+    }
+
+    let known = KnownUser {
+        name: user.full_name(),
+        username: user.username.clone(),
+    };
+    if users()
+        .get(&user.id)
+        .is_some_and(|stored| stored.name == known.name && stored.username == known.username)
+    {
+        return;
+    }
+    users().insert(user.id, known);
+}
+
+pub fn known(user_id: UserId) -> Option<KnownUser> {
+    users().get(&user_id)
+}
+
+/// The user going by `username`, with or without the leading `@`, among
+/// those who talked to the bot.
+pub fn find_by_username(username: &str) -> Option<UserId> {
+    let username = username.trim().trim_start_matches('@');
+    users().keys().into_iter().find(|user_id| {
+        users()
+            .get(user_id)
+            .and_then(|known| known.username)
+            .is_some_and(|known| known.eq_ignore_ascii_case(username))
+    })
+}
+
+pub fn all() -> Vec<UserId> {
+    users().keys()
+}
+
+/// Remembers the groups the bot is used in. Private chats are known by their
+/// user already.
+pub fn record_chat(chat: &Chat) {
+    if chat.is_private() || chats().contains(&chat.id) {
+        return;
+    }
+
+    chats().insert(
+        chat.id,
+        KnownChat {
+            title: chat.title().unwrap_or_default().to_owned(),
+        },
+    );
+}
+
+pub fn groups() -> Vec<ChatId> {
+    chats().keys()
+}
+
+/// Called when the bot is removed from a group.
+pub fn forget_chat(chat_id: ChatId) {
+    if chats().contains(&chat_id) {
+        chats().remove(&chat_id);
+    }
+}
+
+pub fn forget(user_id: UserId) {
+    if users().contains(&user_id) {
+        users().remove(&user_id);
+    }
+}
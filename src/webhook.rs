@@ -0,0 +1,90 @@
+//! The webhook Telegram posts updates to. `JIGARPICH_URL` is the URL Telegram
+//! is told to use, and by default the server listens on its path. Behind a
+//! reverse proxy that adds or strips a path prefix, `JIGARPICH_WEBHOOK_PATH`
+//! sets the path the server listens on instead. The server listens on
+//! localhost unless `JIGARPICH_LISTEN_ADDR` says otherwise, like when it
+//! terminates TLS itself.
+
+use std::{convert::Infallible, env, future::Future, net::SocketAddr};
+
+use axum::Router;
+use teloxide::{
+    prelude::*,
+    update_listeners::{webhooks, UpdateListener},
+};
+
+use crate::sharding;
+
+pub fn listen_addr() -> SocketAddr {
+    env::var("JIGARPICH_LISTEN_ADDR")
+        .map(|addr| {
+            addr.parse()
+                .expect("JIGARPICH_LISTEN_ADDR must be like 0.0.0.0:443")
+        })
+        .unwrap_or_else(|_| ([127, 0, 0, 1], 54647).into())
+}
+
+fn advertised_url() -> reqwest::Url {
+    env::var("JIGARPICH_URL")
+        .expect("JIGARPICH_URL must be set")
+        .parse()
+        .expect("JIGARPICH_URL must be a URL")
+}
+
+/// The path the server listens on for the updates of the main bot.
+pub fn local_path() -> String {
+    match env::var("JIGARPICH_WEBHOOK_PATH") {
+        Ok(path) if path.starts_with('/') => path,
+        Ok(path) => format!("/{}", path),
+        Err(_) => advertised_url().path().to_owned(),
+    }
+}
+
+/// `path` followed by `suffix`, without doubling the slash between them.
+pub fn join_path(path: &str, suffix: &str) -> String {
+    if suffix.is_empty() {
+        return path.to_owned();
+    }
+    path.trim_end_matches('/').to_owned() + suffix
+}
+
+/// Points Telegram at the advertised URL and routes the local path to the
+/// returned listener, both with `suffix` appended to tell the bots apart. The
+/// webhook is deleted again once the listener stops.
+pub async fn setup(
+    bot: Bot,
+    addr: SocketAddr,
+    suffix: &str,
+) -> (
+    impl UpdateListener<Err = Infallible>,
+    impl Future<Output = ()> + Send,
+    Router,
+) {
+    let mut url = advertised_url();
+    let path = join_path(&local_path(), suffix);
+    url.set_path(&join_path(url.path(), suffix));
+
+    let mut local_url = url.clone();
+    local_url.set_path(&path);
+    let mut options = webhooks::Options::new(addr, local_url);
+    if let Some(secret) = sharding::webhook_secret() {
+        options = options.secret_token(secret);
+    }
+    let secret = options.get_or_gen_secret_token().to_owned();
+
+    bot.set_webhook(url.clone())
+        .secret_token(secret)
+        .drop_pending_updates(false)
+        .await
+        .expect("Couldn't setup webhook");
+    log::info!("Serving the webhook {} on {}{}", url, addr, path);
+
+    let (listener, stop_flag, router) = webhooks::axum_no_setup(options);
+    let stop_flag = async move {
+        stop_flag.await;
+        if let Err(err) = bot.delete_webhook().await {
+            log::error!("Couldn't delete webhook: {}", err);
+        }
+    };
+    (listener, stop_flag, router)
+}
@@ -1,33 +1,167 @@
-use std::{collections::HashMap, fmt::Display, fs::File, sync::OnceLock};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::File,
+    sync::{OnceLock, RwLock},
+};
 
+use dashmap::DashMap;
 use rand::{
-    distributions::uniform::{UniformFloat, UniformSampler},
+    distributions::{Distribution, WeightedIndex},
     seq::SliceRandom,
-    thread_rng,
+    Rng,
 };
 use serde_repr::Deserialize_repr;
 use serde_repr::Serialize_repr;
 
+use crate::{storage::Store, submissions};
+
+/// How many other words of a complexity are tried when a random pick turns
+/// out broken.
+const REROLLS: usize = 10;
+
 #[derive(Deserialize_repr, Serialize_repr, Debug, Eq, PartialEq, Hash, Clone, Copy)]
 #[repr(u8)]
-enum Complexity {
+pub enum Complexity {
     Easy = 1,
     Medium = 2,
     Hard = 3,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
-pub struct Word {
-    pub text: String,
+impl Complexity {
+    pub const ALL: [Complexity; 3] = [Complexity::Easy, Complexity::Medium, Complexity::Hard];
+
+    pub fn from_repr(repr: u8) -> Option<Complexity> {
+        Complexity::ALL
+            .into_iter()
+            .find(|complexity| *complexity as u8 == repr)
+    }
+}
+
+impl Display for Complexity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Complexity::Easy => write!(f, "easy"),
+            Complexity::Medium => write!(f, "medium"),
+            Complexity::Hard => write!(f, "hard"),
+        }
+    }
+}
+
+/// How many numbered taboo columns, `taboo1` to `taboo6`, a row of the words
+/// file may have.
+const TABOO_COLUMNS: usize = 6;
+
+/// Separates the taboo words of the `taboo` column, which can be used instead
+/// of or next to the numbered ones.
+const TABOO_SEPARATOR: char = ';';
+
+/// A row of the words file. Unknown columns are rejected so typos and
+/// metadata don't turn into taboo words.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+struct WordRow {
+    text: String,
     complexity: Complexity,
+    taboo1: Option<String>,
+    taboo2: Option<String>,
+    taboo3: Option<String>,
+    taboo4: Option<String>,
+    taboo5: Option<String>,
+    taboo6: Option<String>,
+    taboo: Option<String>,
+}
 
+/// A row of a words file from before the taboo columns were fixed, where
+/// every column but the word and its complexity is a taboo word.
+#[derive(serde::Deserialize)]
+struct LegacyWordRow {
+    text: String,
+    complexity: Complexity,
     #[serde(flatten)]
     taboo_words: HashMap<String, String>,
+}
+
+/// Words kept in storage before the taboo columns were fixed are still read.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StoredWord {
+    Row(WordRow),
+    Legacy(LegacyWordRow),
+}
 
-    #[serde(skip)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+#[serde(from = "StoredWord", into = "WordRow")]
+pub struct Word {
+    pub text: String,
+    complexity: Complexity,
+    taboo_words: Vec<String>,
     selected_taboo_words: Vec<String>,
 }
 
+impl From<WordRow> for Word {
+    fn from(row: WordRow) -> Self {
+        let numbered = [
+            row.taboo1, row.taboo2, row.taboo3, row.taboo4, row.taboo5, row.taboo6,
+        ];
+        let listed = row
+            .taboo
+            .map(|taboo| {
+                taboo
+                    .split(TABOO_SEPARATOR)
+                    .map(str::to_owned)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let taboo_words = numbered.into_iter().flatten().chain(listed).collect();
+
+        Word::new(row.text, row.complexity, taboo_words)
+    }
+}
+
+impl From<LegacyWordRow> for Word {
+    fn from(row: LegacyWordRow) -> Self {
+        let mut taboo_words = row.taboo_words.into_iter().collect::<Vec<_>>();
+        taboo_words.sort();
+        Word::new(
+            row.text,
+            row.complexity,
+            taboo_words
+                .into_iter()
+                .map(|(_, taboo_word)| taboo_word)
+                .collect(),
+        )
+    }
+}
+
+impl From<StoredWord> for Word {
+    fn from(stored: StoredWord) -> Self {
+        match stored {
+            StoredWord::Row(row) => Word::from(row),
+            StoredWord::Legacy(row) => Word::from(row),
+        }
+    }
+}
+
+impl From<Word> for WordRow {
+    fn from(word: Word) -> Self {
+        let mut taboo_words = word.taboo_words.into_iter();
+        let mut numbered = [(); TABOO_COLUMNS].map(|_| taboo_words.next());
+        let rest = taboo_words.collect::<Vec<_>>();
+        WordRow {
+            text: word.text,
+            complexity: word.complexity,
+            taboo1: numbered[0].take(),
+            taboo2: numbered[1].take(),
+            taboo3: numbered[2].take(),
+            taboo4: numbered[3].take(),
+            taboo5: numbered[4].take(),
+            taboo6: numbered[5].take(),
+            taboo: (!rest.is_empty()).then(|| rest.join(&TABOO_SEPARATOR.to_string())),
+        }
+    }
+}
+
 impl Display for Word {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let json = serde_json::to_string(self).unwrap();
@@ -36,10 +170,61 @@ impl Display for Word {
 }
 
 impl Word {
-    fn select_taboo_words(other: &Word) -> Word {
-        let mut rng = thread_rng();
-        let mut taboo_words: Vec<String> = other.taboo_words.values().cloned().collect();
-        taboo_words.shuffle(&mut rng);
+    /// Trims the taboo words, dropping the empty ones rows with fewer taboo
+    /// words leave behind.
+    fn new(text: String, complexity: Complexity, taboo_words: Vec<String>) -> Word {
+        Word {
+            text,
+            complexity,
+            taboo_words: taboo_words
+                .iter()
+                .map(|taboo_word| taboo_word.trim())
+                .filter(|taboo_word| !taboo_word.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            selected_taboo_words: Vec::new(),
+        }
+    }
+
+    /// A word written by players, counted as medium.
+    pub fn custom(text: String, taboo_words: Vec<String>) -> Word {
+        Word::new(text, Complexity::Medium, taboo_words)
+    }
+
+    pub fn complexity(&self) -> Complexity {
+        self.complexity
+    }
+
+    /// What makes the card of this word unplayable, if anything.
+    fn problem(&self) -> Option<String> {
+        if self.text.trim().is_empty() {
+            return Some("the word is empty".to_owned());
+        }
+
+        for (index, taboo_word) in self.taboo_words.iter().enumerate() {
+            if taboo_word == self.text.trim() {
+                return Some(format!("taboo word {} repeats the word", index + 1));
+            }
+            if self.taboo_words[..index].contains(taboo_word) {
+                return Some(format!("taboo word {} is a duplicate", index + 1));
+            }
+        }
+        None
+    }
+
+    pub fn with_complexity(self, complexity: Complexity) -> Word {
+        Word { complexity, ..self }
+    }
+
+    pub fn taboo_words(&self) -> Vec<&str> {
+        self.taboo_words.iter().map(String::as_str).collect()
+    }
+
+    pub fn select_taboo_words(other: &Word, rng: &mut impl Rng) -> Word {
+        let mut taboo_words = other.taboo_words.clone();
+        // Sorted like when taboo words were kept in a map, so seeds replay
+        taboo_words.sort();
+        taboo_words.shuffle(rng);
         let selected_taboo_words = taboo_words.into_iter().take(4).collect();
         Word {
             text: other.text.clone(),
@@ -67,41 +252,291 @@ impl Word {
     }
 }
 
-static WORDS: OnceLock<HashMap<Complexity, Vec<Word>>> = OnceLock::new();
+/// The word bank, loaded from the CSV file and grown by approved submissions.
+static WORDS: OnceLock<RwLock<HashMap<Complexity, Vec<Word>>>> = OnceLock::new();
 
-pub fn get_random_word() -> Word {
-    let words = WORDS.get_or_init(|| {
-        let file_path = std::env::args()
-            .nth(1)
-            .expect("Words CSV file is not provided!");
-        let file = File::open(file_path).unwrap();
-        csv::Reader::from_reader(file)
-            .deserialize::<Word>()
-            .map(|w| w.expect("Can not parse word"))
-            .fold(HashMap::new(), |mut res, w| {
+fn words_file() -> File {
+    // The test harness has arguments of its own
+    if cfg!(test) {
+        return File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/words.csv")).unwrap();
+    }
+    let file_path = std::env::args()
+        .nth(1)
+        .expect("Words CSV file is not provided!");
+    File::open(file_path).unwrap()
+}
+
+/// Reads the words file, logging every invalid row before giving up on it.
+fn read_words_file() -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut invalid_rows = 0;
+    for (index, row) in csv::Reader::from_reader(words_file())
+        .deserialize::<WordRow>()
+        .enumerate()
+    {
+        let word = row.map_err(|err| err.to_string()).and_then(|row| {
+            let word = Word::from(row);
+            match word.problem() {
+                Some(problem) => Err(format!("\"{}\": {}", word.text, problem)),
+                None => Ok(word),
+            }
+        });
+        match word {
+            Ok(word) => words.push(word),
+            Err(err) => {
+                // The header is the first line
+                log::error!("Row {} of the words file is invalid: {}", index + 2, err);
+                invalid_rows += 1;
+            }
+        }
+    }
+
+    if invalid_rows > 0 {
+        panic!(
+            "{} rows of the words file are invalid. Files where every extra column is a \
+             taboo word can be converted with JIGARPICH_MIGRATE_WORDS.",
+            invalid_rows
+        );
+    }
+    words
+}
+
+/// Converts a words file of the old format, where every column but `text`
+/// and `complexity` is a taboo word, to `path` in the current format. Taboo
+/// words repeating the word or each other are dropped on the way.
+pub fn migrate(path: &str) {
+    let result = csv::Writer::from_path(path).and_then(|mut writer| {
+        for (index, row) in csv::Reader::from_reader(words_file())
+            .deserialize::<LegacyWordRow>()
+            .enumerate()
+        {
+            let mut word = match row {
+                Ok(row) => Word::from(row),
+                Err(err) => {
+                    log::warn!("Skipping row {} of the words file: {}", index + 2, err);
+                    continue;
+                }
+            };
+            let mut kept = Vec::new();
+            for taboo_word in word.taboo_words {
+                if taboo_word == word.text.trim() || kept.contains(&taboo_word) {
+                    log::warn!("Dropping {} from row {}", taboo_word, index + 2);
+                } else {
+                    kept.push(taboo_word);
+                }
+            }
+            word.taboo_words = kept;
+            if let Some(problem) = word.problem() {
+                log::warn!(
+                    "Row {} of the words file has a problem: {}",
+                    index + 2,
+                    problem
+                );
+            }
+            writer.serialize(WordRow::from(word))?;
+        }
+        writer.flush()?;
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => log::info!("Converted the words file to {}", path),
+        Err(err) => log::error!("Can not convert the words file to {}: {}", path, err),
+    }
+}
+
+fn words() -> &'static RwLock<HashMap<Complexity, Vec<Word>>> {
+    WORDS.get_or_init(|| {
+        let words = read_words_file()
+            .into_iter()
+            .chain(submissions::approved_words())
+            .fold(HashMap::<_, Vec<Word>>::new(), |mut res, w| {
                 res.entry(w.complexity).or_default().push(w);
                 res
-            })
-    });
+            });
+        RwLock::new(words)
+    })
+}
+
+/// Words whose cards were found broken, with what's wrong with them, for
+/// `/word_report`.
+static BROKEN_WORDS: OnceLock<Store<String, String>> = OnceLock::new();
 
-    let mut rng = thread_rng();
-    let word = match UniformFloat::<f32>::new_inclusive(0.0, 1.0).sample(&mut rng) {
-        x if x < 0.7 => words
-            .get(&Complexity::Easy)
-            .expect("No easy word")
-            .choose(&mut rng)
-            .unwrap(),
-        x if x < 0.9 => words
-            .get(&Complexity::Medium)
-            .expect("No medium word")
-            .choose(&mut rng)
-            .unwrap(),
-        _ => words
-            .get(&Complexity::Hard)
-            .expect("No hard word")
-            .choose(&mut rng)
-            .unwrap(),
+fn broken_words() -> &'static Store<String, String> {
+    BROKEN_WORDS.get_or_init(|| Store::open("broken_words"))
+}
+
+/// Whether the card of the word can be dealt. Broken ones are recorded for
+/// the admins and never reach the describer.
+fn playable(word: &Word) -> bool {
+    let Some(problem) = word.problem() else {
+        return true;
     };
+    if !broken_words().contains(&word.text) {
+        log::warn!("Skipping broken word {}: {}", word, problem);
+        broken_words().insert(word.text.clone(), problem);
+    }
+    false
+}
+
+/// The words found broken so far, with what's wrong with each.
+pub fn broken() -> Vec<(String, String)> {
+    let mut texts = broken_words().keys();
+    texts.sort();
+    texts
+        .into_iter()
+        .filter_map(|text| Some((text.clone(), broken_words().get(&text)?)))
+        .collect()
+}
+
+/// Adds a word to the live bank.
+pub fn add_word(word: Word) {
+    words()
+        .write()
+        .unwrap()
+        .entry(word.complexity)
+        .or_default()
+        .push(word);
+}
+
+/// The text of the first word in the bank matching `predicate`.
+pub fn find(predicate: impl Fn(&str) -> bool) -> Option<String> {
+    words()
+        .read()
+        .unwrap()
+        .values()
+        .flatten()
+        .find(|word| predicate(&word.text))
+        .map(|word| word.text.clone())
+}
+
+/// How many words the bank has.
+pub fn count() -> usize {
+    words().read().unwrap().values().map(Vec::len).sum()
+}
+
+/// The complexities to fall back to, nearest first, when there is no word of
+/// `complexity`.
+fn fallbacks(complexity: Complexity) -> [Complexity; 3] {
+    match complexity {
+        Complexity::Easy => [Complexity::Easy, Complexity::Medium, Complexity::Hard],
+        Complexity::Medium => [Complexity::Medium, Complexity::Easy, Complexity::Hard],
+        Complexity::Hard => [Complexity::Hard, Complexity::Medium, Complexity::Easy],
+    }
+}
+
+/// How often each complexity comes up when a word's complexity is left to
+/// chance, in the order of `Complexity::ALL`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ComplexityWeights([u8; 3]);
+
+/// A sampler for every set of weights drawn with so far. Hosts only pick from
+/// a few hundred possible sets, so it stays small.
+static SAMPLERS: OnceLock<DashMap<ComplexityWeights, WeightedIndex<u8>>> = OnceLock::new();
+
+impl Default for ComplexityWeights {
+    fn default() -> Self {
+        ComplexityWeights([7, 2, 1])
+    }
+}
+
+impl ComplexityWeights {
+    const MAX: u8 = 10;
+
+    pub fn get(&self, complexity: Complexity) -> u8 {
+        self.0[complexity as usize - 1]
+    }
+
+    /// Moves the weight of `complexity` one step up or down, keeping at least
+    /// one complexity possible. Returns whether the weight changed.
+    pub fn adjust(&mut self, complexity: Complexity, increase: bool) -> bool {
+        let total = self.0.iter().sum::<u8>();
+        let weight = &mut self.0[complexity as usize - 1];
+        match increase {
+            true if *weight < Self::MAX => *weight += 1,
+            false if *weight > 0 && total > 1 => *weight -= 1,
+            _ => return false,
+        }
+        true
+    }
+
+    fn sample(&self, rng: &mut impl Rng) -> Complexity {
+        let sampler = SAMPLERS
+            .get_or_init(DashMap::new)
+            .entry(*self)
+            .or_insert_with(|| {
+                WeightedIndex::new(self.0).expect("At least one complexity is possible")
+            })
+            .downgrade();
+        Complexity::ALL[sampler.sample(rng)]
+    }
+}
+
+impl Display for ComplexityWeights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total = self.0.iter().map(|weight| *weight as u32).sum::<u32>();
+        let shares = Complexity::ALL
+            .map(|complexity| {
+                format!(
+                    "{} {}%",
+                    complexity,
+                    self.get(complexity) as u32 * 100 / total
+                )
+            })
+            .join(" · ");
+        write!(f, "{}", shares)
+    }
+}
+
+/// Deals one distinct word per entry of `complexities`, picking a random
+/// complexity following `weights` where it's missing. Broken cards are passed
+/// over. Stops early if the bank runs out of words.
+pub fn deal(
+    rng: &mut impl Rng,
+    complexities: &[Option<Complexity>],
+    weights: ComplexityWeights,
+) -> Vec<Word> {
+    let words = words().read().unwrap();
+    let mut piles = Complexity::ALL
+        .into_iter()
+        .filter_map(|complexity| {
+            let mut pile = words.get(&complexity)?.iter().collect::<Vec<_>>();
+            pile.shuffle(rng);
+            Some((complexity, pile))
+        })
+        .collect::<HashMap<_, _>>();
+
+    complexities
+        .iter()
+        .map_while(|complexity| {
+            let complexity = complexity.unwrap_or_else(|| weights.sample(rng));
+            let word = fallbacks(complexity).iter().find_map(|complexity| {
+                let pile = piles.get_mut(complexity)?;
+                std::iter::from_fn(|| pile.pop()).find(|word| playable(word))
+            })?;
+            Some(Word::select_taboo_words(word, rng))
+        })
+        .collect()
+}
+
+/// A word of the given complexity, or of a random one following `weights`.
+/// Falls back to the nearest complexity with words left, or when `REROLLS`
+/// picks in a row came up broken.
+pub fn get_random_word(
+    rng: &mut impl Rng,
+    complexity: Option<Complexity>,
+    weights: ComplexityWeights,
+) -> Word {
+    let complexity = complexity.unwrap_or_else(|| weights.sample(rng));
+
+    let words = words().read().unwrap();
+    let word = fallbacks(complexity)
+        .iter()
+        .find_map(|complexity| {
+            let pile = words.get(complexity)?;
+            (0..REROLLS).find_map(|_| pile.choose(rng).filter(|word| playable(word)))
+        })
+        .expect("The word bank has no playable words");
 
-    Word::select_taboo_words(word)
+    Word::select_taboo_words(word, rng)
 }